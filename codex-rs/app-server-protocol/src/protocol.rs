@@ -176,6 +176,17 @@ client_request_definitions! {
         params: OrchestrateStreamingPatchParams,
         response: OrchestrateStreamingPatchResponse,
     },
+    /// Apply one candidate of an orchestrated run to the working tree it was started in.
+    /// Candidates stay available for an hour after the run completes.
+    ApplyOrchestrateCandidate {
+        params: ApplyOrchestrateCandidateParams,
+        response: ApplyOrchestrateCandidateResponse,
+    },
+    /// Forget the candidates of an orchestrated run, stopping it if it is still going.
+    DiscardOrchestrateRun {
+        params: DiscardOrchestrateRunParams,
+        response: DiscardOrchestrateRunResponse,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, TS)]
@@ -415,12 +426,22 @@ pub struct ExecOneOffCommandResponse {
 pub struct OrchestrateStreamingPatchParams {
     /// Natural language prompt or task description.
     pub prompt: String,
-    /// Optional working directory; defaults to the server config cwd.
+    /// Optional working directory inside a git repository; defaults to the
+    /// server config cwd. Each candidate runs in its own worktree of that
+    /// repository.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cwd: Option<PathBuf>,
-    /// Optional maximum number of patch candidates to produce.
+    /// Optional maximum number of patch candidates to produce; defaults to 1,
+    /// at most 8.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_candidates: Option<u32>,
+    /// Commands (argv vectors) run in each finished candidate's worktree,
+    /// under its sandbox, to score it; e.g. the project's tests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_commands: Option<Vec<Vec<String>>>,
+    /// Whether the review model should judge each candidate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
@@ -430,6 +451,36 @@ pub struct OrchestrateStreamingPatchResponse {
     pub run_id: Uuid,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyOrchestrateCandidateParams {
+    pub run_id: Uuid,
+    pub candidate_id: u32,
+}
+
+/// Outcome of `git apply --3way`; conflicts are left in the working tree.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyOrchestrateCandidateResponse {
+    /// Whether every change applied without conflicts.
+    pub success: bool,
+    pub applied_paths: Vec<String>,
+    pub skipped_paths: Vec<String>,
+    pub conflicted_paths: Vec<String>,
+    /// Output of `git apply`, for display when it did not succeed.
+    pub stderr: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardOrchestrateRunParams {
+    pub run_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscardOrchestrateRunResponse {}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAuthStatusResponse {
@@ -774,6 +825,9 @@ pub struct LoginChatGptCompleteNotification {
 #[serde(rename_all = "camelCase")]
 pub struct OrchestratePatchProgressNotification {
     pub run_id: Uuid,
+    /// Candidate the message is about; omitted for progress of the run as a whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_id: Option<u32>,
     /// Human-readable progress message.
     pub message: String,
     /// Optional step index (0-based) and total steps if known.
@@ -791,6 +845,49 @@ pub struct OrchestratePatchCandidateNotification {
     pub candidate_id: u32,
     /// Unified diff or patch text.
     pub patch: String,
+    pub score: OrchestratePatchCandidateScore,
+}
+
+/// How a candidate fared, so clients can rank the candidates of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OrchestratePatchCandidateScore {
+    /// Overall score in `[0, 1]`, higher is better; 0 when the patch does not
+    /// apply. Otherwise the weighted mean of the check pass rate (0.5), the
+    /// review verdict (0.3) and a size factor favoring small diffs (0.2), over
+    /// whichever of them were requested.
+    pub score: f64,
+    /// Whether the patch applies cleanly to the working tree the run started in.
+    pub applies_cleanly: bool,
+    /// Results of `checkCommands`, in order.
+    pub checks: Vec<OrchestrateCheckResult>,
+    pub files_changed: u32,
+    pub lines_added: u32,
+    pub lines_removed: u32,
+    /// Verdict of the review model, when `review` was requested and it finished.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review: Option<OrchestrateReviewVerdict>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OrchestrateCheckResult {
+    pub command: Vec<String>,
+    pub exit_code: i32,
+    /// Tail of the combined stdout and stderr.
+    pub output: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
+#[serde(rename_all = "camelCase")]
+pub struct OrchestrateReviewVerdict {
+    /// Whether the reviewer considers the patch correct.
+    pub correct: bool,
+    pub explanation: String,
+    /// Reviewer confidence in `[0, 1]`.
+    pub confidence: f32,
+    /// Number of issues the reviewer found.
+    pub findings: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, TS)]
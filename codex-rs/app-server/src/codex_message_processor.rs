@@ -1,11 +1,13 @@
-use codex_app_server_protocol::JSONRPCErrorError;
 use crate::error_code::INTERNAL_ERROR_CODE;
 use crate::error_code::INVALID_REQUEST_ERROR_CODE;
 use crate::fuzzy_file_search::run_fuzzy_file_search;
+use crate::orchestrate_patch::OrchestrateRuns;
+use crate::orchestrate_patch::run_orchestrate_patch;
 use crate::outgoing_message::OutgoingMessageSender;
 use crate::outgoing_message::OutgoingNotification;
 use codex_app_server_protocol::AddConversationListenerParams;
 use codex_app_server_protocol::AddConversationSubscriptionResponse;
+use codex_app_server_protocol::ApplyOrchestrateCandidateParams;
 use codex_app_server_protocol::ApplyPatchApprovalParams;
 use codex_app_server_protocol::ApplyPatchApprovalResponse;
 use codex_app_server_protocol::ArchiveConversationParams;
@@ -13,6 +15,7 @@ use codex_app_server_protocol::ArchiveConversationResponse;
 use codex_app_server_protocol::AuthStatusChangeNotification;
 use codex_app_server_protocol::ClientRequest;
 use codex_app_server_protocol::ConversationSummary;
+use codex_app_server_protocol::DiscardOrchestrateRunParams;
 use codex_app_server_protocol::ExecCommandApprovalParams;
 use codex_app_server_protocol::ExecCommandApprovalResponse;
 use codex_app_server_protocol::ExecOneOffCommandParams;
@@ -25,19 +28,17 @@ use codex_app_server_protocol::GitDiffToRemoteResponse;
 use codex_app_server_protocol::InputItem as WireInputItem;
 use codex_app_server_protocol::InterruptConversationParams;
 use codex_app_server_protocol::InterruptConversationResponse;
+use codex_app_server_protocol::JSONRPCErrorError;
 use codex_app_server_protocol::ListConversationsParams;
 use codex_app_server_protocol::ListConversationsResponse;
-use codex_app_server_protocol::OrchestrateStreamingPatchParams;
-use codex_app_server_protocol::OrchestrateStreamingPatchResponse;
-use codex_app_server_protocol::OrchestratePatchCandidateNotification;
-use codex_app_server_protocol::OrchestratePatchCompletedNotification;
-use codex_app_server_protocol::OrchestratePatchProgressNotification;
 use codex_app_server_protocol::LoginApiKeyParams;
 use codex_app_server_protocol::LoginApiKeyResponse;
 use codex_app_server_protocol::LoginChatGptCompleteNotification;
 use codex_app_server_protocol::LoginChatGptResponse;
 use codex_app_server_protocol::NewConversationParams;
 use codex_app_server_protocol::NewConversationResponse;
+use codex_app_server_protocol::OrchestrateStreamingPatchParams;
+use codex_app_server_protocol::OrchestrateStreamingPatchResponse;
 use codex_app_server_protocol::RemoveConversationListenerParams;
 use codex_app_server_protocol::RemoveConversationSubscriptionResponse;
 use codex_app_server_protocol::RequestId;
@@ -135,6 +136,7 @@ pub(crate) struct CodexMessageProcessor {
     // Queue of pending interrupt requests per conversation. We reply when TurnAborted arrives.
     pending_interrupts: Arc<Mutex<HashMap<ConversationId, Vec<RequestId>>>>,
     pending_fuzzy_searches: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    orchestrate_runs: OrchestrateRuns,
 }
 
 impl CodexMessageProcessor {
@@ -155,6 +157,7 @@ impl CodexMessageProcessor {
             active_login: Arc::new(Mutex::new(None)),
             pending_interrupts: Arc::new(Mutex::new(HashMap::new())),
             pending_fuzzy_searches: Arc::new(Mutex::new(HashMap::new())),
+            orchestrate_runs: OrchestrateRuns::default(),
         }
     }
 
@@ -247,6 +250,12 @@ impl CodexMessageProcessor {
             ClientRequest::OrchestrateStreamingPatch { request_id, params } => {
                 self.orchestrate_streaming_patch(request_id, params).await;
             }
+            ClientRequest::ApplyOrchestrateCandidate { request_id, params } => {
+                self.apply_orchestrate_candidate(request_id, params).await;
+            }
+            ClientRequest::DiscardOrchestrateRun { request_id, params } => {
+                self.discard_orchestrate_run(request_id, params).await;
+            }
         }
     }
 
@@ -665,74 +674,47 @@ impl CodexMessageProcessor {
         request_id: RequestId,
         params: OrchestrateStreamingPatchParams,
     ) {
-        // Generate a run_id and respond immediately so the client can correlate any logs/stream.
+        // Generate a run_id and respond immediately so the client can correlate the stream.
         let run_id = Uuid::new_v4();
+        let run = self
+            .orchestrate_runs
+            .register(run_id, params, &self.config.cwd)
+            .await;
         let response = OrchestrateStreamingPatchResponse { run_id };
         self.outgoing.send_response(request_id, response).await;
 
-        let prompt = params.prompt;
-        let cwd = params.cwd.unwrap_or_else(|| self.config.cwd.clone());
-        let max_candidates = params.max_candidates.unwrap_or(1);
-        let outgoing = self.outgoing.clone();
-
-        tokio::spawn(async move {
-            info!(
-                "orchestrate_streaming_patch started: run_id={}, cwd={}, max_candidates={}, prompt_len={}",
-                run_id,
-                cwd.display(),
-                max_candidates,
-                prompt.len()
-            );
-
-            // Progress tick 1
-            let _ = outgoing
-                .send_server_notification(ServerNotification::OrchestratePatchProgress(
-                    OrchestratePatchProgressNotification {
-                        run_id,
-                        message: "Analyzing repository context".to_string(),
-                        step: Some(1),
-                        total_steps: Some(3),
-                    },
-                ))
-                .await;
-
-            // Progress tick 2
-            let _ = outgoing
-                .send_server_notification(ServerNotification::OrchestratePatchProgress(
-                    OrchestratePatchProgressNotification {
-                        run_id,
-                        message: "Generating patch candidate".to_string(),
-                        step: Some(2),
-                        total_steps: Some(3),
-                    },
-                ))
-                .await;
-
-            // Emit a dummy candidate (placeholder)
-            let dummy_patch = "--- a/README.md\n+++ b/README.md\n@@\n-Hello Codex\n+Hello Codex!\n".to_string();
-            let _ = outgoing
-                .send_server_notification(ServerNotification::OrchestratePatchCandidate(
-                    OrchestratePatchCandidateNotification {
-                        run_id,
-                        candidate_id: 1,
-                        patch: dummy_patch,
-                    },
-                ))
-                .await;
+        // The server config is loaded without the sandbox executable; new
+        // conversations get it from `derive_config_from_params`.
+        let mut config = (*self.config).clone();
+        config.codex_linux_sandbox_exe = self.codex_linux_sandbox_exe.clone();
+        tokio::spawn(run_orchestrate_patch(
+            run,
+            config,
+            self.conversation_manager.clone(),
+            self.outgoing.clone(),
+        ));
+    }
 
-            // Completed
-            let _ = outgoing
-                .send_server_notification(ServerNotification::OrchestratePatchCompleted(
-                    OrchestratePatchCompletedNotification {
-                        run_id,
-                        success: true,
-                        error: None,
-                    },
-                ))
-                .await;
+    async fn apply_orchestrate_candidate(
+        &self,
+        request_id: RequestId,
+        params: ApplyOrchestrateCandidateParams,
+    ) {
+        match self.orchestrate_runs.apply(params).await {
+            Ok(response) => self.outgoing.send_response(request_id, response).await,
+            Err(error) => self.outgoing.send_error(request_id, error).await,
+        }
+    }
 
-            info!("orchestrate_streaming_patch completed: run_id={}", run_id);
-        });
+    async fn discard_orchestrate_run(
+        &self,
+        request_id: RequestId,
+        params: DiscardOrchestrateRunParams,
+    ) {
+        match self.orchestrate_runs.discard(params.run_id).await {
+            Ok(response) => self.outgoing.send_response(request_id, response).await,
+            Err(error) => self.outgoing.send_error(request_id, error).await,
+        }
     }
 
     async fn process_new_conversation(&self, request_id: RequestId, params: NewConversationParams) {
@@ -0,0 +1,1016 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use codex_app_server_protocol::ApplyOrchestrateCandidateParams;
+use codex_app_server_protocol::ApplyOrchestrateCandidateResponse;
+use codex_app_server_protocol::DiscardOrchestrateRunResponse;
+use codex_app_server_protocol::JSONRPCErrorError;
+use codex_app_server_protocol::OrchestrateCheckResult;
+use codex_app_server_protocol::OrchestratePatchCandidateNotification;
+use codex_app_server_protocol::OrchestratePatchCandidateScore;
+use codex_app_server_protocol::OrchestratePatchCompletedNotification;
+use codex_app_server_protocol::OrchestratePatchProgressNotification;
+use codex_app_server_protocol::OrchestrateReviewVerdict;
+use codex_app_server_protocol::OrchestrateStreamingPatchParams;
+use codex_app_server_protocol::ServerNotification;
+use codex_core::CodexConversation;
+use codex_core::ConversationManager;
+use codex_core::NewConversation;
+use codex_core::config::Config;
+use codex_core::exec::ExecParams;
+use codex_core::exec::SandboxType;
+use codex_core::exec::process_exec_tool_call;
+use codex_core::exec_env::create_env;
+use codex_core::get_platform_sandbox;
+use codex_core::protocol::AskForApproval;
+use codex_core::protocol::EventMsg;
+use codex_core::protocol::InputItem;
+use codex_core::protocol::Op;
+use codex_core::protocol::ReviewDecision;
+use codex_core::protocol::ReviewOutputEvent;
+use codex_core::protocol::ReviewRequest;
+use codex_core::protocol::SandboxPolicy;
+use codex_git_apply::ApplyGitRequest;
+use codex_git_apply::ApplyGitResult;
+use codex_git_apply::apply_git_patch;
+use codex_utils_string::take_last_bytes_at_char_boundary;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::error_code::INTERNAL_ERROR_CODE;
+use crate::error_code::INVALID_REQUEST_ERROR_CODE;
+use crate::outgoing_message::OutgoingMessageSender;
+
+/// Upper bound on `max_candidates`; every candidate is a full agent session.
+const MAX_CANDIDATES: u32 = 8;
+
+/// How long a candidate conversation gets to acknowledge `Op::Shutdown`.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long each of `check_commands` may run; these are typically test suites.
+const CHECK_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// How much of a check command's output is reported back.
+const CHECK_OUTPUT_MAX_BYTES: usize = 4 * 1024;
+
+/// Diff size, in changed lines, at which the size factor of the score is 1/2.
+const HALF_SCORE_LINES: f64 = 200.0;
+
+/// How long the candidates of a completed run stay available to apply. The
+/// worktrees are gone by then; only the patches are kept.
+const RUN_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Directory inside the repository's git dir holding the worktrees of each
+/// run, so they never show up in the user's `git status`.
+const WORKTREES_DIR: &str = "codex-orchestrate";
+
+/// Candidate patches of the runs that have not been discarded or expired, so
+/// clients can apply the one they pick.
+#[derive(Clone, Default)]
+pub(crate) struct OrchestrateRuns {
+    runs: Arc<Mutex<HashMap<Uuid, OrchestrateRun>>>,
+}
+
+struct OrchestrateRun {
+    /// The working tree the run started in, where candidates are applied.
+    cwd: PathBuf,
+    patches: HashMap<u32, String>,
+    /// Set when the run is discarded, to stop candidates that are still going.
+    discarded: watch::Sender<bool>,
+}
+
+/// A run that has been registered and is ready to start.
+pub(crate) struct PatchRun {
+    run_id: Uuid,
+    params: OrchestrateStreamingPatchParams,
+    cwd: PathBuf,
+    runs: OrchestrateRuns,
+    discarded: watch::Receiver<bool>,
+}
+
+impl OrchestrateRuns {
+    /// Register a run for `params`, resolving its `cwd` against `default_cwd`.
+    pub(crate) async fn register(
+        &self,
+        run_id: Uuid,
+        params: OrchestrateStreamingPatchParams,
+        default_cwd: &Path,
+    ) -> PatchRun {
+        let cwd = params
+            .cwd
+            .clone()
+            .unwrap_or_else(|| default_cwd.to_path_buf());
+        let (discarded, discarded_rx) = watch::channel(false);
+        self.runs.lock().await.insert(
+            run_id,
+            OrchestrateRun {
+                cwd: cwd.clone(),
+                patches: HashMap::new(),
+                discarded,
+            },
+        );
+        PatchRun {
+            run_id,
+            params,
+            cwd,
+            runs: self.clone(),
+            discarded: discarded_rx,
+        }
+    }
+
+    /// Record a candidate's patch. Returns false when the run was discarded
+    /// in the meantime.
+    async fn add_candidate(&self, run_id: Uuid, candidate_id: u32, patch: String) -> bool {
+        match self.runs.lock().await.get_mut(&run_id) {
+            Some(run) => {
+                run.patches.insert(candidate_id, patch);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply a candidate with `git apply --3way` to the run's working tree.
+    pub(crate) async fn apply(
+        &self,
+        params: ApplyOrchestrateCandidateParams,
+    ) -> Result<ApplyOrchestrateCandidateResponse, JSONRPCErrorError> {
+        let ApplyOrchestrateCandidateParams {
+            run_id,
+            candidate_id,
+        } = params;
+        let request = {
+            let runs = self.runs.lock().await;
+            let Some(run) = runs.get(&run_id) else {
+                return Err(invalid_request(format!(
+                    "orchestrate run {run_id} not found"
+                )));
+            };
+            let Some(patch) = run.patches.get(&candidate_id) else {
+                return Err(invalid_request(format!(
+                    "orchestrate run {run_id} has no candidate {candidate_id}"
+                )));
+            };
+            ApplyGitRequest {
+                cwd: run.cwd.clone(),
+                diff: patch.clone(),
+                revert: false,
+                preflight: false,
+            }
+        };
+
+        let result = git_apply(request).await.map_err(|err| JSONRPCErrorError {
+            code: INTERNAL_ERROR_CODE,
+            message: format!("failed to apply candidate {candidate_id}: {err}"),
+            data: None,
+        })?;
+        Ok(ApplyOrchestrateCandidateResponse {
+            success: result.exit_code == 0,
+            applied_paths: result.applied_paths,
+            skipped_paths: result.skipped_paths,
+            conflicted_paths: result.conflicted_paths,
+            stderr: result.stderr,
+        })
+    }
+
+    /// Forget a finished run after `delay`, unless it is discarded first.
+    fn expire_after(&self, run_id: Uuid, delay: Duration) {
+        let runs = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if runs.runs.lock().await.remove(&run_id).is_some() {
+                info!("orchestrate run {run_id} expired");
+            }
+        });
+    }
+
+    /// Forget a run's candidates, stopping the run if it is still going.
+    pub(crate) async fn discard(
+        &self,
+        run_id: Uuid,
+    ) -> Result<DiscardOrchestrateRunResponse, JSONRPCErrorError> {
+        let Some(run) = self.runs.lock().await.remove(&run_id) else {
+            return Err(invalid_request(format!(
+                "orchestrate run {run_id} not found"
+            )));
+        };
+        run.discarded.send_replace(true);
+        Ok(DiscardOrchestrateRunResponse {})
+    }
+}
+
+fn invalid_request(message: String) -> JSONRPCErrorError {
+    JSONRPCErrorError {
+        code: INVALID_REQUEST_ERROR_CODE,
+        message,
+        data: None,
+    }
+}
+
+/// Run the prompt in up to `max_candidates` conversations, each in its own git
+/// worktree of the repository containing `cwd`, and stream their progress and
+/// scored diffs as `OrchestratePatch*` notifications.
+pub(crate) async fn run_orchestrate_patch(
+    run: PatchRun,
+    config: Config,
+    conversation_manager: Arc<ConversationManager>,
+    outgoing: Arc<OutgoingMessageSender>,
+) {
+    let run_id = run.run_id;
+    let candidates = run
+        .params
+        .max_candidates
+        .unwrap_or(1)
+        .clamp(1, MAX_CANDIDATES);
+    info!(
+        "orchestrate_streaming_patch started: run_id={run_id}, cwd={}, candidates={candidates}",
+        run.cwd.display()
+    );
+
+    let progress = Progress {
+        run_id,
+        outgoing: outgoing.clone(),
+    };
+    progress
+        .send(None, format!("Creating {candidates} worktree(s)"), None)
+        .await;
+    let result = match RunWorktrees::create(&run.cwd, run_id, candidates).await {
+        Ok(worktrees) => {
+            let result =
+                run_candidates(&run, &worktrees, &config, &conversation_manager, &progress).await;
+            worktrees.remove().await;
+            result
+        }
+        Err(err) => Err(format!("failed to create worktrees: {err}")),
+    };
+
+    // A failed run has nothing worth applying
+    if let Err(err) = &result {
+        warn!("orchestrate_streaming_patch failed: run_id={run_id}: {err}");
+        run.runs.expire_after(run_id, Duration::ZERO);
+    } else {
+        info!("orchestrate_streaming_patch completed: run_id={run_id}");
+        run.runs.expire_after(run_id, RUN_RETENTION);
+    }
+    outgoing
+        .send_server_notification(ServerNotification::OrchestratePatchCompleted(
+            OrchestratePatchCompletedNotification {
+                run_id,
+                success: result.is_ok(),
+                error: result.err(),
+            },
+        ))
+        .await;
+}
+
+/// Run every candidate concurrently, emitting each scored patch as soon as
+/// its candidate finishes. Succeeds when at least one candidate produced a patch.
+async fn run_candidates(
+    run: &PatchRun,
+    worktrees: &RunWorktrees,
+    config: &Config,
+    conversation_manager: &Arc<ConversationManager>,
+    progress: &Progress,
+) -> Result<(), String> {
+    let total = worktrees.worktrees.len() as u32;
+    let mut join_set = JoinSet::new();
+    for worktree in &worktrees.worktrees {
+        let candidate_id = worktree.candidate_id;
+        let candidate = Candidate {
+            worktree: worktree.clone(),
+            params: run.params.clone(),
+            run_cwd: run.cwd.clone(),
+            progress: progress.clone(),
+            discarded: run.discarded.clone(),
+        };
+        let config = config.clone();
+        let conversation_manager = conversation_manager.clone();
+        join_set.spawn(async move {
+            (
+                candidate_id,
+                candidate.run(config, conversation_manager).await,
+            )
+        });
+    }
+
+    let mut finished = 0;
+    let mut patches = 0;
+    let mut failures = Vec::new();
+    while let Some(joined) = join_set.join_next().await {
+        finished += 1;
+        let step = Some((finished, total));
+        let (candidate_id, result) = match joined {
+            Ok(joined) => joined,
+            Err(err) => {
+                failures.push(format!("candidate task failed: {err}"));
+                continue;
+            }
+        };
+        match result {
+            Ok(Some((patch, score))) => {
+                if !run
+                    .runs
+                    .add_candidate(run.run_id, candidate_id, patch.clone())
+                    .await
+                {
+                    continue;
+                }
+                patches += 1;
+                progress
+                    .send(
+                        Some(candidate_id),
+                        format!("Produced a patch scoring {:.2}", score.score),
+                        step,
+                    )
+                    .await;
+                progress
+                    .outgoing
+                    .send_server_notification(ServerNotification::OrchestratePatchCandidate(
+                        OrchestratePatchCandidateNotification {
+                            run_id: run.run_id,
+                            candidate_id,
+                            patch,
+                            score,
+                        },
+                    ))
+                    .await;
+            }
+            Ok(None) => {
+                progress
+                    .send(Some(candidate_id), "Made no changes".to_string(), step)
+                    .await;
+            }
+            Err(err) => {
+                progress
+                    .send(Some(candidate_id), format!("Failed: {err}"), step)
+                    .await;
+                failures.push(format!("candidate {candidate_id}: {err}"));
+            }
+        }
+    }
+
+    if *run.discarded.borrow() {
+        return Err("run was discarded".to_string());
+    }
+    if patches > 0 {
+        return Ok(());
+    }
+    if failures.is_empty() {
+        Err("no candidate produced a patch".to_string())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+/// One conversation working on the prompt in its own worktree.
+struct Candidate {
+    worktree: Worktree,
+    params: OrchestrateStreamingPatchParams,
+    run_cwd: PathBuf,
+    progress: Progress,
+    discarded: watch::Receiver<bool>,
+}
+
+impl Candidate {
+    /// Run the conversation and score the diff of its turn, if it changed
+    /// anything.
+    async fn run(
+        mut self,
+        mut config: Config,
+        conversation_manager: Arc<ConversationManager>,
+    ) -> Result<Option<(String, OrchestratePatchCandidateScore)>, String> {
+        // Nobody is around to answer approval requests, so candidates run
+        // unattended and may only write inside their own worktree.
+        config.cwd = self.worktree.cwd.clone();
+        config.approval_policy = AskForApproval::Never;
+        config.sandbox_policy = SandboxPolicy::WorkspaceWrite {
+            writable_roots: vec![self.worktree.root.clone()],
+            network_access: false,
+            exclude_tmpdir_env_var: false,
+            exclude_slash_tmp: false,
+        };
+
+        let NewConversation {
+            conversation_id,
+            conversation,
+            ..
+        } = conversation_manager
+            .new_conversation(config.clone())
+            .await
+            .map_err(|err| format!("failed to start conversation: {err}"))?;
+        let result = async {
+            let prompt = Op::UserInput {
+                items: vec![InputItem::Text {
+                    text: self.params.prompt.clone(),
+                }],
+            };
+            let Some(patch) = self.run_turn(&conversation, prompt).await?.patch else {
+                return Ok(None);
+            };
+            let score = self.score(&conversation, &config, &patch).await?;
+            Ok(Some((patch, score)))
+        }
+        .await;
+
+        shutdown_conversation(&conversation).await;
+        conversation_manager
+            .remove_conversation(&conversation_id)
+            .await;
+        result
+    }
+
+    async fn score(
+        &mut self,
+        conversation: &CodexConversation,
+        config: &Config,
+        patch: &str,
+    ) -> Result<OrchestratePatchCandidateScore, String> {
+        let mut checks = Vec::new();
+        for command in self.params.check_commands.clone().unwrap_or_default() {
+            self.progress(format!("Checking `{}`", display_command(&command)))
+                .await;
+            checks.push(self.run_check(config, command).await);
+        }
+
+        let mut review = None;
+        if self.params.review.unwrap_or(false) {
+            self.progress("Reviewing".to_string()).await;
+            let request = Op::Review {
+                review_request: ReviewRequest {
+                    prompt: format!(
+                        "Review the following patch, which is applied to the working tree, and provide prioritized findings.\n\n```diff\n{patch}\n```"
+                    ),
+                    user_facing_hint: "candidate patch".to_string(),
+                },
+            };
+            review = self
+                .run_turn(conversation, request)
+                .await?
+                .review
+                .map(review_verdict);
+        }
+
+        let applies_cleanly = match git_apply(ApplyGitRequest {
+            cwd: self.run_cwd.clone(),
+            diff: patch.to_string(),
+            revert: false,
+            preflight: true,
+        })
+        .await
+        {
+            Ok(result) => result.exit_code == 0,
+            Err(err) => {
+                warn!("failed to check whether the patch applies: {err}");
+                false
+            }
+        };
+
+        let stats = DiffStats::parse(patch);
+        Ok(OrchestratePatchCandidateScore {
+            score: overall_score(applies_cleanly, &checks, review.as_ref(), &stats),
+            applies_cleanly,
+            checks,
+            files_changed: stats.files_changed,
+            lines_added: stats.lines_added,
+            lines_removed: stats.lines_removed,
+            review,
+        })
+    }
+
+    /// Run a check command in the worktree, under the candidate's sandbox.
+    async fn run_check(&self, config: &Config, command: Vec<String>) -> OrchestrateCheckResult {
+        let params = ExecParams {
+            command: command.clone(),
+            cwd: self.worktree.cwd.clone(),
+            timeout_ms: Some(CHECK_TIMEOUT_MS),
+            env: create_env(&config.shell_environment_policy),
+            with_escalated_permissions: None,
+            justification: None,
+        };
+        let sandbox_type = match &config.sandbox_policy {
+            SandboxPolicy::DangerFullAccess => SandboxType::None,
+            _ => get_platform_sandbox().unwrap_or(SandboxType::None),
+        };
+        match process_exec_tool_call(
+            params,
+            sandbox_type,
+            &config.sandbox_policy,
+            &self.worktree.cwd,
+            &config.codex_linux_sandbox_exe,
+            None,
+        )
+        .await
+        {
+            Ok(output) => OrchestrateCheckResult {
+                command,
+                exit_code: output.exit_code,
+                output: take_last_bytes_at_char_boundary(
+                    &output.aggregated_output.text,
+                    CHECK_OUTPUT_MAX_BYTES,
+                )
+                .to_string(),
+            },
+            Err(err) => OrchestrateCheckResult {
+                command,
+                exit_code: -1,
+                output: format!("failed to run: {err}"),
+            },
+        }
+    }
+
+    /// Submit `op` and follow the conversation until its task completes.
+    async fn run_turn(
+        &mut self,
+        conversation: &CodexConversation,
+        op: Op,
+    ) -> Result<TurnOutcome, String> {
+        conversation
+            .submit(op)
+            .await
+            .map_err(|err| format!("failed to submit to conversation: {err}"))?;
+
+        let mut outcome = TurnOutcome::default();
+        let mut error = None;
+        loop {
+            let event = tokio::select! {
+                event = conversation.next_event() => {
+                    event.map_err(|err| format!("conversation failed: {err}"))?
+                }
+                _ = self.discarded.wait_for(|discarded| *discarded) => {
+                    return Err("run was discarded".to_string());
+                }
+            };
+            let message = match event.msg {
+                EventMsg::TaskStarted(_) => Some("Started".to_string()),
+                EventMsg::ExecCommandBegin(begin) => {
+                    Some(format!("Running `{}`", display_command(&begin.command)))
+                }
+                EventMsg::PatchApplyBegin(begin) => {
+                    let mut paths = begin
+                        .changes
+                        .keys()
+                        .map(|path| self.worktree.display_path(path))
+                        .collect::<Vec<_>>();
+                    paths.sort();
+                    Some(format!("Editing {}", paths.join(", ")))
+                }
+                EventMsg::McpToolCallBegin(begin) => Some(format!(
+                    "Calling {}.{}",
+                    begin.invocation.server, begin.invocation.tool
+                )),
+                EventMsg::StreamError(stream_error) => Some(stream_error.message),
+                EventMsg::TurnDiff(turn_diff) => {
+                    outcome.patch = Some(turn_diff.unified_diff).filter(|diff| !diff.is_empty());
+                    None
+                }
+                EventMsg::ExitedReviewMode(exited) => {
+                    outcome.review = exited.review_output;
+                    None
+                }
+                // Unreachable with `AskForApproval::Never`, but a pending request
+                // would stall the candidate forever.
+                EventMsg::ExecApprovalRequest(_) => {
+                    submit_denial(
+                        conversation,
+                        Op::ExecApproval {
+                            id: event.id,
+                            decision: ReviewDecision::Denied,
+                        },
+                    )
+                    .await;
+                    None
+                }
+                EventMsg::ApplyPatchApprovalRequest(_) => {
+                    submit_denial(
+                        conversation,
+                        Op::PatchApproval {
+                            id: event.id,
+                            decision: ReviewDecision::Denied,
+                        },
+                    )
+                    .await;
+                    None
+                }
+                EventMsg::Error(error_event) => {
+                    error = Some(error_event.message);
+                    None
+                }
+                EventMsg::TurnAborted(aborted) => {
+                    return Err(format!("turn aborted: {:?}", aborted.reason));
+                }
+                EventMsg::TaskComplete(_) => break,
+                _ => None,
+            };
+            if let Some(message) = message {
+                self.progress(message).await;
+            }
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(outcome),
+        }
+    }
+
+    async fn progress(&self, message: String) {
+        self.progress
+            .send(Some(self.worktree.candidate_id), message, None)
+            .await;
+    }
+}
+
+#[derive(Default)]
+struct TurnOutcome {
+    /// The diff of everything the turn changed, if anything.
+    patch: Option<String>,
+    review: Option<ReviewOutputEvent>,
+}
+
+fn review_verdict(output: ReviewOutputEvent) -> OrchestrateReviewVerdict {
+    OrchestrateReviewVerdict {
+        correct: output.overall_correctness == "patch is correct",
+        explanation: output.overall_explanation,
+        confidence: output.overall_confidence_score.clamp(0.0, 1.0),
+        findings: output.findings.len() as u32,
+    }
+}
+
+/// See [`OrchestratePatchCandidateScore::score`].
+fn overall_score(
+    applies_cleanly: bool,
+    checks: &[OrchestrateCheckResult],
+    review: Option<&OrchestrateReviewVerdict>,
+    stats: &DiffStats,
+) -> f64 {
+    if !applies_cleanly {
+        return 0.0;
+    }
+    let mut parts = Vec::new();
+    if !checks.is_empty() {
+        let passed = checks.iter().filter(|check| check.exit_code == 0).count();
+        parts.push((0.5, passed as f64 / checks.len() as f64));
+    }
+    if let Some(review) = review {
+        let verdict = if review.correct {
+            f64::from(review.confidence)
+        } else {
+            0.0
+        };
+        parts.push((0.3, verdict));
+    }
+    let lines = f64::from(stats.lines_added + stats.lines_removed);
+    parts.push((0.2, HALF_SCORE_LINES / (HALF_SCORE_LINES + lines)));
+
+    let weights = parts.iter().map(|(weight, _)| weight).sum::<f64>();
+    parts
+        .iter()
+        .map(|(weight, value)| weight * value)
+        .sum::<f64>()
+        / weights
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct DiffStats {
+    files_changed: u32,
+    lines_added: u32,
+    lines_removed: u32,
+}
+
+impl DiffStats {
+    fn parse(diff: &str) -> Self {
+        let mut stats = Self::default();
+        let mut in_hunk = false;
+        for line in diff.lines() {
+            if line.starts_with("diff --git ") {
+                stats.files_changed += 1;
+                in_hunk = false;
+            } else if line.starts_with("@@") {
+                in_hunk = true;
+            } else if in_hunk && line.starts_with('+') {
+                stats.lines_added += 1;
+            } else if in_hunk && line.starts_with('-') {
+                stats.lines_removed += 1;
+            }
+        }
+        stats
+    }
+}
+
+/// `apply_git_patch` runs git synchronously.
+async fn git_apply(request: ApplyGitRequest) -> std::io::Result<ApplyGitResult> {
+    tokio::task::spawn_blocking(move || apply_git_patch(&request))
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+async fn submit_denial(conversation: &CodexConversation, op: Op) {
+    if let Err(err) = conversation.submit(op).await {
+        warn!("failed to deny approval request: {err}");
+    }
+}
+
+async fn shutdown_conversation(conversation: &CodexConversation) {
+    if let Err(err) = conversation.submit(Op::Shutdown).await {
+        warn!("failed to submit Shutdown: {err}");
+        return;
+    }
+    let shutdown = async {
+        while let Ok(event) = conversation.next_event().await {
+            if matches!(event.msg, EventMsg::ShutdownComplete) {
+                break;
+            }
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, shutdown)
+        .await
+        .is_err()
+    {
+        warn!("candidate conversation shutdown timed out");
+    }
+}
+
+/// Show `bash -lc <script>` invocations as just the script.
+fn display_command(command: &[String]) -> String {
+    match command {
+        [_, flag, script] if flag == "-lc" => script.clone(),
+        _ => command.join(" "),
+    }
+}
+
+#[derive(Clone)]
+struct Progress {
+    run_id: Uuid,
+    outgoing: Arc<OutgoingMessageSender>,
+}
+
+impl Progress {
+    async fn send(&self, candidate_id: Option<u32>, message: String, step: Option<(u32, u32)>) {
+        self.outgoing
+            .send_server_notification(ServerNotification::OrchestratePatchProgress(
+                OrchestratePatchProgressNotification {
+                    run_id: self.run_id,
+                    candidate_id,
+                    message,
+                    step: step.map(|(step, _)| step),
+                    total_steps: step.map(|(_, total)| total),
+                },
+            ))
+            .await;
+    }
+}
+
+/// A candidate's checkout of the repository.
+#[derive(Clone)]
+struct Worktree {
+    candidate_id: u32,
+    root: PathBuf,
+    /// The requested `cwd`, relocated into this worktree.
+    cwd: PathBuf,
+}
+
+impl Worktree {
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    }
+}
+
+/// The worktrees of one run. They start at `HEAD` plus the uncommitted changes
+/// to tracked files, so candidates see what the user sees; untracked files
+/// are not copied.
+struct RunWorktrees {
+    repo_root: PathBuf,
+    dir: PathBuf,
+    worktrees: Vec<Worktree>,
+}
+
+impl RunWorktrees {
+    async fn create(cwd: &Path, run_id: Uuid, candidates: u32) -> Result<Self, String> {
+        let repo_root = PathBuf::from(git_line(cwd, ["rev-parse", "--show-toplevel"]).await?);
+        let prefix = git_line(cwd, ["rev-parse", "--show-prefix"]).await?;
+        let git_dir = git_line(cwd, ["rev-parse", "--git-common-dir"]).await?;
+        let dir = cwd
+            .join(git_dir)
+            .join(WORKTREES_DIR)
+            .join(run_id.to_string());
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .map_err(|err| format!("failed to create {}: {err}", dir.display()))?;
+
+        let changes = git(&repo_root, ["diff", "--binary", "HEAD"]).await?;
+        let changes_path = dir.join("changes.patch");
+        if !changes.is_empty() {
+            tokio::fs::write(&changes_path, changes)
+                .await
+                .map_err(|err| format!("failed to write {}: {err}", changes_path.display()))?;
+        }
+
+        let mut run = Self {
+            repo_root,
+            dir,
+            worktrees: Vec::new(),
+        };
+        for candidate_id in 1..=candidates {
+            let root = run.dir.join(format!("candidate-{candidate_id}"));
+            if let Err(err) = run.add(&root, &changes_path).await {
+                run.remove().await;
+                return Err(err);
+            }
+            run.worktrees.push(Worktree {
+                candidate_id,
+                cwd: root.join(&prefix),
+                root,
+            });
+        }
+        Ok(run)
+    }
+
+    async fn add(&self, root: &Path, changes_path: &Path) -> Result<(), String> {
+        git(
+            &self.repo_root,
+            [
+                OsStr::new("worktree"),
+                OsStr::new("add"),
+                OsStr::new("--detach"),
+                root.as_os_str(),
+                OsStr::new("HEAD"),
+            ],
+        )
+        .await?;
+        if changes_path.exists() {
+            git(root, [OsStr::new("apply"), changes_path.as_os_str()]).await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the worktrees and everything else the run wrote. Failures are
+    /// logged, not returned: there is nobody left to report them to.
+    async fn remove(&self) {
+        for worktree in &self.worktrees {
+            let args = [
+                OsStr::new("worktree"),
+                OsStr::new("remove"),
+                OsStr::new("--force"),
+                worktree.root.as_os_str(),
+            ];
+            if let Err(err) = git(&self.repo_root, args).await {
+                warn!("{err}");
+            }
+        }
+        if let Err(err) = tokio::fs::remove_dir_all(&self.dir).await {
+            warn!("failed to remove {}: {err}", self.dir.display());
+        }
+        if let Err(err) = git(&self.repo_root, ["worktree", "prune"]).await {
+            warn!("{err}");
+        }
+    }
+}
+
+/// Run git in `cwd` and return its stdout as a single trimmed line.
+async fn git_line<I, S>(cwd: &Path, args: I) -> Result<String, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let stdout = git(cwd, args).await?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+/// Run git in `cwd` and return its stdout.
+async fn git<I, S>(cwd: &Path, args: I) -> Result<Vec<u8>, String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args = args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_os_string())
+        .collect::<Vec<OsString>>();
+    let command = args
+        .iter()
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(cwd)
+        .output()
+        .await
+        .map_err(|err| format!("failed to run `git {command}`: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "`git {command}` failed in {}: {}",
+            cwd.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const DIFF: &str = "diff --git a/README.md b/README.md
+index 1111111..2222222 100644
+--- a/README.md
++++ b/README.md
+@@ -1,2 +1,2 @@
+-Hello Codex
++Hello Codex!
+ Bye
+diff --git a/new.txt b/new.txt
+new file mode 100644
+index 0000000..3333333
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1,2 @@
++--- not a header
++two
+";
+
+    fn check(exit_code: i32) -> OrchestrateCheckResult {
+        OrchestrateCheckResult {
+            command: vec!["true".to_string()],
+            exit_code,
+            output: String::new(),
+        }
+    }
+
+    #[test]
+    fn diff_stats_count_hunk_lines_per_file() {
+        assert_eq!(
+            DiffStats::parse(DIFF),
+            DiffStats {
+                files_changed: 2,
+                lines_added: 3,
+                lines_removed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn overall_score_weighs_requested_signals() {
+        let stats = DiffStats::parse(DIFF);
+        let size = HALF_SCORE_LINES / (HALF_SCORE_LINES + 4.0);
+        assert_eq!(overall_score(false, &[check(0)], None, &stats), 0.0);
+        assert_eq!(overall_score(true, &[], None, &stats), size);
+
+        let checks = [check(0), check(1)];
+        let expected = (0.5 * 0.5 + 0.2 * size) / 0.7;
+        assert!((overall_score(true, &checks, None, &stats) - expected).abs() < 1e-9);
+
+        let review = OrchestrateReviewVerdict {
+            correct: true,
+            explanation: String::new(),
+            confidence: 0.5,
+            findings: 0,
+        };
+        let expected = 0.5 * 0.5 + 0.3 * 0.5 + 0.2 * size;
+        assert!((overall_score(true, &checks, Some(&review), &stats) - expected).abs() < 1e-9);
+
+        let incorrect = OrchestrateReviewVerdict {
+            correct: false,
+            ..review
+        };
+        assert!(
+            overall_score(true, &checks, Some(&incorrect), &stats)
+                < overall_score(true, &checks, None, &stats)
+        );
+    }
+
+    #[tokio::test]
+    async fn finished_runs_expire() {
+        let runs = OrchestrateRuns::default();
+        let run_id = Uuid::new_v4();
+        let params = OrchestrateStreamingPatchParams {
+            prompt: "Fix the bug".to_string(),
+            cwd: None,
+            max_candidates: None,
+            check_commands: None,
+            review: None,
+        };
+        runs.register(run_id, params, Path::new("/repo")).await;
+        assert!(runs.add_candidate(run_id, 0, DIFF.to_string()).await);
+
+        runs.expire_after(run_id, Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(!runs.add_candidate(run_id, 1, DIFF.to_string()).await);
+        let err = runs.discard(run_id).await.err().map(|err| err.message);
+        assert_eq!(err, Some(format!("orchestrate run {run_id} not found")));
+    }
+}
@@ -13,16 +13,19 @@ use tokio::process::ChildStdout;
 use anyhow::Context;
 use assert_cmd::prelude::*;
 use codex_app_server_protocol::AddConversationListenerParams;
+use codex_app_server_protocol::ApplyOrchestrateCandidateParams;
 use codex_app_server_protocol::ArchiveConversationParams;
 use codex_app_server_protocol::CancelLoginChatGptParams;
 use codex_app_server_protocol::ClientInfo;
 use codex_app_server_protocol::ClientNotification;
+use codex_app_server_protocol::DiscardOrchestrateRunParams;
 use codex_app_server_protocol::GetAuthStatusParams;
 use codex_app_server_protocol::InitializeParams;
 use codex_app_server_protocol::InterruptConversationParams;
 use codex_app_server_protocol::ListConversationsParams;
 use codex_app_server_protocol::LoginApiKeyParams;
 use codex_app_server_protocol::NewConversationParams;
+use codex_app_server_protocol::OrchestrateStreamingPatchParams;
 use codex_app_server_protocol::RemoveConversationListenerParams;
 use codex_app_server_protocol::ResumeConversationParams;
 use codex_app_server_protocol::SendUserMessageParams;
@@ -313,6 +316,33 @@ impl McpProcess {
         self.send_request("fuzzyFileSearch", Some(params)).await
     }
 
+    /// Send an `orchestrateStreamingPatch` JSON-RPC request.
+    pub async fn send_orchestrate_streaming_patch_request(
+        &mut self,
+        params: OrchestrateStreamingPatchParams,
+    ) -> anyhow::Result<i64> {
+        let params = Some(serde_json::to_value(params)?);
+        self.send_request("orchestrateStreamingPatch", params).await
+    }
+
+    /// Send an `applyOrchestrateCandidate` JSON-RPC request.
+    pub async fn send_apply_orchestrate_candidate_request(
+        &mut self,
+        params: ApplyOrchestrateCandidateParams,
+    ) -> anyhow::Result<i64> {
+        let params = Some(serde_json::to_value(params)?);
+        self.send_request("applyOrchestrateCandidate", params).await
+    }
+
+    /// Send a `discardOrchestrateRun` JSON-RPC request.
+    pub async fn send_discard_orchestrate_run_request(
+        &mut self,
+        params: DiscardOrchestrateRunParams,
+    ) -> anyhow::Result<i64> {
+        let params = Some(serde_json::to_value(params)?);
+        self.send_request("discardOrchestrateRun", params).await
+    }
+
     async fn send_request(
         &mut self,
         method: &str,
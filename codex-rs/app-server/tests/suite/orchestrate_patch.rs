@@ -0,0 +1,236 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Context;
+use anyhow::Result;
+use app_test_support::McpProcess;
+use app_test_support::create_apply_patch_sse_response;
+use app_test_support::create_final_assistant_message_sse_response;
+use app_test_support::create_mock_chat_completions_server;
+use app_test_support::to_response;
+use codex_app_server_protocol::ApplyOrchestrateCandidateParams;
+use codex_app_server_protocol::ApplyOrchestrateCandidateResponse;
+use codex_app_server_protocol::DiscardOrchestrateRunParams;
+use codex_app_server_protocol::DiscardOrchestrateRunResponse;
+use codex_app_server_protocol::JSONRPCNotification;
+use codex_app_server_protocol::JSONRPCResponse;
+use codex_app_server_protocol::OrchestratePatchCandidateNotification;
+use codex_app_server_protocol::OrchestratePatchCompletedNotification;
+use codex_app_server_protocol::OrchestrateStreamingPatchParams;
+use codex_app_server_protocol::OrchestrateStreamingPatchResponse;
+use codex_app_server_protocol::RequestId;
+use codex_core::spawn::CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+use tokio::time::timeout;
+
+const DEFAULT_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_orchestrate_streaming_patch_scores_applies_and_discards_candidate() -> Result<()> {
+    if env::var(CODEX_SANDBOX_NETWORK_DISABLED_ENV_VAR).is_ok() {
+        println!(
+            "Skipping test because it cannot execute when network is disabled in a Codex sandbox."
+        );
+        return Ok(());
+    }
+
+    let codex_home = TempDir::new().context("create temp codex home")?;
+    let repo = TempDir::new().context("create temp repo")?;
+    std::fs::write(repo.path().join("README.md"), "Hello Codex\n")?;
+    git(repo.path(), &["init", "--quiet"])?;
+    git(repo.path(), &["add", "README.md"])?;
+    git(
+        repo.path(),
+        &[
+            "-c",
+            "user.name=Codex",
+            "-c",
+            "user.email=codex@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "initial",
+        ],
+    )?;
+
+    let patch = "*** Begin Patch\n*** Update File: README.md\n@@\n-Hello Codex\n+Hello Codex!\n*** End Patch";
+    let responses = vec![
+        create_apply_patch_sse_response(patch, "call1")?,
+        create_final_assistant_message_sse_response("Added an exclamation mark.")?,
+    ];
+    let server = create_mock_chat_completions_server(responses).await;
+    create_config_toml(codex_home.path(), &server.uri())?;
+
+    let mut mcp = McpProcess::new(codex_home.path())
+        .await
+        .context("spawn mcp")?;
+    timeout(DEFAULT_READ_TIMEOUT, mcp.initialize())
+        .await
+        .context("init timeout")?
+        .context("init failed")?;
+
+    let request_id = mcp
+        .send_orchestrate_streaming_patch_request(OrchestrateStreamingPatchParams {
+            prompt: "Be more enthusiastic in the README".to_string(),
+            cwd: Some(repo.path().to_path_buf()),
+            max_candidates: Some(1),
+            check_commands: Some(vec![vec![
+                "grep".to_string(),
+                "-q".to_string(),
+                "Codex!".to_string(),
+                "README.md".to_string(),
+            ]]),
+            review: None,
+        })
+        .await
+        .context("send orchestrateStreamingPatch")?;
+    let response: JSONRPCResponse = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_response_message(RequestId::Integer(request_id)),
+    )
+    .await
+    .context("orchestrateStreamingPatch timeout")?
+    .context("orchestrateStreamingPatch response")?;
+    let OrchestrateStreamingPatchResponse { run_id } = to_response(response)?;
+
+    let candidate: JSONRPCNotification = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_notification_message("orchestratePatchCandidate"),
+    )
+    .await
+    .context("orchestratePatchCandidate timeout")?
+    .context("orchestratePatchCandidate notification")?;
+    let candidate: OrchestratePatchCandidateNotification =
+        serde_json::from_value(candidate.params.context("candidate params")?)?;
+    assert_eq!(candidate.run_id, run_id);
+    assert_eq!(candidate.candidate_id, 1);
+    assert!(
+        candidate
+            .patch
+            .contains("--- a/README.md\n+++ b/README.md\n"),
+        "unexpected patch: {}",
+        candidate.patch
+    );
+    assert!(candidate.patch.contains("-Hello Codex\n+Hello Codex!\n"));
+    let score = candidate.score;
+    assert!(score.applies_cleanly);
+    assert_eq!(
+        (score.files_changed, score.lines_added, score.lines_removed),
+        (1, 1, 1)
+    );
+    assert_eq!(score.checks.len(), 1);
+    assert_eq!(score.checks[0].exit_code, 0);
+    assert_eq!(score.review, None);
+    assert!(score.score > 0.9, "unexpected score: {}", score.score);
+
+    let completed: JSONRPCNotification = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_notification_message("orchestratePatchCompleted"),
+    )
+    .await
+    .context("orchestratePatchCompleted timeout")?
+    .context("orchestratePatchCompleted notification")?;
+    let completed: OrchestratePatchCompletedNotification =
+        serde_json::from_value(completed.params.context("completed params")?)?;
+    assert_eq!(
+        completed,
+        OrchestratePatchCompletedNotification {
+            run_id,
+            success: true,
+            error: None,
+        }
+    );
+
+    // The candidate worked in its own worktree, which is gone again.
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "Hello Codex\n"
+    );
+    let worktrees = git(repo.path(), &["worktree", "list", "--porcelain"])?;
+    assert_eq!(worktrees.matches("worktree ").count(), 1, "{worktrees}");
+
+    let apply_id = mcp
+        .send_apply_orchestrate_candidate_request(ApplyOrchestrateCandidateParams {
+            run_id,
+            candidate_id: 1,
+        })
+        .await
+        .context("send applyOrchestrateCandidate")?;
+    let response: JSONRPCResponse = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_response_message(RequestId::Integer(apply_id)),
+    )
+    .await
+    .context("applyOrchestrateCandidate timeout")?
+    .context("applyOrchestrateCandidate response")?;
+    let applied: ApplyOrchestrateCandidateResponse = to_response(response)?;
+    assert!(applied.success, "{applied:?}");
+    assert_eq!(
+        std::fs::read_to_string(repo.path().join("README.md"))?,
+        "Hello Codex!\n"
+    );
+
+    let discard_id = mcp
+        .send_discard_orchestrate_run_request(DiscardOrchestrateRunParams { run_id })
+        .await
+        .context("send discardOrchestrateRun")?;
+    let response: JSONRPCResponse = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_response_message(RequestId::Integer(discard_id)),
+    )
+    .await
+    .context("discardOrchestrateRun timeout")?
+    .context("discardOrchestrateRun response")?;
+    let DiscardOrchestrateRunResponse {} = to_response(response)?;
+
+    // The discarded run's candidates can no longer be applied.
+    let apply_id = mcp
+        .send_apply_orchestrate_candidate_request(ApplyOrchestrateCandidateParams {
+            run_id,
+            candidate_id: 1,
+        })
+        .await
+        .context("send applyOrchestrateCandidate")?;
+    let error = timeout(
+        DEFAULT_READ_TIMEOUT,
+        mcp.read_stream_until_error_message(RequestId::Integer(apply_id)),
+    )
+    .await
+    .context("applyOrchestrateCandidate timeout")?
+    .context("applyOrchestrateCandidate error")?;
+    assert_eq!(
+        error.error.message,
+        format!("orchestrate run {run_id} not found")
+    );
+    Ok(())
+}
+
+fn git(cwd: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(cwd).output()?;
+    anyhow::ensure!(output.status.success(), "git {args:?} failed: {output:?}");
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn create_config_toml(codex_home: &Path, server_uri: &str) -> std::io::Result<()> {
+    let config_toml = codex_home.join("config.toml");
+    std::fs::write(
+        config_toml,
+        format!(
+            r#"
+model = "mock-model"
+approval_policy = "never"
+
+model_provider = "mock_provider"
+
+[model_providers.mock_provider]
+name = "Mock provider for test"
+base_url = "{server_uri}/v1"
+wire_api = "chat"
+request_max_retries = 0
+stream_max_retries = 0
+"#
+        ),
+    )
+}
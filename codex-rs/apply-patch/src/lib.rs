@@ -13,7 +13,7 @@ use anyhow::Result;
 pub use parser::Hunk;
 pub use parser::ParseError;
 use parser::ParseError::*;
-use parser::UpdateFileChunk;
+pub use parser::UpdateFileChunk;
 pub use parser::parse_patch;
 use similar::TextDiff;
 use thiserror::Error;
@@ -647,7 +647,21 @@ fn derive_new_contents_from_chunks(
             }));
         }
     };
+    let new_contents = apply_chunks_to_contents(path, &original_contents, chunks)?;
+    Ok(AppliedPatch {
+        original_contents,
+        new_contents,
+    })
+}
 
+/// Apply the update `chunks` to `original_contents`, the contents of the file
+/// at `path`, without touching the filesystem. `path` is only used in error
+/// messages.
+pub fn apply_chunks_to_contents(
+    path: &Path,
+    original_contents: &str,
+    chunks: &[UpdateFileChunk],
+) -> std::result::Result<String, ApplyPatchError> {
     let mut original_lines: Vec<String> = original_contents.split('\n').map(String::from).collect();
 
     // Drop the trailing empty element that results from the final newline so
@@ -662,11 +676,7 @@ fn derive_new_contents_from_chunks(
     if !new_lines.last().is_some_and(String::is_empty) {
         new_lines.push(String::new());
     }
-    let new_contents = new_lines.join("\n");
-    Ok(AppliedPatch {
-        original_contents,
-        new_contents,
-    })
+    Ok(new_lines.join("\n"))
 }
 
 /// Compute a list of replacements needed to transform `original_lines` into the
@@ -0,0 +1,336 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::anyhow;
+use anyhow::bail;
+use codex_common::CliConfigOverrides;
+use codex_core::config::Config;
+use codex_core::config::ConfigOverrides;
+use codex_core::exec_policy::ExecPolicy;
+use codex_core::exec_policy::ExecPolicyMatch;
+use codex_execpolicy::ArgType;
+use codex_execpolicy::Error as PolicyError;
+use codex_execpolicy::MatchedExec;
+use codex_execpolicy::NegativeExamplePassedCheck;
+use codex_execpolicy::PolicyParser;
+use codex_execpolicy::PositiveExampleFailedCheck;
+use codex_execpolicy::ValidExec;
+
+/// [experimental] Explain and test execpolicy `.policy` files.
+///
+/// Subcommands:
+/// - `check` — show how the configured policies classify a command
+/// - `test`  — verify the `should_match`/`should_not_match` examples of policy files
+#[derive(Debug, clap::Parser)]
+pub struct ExecPolicyCli {
+    #[clap(flatten)]
+    pub config_overrides: CliConfigOverrides,
+
+    #[command(subcommand)]
+    pub subcommand: ExecPolicySubcommand,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum ExecPolicySubcommand {
+    /// [experimental] Show the matching rules, argument classification and
+    /// verdict for a command.
+    Check(CheckArgs),
+
+    /// [experimental] Run the `should_match`/`should_not_match` examples of
+    /// policy files; exits non-zero if any fail.
+    Test(TestArgs),
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct CheckArgs {
+    /// Check against this `.policy` file (plus the bundled default) instead of
+    /// the configured ones. May be repeated.
+    #[arg(long = "policy", value_name = "FILE")]
+    pub policies: Vec<PathBuf>,
+
+    /// Output the result as JSON.
+    #[arg(long)]
+    pub json: bool,
+
+    /// Command to check, as it would be passed to execv(3).
+    #[arg(trailing_var_arg = true, num_args = 1.., required = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(Debug, clap::Parser)]
+pub struct TestArgs {
+    /// Policy files whose examples should be verified.
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+impl ExecPolicyCli {
+    pub async fn run(self) -> Result<()> {
+        let ExecPolicyCli {
+            config_overrides,
+            subcommand,
+        } = self;
+
+        match subcommand {
+            ExecPolicySubcommand::Check(args) => {
+                run_check(&config_overrides, args).await?;
+            }
+            ExecPolicySubcommand::Test(args) => {
+                run_test(args)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn run_check(config_overrides: &CliConfigOverrides, check_args: CheckArgs) -> Result<()> {
+    let CheckArgs {
+        policies,
+        json,
+        command,
+    } = check_args;
+
+    let (policy, errors) = if policies.is_empty() {
+        let overrides = config_overrides.parse_overrides().map_err(|e| anyhow!(e))?;
+        let config = Config::load_with_cli_overrides(overrides, ConfigOverrides::default())
+            .await
+            .context("failed to load configuration")?;
+        ExecPolicy::from_config(&config)
+    } else {
+        ExecPolicy::load(&policies)
+    };
+    if !errors.is_empty() {
+        bail!(errors.join("\n"));
+    }
+
+    let explanations = policy.explain(&command);
+    let verdict = policy.check(&command);
+
+    if json {
+        let commands: Vec<_> = explanations
+            .iter()
+            .map(|explanation| {
+                let results: Vec<_> = explanation
+                    .outcomes
+                    .iter()
+                    .map(|(source, result)| {
+                        let mut value = match result {
+                            Ok(MatchedExec::Match { exec }) => serde_json::json!({
+                                "result": if exec.might_write_files() { "match" } else { "safe" },
+                                "match": exec,
+                            }),
+                            Ok(MatchedExec::Forbidden { cause, reason }) => serde_json::json!({
+                                "result": "forbidden",
+                                "reason": reason,
+                                "cause": cause,
+                            }),
+                            Err(error) => serde_json::json!({
+                                "result": "unverified",
+                                "error": error,
+                            }),
+                        };
+                        value["source"] = serde_json::json!(source);
+                        value
+                    })
+                    .collect();
+                serde_json::json!({
+                    "command": explanation.command,
+                    "results": results,
+                })
+            })
+            .collect();
+        let (verdict_name, reason) = match &verdict {
+            ExecPolicyMatch::Safe => ("safe", None),
+            ExecPolicyMatch::WritesFiles { reason } => ("writes_files", Some(reason)),
+            ExecPolicyMatch::Forbidden { reason } => ("forbidden", Some(reason)),
+            ExecPolicyMatch::Unmatched => ("unmatched", None),
+        };
+        let output = serde_json::json!({
+            "policies": policy.sources().collect::<Vec<_>>(),
+            "commands": commands,
+            "verdict": verdict_name,
+            "reason": reason,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for explanation in &explanations {
+        println!("{}", explanation.command.join(" "));
+        for (source, result) in &explanation.outcomes {
+            match result {
+                Ok(MatchedExec::Match { exec }) => {
+                    println!("  {source}: matched `{}`", exec.program);
+                    for line in describe_exec(exec) {
+                        println!("    {line}");
+                    }
+                }
+                Ok(MatchedExec::Forbidden { reason, .. }) => {
+                    println!("  {source}: forbidden: {reason}");
+                }
+                Err(PolicyError::NoSpecForProgram { program }) => {
+                    println!("  {source}: no rule for `{program}`");
+                }
+                Err(error) => {
+                    println!("  {source}: no rule matched: {}", describe_error(error));
+                }
+            }
+        }
+    }
+
+    let verdict = match verdict {
+        ExecPolicyMatch::Safe => {
+            "safe; runs without prompting inside the platform sandbox, treated as unmatched without one"
+                .to_string()
+        }
+        ExecPolicyMatch::WritesFiles { reason } => {
+            format!("may write files; approval_policy and sandbox_mode decide ({reason})")
+        }
+        ExecPolicyMatch::Forbidden { reason } => format!("forbidden; always rejected ({reason})"),
+        ExecPolicyMatch::Unmatched => "unmatched; the built-in approval rules apply".to_string(),
+    };
+    println!("Verdict: {verdict}");
+
+    Ok(())
+}
+
+fn run_test(test_args: TestArgs) -> Result<()> {
+    let mut failures = 0;
+    for file in &test_args.files {
+        let source = std::fs::read_to_string(file)
+            .with_context(|| format!("failed to read {}", file.display()))?;
+        let policy = PolicyParser::new(&file.to_string_lossy(), &source)
+            .parse()
+            .map_err(|err| anyhow!("failed to parse {}: {err}", file.display()))?;
+
+        for PositiveExampleFailedCheck {
+            program,
+            args,
+            error,
+        } in policy.check_each_good_list_individually()
+        {
+            failures += 1;
+            println!(
+                "{}: should_match example `{}` was rejected: {}",
+                file.display(),
+                display_example(&program, &args),
+                describe_error(&error)
+            );
+        }
+        for NegativeExamplePassedCheck { program, args } in
+            policy.check_each_bad_list_individually()
+        {
+            failures += 1;
+            println!(
+                "{}: should_not_match example `{}` was accepted",
+                file.display(),
+                display_example(&program, &args)
+            );
+        }
+    }
+
+    if failures > 0 {
+        bail!("{failures} policy example(s) failed");
+    }
+    println!("All should_match and should_not_match examples passed.");
+    Ok(())
+}
+
+/// Lists the flags, options and positional arguments of a matched command,
+/// grouped by how the rule classified them.
+fn describe_exec(exec: &ValidExec) -> Vec<String> {
+    let mut readable = Vec::new();
+    let mut writable = Vec::new();
+    let mut other = Vec::new();
+    let values = exec
+        .args
+        .iter()
+        .map(|arg| (&arg.r#type, arg.value.clone()))
+        .chain(
+            exec.opts
+                .iter()
+                .map(|opt| (&opt.r#type, format!("{} {}", opt.name(), opt.value))),
+        );
+    for (arg_type, value) in values {
+        match arg_type {
+            ArgType::ReadableFile => readable.push(value),
+            ArgType::WriteableFile => writable.push(value),
+            ArgType::Unknown => writable.push(format!("{value} (unknown)")),
+            ArgType::Literal(_)
+            | ArgType::OpaqueNonFile
+            | ArgType::PositiveInteger
+            | ArgType::SedCommand => other.push(value),
+        }
+    }
+
+    let mut lines = Vec::new();
+    if !exec.flags.is_empty() {
+        let flags: Vec<&str> = exec.flags.iter().map(|flag| flag.name.as_str()).collect();
+        lines.push(format!("flags: {}", flags.join(" ")));
+    }
+    for (label, values) in [
+        ("readable files", readable),
+        ("writable files", writable),
+        ("other args", other),
+    ] {
+        if !values.is_empty() {
+            lines.push(format!("{label}: {}", values.join(", ")));
+        }
+    }
+    lines
+}
+
+fn describe_error(error: &PolicyError) -> String {
+    serde_json::to_string(error).unwrap_or_else(|_| format!("{error:?}"))
+}
+
+fn display_example(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_test_returns_error_for_failing_examples() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let good = dir.path().join("good.policy");
+        std::fs::write(
+            &good,
+            r#"
+define_program(
+    program="make",
+    args=["lint"],
+    should_match=[["lint"]],
+)
+"#,
+        )?;
+        let bad = dir.path().join("bad.policy");
+        std::fs::write(
+            &bad,
+            r#"
+define_program(
+    program="make",
+    args=[ARG_OPAQUE_VALUE],
+    should_not_match=[["install"]],
+)
+"#,
+        )?;
+
+        run_test(TestArgs {
+            files: vec![good.clone()],
+        })?;
+        let err = run_test(TestArgs {
+            files: vec![good, bad],
+        })
+        .expect_err("bad.policy has a failing example");
+        assert_eq!(err.to_string(), "1 policy example(s) failed");
+        Ok(())
+    }
+}
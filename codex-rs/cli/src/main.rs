@@ -23,8 +23,10 @@ use owo_colors::OwoColorize;
 use std::path::PathBuf;
 use supports_color::Stream;
 
+mod execpolicy_cmd;
 mod mcp_cmd;
 
+use crate::execpolicy_cmd::ExecPolicyCli;
 use crate::mcp_cmd::McpCli;
 
 /// Codex CLI
@@ -80,6 +82,9 @@ enum Subcommand {
     #[clap(visible_alias = "debug")]
     Sandbox(SandboxArgs),
 
+    /// [experimental] Check commands against exec policies and test `.policy` files.
+    Execpolicy(ExecPolicyCli),
+
     /// Apply the latest diff produced by Codex agent as a `git apply` to your local working tree.
     #[clap(visible_alias = "a")]
     Apply(ApplyCommand),
@@ -279,6 +284,13 @@ async fn cli_main(codex_linux_sandbox_exe: Option<PathBuf>) -> anyhow::Result<()
             prepend_config_flags(&mut mcp_cli.config_overrides, root_config_overrides.clone());
             mcp_cli.run().await?;
         }
+        Some(Subcommand::Execpolicy(mut execpolicy_cli)) => {
+            prepend_config_flags(
+                &mut execpolicy_cli.config_overrides,
+                root_config_overrides.clone(),
+            );
+            execpolicy_cli.run().await?;
+        }
         Some(Subcommand::AppServer) => {
             codex_app_server::run_main(codex_linux_sandbox_exe, root_config_overrides).await?;
         }
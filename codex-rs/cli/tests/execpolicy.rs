@@ -0,0 +1,142 @@
+use std::path::Path;
+
+use anyhow::Result;
+use predicates::str::contains;
+use pretty_assertions::assert_eq;
+use serde_json::Value as JsonValue;
+use tempfile::TempDir;
+
+fn codex_command(codex_home: &Path) -> Result<assert_cmd::Command> {
+    let mut cmd = assert_cmd::Command::cargo_bin("codex")?;
+    cmd.env("CODEX_HOME", codex_home);
+    Ok(cmd)
+}
+
+#[test]
+fn check_explains_default_policy_verdict() -> Result<()> {
+    let codex_home = TempDir::new()?;
+
+    let mut cmd = codex_command(codex_home.path())?;
+    let output = cmd
+        .args(["execpolicy", "check", "--", "cp", "a.txt", "b.txt"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("#default: matched `cp`"), "{stdout}");
+    assert!(stdout.contains("readable files: a.txt"), "{stdout}");
+    assert!(stdout.contains("writable files: b.txt"), "{stdout}");
+    assert!(
+        stdout.contains("Verdict: may write files; approval_policy and sandbox_mode decide"),
+        "{stdout}"
+    );
+
+    let mut cmd = codex_command(codex_home.path())?;
+    cmd.args(["execpolicy", "check", "--", "ls", "-l"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "Verdict: safe; runs without prompting inside the platform sandbox",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn check_with_policy_file_reports_forbidden_as_json() -> Result<()> {
+    let codex_home = TempDir::new()?;
+    let policy = codex_home.path().join("user.policy");
+    std::fs::write(
+        &policy,
+        r#"
+define_program(
+    program="rm",
+    args=[ARG_WFILE],
+    forbidden="use git clean instead",
+)
+"#,
+    )?;
+
+    let mut cmd = codex_command(codex_home.path())?;
+    let output = cmd
+        .arg("execpolicy")
+        .arg("check")
+        .arg("--json")
+        .arg("--policy")
+        .arg(&policy)
+        .args(["--", "bash", "-lc", "ls && rm build"])
+        .output()?;
+    assert!(output.status.success());
+    let parsed: JsonValue = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(parsed["verdict"], "forbidden");
+    assert_eq!(parsed["reason"], "use git clean instead");
+    assert_eq!(parsed["commands"].as_array().map(Vec::len), Some(2));
+    let rm_results = &parsed["commands"][1]["results"];
+    assert_eq!(rm_results[0]["source"], "#default");
+    assert_eq!(rm_results[0]["result"], "unverified");
+    assert_eq!(rm_results[1]["source"], policy.display().to_string());
+    assert_eq!(rm_results[1]["result"], "forbidden");
+
+    Ok(())
+}
+
+#[test]
+fn test_reports_failing_examples() -> Result<()> {
+    let codex_home = TempDir::new()?;
+    let good = codex_home.path().join("good.policy");
+    std::fs::write(
+        &good,
+        r#"
+define_program(
+    program="make",
+    args=["lint"],
+    should_match=[["lint"]],
+    should_not_match=[["install"]],
+)
+"#,
+    )?;
+    let bad = codex_home.path().join("bad.policy");
+    std::fs::write(
+        &bad,
+        r#"
+define_program(
+    program="make",
+    args=[ARG_OPAQUE_VALUE],
+    should_match=[["lint", "test"]],
+    should_not_match=[["install"]],
+)
+"#,
+    )?;
+
+    let mut cmd = codex_command(codex_home.path())?;
+    cmd.arg("execpolicy")
+        .arg("test")
+        .arg(&good)
+        .assert()
+        .success()
+        .stdout(contains(
+            "All should_match and should_not_match examples passed.",
+        ));
+
+    let mut cmd = codex_command(codex_home.path())?;
+    let output = cmd
+        .arg("execpolicy")
+        .arg("test")
+        .arg(&good)
+        .arg(&bad)
+        .output()?;
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.contains("should_match example `make lint test` was rejected"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("should_not_match example `make install` was accepted"),
+        "{stdout}"
+    );
+    assert!(!stdout.contains(&good.display().to_string()), "{stdout}");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("2 policy example(s) failed"), "{stderr}");
+
+    Ok(())
+}
@@ -52,6 +52,7 @@ use crate::exec::StreamOutput;
 use crate::exec_command::ExecCommandParams;
 use crate::exec_command::ExecSessionManager;
 use crate::exec_command::WriteStdinParams;
+use crate::exec_policy::ExecPolicy;
 use crate::executor::Executor;
 use crate::executor::ExecutorConfig;
 use crate::executor::normalize_exec_result;
@@ -404,6 +405,17 @@ impl Session {
             }
         }
 
+        // A broken policy file should not keep the session from starting;
+        // report it and fall back to the policies that did load.
+        let (exec_policy, exec_policy_errors) = ExecPolicy::from_config(&config);
+        for message in exec_policy_errors {
+            error!("{message}");
+            post_session_configured_error_events.push(Event {
+                id: INITIAL_SUBMIT_ID.to_owned(),
+                msg: EventMsg::Error(ErrorEvent { message }),
+            });
+        }
+
         let otel_event_manager = OtelEventManager::new(
             conversation_id,
             config.model.as_str(),
@@ -470,6 +482,7 @@ impl Session {
                 turn_context.sandbox_policy.clone(),
                 turn_context.cwd.clone(),
                 config.codex_linux_sandbox_exe.clone(),
+                Arc::new(exec_policy),
             )),
         };
 
@@ -2771,6 +2784,7 @@ mod tests {
                 turn_context.sandbox_policy.clone(),
                 turn_context.cwd.clone(),
                 None,
+                Default::default(),
             )),
         };
         let session = Session {
@@ -2844,6 +2858,7 @@ mod tests {
                 config.sandbox_policy.clone(),
                 config.cwd.clone(),
                 None,
+                Default::default(),
             )),
         };
         let session = Arc::new(Session {
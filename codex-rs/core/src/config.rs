@@ -60,6 +60,9 @@ pub(crate) const PROJECT_DOC_MAX_BYTES: usize = 32 * 1024; // 32 KiB
 
 pub(crate) const CONFIG_TOML_FILE: &str = "config.toml";
 
+/// Project-local exec policy, relative to the repository root.
+const PROJECT_EXEC_POLICY_FILE: &str = ".codex/exec.policy";
+
 /// Application configuration loaded from disk and merged with overrides.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Config {
@@ -93,6 +96,17 @@ pub struct Config {
 
     pub shell_environment_policy: ShellEnvironmentPolicy,
 
+    /// Additional `codex-execpolicy` `.policy` files consulted, after the
+    /// bundled `default.policy`, when deciding whether a shell command can run
+    /// without approval. Relative paths are resolved against `cwd`.
+    pub exec_policy_files: Vec<PathBuf>,
+
+    /// The project's own `.codex/exec.policy`, consulted after
+    /// `exec_policy_files`. Only set when the project is trusted via
+    /// `[projects."/path"] trust_level`, so an untrusted repository cannot
+    /// approve its own commands.
+    pub project_exec_policy: Option<PathBuf>,
+
     /// When `true`, `AgentReasoning` events emitted by the backend will be
     /// suppressed from the frontend output. This can reduce visual noise when
     /// users are only interested in the final agent responses.
@@ -683,6 +697,11 @@ pub struct ConfigToml {
     /// Sandbox configuration to apply if `sandbox` is `WorkspaceWrite`.
     pub sandbox_workspace_write: Option<SandboxWorkspaceWrite>,
 
+    /// Additional `.policy` files used to auto-approve, require approval for,
+    /// or forbid shell commands.
+    #[serde(default)]
+    pub exec_policy_files: Vec<PathBuf>,
+
     /// Optional external command to spawn for end-user notifications.
     #[serde(default)]
     pub notify: Option<Vec<String>>,
@@ -816,6 +835,49 @@ impl From<ToolsToml> for Tools {
     }
 }
 
+/// Whether `resolved_cwd`, or the root git project it belongs to, is marked
+/// `trust_level = "trusted"` in `projects`.
+fn is_cwd_trusted_in(
+    projects: Option<&HashMap<String, ProjectConfig>>,
+    resolved_cwd: &Path,
+) -> bool {
+    let Some(projects) = projects else {
+        return false;
+    };
+    let is_path_trusted = |path: &Path| {
+        let path_str = path.to_string_lossy().to_string();
+        projects
+            .get(&path_str)
+            .map(|p| p.trust_level.as_deref() == Some("trusted"))
+            .unwrap_or(false)
+    };
+
+    // Fast path: exact cwd match
+    if is_path_trusted(resolved_cwd) {
+        return true;
+    }
+
+    // If cwd lives inside a git worktree, check whether the root git project
+    // (the primary repository working directory) is trusted. This lets
+    // worktrees inherit trust from the main project.
+    if let Some(root_project) = resolve_root_git_project_for_trust(resolved_cwd) {
+        return is_path_trusted(&root_project);
+    }
+
+    false
+}
+
+/// Locates `.codex/exec.policy` at the root of the git repository containing
+/// `cwd` (or in `cwd` itself outside a repository).
+fn find_project_exec_policy(cwd: &Path) -> Option<PathBuf> {
+    let root = cwd
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd);
+    let path = root.join(PROJECT_EXEC_POLICY_FILE);
+    path.is_file().then_some(path)
+}
+
 impl ConfigToml {
     /// Derive the effective sandbox policy from the configuration.
     fn derive_sandbox_policy(&self, sandbox_mode_override: Option<SandboxMode>) -> SandboxPolicy {
@@ -843,29 +905,7 @@ impl ConfigToml {
     }
 
     pub fn is_cwd_trusted(&self, resolved_cwd: &Path) -> bool {
-        let projects = self.projects.clone().unwrap_or_default();
-
-        let is_path_trusted = |path: &Path| {
-            let path_str = path.to_string_lossy().to_string();
-            projects
-                .get(&path_str)
-                .map(|p| p.trust_level.as_deref() == Some("trusted"))
-                .unwrap_or(false)
-        };
-
-        // Fast path: exact cwd match
-        if is_path_trusted(resolved_cwd) {
-            return true;
-        }
-
-        // If cwd lives inside a git worktree, check whether the root git project
-        // (the primary repository working directory) is trusted. This lets
-        // worktrees inherit trust from the main project.
-        if let Some(root_project) = resolve_root_git_project_for_trust(resolved_cwd) {
-            return is_path_trusted(&root_project);
-        }
-
-        false
+        is_cwd_trusted_in(self.projects.as_ref(), resolved_cwd)
     }
 
     pub fn get_config_profile(
@@ -1054,6 +1094,24 @@ impl Config {
             .or(cfg.review_model)
             .unwrap_or_else(default_review_model);
 
+        // Resolve relative policy paths against the effective cwd, like
+        // `experimental_instructions_file` above.
+        let exec_policy_files = cfg
+            .exec_policy_files
+            .iter()
+            .map(|path| resolved_cwd.join(path))
+            .collect();
+        let project_exec_policy = find_project_exec_policy(&resolved_cwd).filter(|path| {
+            let trusted = is_cwd_trusted_in(cfg.projects.as_ref(), &resolved_cwd);
+            if !trusted {
+                tracing::info!(
+                    "ignoring {} because the project is not trusted",
+                    path.display()
+                );
+            }
+            trusted
+        });
+
         let config = Self {
             model,
             review_model,
@@ -1070,6 +1128,8 @@ impl Config {
                 .unwrap_or_else(AskForApproval::default),
             sandbox_policy,
             shell_environment_policy,
+            exec_policy_files,
+            project_exec_policy,
             notify: cfg.notify,
             user_instructions,
             base_instructions,
@@ -1892,6 +1952,8 @@ model_verbosity = "high"
                 approval_policy: AskForApproval::Never,
                 sandbox_policy: SandboxPolicy::new_read_only_policy(),
                 shell_environment_policy: ShellEnvironmentPolicy::default(),
+                exec_policy_files: Vec::new(),
+                project_exec_policy: None,
                 user_instructions: None,
                 notify: None,
                 cwd: fixture.cwd(),
@@ -1954,6 +2016,8 @@ model_verbosity = "high"
             approval_policy: AskForApproval::UnlessTrusted,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            exec_policy_files: Vec::new(),
+            project_exec_policy: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -2031,6 +2095,8 @@ model_verbosity = "high"
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            exec_policy_files: Vec::new(),
+            project_exec_policy: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -2094,6 +2160,8 @@ model_verbosity = "high"
             approval_policy: AskForApproval::OnFailure,
             sandbox_policy: SandboxPolicy::new_read_only_policy(),
             shell_environment_policy: ShellEnvironmentPolicy::default(),
+            exec_policy_files: Vec::new(),
+            project_exec_policy: None,
             user_instructions: None,
             notify: None,
             cwd: fixture.cwd(),
@@ -2226,6 +2294,50 @@ trust_level = "trusted"
 
         Ok(())
     }
+
+    #[test]
+    fn project_exec_policy_requires_trusted_project() -> anyhow::Result<()> {
+        let codex_home = TempDir::new()?;
+        let project = TempDir::new()?;
+        let project_root = std::fs::canonicalize(project.path())?;
+        let status = std::process::Command::new("git")
+            .args(["init", "--quiet"])
+            .current_dir(&project_root)
+            .status()?;
+        assert!(status.success(), "git init failed");
+        std::fs::create_dir_all(project_root.join(".codex"))?;
+        let policy_path = project_root.join(".codex").join("exec.policy");
+        std::fs::write(&policy_path, "")?;
+        let cwd = project_root.join("src");
+        std::fs::create_dir_all(&cwd)?;
+
+        let load = |cfg: ConfigToml| {
+            Config::load_from_base_config_with_overrides(
+                cfg,
+                ConfigOverrides {
+                    cwd: Some(cwd.clone()),
+                    ..Default::default()
+                },
+                codex_home.path().to_path_buf(),
+            )
+        };
+
+        let untrusted = load(ConfigToml::default())?;
+        assert_eq!(untrusted.project_exec_policy, None);
+
+        let trusted = load(ConfigToml {
+            projects: Some(HashMap::from([(
+                project_root.to_string_lossy().to_string(),
+                ProjectConfig {
+                    trust_level: Some("trusted".to_string()),
+                },
+            )])),
+            ..Default::default()
+        })?;
+        assert_eq!(trusted.project_exec_policy, Some(policy_path));
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,388 @@
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use codex_execpolicy::ArgType;
+use codex_execpolicy::Error as PolicyError;
+use codex_execpolicy::ExecCall;
+use codex_execpolicy::MatchedExec;
+use codex_execpolicy::Policy;
+use codex_execpolicy::PolicyParser;
+use codex_execpolicy::Result as PolicyResult;
+use codex_execpolicy::ValidExec;
+use codex_execpolicy::get_default_policy;
+
+use crate::bash::parse_bash_lc_plain_commands;
+use crate::config::Config;
+
+/// Source name reported for the bundled `default.policy`.
+const DEFAULT_POLICY_SOURCE: &str = "#default";
+
+/// `.policy` files consulted before a shell command is run: the bundled
+/// `default.policy`, every file listed in `exec_policy_files`, and finally the
+/// trusted project's `.codex/exec.policy`.
+#[derive(Default)]
+pub struct ExecPolicy {
+    /// Each policy paired with where it came from: `#default` or the path of
+    /// the `.policy` file.
+    policies: Vec<(String, Policy)>,
+}
+
+/// How the loaded policies classify a proposed command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecPolicyMatch {
+    /// Every command matched a rule and none of them can write files.
+    Safe,
+    /// Every command matched a rule, but at least one may write files.
+    WritesFiles { reason: String },
+    /// A rule forbids the command outright.
+    Forbidden { reason: String },
+    /// No policy has a rule for (some part of) the command.
+    Unmatched,
+}
+
+impl ExecPolicyMatch {
+    /// Explanation worth surfacing alongside an approval prompt.
+    pub(crate) fn reason(&self) -> Option<&str> {
+        match self {
+            ExecPolicyMatch::WritesFiles { reason } | ExecPolicyMatch::Forbidden { reason } => {
+                Some(reason)
+            }
+            ExecPolicyMatch::Safe | ExecPolicyMatch::Unmatched => None,
+        }
+    }
+}
+
+/// How every loaded policy classified one command of an invocation.
+#[derive(Debug)]
+pub struct CommandExplanation<'a> {
+    pub command: Vec<String>,
+    /// One entry per policy, in the order they are consulted.
+    pub outcomes: Vec<(&'a str, PolicyResult<MatchedExec>)>,
+}
+
+impl ExecPolicy {
+    /// Loads the policies `config` asks for.
+    pub fn from_config(config: &Config) -> (Self, Vec<String>) {
+        let paths: Vec<PathBuf> = config
+            .exec_policy_files
+            .iter()
+            .cloned()
+            .chain(config.project_exec_policy.clone())
+            .collect();
+        Self::load(&paths)
+    }
+
+    /// Loads the bundled default policy followed by `paths`. Files that cannot
+    /// be read or parsed are skipped; a message describing each failure is
+    /// returned so the caller can surface it to the user.
+    pub fn load(paths: &[PathBuf]) -> (Self, Vec<String>) {
+        let mut policies = Vec::new();
+        let mut errors = Vec::new();
+        match get_default_policy() {
+            Ok(policy) => policies.push((DEFAULT_POLICY_SOURCE.to_string(), policy)),
+            Err(err) => errors.push(format!("failed to parse default exec policy: {err}")),
+        }
+        for path in paths {
+            match parse_policy_file(path) {
+                Ok(policy) => policies.push((path.display().to_string(), policy)),
+                Err(err) => errors.push(format!(
+                    "failed to load exec policy {}: {err}",
+                    path.display()
+                )),
+            }
+        }
+        (Self { policies }, errors)
+    }
+
+    /// Classifies `command`, splitting plain `bash -lc` scripts into their
+    /// individual commands.
+    pub fn check(&self, command: &[String]) -> ExecPolicyMatch {
+        if self.policies.is_empty() {
+            return ExecPolicyMatch::Unmatched;
+        }
+
+        let matches: Vec<ExecPolicyMatch> = split_commands(command)
+            .iter()
+            .map(|command| self.check_exec(command))
+            .collect();
+        if let Some(forbidden) = matches
+            .iter()
+            .find(|m| matches!(m, ExecPolicyMatch::Forbidden { .. }))
+        {
+            return forbidden.clone();
+        }
+        if matches.contains(&ExecPolicyMatch::Unmatched) {
+            return ExecPolicyMatch::Unmatched;
+        }
+        matches
+            .into_iter()
+            .find(|m| matches!(m, ExecPolicyMatch::WritesFiles { .. }))
+            .unwrap_or(ExecPolicyMatch::Safe)
+    }
+
+    /// Where each loaded policy came from, in the order they are consulted.
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        self.policies.iter().map(|(source, _)| source.as_str())
+    }
+
+    /// The raw result of every policy for every command `check` would look
+    /// at, for explaining a verdict.
+    pub fn explain(&self, command: &[String]) -> Vec<CommandExplanation<'_>> {
+        split_commands(command)
+            .into_iter()
+            .map(|command| {
+                let outcomes = match exec_call(&command) {
+                    Some(exec_call) => self
+                        .policies
+                        .iter()
+                        .map(|(source, policy)| (source.as_str(), policy.check(&exec_call)))
+                        .collect(),
+                    None => Vec::new(),
+                };
+                CommandExplanation { command, outcomes }
+            })
+            .collect()
+    }
+
+    fn check_exec(&self, command: &[String]) -> ExecPolicyMatch {
+        let Some(exec_call) = exec_call(command) else {
+            return ExecPolicyMatch::Unmatched;
+        };
+
+        // A forbidden rule in any policy wins; otherwise the first policy
+        // that defines the program decides, whether or not one of its rules
+        // matched. Later policies (such as the project's) can therefore add
+        // rules for new programs but never loosen earlier ones.
+        let mut result = None;
+        for (_, policy) in &self.policies {
+            match policy.check(&exec_call) {
+                Ok(MatchedExec::Forbidden { reason, .. }) => {
+                    return ExecPolicyMatch::Forbidden { reason };
+                }
+                Ok(MatchedExec::Match { exec }) if result.is_none() => {
+                    result = Some(if exec.might_write_files() {
+                        ExecPolicyMatch::WritesFiles {
+                            reason: writes_files_reason(&exec),
+                        }
+                    } else {
+                        ExecPolicyMatch::Safe
+                    });
+                }
+                Err(PolicyError::NoSpecForProgram { .. }) => {}
+                Err(_) if result.is_none() => result = Some(ExecPolicyMatch::Unmatched),
+                Ok(MatchedExec::Match { .. }) | Err(_) => {}
+            }
+        }
+        result.unwrap_or(ExecPolicyMatch::Unmatched)
+    }
+}
+
+impl fmt::Debug for ExecPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecPolicy")
+            .field("policies", &self.policies.len())
+            .finish()
+    }
+}
+
+/// A `bash -lc` script made up solely of plain commands is checked command by
+/// command, mirroring `is_known_safe_command`.
+fn split_commands(command: &[String]) -> Vec<Vec<String>> {
+    match parse_bash_lc_plain_commands(command) {
+        Some(commands) if !commands.is_empty() => commands,
+        _ => vec![command.to_vec()],
+    }
+}
+
+fn exec_call(command: &[String]) -> Option<ExecCall> {
+    let (program, args) = command.split_first()?;
+    Some(ExecCall {
+        program: program.clone(),
+        args: args.to_vec(),
+    })
+}
+
+fn parse_policy_file(path: &Path) -> Result<Policy, String> {
+    let source = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    PolicyParser::new(&path.to_string_lossy(), &source)
+        .parse()
+        .map_err(|err| err.to_string())
+}
+
+fn writes_files_reason(exec: &ValidExec) -> String {
+    let files: Vec<String> = exec
+        .args
+        .iter()
+        .map(|arg| (&arg.r#type, &arg.value))
+        .chain(exec.opts.iter().map(|opt| (&opt.r#type, &opt.value)))
+        .filter(|(arg_type, _)| arg_type.might_write_file())
+        .map(|(arg_type, value)| match arg_type {
+            ArgType::WriteableFile => format!("`{value}`"),
+            _ => format!("`{value}` (unknown)"),
+        })
+        .collect();
+    format!(
+        "exec policy: `{}` may write to {}",
+        exec.program,
+        files.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn vec_str(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn default_policy_classifies_reads_and_writes() {
+        let (policy, errors) = ExecPolicy::load(&[]);
+        assert_eq!(errors, Vec::<String>::new());
+
+        assert_eq!(
+            policy.check(&vec_str(&["ls", "-l", "src"])),
+            ExecPolicyMatch::Safe
+        );
+        assert_eq!(
+            policy.check(&vec_str(&["cp", "a.txt", "b.txt"])),
+            ExecPolicyMatch::WritesFiles {
+                reason: "exec policy: `cp` may write to `b.txt`".to_string(),
+            }
+        );
+        assert_eq!(
+            policy.check(&vec_str(&["cat", "-l", "a.txt"])),
+            ExecPolicyMatch::Unmatched
+        );
+        assert_eq!(
+            policy.check(&vec_str(&["bash", "-lc", "ls && pwd"])),
+            ExecPolicyMatch::Safe
+        );
+        assert_eq!(
+            policy.check(&vec_str(&["bash", "-lc", "ls && make"])),
+            ExecPolicyMatch::Unmatched
+        );
+    }
+
+    #[test]
+    fn later_policy_cannot_loosen_earlier_rule() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let user = dir.path().join("user.policy");
+        std::fs::write(
+            &user,
+            r#"
+define_program(
+    program="make",
+    args=[ARG_WFILE],
+)
+"#,
+        )
+        .expect("write user policy");
+        let project = dir.path().join("exec.policy");
+        std::fs::write(
+            &project,
+            r#"
+define_program(
+    program="make",
+    args=[ARG_OPAQUE_VALUE],
+)
+
+define_program(
+    program="cargo",
+    args=["nextest", "run"],
+)
+"#,
+        )
+        .expect("write project policy");
+
+        let (policy, errors) = ExecPolicy::load(&[user, project]);
+        assert_eq!(errors, Vec::<String>::new());
+
+        assert_eq!(
+            policy.check(&vec_str(&["make", "lint"])),
+            ExecPolicyMatch::WritesFiles {
+                reason: "exec policy: `make` may write to `lint`".to_string(),
+            }
+        );
+        assert_eq!(
+            policy.check(&vec_str(&["cargo", "nextest", "run"])),
+            ExecPolicyMatch::Safe
+        );
+    }
+
+    #[test]
+    fn later_policy_cannot_add_rules_for_defined_program() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let user = dir.path().join("user.policy");
+        std::fs::write(
+            &user,
+            r#"
+define_program(
+    program="make",
+    args=["lint"],
+)
+"#,
+        )
+        .expect("write user policy");
+        let project = dir.path().join("exec.policy");
+        std::fs::write(
+            &project,
+            r#"
+define_program(
+    program="make",
+    args=[ARG_OPAQUE_VALUE],
+)
+"#,
+        )
+        .expect("write project policy");
+
+        let (policy, errors) = ExecPolicy::load(&[user, project]);
+        assert_eq!(errors, Vec::<String>::new());
+
+        assert_eq!(
+            policy.check(&vec_str(&["make", "lint"])),
+            ExecPolicyMatch::Safe
+        );
+        // The user's rule for `make` does not match, so the project's looser
+        // rule is never consulted.
+        assert_eq!(
+            policy.check(&vec_str(&["make", "deploy"])),
+            ExecPolicyMatch::Unmatched
+        );
+    }
+
+    #[test]
+    fn forbidden_rule_in_user_policy_wins() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("user.policy");
+        std::fs::write(
+            &path,
+            r#"
+define_program(
+    program="ls",
+    args=[ARG_RFILES_OR_CWD],
+    forbidden="listing is not allowed here",
+)
+"#,
+        )
+        .expect("write policy");
+        let missing = dir.path().join("missing.policy");
+
+        let (policy, errors) = ExecPolicy::load(&[path, missing.clone()]);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with(&format!("failed to load exec policy {}", missing.display())),
+            "{errors:?}"
+        );
+
+        assert_eq!(
+            policy.check(&vec_str(&["bash", "-lc", "pwd && ls"])),
+            ExecPolicyMatch::Forbidden {
+                reason: "listing is not allowed here".to_string(),
+            }
+        );
+    }
+}
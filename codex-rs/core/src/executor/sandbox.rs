@@ -100,12 +100,14 @@ async fn select_shell_sandbox(
         request.approval_command.clone()
     };
 
+    let policy_match = config.exec_policy.check(&command_for_safety);
     let safety = assess_command_safety(
         &command_for_safety,
         approval_policy,
         &config.sandbox_policy,
         &approved_snapshot,
         request.params.with_escalated_permissions.unwrap_or(false),
+        &policy_match,
     );
 
     match safety {
@@ -129,13 +131,23 @@ async fn select_shell_sandbox(
             Ok(decision)
         }
         SafetyCheck::AskUser => {
+            // Show the exec policy's explanation alongside the model's own
+            // justification, if any.
+            let reason = match (request.params.justification.clone(), policy_match.reason()) {
+                (Some(justification), Some(policy_reason)) => {
+                    Some(format!("{justification}; {policy_reason}"))
+                }
+                (justification, policy_reason) => {
+                    justification.or_else(|| policy_reason.map(str::to_string))
+                }
+            };
             let decision = session
                 .request_command_approval(
                     sub_id.to_string(),
                     call_id.to_string(),
                     request.approval_command.clone(),
                     request.params.cwd.clone(),
-                    request.params.justification.clone(),
+                    reason,
                 )
                 .await;
 
@@ -207,7 +219,12 @@ mod tests {
             action,
             user_explicitly_approved_this_action: true,
         };
-        let cfg = ExecutorConfig::new(SandboxPolicy::ReadOnly, std::env::temp_dir(), None);
+        let cfg = ExecutorConfig::new(
+            SandboxPolicy::ReadOnly,
+            std::env::temp_dir(),
+            None,
+            Default::default(),
+        );
         let request = ExecutionRequest {
             params: ExecParams {
                 command: vec!["apply_patch".into()],
@@ -250,7 +267,12 @@ mod tests {
             action,
             user_explicitly_approved_this_action: false,
         };
-        let cfg = ExecutorConfig::new(SandboxPolicy::DangerFullAccess, std::env::temp_dir(), None);
+        let cfg = ExecutorConfig::new(
+            SandboxPolicy::DangerFullAccess,
+            std::env::temp_dir(),
+            None,
+            Default::default(),
+        );
         let request = ExecutionRequest {
             params: ExecParams {
                 command: vec!["apply_patch".into()],
@@ -294,7 +316,12 @@ mod tests {
             action,
             user_explicitly_approved_this_action: false,
         };
-        let cfg = ExecutorConfig::new(SandboxPolicy::ReadOnly, std::env::temp_dir(), None);
+        let cfg = ExecutorConfig::new(
+            SandboxPolicy::ReadOnly,
+            std::env::temp_dir(),
+            None,
+            Default::default(),
+        );
         let request = ExecutionRequest {
             params: ExecParams {
                 command: vec!["apply_patch".into()],
@@ -333,7 +360,12 @@ mod tests {
     #[tokio::test]
     async fn select_shell_autoapprove_in_danger_mode() {
         let (session, ctx) = make_session_and_context();
-        let cfg = ExecutorConfig::new(SandboxPolicy::DangerFullAccess, std::env::temp_dir(), None);
+        let cfg = ExecutorConfig::new(
+            SandboxPolicy::DangerFullAccess,
+            std::env::temp_dir(),
+            None,
+            Default::default(),
+        );
         let request = ExecutionRequest {
             params: ExecParams {
                 command: vec!["some-unknown".into()],
@@ -369,7 +401,12 @@ mod tests {
     #[tokio::test]
     async fn select_shell_escalates_on_failure_with_platform_sandbox() {
         let (session, ctx) = make_session_and_context();
-        let cfg = ExecutorConfig::new(SandboxPolicy::ReadOnly, std::env::temp_dir(), None);
+        let cfg = ExecutorConfig::new(
+            SandboxPolicy::ReadOnly,
+            std::env::temp_dir(),
+            None,
+            Default::default(),
+        );
         let request = ExecutionRequest {
             params: ExecParams {
                 // Unknown command => untrusted but not flagged dangerous
@@ -28,6 +28,7 @@ pub mod error;
 pub mod exec;
 mod exec_command;
 pub mod exec_env;
+pub mod exec_policy;
 pub mod executor;
 mod flags;
 pub mod git_info;
@@ -7,6 +7,7 @@ use codex_apply_patch::ApplyPatchAction;
 use codex_apply_patch::ApplyPatchFileChange;
 
 use crate::exec::SandboxType;
+use crate::exec_policy::ExecPolicyMatch;
 
 use crate::command_safety::is_dangerous_command::command_might_be_dangerous;
 use crate::command_safety::is_safe_command::is_known_safe_command;
@@ -89,13 +90,24 @@ pub fn assess_patch_safety(
 /// - the user has explicitly approved the command
 /// - the command is on the "known safe" list
 /// - `DangerFullAccess` was specified and `UnlessTrusted` was not
+///
+/// A command that matches an exec policy rule that cannot write files is
+/// approved without asking, but still runs in the platform sandbox. A command
+/// forbidden by an exec policy is always rejected.
 pub fn assess_command_safety(
     command: &[String],
     approval_policy: AskForApproval,
     sandbox_policy: &SandboxPolicy,
     approved: &HashSet<Vec<String>>,
     with_escalated_permissions: bool,
+    policy_match: &ExecPolicyMatch,
 ) -> SafetyCheck {
+    if let ExecPolicyMatch::Forbidden { reason } = policy_match {
+        return SafetyCheck::Reject {
+            reason: format!("forbidden by exec policy: {reason}"),
+        };
+    }
+
     // Some commands look dangerous. Even if they are run inside a sandbox,
     // unless the user has explicitly approved them, we should ask,
     // or reject if the approval_policy tells us not to ask.
@@ -132,6 +144,18 @@ pub fn assess_command_safety(
         };
     }
 
+    // An exec policy match only vouches for the arguments, not for running
+    // outside the sandbox; without a platform sandbox the command is treated
+    // like any other untrusted command.
+    if *policy_match == ExecPolicyMatch::Safe
+        && let Some(sandbox_type) = get_platform_sandbox()
+    {
+        return SafetyCheck::AutoApprove {
+            sandbox_type,
+            user_explicitly_approved: false,
+        };
+    }
+
     assess_safety_for_untrusted_command(approval_policy, sandbox_policy, with_escalated_permissions)
 }
 
@@ -355,6 +379,7 @@ mod tests {
             &sandbox_policy,
             &approved,
             request_escalated_privileges,
+            &ExecPolicyMatch::Unmatched,
         );
 
         assert_eq!(safety_check, SafetyCheck::AskUser);
@@ -375,6 +400,7 @@ mod tests {
             &sandbox_policy,
             &approved,
             request_escalated_privileges,
+            &ExecPolicyMatch::Unmatched,
         );
 
         assert_eq!(
@@ -400,6 +426,7 @@ mod tests {
             &sandbox_policy,
             &approved,
             request_escalated_privileges,
+            &ExecPolicyMatch::Unmatched,
         );
 
         assert_eq!(
@@ -411,6 +438,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn exec_policy_safe_match_runs_in_sandbox() {
+        let command = vec!["cp".to_string(), "--help".to_string()];
+        let approved: HashSet<Vec<String>> = HashSet::new();
+
+        let safety_check = assess_command_safety(
+            &command,
+            AskForApproval::UnlessTrusted,
+            &SandboxPolicy::ReadOnly,
+            &approved,
+            false,
+            &ExecPolicyMatch::Safe,
+        );
+
+        let expected = match get_platform_sandbox() {
+            Some(sandbox_type) => SafetyCheck::AutoApprove {
+                sandbox_type,
+                user_explicitly_approved: false,
+            },
+            None => SafetyCheck::AskUser,
+        };
+        assert_eq!(safety_check, expected);
+    }
+
+    #[test]
+    fn exec_policy_forbidden_rejects_even_if_approved() {
+        let command = vec!["applied".to_string(), "deploy".to_string()];
+        let mut approved: HashSet<Vec<String>> = HashSet::new();
+        approved.insert(command.clone());
+
+        let safety_check = assess_command_safety(
+            &command,
+            AskForApproval::OnRequest,
+            &SandboxPolicy::DangerFullAccess,
+            &approved,
+            false,
+            &ExecPolicyMatch::Forbidden {
+                reason: "no deploys".to_string(),
+            },
+        );
+
+        assert_eq!(
+            safety_check,
+            SafetyCheck::Reject {
+                reason: "forbidden by exec policy: no deploys".to_string(),
+            }
+        );
+    }
+
     #[test]
     fn test_request_escalated_privileges_no_sandbox_fallback() {
         let command = vec!["git".to_string(), "commit".to_string()];
@@ -425,6 +501,7 @@ mod tests {
             &sandbox_policy,
             &approved,
             request_escalated_privileges,
+            &ExecPolicyMatch::Unmatched,
         );
 
         let expected = match get_platform_sandbox() {
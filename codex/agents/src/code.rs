@@ -0,0 +1,770 @@
+use codex_apply_patch::{ApplyPatchFileChange, Hunk};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use similar::TextDiff;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::paths::resolve_within;
+use crate::reviewer::ReviewFinding;
+use crate::structured::{array_schema, generate_json, object_schema, string_array_schema};
+use crate::{AgentError, ModelProvider};
+
+/// How the model is asked to write patches in the grammar understood by `apply_patch`.
+pub(crate) const PATCH_FORMAT_INSTRUCTIONS: &str = r#"Patches use the apply_patch format. Each patch is wrapped in "*** Begin Patch" and "*** End Patch" and contains one file operation:
+
+*** Add File: <path> - create a new file. Every following line is a + line (the initial contents).
+*** Delete File: <path> - remove an existing file. Nothing follows.
+*** Update File: <path> - patch an existing file in place, optionally followed by "*** Move to: <new path>" to rename it.
+
+An update consists of hunks introduced by "@@" (optionally followed by the enclosing class or function). Within a hunk, context lines start with a space, removed lines with "-" and added lines with "+". Show 3 lines of context above and below each change, copied exactly from the current file. File paths must be relative, never absolute."#;
+
+/// Code Agent: Writes code changes with file-level edit streams
+#[derive(Clone)]
+pub struct CodeAgent {
+    model_provider: Arc<dyn ModelProvider>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeRequest {
+    pub prompt: String,
+    pub context: Option<HashMap<String, String>>,
+    pub requirements: Option<Vec<String>>,
+    pub existing_files: Option<Vec<ExistingFile>>,
+    pub target_files: Option<Vec<String>>,
+    /// Working tree the generated patches are verified against. Defaults to the
+    /// current directory.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Reviewer feedback on a previous attempt to address in this one.
+    #[serde(default)]
+    pub revision: Option<CodeRevision>,
+}
+
+/// Feedback on a previous attempt, for the review/fix loop.
+///
+/// The previous patch was never applied, so a revision regenerates the
+/// complete set of patches against the working tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeRevision {
+    pub previous_patch: String,
+    pub findings: Vec<ReviewFinding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExistingFile {
+    pub path: String,
+    pub content: String,
+    pub language: String,
+}
+
+/// A verified change to a single file.
+///
+/// `patch` is the model's edit in the `*** Begin Patch` grammar understood by
+/// `apply_patch`; the contents and diff are computed by applying it to the
+/// working tree in memory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChange {
+    pub file_path: String,
+    pub patch: String,
+    pub unified_diff: Option<String>,
+    pub old_content: String,
+    pub new_content: String,
+    pub change_type: ChangeType,
+    pub explanation: String,
+    pub confidence: f32,
+    /// IDs of the spec requirements the change implements, e.g. `REQ-001`.
+    #[serde(default)]
+    pub requirement_ids: Vec<String>,
+}
+
+/// A generated patch that could not be applied to the working tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFailure {
+    pub file_path: String,
+    pub patch: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ChangeType {
+    Create,
+    Modify,
+    Delete,
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeStream {
+    pub changes: Vec<CodeChange>,
+    /// All verified changes combined into one `apply_patch` patch.
+    pub patch: String,
+    pub failures: Vec<PatchFailure>,
+    pub metadata: CodeMetadata,
+    pub dependencies: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeMetadata {
+    pub language: String,
+    pub framework: Option<String>,
+    pub patterns_used: Vec<String>,
+    pub complexity_score: f32,
+}
+
+impl CodeAgent {
+    pub fn new(model_provider: Arc<dyn ModelProvider>) -> Self {
+        Self { model_provider }
+    }
+
+    pub async fn generate_code(&self, request: CodeRequest) -> Result<CodeStream, AgentError> {
+        // Analyze existing codebase context
+        let context_analysis = self.analyze_context(&request).await?;
+
+        // Generate patches based on requirements
+        let generated = self.generate_changes(&request, &context_analysis).await?;
+
+        // Verify the patches against the working tree
+        let cwd = match &request.cwd {
+            Some(cwd) if cwd.is_absolute() => cwd.clone(),
+            Some(cwd) => std::env::current_dir()?.join(cwd),
+            None => std::env::current_dir()?,
+        };
+        let (changes, failures) = verify_patches(generated.patches, &cwd);
+        let patch = combine_patches(&changes);
+
+        // Validate generated code
+        let validation_result = self.validate_changes(&changes, generated.dependencies).await?;
+
+        // Generate metadata
+        let metadata = self.generate_metadata(&changes, &context_analysis)?;
+
+        Ok(CodeStream {
+            changes,
+            patch,
+            failures,
+            metadata,
+            dependencies: validation_result.dependencies,
+            warnings: validation_result.warnings,
+        })
+    }
+
+    async fn analyze_context(&self, request: &CodeRequest) -> Result<ContextAnalysis, AgentError> {
+        let system_prompt = r#"You are a senior software engineer analyzing a codebase. Given existing files and requirements, provide:
+
+1. Current architecture patterns
+2. Language/framework conventions
+3. Existing dependencies and imports
+4. Code style and structure patterns
+5. Integration points to consider
+
+Use null for the framework when none is in use."#;
+
+        let mut context_info = String::new();
+        if let Some(files) = &request.existing_files {
+            for file in files {
+                context_info.push_str(&format!("File: {}\nContent:\n{}\n\n", file.path, file.content));
+            }
+        }
+
+        let mut user_prompt = format!(
+            "Analyze this codebase context for implementing:\n\n{}\n\nCodebase:\n{}",
+            request.prompt, context_info
+        );
+        if let Some(context) = &request.context {
+            user_prompt.push_str(&format!("\n\nAdditional context: {:?}", context));
+        }
+
+        let schema = object_schema(json!({
+            "language": { "type": "string" },
+            "framework": { "type": ["string", "null"] },
+            "patterns": string_array_schema(),
+            "conventions": string_array_schema(),
+            "dependencies": string_array_schema(),
+            "style_guide": { "type": "string" },
+        }));
+
+        generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await
+    }
+
+    async fn generate_changes(&self, request: &CodeRequest, context: &ContextAnalysis) -> Result<GeneratedChanges, AgentError> {
+        let system_prompt = format!(
+            r#"You are an expert software engineer implementing new features. Generate precise code changes that:
+
+1. Follow the established patterns and conventions
+2. Integrate cleanly with existing code
+3. Include proper error handling
+4. Are well-documented and testable
+5. Follow security best practices
+
+For each file, provide:
+- The file path, relative to the repository root
+- A patch that edits only that file
+- Clear explanation of changes
+- Confidence score (0.0-1.0)
+- The IDs of the listed requirements the patch implements (empty when none are listed)
+
+Also list any new third-party packages the changes depend on.
+
+{PATCH_FORMAT_INSTRUCTIONS}"#
+        );
+
+        let mut user_prompt = format!(
+            "Implement this feature:\n\n{}\n\nContext analysis:\n- Language: {}\n- Framework: {}\n- Patterns: {}\n- Conventions: {}\n- Dependencies: {}\n- Style guide: {}",
+            request.prompt,
+            context.language,
+            context.framework.as_deref().unwrap_or("none"),
+            context.patterns.join(", "),
+            context.conventions.join(", "),
+            context.dependencies.join(", "),
+            context.style_guide,
+        );
+        if let Some(requirements) = request.requirements.as_ref().filter(|requirements| !requirements.is_empty()) {
+            user_prompt.push_str(&format!("\n\nRequirements:\n- {}", requirements.join("\n- ")));
+        }
+        if let Some(target_files) = &request.target_files {
+            user_prompt.push_str(&format!("\n\nTarget files: {}", target_files.join(", ")));
+        }
+        if let Some(files) = &request.existing_files {
+            for file in files {
+                user_prompt.push_str(&format!("\n\nFile: {} ({})\nContent:\n{}", file.path, file.language, file.content));
+            }
+        }
+        if let Some(revision) = &request.revision {
+            user_prompt.push_str(&format!(
+                "\n\nA reviewer requested changes to your previous attempt. Address every finding and return the complete corrected set of patches against the current files; the previous patch has not been applied.\n\nPrevious patch:\n{}\n\nReview findings:\n{}",
+                revision.previous_patch,
+                describe_findings(&revision.findings),
+            ));
+        }
+
+        let schema = object_schema(json!({
+            "patches": array_schema(generated_patch_schema()),
+            "dependencies": string_array_schema(),
+        }));
+
+        generate_json(self.model_provider.as_ref(), &system_prompt, &user_prompt, &schema).await
+    }
+
+    async fn validate_changes(&self, changes: &[CodeChange], dependencies: Vec<String>) -> Result<ValidationResult, AgentError> {
+        let mut warnings = Vec::new();
+
+        for change in changes {
+            // Check for potential issues
+            if change.new_content.contains("TODO") {
+                warnings.push(format!("TODO comment found in {}", change.file_path));
+            }
+
+            if change.confidence < 0.7 {
+                warnings.push(format!("Low confidence ({:.2}) for changes in {}", change.confidence, change.file_path));
+            }
+        }
+
+        Ok(ValidationResult {
+            dependencies,
+            warnings,
+        })
+    }
+
+    fn generate_metadata(&self, changes: &[CodeChange], context: &ContextAnalysis) -> Result<CodeMetadata, AgentError> {
+        let languages: Vec<String> = changes.iter()
+            .map(|c| self.detect_language(&c.file_path))
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let primary_language = languages
+            .into_iter()
+            .find(|language| language != "unknown")
+            .unwrap_or_else(|| context.language.clone());
+
+        Ok(CodeMetadata {
+            language: primary_language,
+            framework: context.framework.clone(),
+            patterns_used: context.patterns.clone(),
+            complexity_score: self.calculate_complexity(changes),
+        })
+    }
+
+    fn detect_language(&self, file_path: &str) -> String {
+        if file_path.ends_with(".js") || file_path.ends_with(".mjs") {
+            "javascript".to_string()
+        } else if file_path.ends_with(".ts") || file_path.ends_with(".tsx") {
+            "typescript".to_string()
+        } else if file_path.ends_with(".py") {
+            "python".to_string()
+        } else if file_path.ends_with(".rs") {
+            "rust".to_string()
+        } else {
+            "unknown".to_string()
+        }
+    }
+
+    fn calculate_complexity(&self, changes: &[CodeChange]) -> f32 {
+        let total_lines: usize = changes.iter()
+            .map(|c| c.new_content.lines().count())
+            .sum();
+
+        match total_lines {
+            0..=50 => 0.3,
+            51..=200 => 0.5,
+            201..=500 => 0.7,
+            _ => 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ContextAnalysis {
+    language: String,
+    framework: Option<String>,
+    patterns: Vec<String>,
+    conventions: Vec<String>,
+    dependencies: Vec<String>,
+    style_guide: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeneratedChanges {
+    patches: Vec<GeneratedPatch>,
+    dependencies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GeneratedPatch {
+    pub(crate) file_path: String,
+    pub(crate) patch: String,
+    pub(crate) explanation: String,
+    pub(crate) confidence: f32,
+    #[serde(default)]
+    pub(crate) requirement_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ValidationResult {
+    dependencies: Vec<String>,
+    warnings: Vec<String>,
+}
+
+pub(crate) fn generated_patch_schema() -> Value {
+    object_schema(json!({
+        "file_path": { "type": "string" },
+        "patch": { "type": "string" },
+        "explanation": { "type": "string" },
+        "confidence": { "type": "number" },
+        "requirement_ids": string_array_schema(),
+    }))
+}
+
+impl CodeStream {
+    /// Write the verified changes to the working tree at `cwd`.
+    ///
+    /// Each patch is verified again first, so nothing is written when the tree
+    /// has changed since the patches were generated.
+    pub fn apply(&self, cwd: &Path) -> Result<(), AgentError> {
+        let patches: Vec<(&str, &str)> = self
+            .changes
+            .iter()
+            .map(|change| (change.file_path.as_str(), change.patch.as_str()))
+            .collect();
+        apply_patches(&patches, cwd)
+    }
+}
+
+/// Verify every `(file_path, patch)` pair against `cwd`, each on top of the
+/// ones before it, then write them all; nothing is written if any patch no
+/// longer applies.
+///
+/// A patch that touches several files yields one change per file, so a patch
+/// listed more than once is applied once.
+pub(crate) fn apply_patches(patches: &[(&str, &str)], cwd: &Path) -> Result<(), AgentError> {
+    let mut tree = PatchedTree::default();
+    let mut seen = std::collections::HashSet::new();
+    for (file_path, patch) in patches.iter().filter(|(_, patch)| seen.insert(*patch)) {
+        verify_patch(patch, cwd, &mut tree).map_err(|e| format!("{file_path}: {e}"))?;
+    }
+    tree.write()
+}
+
+/// The working tree as it is after the patches verified so far, kept in
+/// memory: `Some` holds a file's new content, `None` marks a deleted file and
+/// files that no patch touched are read from disk.
+#[derive(Default)]
+struct PatchedTree {
+    files: BTreeMap<PathBuf, Option<String>>,
+}
+
+impl PatchedTree {
+    fn read(&self, path: &Path) -> Result<String, String> {
+        match self.files.get(path) {
+            Some(Some(content)) => Ok(content.clone()),
+            Some(None) => Err(format!("{} was deleted by an earlier patch", path.display())),
+            None => std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display())),
+        }
+    }
+
+    fn record(&mut self, edit: &FileEdit) {
+        match &edit.change {
+            ApplyPatchFileChange::Add { content } => {
+                self.files.insert(edit.path.clone(), Some(content.clone()));
+            }
+            ApplyPatchFileChange::Delete { .. } => {
+                self.files.insert(edit.path.clone(), None);
+            }
+            ApplyPatchFileChange::Update { move_path, new_content, .. } => match move_path {
+                Some(dest) => {
+                    self.files.insert(edit.path.clone(), None);
+                    self.files.insert(dest.clone(), Some(new_content.clone()));
+                }
+                None => {
+                    self.files.insert(edit.path.clone(), Some(new_content.clone()));
+                }
+            },
+        }
+    }
+
+    /// Write the tree to disk. New contents are staged next to their targets
+    /// first, so a failed write leaves the existing files untouched.
+    fn write(self) -> Result<(), AgentError> {
+        let mut staged = Vec::new();
+        let mut deleted = Vec::new();
+        for (path, content) in self.files {
+            let Some(content) = content else {
+                deleted.push(path);
+                continue;
+            };
+            let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            let temp = path.with_file_name(format!(".{file_name}.codex-patch"));
+            let written = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(&temp, content));
+            if let Err(e) = written {
+                for (temp, _) in &staged {
+                    let _ = std::fs::remove_file(temp);
+                }
+                let _ = std::fs::remove_file(&temp);
+                return Err(e.into());
+            }
+            staged.push((temp, path));
+        }
+
+        for (temp, path) in staged {
+            std::fs::rename(temp, path)?;
+        }
+        for path in deleted {
+            match std::fs::remove_file(&path) {
+                // Added by one patch and deleted by a later one
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One file operation of a verified patch.
+struct FileEdit {
+    path: PathBuf,
+    /// Content before the patch; empty for an added file
+    old_content: String,
+    change: ApplyPatchFileChange,
+}
+
+/// One line per finding, e.g. `- [High/Security] src/auth.rs:12 Hardcoded secret: ...`.
+fn describe_findings(findings: &[ReviewFinding]) -> String {
+    findings
+        .iter()
+        .map(|finding| {
+            let location = match finding.line_start {
+                Some(line) => format!("{}:{}", finding.file_path, line),
+                None => finding.file_path.clone(),
+            };
+            let mut line = format!(
+                "- [{:?}/{:?}] {} {}: {}",
+                finding.severity, finding.category, location, finding.title, finding.description
+            );
+            if let Some(suggestion) = &finding.suggestion {
+                line.push_str(&format!(" Suggestion: {}", suggestion));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse `patch` and compute its effect on `tree`, the working tree at `cwd`
+/// with the earlier patches applied, then record it there. Nothing is written
+/// to disk, and `tree` is left as it was if the patch does not apply.
+///
+/// Patches come from the model, so any file operation whose path or move
+/// target resolves outside `cwd` is refused.
+fn verify_patch(patch: &str, cwd: &Path, tree: &mut PatchedTree) -> Result<Vec<FileEdit>, String> {
+    let args = codex_apply_patch::parse_patch(patch).map_err(|e| e.to_string())?;
+    if args.hunks.is_empty() {
+        return Err("patch contains no file operations".to_string());
+    }
+
+    // Checked before anything is read from the files being updated or deleted
+    for hunk in &args.hunks {
+        let (path, move_path) = match hunk {
+            Hunk::AddFile { path, .. } | Hunk::DeleteFile { path } => (path, None),
+            Hunk::UpdateFile { path, move_path, .. } => (path, move_path.as_ref()),
+        };
+        for path in std::iter::once(path).chain(move_path) {
+            if resolve_within(cwd, path).is_none() {
+                return Err(format!("{} is outside the working tree", path.display()));
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    for hunk in args.hunks {
+        let path = hunk.resolve_path(cwd);
+        let (old_content, change) = match hunk {
+            Hunk::AddFile { contents, .. } => (String::new(), ApplyPatchFileChange::Add { content: contents }),
+            Hunk::DeleteFile { .. } => {
+                let content = tree.read(&path)?;
+                (content.clone(), ApplyPatchFileChange::Delete { content })
+            }
+            Hunk::UpdateFile { move_path, chunks, .. } => {
+                let old_content = tree.read(&path)?;
+                let new_content =
+                    codex_apply_patch::apply_chunks_to_contents(&path, &old_content, &chunks).map_err(|e| e.to_string())?;
+                // One line of context, as in apply_patch's own diffs
+                let unified_diff = TextDiff::from_lines(&old_content, &new_content).unified_diff().context_radius(1).to_string();
+                let change = ApplyPatchFileChange::Update {
+                    unified_diff,
+                    move_path: move_path.map(|dest| cwd.join(dest)),
+                    new_content,
+                };
+                (old_content, change)
+            }
+        };
+        edits.push(FileEdit { path, old_content, change });
+    }
+
+    for edit in &edits {
+        tree.record(edit);
+    }
+    Ok(edits)
+}
+
+/// Verify the patches in order, each on top of the ones before it that
+/// applied, without touching the working tree at `cwd`.
+pub(crate) fn verify_patches(patches: Vec<GeneratedPatch>, cwd: &Path) -> (Vec<CodeChange>, Vec<PatchFailure>) {
+    let mut tree = PatchedTree::default();
+    let mut changes = Vec::new();
+    let mut failures = Vec::new();
+
+    for generated in patches {
+        let edits = match verify_patch(&generated.patch, cwd, &mut tree) {
+            Ok(edits) => edits,
+            Err(error) => {
+                failures.push(PatchFailure {
+                    file_path: generated.file_path,
+                    patch: generated.patch,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        for FileEdit { path, old_content, change } in edits {
+            let file_path = path.strip_prefix(cwd).unwrap_or(&path).display().to_string();
+            let (change_type, unified_diff, new_content) = match change {
+                ApplyPatchFileChange::Add { content } => (ChangeType::Create, None, content),
+                ApplyPatchFileChange::Delete { .. } => (ChangeType::Delete, None, String::new()),
+                ApplyPatchFileChange::Update { unified_diff, move_path, new_content } => {
+                    let change_type = if move_path.is_some() { ChangeType::Rename } else { ChangeType::Modify };
+                    (change_type, Some(unified_diff), new_content)
+                }
+            };
+
+            changes.push(CodeChange {
+                file_path,
+                patch: generated.patch.clone(),
+                unified_diff,
+                old_content,
+                new_content,
+                change_type,
+                explanation: generated.explanation.clone(),
+                confidence: generated.confidence.clamp(0.0, 1.0),
+                requirement_ids: generated.requirement_ids.clone(),
+            });
+        }
+    }
+
+    (changes, failures)
+}
+
+/// Merge single-file patches into one `*** Begin Patch` envelope.
+fn combine_patches(changes: &[CodeChange]) -> String {
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut combined = String::from("*** Begin Patch\n");
+    let mut seen = std::collections::HashSet::new();
+    for change in changes {
+        // A patch that touches several files yields one change per file.
+        if !seen.insert(change.patch.as_str()) {
+            continue;
+        }
+        for line in change.patch.trim().lines() {
+            let line = line.trim_end_matches('\r');
+            if line != "*** Begin Patch" && line != "*** End Patch" {
+                combined.push_str(line);
+                combined.push('\n');
+            }
+        }
+    }
+    combined.push_str("*** End Patch\n");
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockModelProvider;
+
+    #[tokio::test]
+    async fn test_code_agent_generation() {
+        let workdir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(workdir.path().join("src")).unwrap();
+        std::fs::write(workdir.path().join("src/app.js"), "const app = express();\napp.listen(3000);\n").unwrap();
+
+        let patches = json!({
+            "patches": [
+                {
+                    "file_path": "src/app.js",
+                    "patch": "*** Begin Patch\n*** Update File: src/app.js\n@@\n const app = express();\n+app.use(auth);\n app.listen(3000);\n*** End Patch",
+                    "explanation": "Register the auth middleware",
+                    "confidence": 0.9
+                },
+                {
+                    "file_path": "src/missing.js",
+                    "patch": "*** Begin Patch\n*** Update File: src/missing.js\n@@\n-old\n+new\n*** End Patch",
+                    "explanation": "Edit a file that does not exist",
+                    "confidence": 0.9
+                }
+            ],
+            "dependencies": ["bcrypt"]
+        });
+        let mock_provider = Arc::new(MockModelProvider::with_responses(
+            vec![
+                r#"{"language": "javascript", "framework": "express", "patterns": ["MVC"], "conventions": ["camelCase"], "dependencies": ["express"], "style_guide": "Standard JavaScript"}"#.to_string(),
+            ],
+            patches.to_string(),
+        ));
+
+        let agent = CodeAgent::new(mock_provider);
+        let request = CodeRequest {
+            prompt: "Add user authentication".to_string(),
+            context: None,
+            requirements: None,
+            existing_files: None,
+            target_files: None,
+            cwd: Some(workdir.path().to_path_buf()),
+            revision: None,
+        };
+
+        let response = agent.generate_code(request).await.unwrap();
+        assert_eq!(response.changes.len(), 1);
+        assert_eq!(response.changes[0].file_path, "src/app.js");
+        assert!(matches!(response.changes[0].change_type, ChangeType::Modify));
+        assert_eq!(response.changes[0].new_content, "const app = express();\napp.use(auth);\napp.listen(3000);\n");
+        assert_eq!(response.failures.len(), 1);
+        assert_eq!(response.failures[0].file_path, "src/missing.js");
+        assert!(response.patch.starts_with("*** Begin Patch\n*** Update File: src/app.js\n"));
+        assert_eq!(response.metadata.framework.as_deref(), Some("express"));
+        assert_eq!(response.dependencies, vec!["bcrypt".to_string()]);
+
+        // Verification does not touch the working tree; applying does.
+        let app = workdir.path().join("src/app.js");
+        assert_eq!(std::fs::read_to_string(&app).unwrap(), "const app = express();\napp.listen(3000);\n");
+        response.apply(workdir.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(&app).unwrap(), response.changes[0].new_content);
+    }
+
+    #[test]
+    fn test_patches_outside_cwd_are_rejected() {
+        let workdir = tempfile::tempdir().unwrap();
+        let cwd = workdir.path().join("repo");
+        std::fs::create_dir_all(&cwd).unwrap();
+        std::fs::write(workdir.path().join("victim.txt"), "keep\n").unwrap();
+        std::fs::write(cwd.join("lib.rs"), "fn main() {}\n").unwrap();
+        let absolute = workdir.path().join("absolute.txt");
+
+        let patches = [
+            format!("*** Begin Patch\n*** Add File: {}\n+pwned\n*** End Patch", absolute.display()),
+            "*** Begin Patch\n*** Add File: ../escaped.txt\n+pwned\n*** End Patch".to_string(),
+            "*** Begin Patch\n*** Delete File: ../victim.txt\n*** End Patch".to_string(),
+            "*** Begin Patch\n*** Update File: lib.rs\n*** Move to: ../moved.rs\n@@\n-fn main() {}\n+fn main() { }\n*** End Patch".to_string(),
+        ];
+        let generated = patches
+            .iter()
+            .map(|patch| GeneratedPatch {
+                file_path: "escape".to_string(),
+                patch: patch.clone(),
+                explanation: String::new(),
+                confidence: 1.0,
+                requirement_ids: Vec::new(),
+            })
+            .collect();
+
+        let (changes, failures) = verify_patches(generated, &cwd);
+        assert!(changes.is_empty());
+        assert_eq!(failures.len(), patches.len());
+        assert!(failures.iter().all(|failure| failure.error.contains("outside the working tree")));
+
+        for patch in &patches {
+            assert!(apply_patches(&[("escape", patch.as_str())], &cwd).is_err());
+        }
+        assert!(!absolute.exists());
+        assert!(!workdir.path().join("escaped.txt").exists());
+        assert!(!workdir.path().join("moved.rs").exists());
+        assert_eq!(std::fs::read_to_string(workdir.path().join("victim.txt")).unwrap(), "keep\n");
+    }
+
+    #[test]
+    fn test_patches_build_on_earlier_patches() {
+        let workdir = tempfile::tempdir().unwrap();
+        let cwd = workdir.path();
+        std::fs::write(cwd.join("lib.rs"), "mod a;\n").unwrap();
+
+        let add = "*** Begin Patch\n*** Add File: auth.rs\n+fn login() {}\n*** End Patch";
+        let update_added = "*** Begin Patch\n*** Update File: auth.rs\n@@\n fn login() {}\n+fn logout() {}\n*** End Patch";
+        let update_twice = [
+            "*** Begin Patch\n*** Update File: lib.rs\n@@\n mod a;\n+mod auth;\n*** End Patch",
+            "*** Begin Patch\n*** Update File: lib.rs\n@@\n mod auth;\n+mod session;\n*** End Patch",
+        ];
+        let generated = [add, update_added, update_twice[0], update_twice[1]]
+            .iter()
+            .map(|patch| GeneratedPatch {
+                file_path: String::new(),
+                patch: patch.to_string(),
+                explanation: String::new(),
+                confidence: 1.0,
+                requirement_ids: Vec::new(),
+            })
+            .collect();
+
+        let (changes, failures) = verify_patches(generated, cwd);
+        assert!(failures.is_empty(), "{failures:?}");
+        assert_eq!(changes[1].old_content, "fn login() {}\n");
+        assert_eq!(changes[3].old_content, "mod a;\nmod auth;\n");
+        assert_eq!(changes[3].new_content, "mod a;\nmod auth;\nmod session;\n");
+        assert!(!cwd.join("auth.rs").exists());
+
+        // A later patch that does not apply leaves the tree untouched
+        let broken = "*** Begin Patch\n*** Update File: lib.rs\n@@\n-mod missing;\n+mod other;\n*** End Patch";
+        assert!(apply_patches(&[("auth.rs", add), ("lib.rs", broken)], cwd).is_err());
+        assert!(!cwd.join("auth.rs").exists());
+
+        let patches = [("auth.rs", add), ("auth.rs", update_added), ("lib.rs", update_twice[0]), ("lib.rs", update_twice[1])];
+        apply_patches(&patches, cwd).unwrap();
+        assert_eq!(std::fs::read_to_string(cwd.join("auth.rs")).unwrap(), "fn login() {}\nfn logout() {}\n");
+        assert_eq!(std::fs::read_to_string(cwd.join("lib.rs")).unwrap(), "mod a;\nmod auth;\nmod session;\n");
+        assert_eq!(std::fs::read_dir(cwd).unwrap().count(), 2);
+    }
+}
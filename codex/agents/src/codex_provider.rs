@@ -0,0 +1,414 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use codex_core::config::{Config, ConfigOverrides};
+use codex_core::error::CodexErr;
+use codex_core::{AuthManager, ContentItem, ModelClient, Prompt, ResponseEvent, ResponseItem, ResponseStream};
+use codex_otel::otel_event_manager::OtelEventManager;
+use codex_protocol::ConversationId;
+use futures::StreamExt;
+
+use crate::model_provider::{AgentError, ModelProvider, StreamCompletion, TokenUsage};
+
+/// Instructions used when an agent asks for a completion without a system prompt.
+const DEFAULT_INSTRUCTIONS: &str = "You are a helpful software engineering assistant. Answer the request directly.";
+
+/// Model provider backed by codex-core's `ModelClient`.
+///
+/// The provider honours the `model_provider`, auth, retry and wire API
+/// settings from `config.toml`, so every agent talks to the same backend as
+/// the interactive CLI.
+#[derive(Clone)]
+pub struct CodexModelProvider {
+    client: ModelClient,
+    stream_max_retries: u64,
+    token_usage: Arc<Mutex<TokenUsage>>,
+}
+
+impl std::fmt::Debug for CodexModelProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodexModelProvider").finish_non_exhaustive()
+    }
+}
+
+impl CodexModelProvider {
+    pub fn new(config: Arc<Config>, auth_manager: Option<Arc<AuthManager>>) -> Self {
+        let conversation_id = ConversationId::new();
+        let auth = auth_manager.as_ref().and_then(|manager| manager.auth());
+        let otel_event_manager = OtelEventManager::new(
+            conversation_id,
+            config.model.as_str(),
+            config.model_family.slug.as_str(),
+            auth.as_ref().and_then(|a| a.get_account_id()),
+            auth.as_ref().map(|a| a.mode),
+            config.otel.log_user_prompt,
+            codex_core::terminal::user_agent(),
+        );
+
+        let provider = config.model_provider.clone();
+        let stream_max_retries = provider.stream_max_retries();
+        let client = ModelClient::new(
+            config.clone(),
+            auth_manager,
+            otel_event_manager,
+            provider,
+            config.model_reasoning_effort,
+            config.model_reasoning_summary,
+            conversation_id,
+        );
+
+        Self {
+            client,
+            stream_max_retries,
+            token_usage: Arc::new(Mutex::new(TokenUsage::default())),
+        }
+    }
+
+    /// Load `config.toml` from `CODEX_HOME` and authenticate with the stored credentials.
+    pub async fn from_codex_home(overrides: ConfigOverrides) -> std::io::Result<Self> {
+        let config = Config::load_with_cli_overrides(Vec::new(), overrides).await?;
+        let auth_manager = AuthManager::shared(config.codex_home.clone(), true);
+        Ok(Self::new(Arc::new(config), Some(auth_manager)))
+    }
+
+    /// Run a single model turn and return the final assistant message.
+    pub async fn complete(&self, system_prompt: Option<&str>, user_prompt: &str) -> Result<String, CodexErr> {
+        self.complete_prompt(build_prompt(system_prompt, user_prompt)).await
+    }
+
+    async fn complete_prompt(&self, prompt: Prompt) -> Result<String, CodexErr> {
+        let mut attempt = 0;
+
+        loop {
+            match self.try_complete(&prompt).await {
+                Ok(text) => return Ok(text),
+                Err(e) => {
+                    attempt += 1;
+                    match self.retry_delay(&e, attempt) {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        None => return Err(e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Backoff before retry number `attempt` (1-based), or `None` when `err`
+    /// is not a stream error or `stream_max_retries` is exhausted.
+    fn retry_delay(&self, err: &CodexErr, attempt: u64) -> Option<Duration> {
+        let CodexErr::Stream(message, delay) = err else {
+            return None;
+        };
+        if attempt > self.stream_max_retries {
+            return None;
+        }
+
+        let delay = delay.unwrap_or_else(|| Duration::from_millis(200 * 2u64.pow(attempt as u32 - 1)));
+        tracing::warn!("model stream error ({message}); retrying {attempt}/{} in {delay:?}", self.stream_max_retries);
+        Some(delay)
+    }
+
+    /// Start a model turn and stream the assistant's text deltas.
+    pub async fn stream(&self, system_prompt: Option<&str>, user_prompt: &str) -> Result<CodexStreamCompletion, CodexErr> {
+        self.stream_prompt(build_prompt(system_prompt, user_prompt)).await
+    }
+
+    async fn stream_prompt(&self, prompt: Prompt) -> Result<CodexStreamCompletion, CodexErr> {
+        let mut attempt = 0;
+        let stream = loop {
+            match self.client.stream(&prompt).await {
+                Ok(stream) => break stream,
+                Err(e) => {
+                    attempt += 1;
+                    match self.retry_delay(&e, attempt) {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        None => return Err(e),
+                    }
+                }
+            }
+        };
+
+        Ok(CodexStreamCompletion {
+            provider: self.clone(),
+            prompt,
+            stream,
+            attempt,
+            saw_delta: false,
+            yielded: false,
+            done: false,
+        })
+    }
+
+    async fn try_complete(&self, prompt: &Prompt) -> Result<String, CodexErr> {
+        let mut stream = self.client.stream(prompt).await?;
+        let mut deltas = String::new();
+        let mut message = None;
+
+        while let Some(event) = stream.next().await {
+            match event? {
+                ResponseEvent::OutputTextDelta(delta) => deltas.push_str(&delta),
+                ResponseEvent::OutputItemDone(item) => {
+                    if let Some(text) = assistant_text(&item) {
+                        message = Some(text);
+                    }
+                }
+                ResponseEvent::Completed { token_usage, .. } => {
+                    record_usage(&self.token_usage, token_usage.as_ref());
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(message.unwrap_or(deltas))
+    }
+}
+
+/// Streaming completion over a `ResponseStream`.
+///
+/// A stream that fails before yielding any text is reopened with the same
+/// prompt, sharing `stream_max_retries` with the initial request. Once text
+/// has been yielded an error is returned as is, since a retry would repeat it.
+pub struct CodexStreamCompletion {
+    provider: CodexModelProvider,
+    prompt: Prompt,
+    stream: ResponseStream,
+    attempt: u64,
+    saw_delta: bool,
+    yielded: bool,
+    done: bool,
+}
+
+impl CodexStreamCompletion {
+    /// Next chunk of assistant text, or `None` once the turn is complete.
+    ///
+    /// Chat providers aggregate their output into a single message, so when no
+    /// deltas were observed the final message is yielded as one chunk.
+    pub async fn next_chunk(&mut self) -> Option<Result<String, CodexErr>> {
+        if self.done {
+            return None;
+        }
+
+        while let Some(event) = self.stream.next().await {
+            match event {
+                Ok(ResponseEvent::OutputTextDelta(delta)) => {
+                    self.saw_delta = true;
+                    self.yielded = true;
+                    return Some(Ok(delta));
+                }
+                Ok(ResponseEvent::OutputItemDone(item)) if !self.saw_delta => {
+                    if let Some(text) = assistant_text(&item) {
+                        self.yielded = true;
+                        return Some(Ok(text));
+                    }
+                }
+                Ok(ResponseEvent::Completed { token_usage, .. }) => {
+                    record_usage(&self.provider.token_usage, token_usage.as_ref());
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    if let Err(e) = self.reopen(e).await {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }
+
+        self.done = true;
+        None
+    }
+
+    /// Replace the failed stream with a fresh one, or hand `err` back when
+    /// text was already yielded or no retries are left.
+    async fn reopen(&mut self, mut err: CodexErr) -> Result<(), CodexErr> {
+        if self.yielded {
+            return Err(err);
+        }
+
+        loop {
+            self.attempt += 1;
+            let Some(delay) = self.provider.retry_delay(&err, self.attempt) else {
+                return Err(err);
+            };
+            tokio::time::sleep(delay).await;
+
+            match self.provider.client.stream(&self.prompt).await {
+                Ok(stream) => {
+                    self.stream = stream;
+                    return Ok(());
+                }
+                Err(e) => err = e,
+            }
+        }
+    }
+}
+
+fn build_prompt(system_prompt: Option<&str>, user_prompt: &str) -> Prompt {
+    let mut prompt = Prompt::default();
+    prompt.input = vec![ResponseItem::Message {
+        id: None,
+        role: "user".to_string(),
+        content: vec![ContentItem::InputText { text: user_prompt.to_string() }],
+    }];
+    prompt.base_instructions_override = Some(system_prompt.unwrap_or(DEFAULT_INSTRUCTIONS).to_string());
+    prompt
+}
+
+fn record_usage(total: &Mutex<TokenUsage>, usage: Option<&TokenUsage>) {
+    if let (Some(usage), Ok(mut total)) = (usage, total.lock()) {
+        total.add_assign(usage);
+    }
+}
+
+fn assistant_text(item: &ResponseItem) -> Option<String> {
+    let ResponseItem::Message { role, content, .. } = item else {
+        return None;
+    };
+    if role != "assistant" {
+        return None;
+    }
+
+    let text: String = content
+        .iter()
+        .filter_map(|part| match part {
+            ContentItem::OutputText { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    Some(text)
+}
+
+#[async_trait]
+impl ModelProvider for CodexModelProvider {
+    async fn generate_completion(&self, prompt: &str) -> Result<String, AgentError> {
+        Ok(self.complete(None, prompt).await?)
+    }
+
+    async fn generate_with_context(&self, system_prompt: &str, user_prompt: &str) -> Result<String, AgentError> {
+        Ok(self.complete(Some(system_prompt), user_prompt).await?)
+    }
+
+    async fn generate_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<String, AgentError> {
+        let mut prompt = build_prompt(Some(system_prompt), user_prompt);
+        prompt.output_schema = Some(output_schema.clone());
+        Ok(self.complete_prompt(prompt).await?)
+    }
+
+    async fn stream_completion(&self, system_prompt: Option<&str>, user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        Ok(Box::new(self.stream(system_prompt, user_prompt).await?))
+    }
+
+    async fn stream_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        let mut prompt = build_prompt(Some(system_prompt), user_prompt);
+        prompt.output_schema = Some(output_schema.clone());
+        Ok(Box::new(self.stream_prompt(prompt).await?))
+    }
+
+    fn token_usage(&self) -> TokenUsage {
+        self.token_usage.lock().map(|usage| usage.clone()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl StreamCompletion for CodexStreamCompletion {
+    async fn next(&mut self) -> Option<Result<String, AgentError>> {
+        Some(self.next_chunk().await?.map_err(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codex_core::config::ConfigToml;
+    use codex_core::{ModelProviderInfo, WireApi};
+    use core_test_support::responses::{
+        ev_assistant_message, ev_completed_with_tokens, ev_response_created, mount_sse_once_match, mount_sse_sequence, sse,
+    };
+    use tempfile::TempDir;
+    use wiremock::matchers::body_string_contains;
+    use wiremock::MockServer;
+
+    fn provider_for(server: &MockServer, codex_home: &TempDir, stream_max_retries: u64) -> CodexModelProvider {
+        let mut config =
+            Config::load_from_base_config_with_overrides(ConfigToml::default(), ConfigOverrides::default(), codex_home.path().to_path_buf())
+                .unwrap();
+        config.model_provider = ModelProviderInfo {
+            name: "mock".into(),
+            base_url: Some(format!("{}/v1", server.uri())),
+            env_key: None,
+            env_key_instructions: None,
+            wire_api: WireApi::Responses,
+            query_params: None,
+            http_headers: None,
+            env_http_headers: None,
+            request_max_retries: Some(0),
+            stream_max_retries: Some(stream_max_retries),
+            stream_idle_timeout_ms: Some(5_000),
+            requires_openai_auth: false,
+        };
+        CodexModelProvider::new(Arc::new(config), None)
+    }
+
+    #[tokio::test]
+    async fn test_generate_with_context_uses_model_client() {
+        let server = MockServer::start().await;
+        let body = sse(vec![ev_assistant_message("msg-1", "Generated answer"), ev_completed_with_tokens("resp-1", 42)]);
+        mount_sse_once_match(&server, body_string_contains("Be terse."), body).await;
+
+        let codex_home = TempDir::new().unwrap();
+        let provider = provider_for(&server, &codex_home, 0);
+
+        let response = provider.generate_with_context("Be terse.", "Say something").await.unwrap();
+        assert_eq!(response, "Generated answer");
+        assert_eq!(provider.token_usage().total_tokens, 42);
+    }
+
+    #[tokio::test]
+    async fn test_stream_retries_failure_before_first_chunk() {
+        let server = MockServer::start().await;
+        // The first response closes before `response.completed`
+        mount_sse_sequence(
+            &server,
+            vec![
+                sse(vec![ev_response_created("resp-1")]),
+                sse(vec![ev_assistant_message("msg-2", "Retried answer"), ev_completed_with_tokens("resp-2", 7)]),
+            ],
+        )
+        .await;
+
+        let codex_home = TempDir::new().unwrap();
+        let provider = provider_for(&server, &codex_home, 1);
+
+        let mut stream = provider.stream_completion(None, "Say something").await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), "Retried answer");
+        assert!(stream.next().await.is_none());
+        assert_eq!(provider.token_usage().total_tokens, 7);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_error_once_retries_are_exhausted() {
+        let server = MockServer::start().await;
+        mount_sse_sequence(&server, vec![sse(vec![ev_response_created("resp-1")])]).await;
+
+        let codex_home = TempDir::new().unwrap();
+        let provider = provider_for(&server, &codex_home, 0);
+
+        let mut stream = provider.stream_completion(None, "Say something").await.unwrap();
+        let err = stream.next().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("stream closed before response.completed"));
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,1069 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use codex_core::protocol::SandboxPolicy;
+
+use crate::code::{apply_patches, generated_patch_schema, verify_patches, CodeChange, GeneratedPatch, PatchFailure, PATCH_FORMAT_INSTRUCTIONS};
+use crate::log_ingest::{LogCluster, LogDigest, LogIngester, LogSource, StackFrame};
+use crate::test_runner::{duration_ms, sandboxed_exec, tail, ScratchWorktree, OUTPUT_TAIL_BYTES};
+use crate::structured::{array_schema, enum_schema, generate_json, object_schema, string_array_schema};
+use crate::{AgentError, ModelProvider};
+
+/// Debug Agent: Analyzes logs and suggests fixes; produces patch candidates
+#[derive(Clone)]
+pub struct DebugAgent {
+    model_provider: Arc<dyn ModelProvider>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugRequest {
+    /// Pre-parsed entries; ingested together with `raw_logs` and `log_sources`.
+    #[serde(default)]
+    pub logs: Vec<LogEntry>,
+    /// Raw log text in any format [`LogFormat::Auto`](crate::log_ingest::LogFormat::Auto) detects.
+    #[serde(default)]
+    pub raw_logs: Option<String>,
+    /// Log files to stream from disk.
+    #[serde(default)]
+    pub log_sources: Vec<LogSource>,
+    /// Repository root that stack frames are mapped to. Files named by the
+    /// frames are read from it when not in `codebase_files`, and patch
+    /// suggestions are verified against it.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Command that reproduces the failure; requires `cwd`.
+    #[serde(default)]
+    pub reproduction: Option<Reproduction>,
+    pub error_context: Option<HashMap<String, String>>,
+    pub codebase_files: Option<Vec<CodebaseFile>>,
+    pub recent_changes: Option<Vec<String>>,
+    pub debug_focus: Option<Vec<DebugFocus>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: LogLevel,
+    pub message: String,
+    pub source: String,
+    pub context: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodebaseFile {
+    pub path: String,
+    pub content: String,
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DebugFocus {
+    ErrorAnalysis,
+    PerformanceIssues,
+    MemoryLeaks,
+    RaceConditions,
+    IntegrationIssues,
+    ConfigurationProblems,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugAnalysis {
+    pub issues: Vec<DebugIssue>,
+    pub root_causes: Vec<RootCause>,
+    pub patterns: Vec<LogPattern>,
+    pub recommendations: Vec<DebugRecommendation>,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugIssue {
+    pub id: String,
+    pub severity: IssueSeverity,
+    pub category: IssueCategory,
+    pub title: String,
+    pub description: String,
+    pub affected_files: Vec<String>,
+    pub related_logs: Vec<String>,
+    pub reproduction_steps: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IssueSeverity {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IssueCategory {
+    RuntimeError,
+    Performance,
+    Memory,
+    Configuration,
+    Integration,
+    Logic,
+    Security,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RootCause {
+    pub id: String,
+    pub description: String,
+    pub confidence: f32,
+    pub evidence: Vec<String>,
+    pub fix_suggestion: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogPattern {
+    pub pattern_type: PatternType,
+    pub description: String,
+    pub frequency: usize,
+    pub severity: PatternSeverity,
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PatternType {
+    ErrorSpike,
+    ResourceExhaustion,
+    SlowQuery,
+    MemoryGrowth,
+    FailedConnection,
+    Timeout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PatternSeverity {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugRecommendation {
+    pub id: String,
+    pub priority: RecommendationPriority,
+    pub title: String,
+    pub description: String,
+    pub action_items: Vec<String>,
+    pub estimated_effort: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecommendationPriority {
+    Immediate,
+    High,
+    Medium,
+    Low,
+}
+
+/// A verified fix for one file.
+///
+/// `patch` is in the `*** Begin Patch` grammar understood by `apply_patch` and
+/// applies cleanly to the files the suggestion was generated against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchSuggestion {
+    pub file_path: String,
+    pub patch: String,
+    pub unified_diff: Option<String>,
+    pub old_content: String,
+    pub new_content: String,
+    pub explanation: String,
+    pub confidence: f32,
+    pub related_issue_id: String,
+    /// The reproduction command run with all of the issue's patches applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reproduction: Option<ReproductionRun>,
+}
+
+/// Command that reproduces a failure, e.g. a failing test.
+///
+/// It is run in a sandboxed scratch copy of the repository, once unpatched
+/// and once per issue with that issue's patches applied; patches are only
+/// suggested if the command then succeeds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reproduction {
+    pub command: Vec<String>,
+    #[serde(default = "default_reproduction_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Defaults to writing only inside the scratch copy, without network
+    /// access; `danger-full-access` runs the command unsandboxed.
+    #[serde(default)]
+    pub sandbox_policy: Option<SandboxPolicy>,
+    #[serde(default)]
+    pub codex_linux_sandbox_exe: Option<PathBuf>,
+}
+
+fn default_reproduction_timeout_ms() -> u64 {
+    5 * 60 * 1000
+}
+
+/// One run of the reproduction command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproductionRun {
+    pub command: Vec<String>,
+    /// The command exited zero within its time limit.
+    pub passed: bool,
+    pub exit_code: i32,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    /// Tail of the combined stdout and stderr.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugReport {
+    pub analysis: DebugAnalysis,
+    pub patch_suggestions: Vec<PatchSuggestion>,
+    pub monitoring_recommendations: Vec<String>,
+    pub next_steps: Vec<String>,
+    /// The ingested logs, clustered into templates.
+    #[serde(default)]
+    pub log_digest: LogDigest,
+    /// Generated patches that did not apply or did not fix the reproduction.
+    #[serde(default)]
+    pub rejected_patches: Vec<PatchFailure>,
+    /// The reproduction command run without any patch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reproduction: Option<ReproductionRun>,
+}
+
+/// Patch suggestions for all issues, and the runs that vetted them.
+#[derive(Debug, Default)]
+struct PatchSuggestions {
+    suggestions: Vec<PatchSuggestion>,
+    rejected: Vec<PatchFailure>,
+    reproduction: Option<ReproductionRun>,
+}
+
+/// Clusters put in one prompt; the digest lists the most severe first.
+const MAX_PROMPT_CLUSTERS: usize = 100;
+/// Source files loaded from `cwd` because stack frames point at them, and
+/// files put in one fix prompt.
+const MAX_FRAME_FILES: usize = 10;
+/// Largest source file loaded for a stack frame.
+const MAX_FRAME_FILE_BYTES: u64 = 256 * 1024;
+
+impl DebugAgent {
+    pub fn new(model_provider: Arc<dyn ModelProvider>) -> Self {
+        Self { model_provider }
+    }
+
+    pub async fn analyze_logs(&self, mut request: DebugRequest) -> Result<DebugReport, AgentError> {
+        request.cwd = match request.cwd.take() {
+            Some(cwd) if cwd.is_absolute() => Some(cwd),
+            Some(cwd) => Some(std::env::current_dir()?.join(cwd)),
+            None => None,
+        };
+        if request.reproduction.is_some() && request.cwd.is_none() {
+            return Err("a reproduction command requires cwd".into());
+        }
+
+        // Parse and cluster the logs, and load the files their stack traces point at
+        let log_digest = self.ingest_logs(&mut request).await?;
+
+        // Analyze log patterns and trends
+        let log_analysis = self.analyze_log_patterns(&log_digest).await?;
+
+        // Identify specific issues from logs
+        let issues = self.identify_issues(&log_digest, &request).await?;
+
+        // Determine root causes
+        let root_causes = self.analyze_root_causes(&issues, &request).await?;
+
+        // Generate recommendations
+        let recommendations = self.generate_recommendations(&issues, &root_causes)?;
+
+        // Generate patch suggestions for fixes and check them against the reproduction
+        let patches = self.generate_patch_suggestions(&issues, &root_causes, &log_digest, &request).await?;
+
+        // Generate monitoring recommendations
+        let monitoring_recommendations = self.generate_monitoring_recommendations(&log_analysis)?;
+
+        // Determine next steps
+        let next_steps = self.generate_next_steps(&issues)?;
+
+        // Overall confidence is the mean confidence of the individual root causes
+        let confidence = if root_causes.is_empty() {
+            0.0
+        } else {
+            root_causes.iter().map(|rc| rc.confidence).sum::<f32>() / root_causes.len() as f32
+        };
+
+        let analysis = DebugAnalysis {
+            issues,
+            root_causes,
+            patterns: log_analysis,
+            recommendations,
+            confidence,
+        };
+
+        Ok(DebugReport {
+            analysis,
+            patch_suggestions: patches.suggestions,
+            monitoring_recommendations,
+            next_steps,
+            log_digest,
+            rejected_patches: patches.rejected,
+            reproduction: patches.reproduction,
+        })
+    }
+
+    async fn ingest_logs(&self, request: &mut DebugRequest) -> Result<LogDigest, AgentError> {
+        let cwd = request.cwd.clone();
+        let mut repo_files: Vec<String> = request.codebase_files.iter().flatten().map(|file| file.path.clone()).collect();
+        if let Some(cwd) = &cwd {
+            repo_files.extend(tracked_files(cwd).await);
+        }
+
+        // Files can be hundreds of MB, so parse them off the async runtime
+        let logs = std::mem::take(&mut request.logs);
+        let raw_logs = request.raw_logs.take();
+        let sources = request.log_sources.clone();
+        let digest_root = cwd.clone();
+        let digest = tokio::task::spawn_blocking(move || -> Result<LogDigest, AgentError> {
+            let mut ingester = LogIngester::new();
+            for entry in logs {
+                ingester.push(entry);
+            }
+            if let Some(raw_logs) = raw_logs {
+                ingester.ingest_reader(raw_logs.as_bytes(), Default::default())?;
+            }
+            for source in &sources {
+                ingester
+                    .ingest_file(&source.path, source.format)
+                    .map_err(|e| format!("failed to read {}: {e}", source.path.display()))?;
+            }
+            Ok(ingester.finish(digest_root.as_deref(), &repo_files))
+        })
+        .await??;
+
+        if let Some(cwd) = &cwd {
+            let files = request.codebase_files.get_or_insert_with(Vec::new);
+            for path in digest.repo_files() {
+                if files.len() >= MAX_FRAME_FILES || files.iter().any(|file| file.path == path) {
+                    continue;
+                }
+                if let Some(file) = load_codebase_file(cwd, &path).await {
+                    files.push(file);
+                }
+            }
+        }
+
+        Ok(digest)
+    }
+
+    async fn analyze_log_patterns(&self, digest: &LogDigest) -> Result<Vec<LogPattern>, AgentError> {
+        let system_prompt = r#"You are a log analysis expert. Analyze these logs for patterns:
+
+1. Error frequency and spikes
+2. Resource exhaustion patterns
+3. Performance degradation indicators
+4. Connection and timeout patterns
+5. Memory usage patterns
+
+Identify recurring issues and their characteristics.
+
+The logs have been grouped into message templates, with variable tokens replaced by <*>; each template shows how often it occurred and when."#;
+
+        // Most frequent templates across all levels
+        let mut clusters: Vec<&LogCluster> = digest.clusters.iter().collect();
+        clusters.sort_by_key(|cluster| std::cmp::Reverse(cluster.count));
+        clusters.truncate(MAX_PROMPT_CLUSTERS);
+
+        let user_prompt = format!(
+            "Analyze these logs for patterns ({} entries, by level: {:?}):\n\n{}",
+            digest.total_entries,
+            digest.entries_by_level,
+            describe_clusters(&clusters)
+        );
+
+        let schema = object_schema(json!({ "patterns": array_schema(log_pattern_schema()) }));
+        let output: PatternsOutput =
+            generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+        Ok(output.patterns)
+    }
+
+    async fn identify_issues(&self, digest: &LogDigest, request: &DebugRequest) -> Result<Vec<DebugIssue>, AgentError> {
+        let system_prompt = r#"You are a debugging expert. Given these logs and context, identify specific issues:
+
+1. Runtime errors and exceptions
+2. Performance bottlenecks
+3. Memory leaks or excessive usage
+4. Configuration problems
+5. Integration failures
+6. Logic errors
+
+For each issue, provide:
+- Clear description
+- Affected components/files
+- Reproduction steps when possible
+- Related log entries
+
+Number issues sequentially as ISSUE-001, ISSUE-002, ...
+
+Error logs are grouped into message templates with occurrence counts, examples and the stack frames that map to repository files; list those files as affected files where relevant."#;
+
+        let errors: Vec<&LogCluster> = digest
+            .clusters
+            .iter()
+            .filter(|cluster| matches!(cluster.level, LogLevel::Error | LogLevel::Fatal))
+            .take(MAX_PROMPT_CLUSTERS)
+            .collect();
+
+        let mut user_prompt = format!("Identify issues from these error logs:\n\n{}", describe_clusters(&errors));
+        if let Some(context) = &request.error_context {
+            user_prompt.push_str(&format!("\n\nAdditional context: {:?}", context));
+        }
+        if let Some(focus) = &request.debug_focus {
+            user_prompt.push_str(&format!("\n\nFocus areas: {:?}", focus));
+        }
+        if let Some(files) = &request.codebase_files {
+            let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+            user_prompt.push_str(&format!("\n\nKnown files: {}", paths.join(", ")));
+        }
+
+        let schema = object_schema(json!({ "issues": array_schema(debug_issue_schema()) }));
+        let output: IssuesOutput =
+            generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+        Ok(output.issues)
+    }
+
+    async fn analyze_root_causes(&self, issues: &[DebugIssue], request: &DebugRequest) -> Result<Vec<RootCause>, AgentError> {
+        let mut root_causes = Vec::new();
+
+        for issue in issues {
+            let system_prompt = "Analyze this issue to determine the root cause. Consider code logic, configuration, environment, and dependencies. Give your confidence between 0.0 and 1.0 and cite the evidence that supports it.";
+
+            let mut user_prompt = format!(
+                "Determine root cause for issue: {} - {}\n\nRelated logs:\n{}",
+                issue.title,
+                issue.description,
+                issue.related_logs.join("\n")
+            );
+            if let Some(changes) = request.recent_changes.as_ref().filter(|changes| !changes.is_empty()) {
+                user_prompt.push_str(&format!("\n\nRecent changes:\n- {}", changes.join("\n- ")));
+            }
+            for file in request.codebase_files.iter().flatten() {
+                if issue.affected_files.contains(&file.path) {
+                    user_prompt.push_str(&format!("\n\nFile: {}\n{}", file.path, file.content));
+                }
+            }
+
+            let schema = object_schema(json!({
+                "description": { "type": "string" },
+                "confidence": { "type": "number" },
+                "evidence": string_array_schema(),
+                "fix_suggestion": { "type": "string" },
+            }));
+            let output: RootCauseOutput =
+                generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+            root_causes.push(RootCause {
+                id: format!("RC-{}", issue.id),
+                description: output.description,
+                confidence: output.confidence.clamp(0.0, 1.0),
+                evidence: output.evidence,
+                fix_suggestion: output.fix_suggestion,
+            });
+        }
+
+        Ok(root_causes)
+    }
+
+    fn generate_recommendations(&self, issues: &[DebugIssue], root_causes: &[RootCause]) -> Result<Vec<DebugRecommendation>, AgentError> {
+        let mut recommendations = Vec::new();
+
+        let critical_count = issues.iter().filter(|i| matches!(i.severity, IssueSeverity::Critical)).count();
+        if critical_count > 0 {
+            recommendations.push(DebugRecommendation {
+                id: "REC-001".to_string(),
+                priority: RecommendationPriority::Immediate,
+                title: "Fix critical issues immediately".to_string(),
+                description: format!("{} critical issues require immediate attention", critical_count),
+                action_items: vec![
+                    "Deploy hotfix for critical issues".to_string(),
+                    "Implement monitoring alerts".to_string(),
+                ],
+                estimated_effort: "2-4 hours".to_string(),
+            });
+        }
+
+        // Add specific recommendations based on issue categories
+        for issue in issues {
+            match issue.category {
+                IssueCategory::Performance => {
+                    recommendations.push(DebugRecommendation {
+                        id: format!("REC-PERF-{}", issue.id),
+                        priority: RecommendationPriority::High,
+                        title: format!("Optimize {}", issue.title),
+                        description: issue.description.clone(),
+                        action_items: vec![
+                            "Profile performance bottlenecks".to_string(),
+                            "Implement caching where appropriate".to_string(),
+                        ],
+                        estimated_effort: "4-8 hours".to_string(),
+                    });
+                }
+                IssueCategory::Memory => {
+                    recommendations.push(DebugRecommendation {
+                        id: format!("REC-MEM-{}", issue.id),
+                        priority: RecommendationPriority::High,
+                        title: format!("Fix {}", issue.title),
+                        description: issue.description.clone(),
+                        action_items: vec![
+                            "Review memory allocation patterns".to_string(),
+                            "Implement proper cleanup".to_string(),
+                        ],
+                        estimated_effort: "2-4 hours".to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // Turn confident root cause analyses into concrete fixes
+        for root_cause in root_causes.iter().filter(|rc| rc.confidence >= 0.5) {
+            recommendations.push(DebugRecommendation {
+                id: format!("REC-{}", root_cause.id),
+                priority: RecommendationPriority::Medium,
+                title: "Address root cause".to_string(),
+                description: root_cause.description.clone(),
+                action_items: vec![root_cause.fix_suggestion.clone()],
+                estimated_effort: "Unknown".to_string(),
+            });
+        }
+
+        Ok(recommendations)
+    }
+
+    async fn generate_patch_suggestions(
+        &self,
+        issues: &[DebugIssue],
+        root_causes: &[RootCause],
+        digest: &LogDigest,
+        request: &DebugRequest,
+    ) -> Result<PatchSuggestions, AgentError> {
+        let mut patches = PatchSuggestions::default();
+        let files = request.codebase_files.as_deref().unwrap_or_default();
+        if files.is_empty() && request.cwd.is_none() {
+            return Ok(patches);
+        }
+
+        // Patches are verified against the repository, or against the given
+        // files when there is none
+        let scratch;
+        let verify_root = match &request.cwd {
+            Some(cwd) => cwd.clone(),
+            None => {
+                scratch = ScratchWorktree::new()?;
+                for file in files {
+                    scratch.write(&file.path, &file.content)?;
+                }
+                scratch.root().to_path_buf()
+            }
+        };
+
+        let reproduction = request.reproduction.as_ref().zip(request.cwd.as_deref());
+        if let Some((reproduction, cwd)) = reproduction {
+            patches.reproduction = Some(run_reproduction(cwd, reproduction, &[]).await?);
+        }
+
+        for (issue, root_cause) in issues.iter().zip(root_causes.iter()) {
+            let frames = related_frames(issue, digest);
+            let mut paths: Vec<&str> = issue.affected_files.iter().map(String::as_str).collect();
+            paths.extend(frames.iter().filter_map(|frame| frame.repo_path.as_deref()));
+
+            // Files the request does not carry are read from the repository
+            let mut issue_files: Vec<CodebaseFile> = Vec::new();
+            for path in paths {
+                if issue_files.len() >= MAX_FRAME_FILES || issue_files.iter().any(|file| file.path == path) {
+                    continue;
+                }
+                if let Some(file) = files.iter().find(|file| file.path == path) {
+                    issue_files.push(file.clone());
+                } else if let Some(cwd) = &request.cwd {
+                    issue_files.extend(load_codebase_file(cwd, path).await);
+                }
+            }
+            if issue_files.is_empty() {
+                continue;
+            }
+
+            let generated = self.generate_fix_patches(&issue_files, &frames, issue, root_cause).await?;
+            let (changes, failures) = verify_patches(generated, &verify_root);
+            patches.rejected.extend(failures);
+            if changes.is_empty() {
+                continue;
+            }
+
+            let run = match reproduction {
+                Some((reproduction, cwd)) => {
+                    let issue_patches: Vec<(&str, &str)> = changes
+                        .iter()
+                        .map(|change| (change.file_path.as_str(), change.patch.as_str()))
+                        .collect();
+                    Some(run_reproduction(cwd, reproduction, &issue_patches).await?)
+                }
+                None => None,
+            };
+            if let Some(run) = run.as_ref().filter(|run| !run.passed) {
+                let error = if run.timed_out {
+                    "reproduction command timed out with the patch applied".to_string()
+                } else {
+                    format!("reproduction command still fails with the patch applied (exit code {})", run.exit_code)
+                };
+                patches.rejected.extend(changes.into_iter().map(|change| PatchFailure {
+                    file_path: change.file_path,
+                    patch: change.patch,
+                    error: error.clone(),
+                }));
+                continue;
+            }
+
+            patches
+                .suggestions
+                .extend(changes.into_iter().map(|change| suggestion(change, issue, run.clone())));
+        }
+
+        Ok(patches)
+    }
+
+    async fn generate_fix_patches(
+        &self,
+        files: &[CodebaseFile],
+        frames: &[&StackFrame],
+        issue: &DebugIssue,
+        root_cause: &RootCause,
+    ) -> Result<Vec<GeneratedPatch>, AgentError> {
+        let system_prompt = format!(
+            r#"You are an expert developer generating code fixes. Given the issue and root cause, generate targeted patches that:
+
+1. Fix the specific problem
+2. Maintain existing functionality
+3. Follow the codebase patterns
+4. Include proper error handling
+5. Are minimal and focused
+
+For each file that needs to change, provide its path relative to the repository root, a patch that edits only that file, an explanation of the change, and your confidence between 0.0 and 1.0. Return no patches when no file needs to change.
+
+{PATCH_FORMAT_INSTRUCTIONS}"#
+        );
+
+        let mut user_prompt = format!(
+            "Generate a fix for issue {}: {}\n\n{}\n\nRoot cause: {}\nSuggested fix: {}",
+            issue.id, issue.title, issue.description, root_cause.description, root_cause.fix_suggestion
+        );
+        if !issue.related_logs.is_empty() {
+            user_prompt.push_str(&format!("\n\nRelated logs:\n{}", issue.related_logs.join("\n")));
+        }
+        if !frames.is_empty() {
+            user_prompt.push_str("\n\nStack frames in the repository:");
+            for frame in frames {
+                let line = frame.line.map(|line| format!(":{line}")).unwrap_or_default();
+                let function = frame.function.as_deref().map(|f| format!(" in {f}")).unwrap_or_default();
+                user_prompt.push_str(&format!("\n- {}{}{}", frame.repo_path.as_deref().unwrap_or(&frame.file), line, function));
+            }
+        }
+        for file in files {
+            user_prompt.push_str(&format!("\n\nFile: {} ({})\nContent:\n{}", file.path, file.language, file.content));
+        }
+
+        let schema = object_schema(json!({
+            "patches": array_schema(generated_patch_schema()),
+        }));
+        let output: FixOutput =
+            generate_json(self.model_provider.as_ref(), &system_prompt, &user_prompt, &schema).await?;
+
+        Ok(output.patches)
+    }
+
+    fn generate_monitoring_recommendations(&self, patterns: &[LogPattern]) -> Result<Vec<String>, AgentError> {
+        let mut recommendations = Vec::new();
+
+        for pattern in patterns {
+            match pattern.pattern_type {
+                PatternType::ErrorSpike => {
+                    recommendations.push("Set up alerts for error rate thresholds".to_string());
+                }
+                PatternType::MemoryGrowth => {
+                    recommendations.push("Monitor memory usage and set up garbage collection alerts".to_string());
+                }
+                PatternType::SlowQuery => {
+                    recommendations.push("Monitor database query performance".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        recommendations.push("Implement structured logging with correlation IDs".to_string());
+        recommendations.push("Set up log aggregation and alerting system".to_string());
+
+        Ok(recommendations)
+    }
+
+    fn generate_next_steps(&self, issues: &[DebugIssue]) -> Result<Vec<String>, AgentError> {
+        let mut next_steps = Vec::new();
+
+        if issues.iter().any(|i| matches!(i.severity, IssueSeverity::Critical)) {
+            next_steps.push("Deploy immediate fix for critical issues".to_string());
+        }
+
+        if issues.len() > 5 {
+            next_steps.push("Conduct comprehensive system audit".to_string());
+        }
+
+        next_steps.push("Implement automated monitoring and alerting".to_string());
+        next_steps.push("Review and update error handling patterns".to_string());
+        next_steps.push("Consider implementing circuit breaker patterns".to_string());
+
+        Ok(next_steps)
+    }
+}
+
+/// One block per cluster, e.g. `[12x] Error db::pool: timeout after <*> (first .. last)`
+/// followed by an example and the frames that map to repository files.
+fn describe_clusters(clusters: &[&LogCluster]) -> String {
+    let mut text = String::new();
+    for cluster in clusters {
+        text.push_str(&format!("[{}x] {:?} {}: {}", cluster.count, cluster.level, cluster.source, cluster.template));
+        if let (Some(first), Some(last)) = (&cluster.first_seen, &cluster.last_seen) {
+            text.push_str(&format!(" ({} .. {})", first, last));
+        }
+        text.push('\n');
+        if let Some(example) = cluster.examples.first() {
+            for line in example.lines() {
+                text.push_str(&format!("    {}\n", line));
+            }
+        }
+        for frame in &cluster.frames {
+            if let Some(repo_path) = &frame.repo_path {
+                let line = frame.line.map(|line| format!(":{line}")).unwrap_or_default();
+                let function = frame.function.as_deref().map(|f| format!(" in {f}")).unwrap_or_default();
+                text.push_str(&format!("    frame: {}{}{}\n", repo_path, line, function));
+            }
+        }
+    }
+    text
+}
+
+/// Stack frames in the repository from the error clusters behind `issue`,
+/// i.e. those with an example matching one of its related logs.
+fn related_frames<'a>(issue: &DebugIssue, digest: &'a LogDigest) -> Vec<&'a StackFrame> {
+    let mut frames: Vec<&StackFrame> = Vec::new();
+    let clusters = digest.clusters.iter().filter(|cluster| matches!(cluster.level, LogLevel::Error | LogLevel::Fatal));
+    for cluster in clusters {
+        let related = cluster.examples.iter().any(|example| {
+            issue
+                .related_logs
+                .iter()
+                .any(|log| !log.is_empty() && (example.contains(log.as_str()) || log.contains(example.as_str())))
+        });
+        if !related {
+            continue;
+        }
+        for frame in cluster.frames.iter().filter(|frame| frame.repo_path.is_some()) {
+            if !frames.iter().any(|seen| seen.repo_path == frame.repo_path && seen.line == frame.line) {
+                frames.push(frame);
+            }
+        }
+    }
+    frames
+}
+
+fn suggestion(change: CodeChange, issue: &DebugIssue, reproduction: Option<ReproductionRun>) -> PatchSuggestion {
+    PatchSuggestion {
+        file_path: change.file_path,
+        patch: change.patch,
+        unified_diff: change.unified_diff,
+        old_content: change.old_content,
+        new_content: change.new_content,
+        explanation: change.explanation,
+        confidence: change.confidence,
+        related_issue_id: issue.id.clone(),
+        reproduction,
+    }
+}
+
+/// Run the reproduction command in a scratch copy of `cwd` with `patches` applied.
+///
+/// Patches that reach outside the copy are refused before anything is written.
+async fn run_reproduction(cwd: &Path, reproduction: &Reproduction, patches: &[(&str, &str)]) -> Result<ReproductionRun, AgentError> {
+    if reproduction.command.is_empty() {
+        return Err("the reproduction command is empty".into());
+    }
+    let worktree = ScratchWorktree::create(cwd, &[]).await?;
+    apply_patches(patches, worktree.root())?;
+
+    let policy = reproduction.sandbox_policy.clone().unwrap_or_else(SandboxPolicy::new_workspace_write_policy);
+    let output = sandboxed_exec(
+        worktree.root(),
+        reproduction.command.clone(),
+        reproduction.timeout_ms,
+        &policy,
+        reproduction.codex_linux_sandbox_exe.clone(),
+    )
+    .await
+    .map_err(|e| format!("failed to run the reproduction command: {e}"))?;
+
+    Ok(ReproductionRun {
+        command: reproduction.command.clone(),
+        passed: output.exit_code == 0 && !output.timed_out,
+        exit_code: output.exit_code,
+        timed_out: output.timed_out,
+        duration_ms: duration_ms(output.duration),
+        output: tail(&output.aggregated_output.text, OUTPUT_TAIL_BYTES),
+    })
+}
+
+/// Files tracked by git under `cwd`, relative to it; empty outside a repository.
+async fn tracked_files(cwd: &Path) -> Vec<String> {
+    let output = tokio::process::Command::new("git").args(["ls-files", "-z"]).current_dir(cwd).output().await;
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Read `path` under `cwd`; paths that leave `cwd` are refused, since they
+/// may come from the model.
+async fn load_codebase_file(cwd: &Path, path: &str) -> Option<CodebaseFile> {
+    if !Path::new(path).components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+        return None;
+    }
+    let full_path = cwd.join(path);
+    let metadata = tokio::fs::metadata(&full_path).await.ok()?;
+    if metadata.len() > MAX_FRAME_FILE_BYTES {
+        return None;
+    }
+    let content = tokio::fs::read_to_string(&full_path).await.ok()?;
+    let language = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_string();
+    Some(CodebaseFile {
+        path: path.to_string(),
+        content,
+        language,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct PatternsOutput {
+    patterns: Vec<LogPattern>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssuesOutput {
+    issues: Vec<DebugIssue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RootCauseOutput {
+    description: String,
+    confidence: f32,
+    evidence: Vec<String>,
+    fix_suggestion: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixOutput {
+    patches: Vec<GeneratedPatch>,
+}
+
+fn log_pattern_schema() -> Value {
+    object_schema(json!({
+        "pattern_type": enum_schema(&[
+            "ErrorSpike",
+            "ResourceExhaustion",
+            "SlowQuery",
+            "MemoryGrowth",
+            "FailedConnection",
+            "Timeout",
+        ]),
+        "description": { "type": "string" },
+        "frequency": { "type": "integer" },
+        "severity": enum_schema(&["Critical", "High", "Medium", "Low"]),
+        "examples": string_array_schema(),
+    }))
+}
+
+fn debug_issue_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "string" },
+        "severity": enum_schema(&["Critical", "High", "Medium", "Low"]),
+        "category": enum_schema(&[
+            "RuntimeError",
+            "Performance",
+            "Memory",
+            "Configuration",
+            "Integration",
+            "Logic",
+            "Security",
+        ]),
+        "title": { "type": "string" },
+        "description": { "type": "string" },
+        "affected_files": string_array_schema(),
+        "related_logs": string_array_schema(),
+        "reproduction_steps": string_array_schema(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockModelProvider;
+
+    #[tokio::test]
+    async fn test_debug_analysis() {
+        let repo = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(repo.path().join("src")).unwrap();
+        std::fs::write(repo.path().join("src/db.js"), "const pool = createPool({\n  max: 2,\n});\n").unwrap();
+
+        let patches = json!({
+            "patches": [
+                {
+                    "file_path": "src/db.js",
+                    "patch": "*** Begin Patch\n*** Update File: src/db.js\n@@\n const pool = createPool({\n-  max: 2,\n+  max: 20,\n });\n*** End Patch",
+                    "explanation": "Raise the pool size",
+                    "confidence": 0.8
+                },
+                {
+                    "file_path": "src/db.js",
+                    "patch": "*** Begin Patch\n*** Update File: src/db.js\n@@\n-  timeout: 5,\n+  timeout: 50,\n*** End Patch",
+                    "explanation": "Edit a line that does not exist",
+                    "confidence": 0.4
+                }
+            ]
+        });
+        let mock_provider = Arc::new(MockModelProvider::with_responses(
+            vec![
+                r#"{"patterns": [{"pattern_type": "Timeout", "description": "Database connections time out", "frequency": 1, "severity": "High", "examples": ["Connection timeout"]}]}"#.to_string(),
+                r#"{"issues": [{"id": "ISSUE-001", "severity": "High", "category": "Integration", "title": "Database connection timeout", "description": "Queries fail after the pool is exhausted", "affected_files": ["src/db.js"], "related_logs": ["Connection timeout"], "reproduction_steps": []}]}"#.to_string(),
+                r#"{"description": "Connection pool is too small", "confidence": 0.6, "evidence": ["Connection timeout"], "fix_suggestion": "Increase the pool size"}"#.to_string(),
+            ],
+            patches.to_string(),
+        ));
+
+        let agent = DebugAgent::new(mock_provider);
+        let request = DebugRequest {
+            logs: vec![
+                LogEntry {
+                    timestamp: "2024-01-01T10:00:00Z".to_string(),
+                    level: LogLevel::Error,
+                    message: "Connection timeout".to_string(),
+                    source: "database".to_string(),
+                    context: None,
+                }
+            ],
+            raw_logs: None,
+            log_sources: Vec::new(),
+            cwd: Some(repo.path().to_path_buf()),
+            reproduction: Some(Reproduction {
+                command: vec!["grep".to_string(), "-q".to_string(), "max: 20".to_string(), "src/db.js".to_string()],
+                timeout_ms: 10_000,
+                sandbox_policy: Some(SandboxPolicy::DangerFullAccess),
+                codex_linux_sandbox_exe: None,
+            }),
+            error_context: None,
+            codebase_files: None,
+            recent_changes: None,
+            debug_focus: Some(vec![DebugFocus::ErrorAnalysis]),
+        };
+
+        let response = agent.analyze_logs(request).await.unwrap();
+        assert!(matches!(response.analysis.patterns[0].pattern_type, PatternType::Timeout));
+        assert_eq!(response.analysis.issues[0].id, "ISSUE-001");
+        assert_eq!(response.analysis.root_causes[0].id, "RC-ISSUE-001");
+        assert!((response.analysis.confidence - 0.6).abs() < f32::EPSILON);
+
+        // The file is loaded from the repository, the broken patch is rejected
+        // and the remaining one makes the reproduction pass
+        assert!(!response.reproduction.unwrap().passed);
+        assert_eq!(response.patch_suggestions.len(), 1);
+        let suggestion = &response.patch_suggestions[0];
+        assert_eq!(suggestion.file_path, "src/db.js");
+        assert!(suggestion.new_content.contains("max: 20"));
+        assert!(suggestion.reproduction.as_ref().unwrap().passed);
+        assert_eq!(response.rejected_patches.len(), 1);
+        // The repository itself is left untouched
+        assert!(std::fs::read_to_string(repo.path().join("src/db.js")).unwrap().contains("max: 2,"));
+    }
+
+    #[tokio::test]
+    async fn test_patches_escaping_the_scratch_copy_are_rejected() {
+        // The scratch copies live in the system temp dir, so `../` from their
+        // root lands next to them
+        let escaped = format!("codex-escape-{}.txt", uuid::Uuid::new_v4());
+        let target = std::env::temp_dir().join(&escaped);
+        let patch = format!("*** Begin Patch\n*** Add File: ../{escaped}\n+pwned\n*** End Patch");
+
+        // Suggestions verified against the given files
+        let patches = json!({
+            "patches": [
+                { "file_path": format!("../{escaped}"), "patch": patch, "explanation": "Escape", "confidence": 0.9 }
+            ]
+        });
+        let mock_provider = Arc::new(MockModelProvider::with_responses(
+            vec![
+                r#"{"patterns": []}"#.to_string(),
+                r#"{"issues": [{"id": "ISSUE-001", "severity": "High", "category": "Integration", "title": "Crash", "description": "Crash", "affected_files": ["src/db.js"], "related_logs": [], "reproduction_steps": []}]}"#.to_string(),
+                r#"{"description": "Bug", "confidence": 0.6, "evidence": [], "fix_suggestion": "Fix it"}"#.to_string(),
+            ],
+            patches.to_string(),
+        ));
+        let request = DebugRequest {
+            logs: Vec::new(),
+            raw_logs: Some("ERROR crash".to_string()),
+            log_sources: Vec::new(),
+            cwd: None,
+            reproduction: None,
+            error_context: None,
+            codebase_files: Some(vec![CodebaseFile {
+                path: "src/db.js".to_string(),
+                content: "const pool = createPool();\n".to_string(),
+                language: "javascript".to_string(),
+            }]),
+            recent_changes: None,
+            debug_focus: None,
+        };
+
+        let response = DebugAgent::new(mock_provider).analyze_logs(request).await.unwrap();
+        assert!(response.patch_suggestions.is_empty());
+        assert_eq!(response.rejected_patches.len(), 1);
+        assert!(response.rejected_patches[0].error.contains("outside the working tree"));
+        assert!(!target.exists());
+
+        // Patches applied to the copy the reproduction runs in
+        let repo = tempfile::tempdir().unwrap();
+        let reproduction = Reproduction {
+            command: vec!["true".to_string()],
+            timeout_ms: 10_000,
+            sandbox_policy: Some(SandboxPolicy::DangerFullAccess),
+            codex_linux_sandbox_exe: None,
+        };
+        let result = run_reproduction(repo.path(), &reproduction, &[("escape", patch.as_str())]).await;
+        assert!(result.is_err());
+        assert!(!target.exists());
+    }
+}
+
@@ -0,0 +1,308 @@
+use std::path::Path;
+
+use similar::TextDiff;
+use tokio::process::Command;
+
+use crate::reviewer::{DiffHunk, DiffLine, DiffLineType};
+use crate::AgentError;
+
+/// Context lines around each hunk, as in `git diff`.
+const CONTEXT_LINES: usize = 3;
+
+/// The hunks of one changed file.
+#[derive(Debug, Clone)]
+pub(crate) struct FileDiff {
+    /// Path relative to the directory that was diffed; the old path for
+    /// deleted files.
+    pub(crate) file_path: String,
+    pub(crate) hunks: Vec<DiffHunk>,
+}
+
+impl FileDiff {
+    /// Diff two versions of a file in memory.
+    pub(crate) fn between(file_path: &str, old_content: &str, new_content: &str) -> Self {
+        let diff = TextDiff::from_lines(old_content, new_content)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&format!("a/{file_path}"), &format!("b/{file_path}"))
+            .to_string();
+        let hunks = parse_unified_diff(&diff).into_iter().flat_map(|file| file.hunks).collect();
+        Self {
+            file_path: file_path.to_string(),
+            hunks,
+        }
+    }
+
+    /// Render the hunks for a prompt, each line prefixed with its number in the
+    /// new file so findings can cite real lines.
+    pub(crate) fn numbered(&self) -> String {
+        let mut out = String::new();
+        for hunk in &self.hunks {
+            out.push_str(&format!(
+                "@@ -{},{} +{},{} @@\n",
+                hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines
+            ));
+            for line in &hunk.lines {
+                let number = line.new_line_number.map(|n| n.to_string()).unwrap_or_default();
+                let marker = match line.line_type {
+                    DiffLineType::Added => '+',
+                    DiffLineType::Removed => '-',
+                    DiffLineType::Context => ' ',
+                };
+                out.push_str(&format!("{number:>5} {marker} {}\n", line.content));
+            }
+        }
+        out
+    }
+
+    /// Move a reported line range onto the changed lines.
+    ///
+    /// A range that overlaps a hunk is clipped to it; any other range, or a
+    /// missing one, is moved to the changed lines of the nearest hunk.
+    /// Returns `None` when the file has no lines left, e.g. it was deleted.
+    pub(crate) fn anchor(&self, line_start: Option<usize>, line_end: Option<usize>) -> Option<(usize, usize)> {
+        let start = line_start.or(line_end).unwrap_or(0);
+        let end = line_end.unwrap_or(start).max(start);
+
+        let ranges: Vec<(usize, usize)> = self
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.new_lines > 0)
+            .map(|hunk| (hunk.new_start, hunk.new_start + hunk.new_lines - 1))
+            .collect();
+        if let Some((first, last)) = ranges.iter().find(|(first, last)| start <= *last && end >= *first) {
+            return Some((start.max(*first), end.min(*last)));
+        }
+
+        let nearest = self
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.new_lines > 0)
+            .min_by_key(|hunk| hunk.new_start.abs_diff(start))?;
+        let changed: Vec<usize> = nearest
+            .lines
+            .iter()
+            .filter(|line| line.line_type == DiffLineType::Added)
+            .filter_map(|line| line.new_line_number)
+            .collect();
+        match (changed.first(), changed.last()) {
+            (Some(first), Some(last)) => Some((*first, *last)),
+            _ => Some((nearest.new_start, nearest.new_start)),
+        }
+    }
+}
+
+/// Diff the working tree at `cwd`, including untracked files, against `base_ref`.
+///
+/// Only files under `cwd` are included, with paths relative to `cwd` like the
+/// paths of generated code changes. `git diff` reports paths from the
+/// repository root unless given `--relative`, while `ls-files` is always
+/// relative to its working directory.
+///
+/// codex-core's `git_info` only diffs against the closest remote commit it
+/// picks itself, and `TurnDiffTracker` only sees files touched by apply_patch
+/// during a turn, so neither can diff the tree against an arbitrary ref.
+pub(crate) async fn git_diff(cwd: &Path, base_ref: &str) -> Result<Vec<FileDiff>, AgentError> {
+    if codex_core::git_info::get_git_repo_root(cwd).is_none() {
+        return Err(format!("{} is not in a git repository", cwd.display()).into());
+    }
+    if base_ref.starts_with('-') {
+        return Err(format!("invalid base ref '{}'", base_ref).into());
+    }
+
+    let base_sha = git(cwd, &["rev-parse", "--verify", "--quiet", &format!("{base_ref}^{{commit}}")])
+        .await
+        .map_err(|_| format!("unknown base ref '{}'", base_ref))?;
+    let diff = git(
+        cwd,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+            "--relative",
+            base_sha.trim(),
+            "--",
+        ],
+    )
+    .await?;
+    let mut files = parse_unified_diff(&diff);
+
+    let untracked = git(cwd, &["ls-files", "--others", "--exclude-standard", "-z"]).await?;
+    for file_path in untracked.split('\0').filter(|path| !path.is_empty()) {
+        // Binary or unreadable files have nothing to review line by line
+        if let Ok(content) = tokio::fs::read_to_string(cwd.join(file_path)).await {
+            files.push(FileDiff::between(file_path, "", &content));
+        }
+    }
+
+    Ok(files)
+}
+
+async fn git(cwd: &Path, args: &[&str]) -> Result<String, AgentError> {
+    let output = Command::new("git").args(args).current_dir(cwd).output().await?;
+    if !output.status.success() {
+        return Err(format!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `git diff` style output into per-file hunks. Binary files and files
+/// whose only change is their mode have no hunks and are left out.
+pub(crate) fn parse_unified_diff(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let mut old_path: Option<String> = None;
+    let mut old_line = 0;
+    let mut new_line = 0;
+    // Lines still expected in the current hunk, so that a removed line such as
+    // "-- comment" is not mistaken for a file header
+    let mut old_remaining = 0;
+    let mut new_remaining = 0;
+
+    for line in diff.lines() {
+        let in_hunk = old_remaining > 0 || new_remaining > 0;
+        if !in_hunk && line.starts_with("diff ") {
+            old_path = None;
+        } else if let (false, Some(path)) = (in_hunk, line.strip_prefix("--- ")) {
+            old_path = strip_prefix_path(path, "a/");
+        } else if let (false, Some(path)) = (in_hunk, line.strip_prefix("+++ ")) {
+            let file_path = strip_prefix_path(path, "b/").or_else(|| old_path.clone()).unwrap_or_default();
+            files.push(FileDiff {
+                file_path,
+                hunks: Vec::new(),
+            });
+        } else if let (false, Some(header)) = (in_hunk, line.strip_prefix("@@ ")) {
+            let Some(file) = files.last_mut() else { continue };
+            let Some((old_start, old_lines, new_start, new_lines)) = parse_hunk_header(header) else { continue };
+            (old_line, new_line) = (old_start, new_start);
+            (old_remaining, new_remaining) = (old_lines, new_lines);
+            file.hunks.push(DiffHunk {
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+                annotations: Vec::new(),
+            });
+        } else if let Some(hunk) = files.last_mut().and_then(|file| file.hunks.last_mut()) {
+            let (line_type, content) = match line.chars().next() {
+                Some('+') => (DiffLineType::Added, &line[1..]),
+                Some('-') => (DiffLineType::Removed, &line[1..]),
+                Some(' ') => (DiffLineType::Context, &line[1..]),
+                // Blank context lines can lose their leading space
+                None => (DiffLineType::Context, ""),
+                // "\ No newline at end of file"
+                _ => continue,
+            };
+            let (old_line_number, new_line_number) = match line_type {
+                DiffLineType::Added => (None, Some(new_line)),
+                DiffLineType::Removed => (Some(old_line), None),
+                _ => (Some(old_line), Some(new_line)),
+            };
+            if old_line_number.is_some() {
+                old_line += 1;
+                old_remaining = old_remaining.saturating_sub(1);
+            }
+            if new_line_number.is_some() {
+                new_line += 1;
+                new_remaining = new_remaining.saturating_sub(1);
+            }
+            hunk.lines.push(DiffLine {
+                line_type,
+                content: content.to_string(),
+                old_line_number,
+                new_line_number,
+            });
+        }
+    }
+
+    files.retain(|file| !file.hunks.is_empty());
+    files
+}
+
+/// `a/src/lib.rs` -> `src/lib.rs`; `/dev/null` -> `None`.
+fn strip_prefix_path(path: &str, prefix: &str) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or(path);
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `-10,4 +10,6 @@ fn login()` -> `(10, 4, 10, 6)`; a missing count means one line.
+fn parse_hunk_header(header: &str) -> Option<(usize, usize, usize, usize)> {
+    let mut ranges = header.split_whitespace();
+    let old = ranges.next()?.strip_prefix('-')?;
+    let new = ranges.next()?.strip_prefix('+')?;
+    let parse = |range: &str| -> Option<(usize, usize)> {
+        match range.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = parse(old)?;
+    let (new_start, new_lines) = parse(new)?;
+    Some((old_start, old_lines, new_start, new_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_git_diff_hunks_and_anchoring() {
+        let repo = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        let lines: Vec<String> = (1..=20).map(|n| format!("line {n}")).collect();
+        std::fs::write(repo.path().join("lib.txt"), lines.join("\n") + "\n").unwrap();
+        run(&["init", "-q"]);
+        run(&["add", "."]);
+        run(&["commit", "-q", "-m", "base"]);
+
+        let mut changed = lines.clone();
+        changed[14] = "line fifteen".to_string();
+        std::fs::write(repo.path().join("lib.txt"), changed.join("\n") + "\n").unwrap();
+        std::fs::write(repo.path().join("new.txt"), "hello\n").unwrap();
+
+        let files = git_diff(repo.path(), "HEAD").await.unwrap();
+        assert_eq!(files.len(), 2);
+        let lib = &files[0];
+        assert_eq!(lib.file_path, "lib.txt");
+        assert_eq!((lib.hunks[0].new_start, lib.hunks[0].new_lines), (12, 7));
+        let added: Vec<_> = lib.hunks[0].lines.iter().filter(|l| l.line_type == DiffLineType::Added).collect();
+        assert_eq!(added[0].new_line_number, Some(15));
+        assert!(lib.numbered().contains("   15 + line fifteen"));
+        assert_eq!(files[1].file_path, "new.txt");
+
+        // Inside a hunk the range is kept; elsewhere it moves to the changed line
+        assert_eq!(lib.anchor(Some(14), Some(16)), Some((14, 16)));
+        assert_eq!(lib.anchor(Some(2), None), Some((15, 15)));
+        assert_eq!(lib.anchor(None, None), Some((15, 15)));
+
+        // From a subdirectory both tracked and untracked paths are relative to it
+        std::fs::create_dir(repo.path().join("src")).unwrap();
+        std::fs::write(repo.path().join("src/mod.txt"), "tracked\n").unwrap();
+        run(&["add", "src/mod.txt"]);
+        run(&["commit", "-q", "-m", "src"]);
+        std::fs::write(repo.path().join("src/mod.txt"), "changed\n").unwrap();
+        std::fs::write(repo.path().join("src/extra.txt"), "untracked\n").unwrap();
+        let files = git_diff(&repo.path().join("src"), "HEAD").await.unwrap();
+        let paths: Vec<_> = files.iter().map(|file| file.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["mod.txt", "extra.txt"]);
+
+        assert!(git_diff(repo.path(), "no-such-ref").await.is_err());
+        assert!(git_diff(repo.path(), "--output=x").await.is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::model_provider::{AgentError, ModelProvider, StreamCompletion, TokenUsage};
+use crate::{AgentExecution, AgentOrchestrationResponse, AgentType};
+
+/// Progress event emitted while an orchestration runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    AgentStarted {
+        step_id: String,
+        agent_type: AgentType,
+    },
+    TokenDelta {
+        step_id: String,
+        agent_type: AgentType,
+        delta: String,
+    },
+    /// A pipeline step's `when` condition did not hold.
+    StepSkipped {
+        step_id: String,
+        agent_type: AgentType,
+    },
+    AgentFinished {
+        execution: AgentExecution,
+    },
+    Warning {
+        message: String,
+    },
+    Completed {
+        response: AgentOrchestrationResponse,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Model provider that streams every completion from `inner` and forwards the
+/// deltas as [`AgentEvent::TokenDelta`] events for one pipeline step.
+pub(crate) struct StreamingModelProvider {
+    inner: Arc<dyn ModelProvider>,
+    agent_type: AgentType,
+    step_id: String,
+    events: UnboundedSender<AgentEvent>,
+}
+
+impl StreamingModelProvider {
+    pub(crate) fn new(
+        inner: Arc<dyn ModelProvider>,
+        agent_type: AgentType,
+        step_id: String,
+        events: UnboundedSender<AgentEvent>,
+    ) -> Self {
+        Self {
+            inner,
+            agent_type,
+            step_id,
+            events,
+        }
+    }
+
+    /// Forward every delta and return the full text, stopping at the first
+    /// stream error so a broken response fails the step.
+    async fn forward(&self, mut stream: Box<dyn StreamCompletion>) -> Result<String, AgentError> {
+        let mut text = String::new();
+        while let Some(delta) = stream.next().await {
+            let delta = delta?;
+            // A closed receiver cancels the whole run in `orchestrate_stream`.
+            let _ = self.events.send(AgentEvent::TokenDelta {
+                step_id: self.step_id.clone(),
+                agent_type: self.agent_type.clone(),
+                delta: delta.clone(),
+            });
+            text.push_str(&delta);
+        }
+        Ok(text)
+    }
+}
+
+#[async_trait]
+impl ModelProvider for StreamingModelProvider {
+    async fn generate_completion(&self, prompt: &str) -> Result<String, AgentError> {
+        let stream = self.inner.stream_completion(None, prompt).await?;
+        self.forward(stream).await
+    }
+
+    async fn generate_with_context(&self, system_prompt: &str, user_prompt: &str) -> Result<String, AgentError> {
+        let stream = self.inner.stream_completion(Some(system_prompt), user_prompt).await?;
+        self.forward(stream).await
+    }
+
+    async fn generate_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<String, AgentError> {
+        let stream = self.inner.stream_structured(system_prompt, user_prompt, output_schema).await?;
+        self.forward(stream).await
+    }
+
+    async fn stream_completion(&self, system_prompt: Option<&str>, user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        self.inner.stream_completion(system_prompt, user_prompt).await
+    }
+
+    async fn stream_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        self.inner.stream_structured(system_prompt, user_prompt, output_schema).await
+    }
+
+    fn token_usage(&self) -> TokenUsage {
+        self.inner.token_usage()
+    }
+}
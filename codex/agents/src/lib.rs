@@ -1,28 +1,57 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::sync::mpsc;
+
+use pipeline::StepOutcome;
 
 /// Main Agents module that orchestrates all agent types
 pub mod spec;
+pub mod spec_document;
 pub mod code;
 pub mod test_generator;
+pub mod test_runner;
 pub mod reviewer;
 pub mod debug;
 
+mod codex_provider;
+mod diff;
+mod events;
+pub mod log_ingest;
+mod model_provider;
+mod paths;
+pub mod pipeline;
+mod structured;
+#[cfg(test)]
+mod test_support;
+
+pub use codex_provider::{CodexModelProvider, CodexStreamCompletion};
+pub use events::AgentEvent;
+pub use model_provider::{AgentError, ModelProvider, StaticStreamCompletion, StreamCompletion, TokenUsage};
+pub use pipeline::{Pipeline, PipelineStep, ReviewLoop, StepCondition, StepStatus};
+
 pub use spec::{SpecAgent, SpecRequest, SpecResponse};
+pub use spec_document::{PersistedSpec, SpecDiff, SpecDocument, SpecDocumentOptions};
 pub use code::{CodeAgent, CodeRequest as CodeGenerationRequest, CodeStream};
 pub use test_generator::{TestGeneratorAgent, TestRequest as TestGenerationRequest, TestSuite};
+pub use test_runner::{TestRun, TestRunOptions, TestRunStatus, TestVerification};
 pub use reviewer::{ReviewerAgent, ReviewRequest, ReviewReport};
-pub use debug::{DebugAgent, DebugRequest, DebugReport};
+pub use debug::{DebugAgent, DebugRequest, DebugReport, PatchSuggestion, Reproduction, ReproductionRun};
+pub use log_ingest::{LogDigest, LogFormat, LogSource};
 
 /// Agent orchestration and coordination
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct AgentSuite {
     spec_agent: Arc<SpecAgent>,
     code_agent: Arc<CodeAgent>,
     test_generator: Arc<TestGeneratorAgent>,
     reviewer: Arc<ReviewerAgent>,
     debug_agent: Arc<DebugAgent>,
+    /// Provider shared by all agents, when the suite was built from one. Used to
+    /// stream token deltas during `orchestrate_stream`.
+    model_provider: Option<Arc<dyn ModelProvider>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,14 +60,28 @@ pub struct AgentOrchestrationRequest {
     pub context: Option<HashMap<String, String>>,
     pub agent_sequence: Option<Vec<AgentType>>,
     pub options: Option<HashMap<String, String>>,
+    /// Steps to run; takes precedence over `agent_sequence`. Without either,
+    /// [`Pipeline::default`] runs.
+    #[serde(default)]
+    pub pipeline: Option<Pipeline>,
+    /// Directory the path options (`cwd`, `log_files`, `spec_dir`) must stay in. Relative
+    /// paths are resolved against it and `cwd` defaults to it. Set by the
+    /// service accepting the request, never by its caller.
+    #[serde(skip)]
+    pub workspace_root: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum AgentType {
+    #[serde(alias = "spec")]
     Spec,
+    #[serde(alias = "code")]
     Code,
+    #[serde(alias = "test_generator")]
     TestGenerator,
+    #[serde(alias = "reviewer")]
     Reviewer,
+    #[serde(alias = "debug")]
     Debug,
 }
 
@@ -51,12 +94,33 @@ pub struct AgentOrchestrationResponse {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentExecution {
+    /// Pipeline step that ran the agent.
+    #[serde(default)]
+    pub step_id: String,
     pub agent_type: AgentType,
     pub input: serde_json::Value,
     pub output: serde_json::Value,
     pub success: bool,
     pub execution_time_ms: u64,
     pub error_message: Option<String>,
+    /// Rounds of a code step's review/fix loop, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub review_rounds: Vec<ReviewRound>,
+}
+
+/// One attempt of a review/fix loop and the reviewer's verdict on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRound {
+    pub iteration: usize,
+    pub patch: String,
+    pub approval: reviewer::ApprovalStatus,
+    pub findings: Vec<reviewer::ReviewFinding>,
+}
+
+impl ReviewRound {
+    pub fn approved(&self) -> bool {
+        matches!(self.approval, reviewer::ApprovalStatus::Approved | reviewer::ApprovalStatus::ApprovedWithComments)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +129,9 @@ pub struct OrchestrationMetadata {
     pub agents_executed: usize,
     pub success_rate: f32,
     pub warnings: Vec<String>,
+    /// Steps whose `when` condition did not hold.
+    #[serde(default)]
+    pub skipped_steps: Vec<String>,
 }
 
 impl AgentSuite {
@@ -81,55 +148,144 @@ impl AgentSuite {
             test_generator: Arc::new(test_generator),
             reviewer: Arc::new(reviewer),
             debug_agent: Arc::new(debug_agent),
+            model_provider: None,
         }
     }
 
-    pub async fn orchestrate(&self, request: AgentOrchestrationRequest) -> Result<AgentOrchestrationResponse, Box<dyn std::error::Error>> {
-        let start_time = std::time::Instant::now();
-        let mut executions = Vec::new();
-        let mut warnings = Vec::new();
+    /// Create a suite whose agents all share one model provider
+    pub fn with_model_provider(model_provider: Arc<dyn ModelProvider>) -> Self {
+        let mut suite = Self::new(
+            SpecAgent::new(model_provider.clone()),
+            CodeAgent::new(model_provider.clone()),
+            TestGeneratorAgent::new(model_provider.clone()),
+            ReviewerAgent::new(model_provider.clone()),
+            DebugAgent::new(model_provider.clone()),
+        );
+        suite.model_provider = Some(model_provider);
+        suite
+    }
 
-        // Determine execution order
-        let agent_sequence = request.agent_sequence.unwrap_or_else(|| {
-            vec![
-                AgentType::Spec,      // Generate requirements first
-                AgentType::Code,      // Generate code based on spec
-                AgentType::Reviewer,   // Review the generated code
-                AgentType::TestGenerator, // Generate tests for the code
-            ]
+    pub async fn orchestrate(&self, request: AgentOrchestrationRequest) -> Result<AgentOrchestrationResponse, AgentError> {
+        self.run(request, None).await
+    }
+
+    /// Run the orchestration in the background and stream its progress.
+    ///
+    /// The last event is always [`AgentEvent::Completed`] or [`AgentEvent::Failed`].
+    /// Token deltas are only emitted for suites built with [`AgentSuite::with_model_provider`].
+    /// Dropping the receiver cancels the orchestration, including any model
+    /// call in flight.
+    pub fn orchestrate_stream(&self, request: AgentOrchestrationRequest) -> mpsc::UnboundedReceiver<AgentEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let suite = self.clone();
+
+        tokio::spawn(async move {
+            let event = tokio::select! {
+                result = suite.run(request, Some(&tx)) => match result {
+                    Ok(response) => AgentEvent::Completed { response },
+                    Err(e) => AgentEvent::Failed { error: e.to_string() },
+                },
+                _ = tx.closed() => return,
+            };
+            let _ = tx.send(event);
         });
 
-        let mut previous_output = None;
+        rx
+    }
 
-        for agent_type in agent_sequence {
-            let execution_start = std::time::Instant::now();
+    async fn run(
+        &self,
+        request: AgentOrchestrationRequest,
+        events: Option<&mpsc::UnboundedSender<AgentEvent>>,
+    ) -> Result<AgentOrchestrationResponse, AgentError> {
+        let emit = |event: AgentEvent| {
+            if let Some(events) = events {
+                let _ = events.send(event);
+            }
+        };
+        let start_time = std::time::Instant::now();
+        let mut executions = Vec::new();
+        let mut warnings = Vec::new();
+        let mut skipped_steps = Vec::new();
 
-            let (input, output, success, error) = match self.execute_agent(&agent_type, &request, previous_output.as_ref()).await {
-                Ok((input, output)) => {
-                    previous_output = Some(output.clone());
-                    (input, output, true, None)
-                }
-                Err(e) => {
-                    warnings.push(format!("Agent {:?} failed: {}", agent_type, e));
-                    let error_output = serde_json::json!({
-                        "error": e.to_string(),
-                        "agent": format!("{:?}", agent_type)
-                    });
-                    (serde_json::Value::Null, error_output, false, Some(e.to_string()))
+        let mut pipeline = match (&request.pipeline, &request.agent_sequence) {
+            (Some(pipeline), _) => pipeline.clone(),
+            (None, Some(agent_sequence)) => Pipeline::linear(agent_sequence),
+            (None, None) => Pipeline::default(),
+        };
+        // `max_review_iterations` turns on the review/fix loop for every code step
+        if let Some(max_iterations) = request.options.as_ref().and_then(|o| o.get("max_review_iterations")) {
+            let max_iterations = max_iterations.parse().map_err(|e| format!("invalid max_review_iterations: {e}"))?;
+            for step in pipeline.steps.iter_mut().filter(|step| step.agent == AgentType::Code && step.review.is_none()) {
+                step.review = Some(ReviewLoop {
+                    max_iterations,
+                    ..Default::default()
+                });
+            }
+        }
+        pipeline.validate()?;
+
+        let mut outcomes: HashMap<String, StepOutcome> = HashMap::new();
+        let mut pending: Vec<&PipelineStep> = pipeline.steps.iter().collect();
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            // Start every step whose dependencies have finished
+            while let Some(index) = pending.iter().position(|step| step.dependencies().all(|d| outcomes.contains_key(d))) {
+                let step = pending.remove(index);
+                if step.when.as_ref().is_some_and(|when| !when.holds(&outcomes)) {
+                    emit(AgentEvent::StepSkipped { step_id: step.id.clone(), agent_type: step.agent.clone() });
+                    skipped_steps.push(step.id.clone());
+                    outcomes.insert(step.id.clone(), StepOutcome::Skipped);
+                    continue;
                 }
-            };
 
-            executions.push(AgentExecution {
-                agent_type,
-                input,
-                output,
-                success,
-                execution_time_ms: execution_start.elapsed().as_millis() as u64,
-                error_message: error,
-            });
+                let inputs: Vec<StepInput> = pipeline
+                    .steps
+                    .iter()
+                    .filter(|input| step.inputs.contains(&input.id))
+                    .filter_map(|input| match outcomes.get(&input.id) {
+                        Some(StepOutcome::Succeeded(output)) => Some((input.id.clone(), input.agent.clone(), output.clone())),
+                        _ => None,
+                    })
+                    .collect();
+                running.push(self.run_step(step, &request, inputs, events));
+            }
+
+            let Some(execution) = running.next().await else {
+                break;
+            };
+            if let Some(error) = &execution.error_message {
+                let warning = format!("Agent {:?} failed: {}", execution.agent_type, error);
+                emit(AgentEvent::Warning { message: warning.clone() });
+                warnings.push(warning);
+            }
+            if let Some(round) = execution.review_rounds.last().filter(|round| !round.approved()) {
+                let warning = format!(
+                    "Step {} was not approved after {} review rounds ({:?})",
+                    execution.step_id, round.iteration, round.approval
+                );
+                emit(AgentEvent::Warning { message: warning.clone() });
+                warnings.push(warning);
+            }
+            let outcome = if execution.success {
+                StepOutcome::Succeeded(execution.output.clone())
+            } else {
+                StepOutcome::Failed
+            };
+            outcomes.insert(execution.step_id.clone(), outcome);
+            emit(AgentEvent::AgentFinished { execution: execution.clone() });
+            executions.push(execution);
         }
 
+        // The last step in pipeline order that produced an output
+        let final_result = pipeline.steps.iter().rev().find_map(|step| match outcomes.remove(&step.id) {
+            Some(StepOutcome::Succeeded(output)) => Some(output),
+            _ => None,
+        });
+
         let total_time = start_time.elapsed().as_millis() as u64;
+        let agents_executed = executions.len();
         let success_count = executions.iter().filter(|e| e.success).count();
         let success_rate = if executions.is_empty() {
             0.0
@@ -139,23 +295,121 @@ impl AgentSuite {
 
         Ok(AgentOrchestrationResponse {
             execution_order: executions,
-            final_result: previous_output,
+            final_result,
             metadata: OrchestrationMetadata {
                 total_execution_time_ms: total_time,
-                agents_executed: agent_sequence.len(),
+                agents_executed,
                 success_rate,
                 warnings,
+                skipped_steps,
             },
         })
     }
 
+    /// Run one pipeline step, recording failures in the returned execution.
+    async fn run_step(
+        &self,
+        step: &PipelineStep,
+        request: &AgentOrchestrationRequest,
+        inputs: Vec<StepInput>,
+        events: Option<&mpsc::UnboundedSender<AgentEvent>>,
+    ) -> AgentExecution {
+        let execution_start = std::time::Instant::now();
+        if let Some(events) = events {
+            let _ = events.send(AgentEvent::AgentStarted { step_id: step.id.clone(), agent_type: step.agent.clone() });
+        }
+
+        // When streaming, run the agent against a provider that forwards its deltas
+        let streaming_suite = match (events, &self.model_provider) {
+            (Some(events), Some(provider)) => Some(Self::with_model_provider(Arc::new(
+                events::StreamingModelProvider::new(provider.clone(), step.agent.clone(), step.id.clone(), events.clone()),
+            ))),
+            _ => None,
+        };
+        let suite = streaming_suite.as_ref().unwrap_or(self);
+
+        let result = match &step.review {
+            Some(review) => suite.execute_review_loop(request, &inputs, review).await,
+            None => suite.execute_agent(&step.agent, request, &inputs).await.map(|(input, output)| (input, output, Vec::new())),
+        };
+        let (input, output, success, error, review_rounds) = match result {
+            Ok((input, output, review_rounds)) => (input, output, true, None, review_rounds),
+            Err(e) => {
+                let error_output = serde_json::json!({
+                    "error": e.to_string(),
+                    "agent": format!("{:?}", step.agent)
+                });
+                (serde_json::Value::Null, error_output, false, Some(e.to_string()), Vec::new())
+            }
+        };
+
+        AgentExecution {
+            step_id: step.id.clone(),
+            agent_type: step.agent.clone(),
+            input,
+            output,
+            success,
+            execution_time_ms: execution_start.elapsed().as_millis() as u64,
+            error_message: error,
+            review_rounds,
+        }
+    }
+
+    /// Generate code, review it and feed the findings back as a revision until
+    /// the reviewer approves or the iteration budget runs out.
+    ///
+    /// Returns the code agent's input, its last output and every round.
+    async fn execute_review_loop(
+        &self,
+        request: &AgentOrchestrationRequest,
+        inputs: &[StepInput],
+        settings: &ReviewLoop,
+    ) -> Result<(serde_json::Value, serde_json::Value, Vec<ReviewRound>), AgentError> {
+        let input = self.prepare_agent_input(&AgentType::Code, request, inputs)?;
+        let mut code_request: code::CodeRequest = serde_json::from_value(input.clone())?;
+        let mut rounds = Vec::new();
+
+        loop {
+            let iteration = rounds.len() + 1;
+            let code = self.code_agent.generate_code(code_request.clone()).await?;
+            let review = self
+                .reviewer
+                .review_changes(ReviewRequest {
+                    code_changes: serde_json::from_value(serde_json::to_value(&code.changes)?)?,
+                    requirements: code_request.requirements.clone(),
+                    context: code_request.context.clone(),
+                    review_focus: settings.review_focus.clone(),
+                    cwd: code_request.cwd.clone(),
+                    base_ref: None,
+                })
+                .await?;
+
+            let round = ReviewRound {
+                iteration,
+                patch: code.patch.clone(),
+                approval: review.overall_approval,
+                findings: review.findings,
+            };
+            let approved = round.approved();
+            code_request.revision = Some(code::CodeRevision {
+                previous_patch: round.patch.clone(),
+                findings: round.findings.clone(),
+            });
+            rounds.push(round);
+
+            if approved || iteration >= settings.max_iterations {
+                return Ok((input, serde_json::to_value(code)?, rounds));
+            }
+        }
+    }
+
     async fn execute_agent(
         &self,
         agent_type: &AgentType,
         request: &AgentOrchestrationRequest,
-        previous_output: Option<&serde_json::Value>,
-    ) -> Result<(serde_json::Value, serde_json::Value), Box<dyn std::error::Error>> {
-        let input = self.prepare_agent_input(agent_type, request, previous_output)?;
+        inputs: &[StepInput],
+    ) -> Result<(serde_json::Value, serde_json::Value), AgentError> {
+        let input = self.prepare_agent_input(agent_type, request, inputs)?;
 
         match agent_type {
             AgentType::Spec => {
@@ -191,47 +445,72 @@ impl AgentSuite {
         }
     }
 
+    /// Build an agent's request from the original request and the outputs
+    /// of the steps it consumes, in pipeline order.
+    ///
+    /// Requirements are gathered from every spec input; when several code
+    /// steps are inputs, the last one's changes are used.
     fn prepare_agent_input(
         &self,
         agent_type: &AgentType,
         request: &AgentOrchestrationRequest,
-        previous_output: Option<&serde_json::Value>,
-    ) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        inputs: &[StepInput],
+    ) -> Result<serde_json::Value, AgentError> {
+        let option = |key: &str| request.options.as_ref().and_then(|o| o.get(key));
+        let cwd = match &request.workspace_root {
+            Some(root) => Some(workspace_path(root, root, option("cwd").map_or(".", String::as_str))?),
+            None => option("cwd").cloned(),
+        };
+        let spec_outputs: Vec<&serde_json::Value> = inputs
+            .iter()
+            .filter(|(_, agent, _)| *agent == AgentType::Spec)
+            .map(|(_, _, output)| output)
+            .collect();
+        let requirements = (!spec_outputs.is_empty())
+            .then(|| spec_outputs.into_iter().flat_map(requirement_summaries).collect::<Vec<_>>());
+        let code_output = inputs
+            .iter()
+            .rev()
+            .find(|(_, agent, _)| *agent == AgentType::Code)
+            .map(|(_, _, output)| output);
+
         match agent_type {
             AgentType::Spec => {
                 // Spec agent takes the original prompt and context
+                let spec_dir = match (&request.workspace_root, &cwd, option("spec_dir")) {
+                    (Some(root), Some(cwd), Some(dir)) => Some(workspace_path(root, Path::new(cwd), dir)?),
+                    (_, _, dir) => dir.cloned(),
+                };
                 Ok(serde_json::json!({
                     "prompt": request.prompt,
                     "context": request.context,
-                    "project_type": request.options.as_ref().and_then(|o| o.get("project_type")),
-                    "existing_requirements": request.options.as_ref().and_then(|o| o.get("existing_requirements")),
+                    "project_type": option("project_type"),
+                    "existing_requirements": option("existing_requirements"),
+                    "document": spec_dir.map(|dir| serde_json::json!({ "dir": dir, "slug": option("spec_slug") })),
+                    "cwd": cwd,
                 }))
             }
             AgentType::Code => {
                 // Code agent needs spec output and context
-                if let Some(spec_output) = previous_output {
-                    Ok(serde_json::json!({
-                        "prompt": request.prompt,
-                        "context": request.context,
-                        "requirements": spec_output.get("requirements"),
-                        "existing_files": request.options.as_ref().and_then(|o| o.get("existing_files")),
-                        "target_files": request.options.as_ref().and_then(|o| o.get("target_files")),
-                    }))
-                } else {
-                    Ok(serde_json::json!({
-                        "prompt": request.prompt,
-                        "context": request.context,
-                    }))
-                }
+                Ok(serde_json::json!({
+                    "prompt": request.prompt,
+                    "context": request.context,
+                    "requirements": requirements,
+                    "existing_files": option("existing_files"),
+                    "target_files": option("target_files"),
+                    "cwd": cwd,
+                }))
             }
             AgentType::TestGenerator => {
                 // Test generator needs code changes and requirements
-                if let Some(code_output) = previous_output {
+                if let Some(code_output) = code_output {
                     Ok(serde_json::json!({
                         "code_changes": code_output.get("changes"),
-                        "requirements": code_output.get("requirements"),
-                        "test_framework": request.options.as_ref().and_then(|o| o.get("test_framework")),
-                        "coverage_goals": request.options.as_ref().and_then(|o| o.get("coverage_goals")),
+                        "requirements": requirements,
+                        "test_framework": option("test_framework"),
+                        "coverage_goals": option("coverage_goals"),
+                        "cwd": cwd,
+                        "run": option("run_tests").filter(|run| run.as_str() == "true").map(|_| TestRunOptions::default()),
                     }))
                 } else {
                     Ok(serde_json::json!({
@@ -241,116 +520,388 @@ impl AgentSuite {
             }
             AgentType::Reviewer => {
                 // Reviewer needs code changes for review
-                if let Some(code_output) = previous_output {
+                if let Some(code_output) = code_output {
                     Ok(serde_json::json!({
                         "code_changes": code_output.get("changes"),
-                        "requirements": code_output.get("requirements"),
-                        "review_focus": request.options.as_ref().and_then(|o| o.get("review_focus")),
+                        "requirements": requirements,
+                        "review_focus": option("review_focus"),
+                        "cwd": cwd,
+                        "base_ref": option("base_ref"),
                     }))
                 } else {
+                    // Without a code step, review the repository's diff against `base_ref`
                     Ok(serde_json::json!({
                         "prompt": request.prompt,
+                        "requirements": requirements,
+                        "cwd": cwd,
+                        "base_ref": option("base_ref"),
                     }))
                 }
             }
             AgentType::Debug => {
                 // Debug agent needs logs and context
+                let mut log_sources = Vec::new();
+                for path in option("log_files").into_iter().flat_map(|files| files.split(',')) {
+                    let path = match (&request.workspace_root, &cwd) {
+                        (Some(root), Some(cwd)) => workspace_path(root, Path::new(cwd), path.trim())?,
+                        _ => path.trim().to_string(),
+                    };
+                    log_sources.push(serde_json::json!({ "path": path }));
+                }
                 Ok(serde_json::json!({
-                    "logs": request.options.as_ref().and_then(|o| o.get("logs")),
+                    "raw_logs": option("logs"),
+                    "log_sources": log_sources,
+                    "cwd": cwd,
+                    "reproduction": option("reproduction_command")
+                        .map(|command| serde_json::json!({ "command": ["bash", "-lc", command] })),
                     "error_context": request.context,
-                    "codebase_files": request.options.as_ref().and_then(|o| o.get("codebase_files")),
-                    "debug_focus": request.options.as_ref().and_then(|o| o.get("debug_focus")),
+                    "codebase_files": option("codebase_files"),
+                    "debug_focus": option("debug_focus"),
                 }))
             }
         }
     }
 }
 
+/// Resolve the path option `path` against `base`, refusing it when the result
+/// is outside the workspace `root`.
+fn workspace_path(root: &Path, base: &Path, path: &str) -> Result<String, AgentError> {
+    match paths::resolve_within(root, &base.join(path)) {
+        Some(resolved) => Ok(resolved.display().to_string()),
+        None => Err(format!("{} is outside the workspace {}", path, root.display()).into()),
+    }
+}
+
+/// Output of a step consumed by another: the step's id, its agent and its output.
+type StepInput = (String, AgentType, serde_json::Value);
+
+/// One line per requirement in a spec output, e.g. `REQ-001: User login`.
+fn requirement_summaries(spec_output: &serde_json::Value) -> Vec<String> {
+    spec_output
+        .get("requirements")
+        .and_then(|requirements| requirements.as_array())
+        .map(|requirements| {
+            requirements
+                .iter()
+                .filter_map(|requirement| {
+                    let id = requirement.get("id")?.as_str()?;
+                    let title = requirement.get("title").and_then(|t| t.as_str()).unwrap_or_default();
+                    Some(format!("{id}: {title}"))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::MockModelProvider;
 
-    struct MockModelProvider {
-        response: String,
-    }
+    #[tokio::test]
+    async fn test_agent_orchestration() {
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::new("Mock response")));
 
-    impl spec::ModelProvider for MockModelProvider {
-        async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
+        let request = AgentOrchestrationRequest {
+            prompt: "Add user authentication".to_string(),
+            context: None,
+            agent_sequence: Some(vec![AgentType::Spec, AgentType::Code]),
+            options: None,
+            pipeline: None,
+            workspace_root: None,
+        };
 
-        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
+        let response = suite.orchestrate(request).await.unwrap();
+        assert_eq!(response.execution_order.len(), 2);
+        assert_eq!(response.metadata.agents_executed, 2);
     }
 
-    impl code::ModelProvider for MockModelProvider {
-        async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
+    #[tokio::test]
+    async fn test_agent_orchestration_stream() {
+        let analysis = r#"{"language": "rust", "framework": null, "patterns": [], "conventions": [], "dependencies": [], "style_guide": "rustfmt"}"#;
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::with_responses(
+            vec![analysis.to_string()],
+            r#"{"patches": [], "dependencies": []}"#,
+        )));
 
-        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
+        let request = AgentOrchestrationRequest {
+            prompt: "Add user authentication".to_string(),
+            context: None,
+            agent_sequence: Some(vec![AgentType::Code]),
+            options: None,
+            pipeline: None,
+            workspace_root: None,
+        };
 
-        async fn stream_completion(&self, _prompt: &str) -> Result<Box<dyn code::StreamCompletion>, Box<dyn std::error::Error>> {
-            Ok(Box::new(crate::code::MockStreamCompletion::new(self.response.clone())))
+        let mut events = suite.orchestrate_stream(request);
+        let mut received = Vec::new();
+        while let Some(event) = events.recv().await {
+            received.push(event);
         }
+
+        assert!(matches!(received.first(), Some(AgentEvent::AgentStarted { agent_type: AgentType::Code, .. })));
+        let deltas: String = received
+            .iter()
+            .filter_map(|event| match event {
+                AgentEvent::TokenDelta { delta, .. } => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(deltas.starts_with(analysis));
+        assert!(received.iter().any(|event| matches!(event, AgentEvent::AgentFinished { execution } if execution.success)));
+        assert!(matches!(received.last(), Some(AgentEvent::Completed { .. })));
     }
 
-    impl test_generator::ModelProvider for MockModelProvider {
-        async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
+    #[tokio::test]
+    async fn test_agent_orchestration_stream_error_fails_step() {
+        /// Yields one chunk, then fails as a dropped connection would.
+        struct BrokenStream {
+            chunks: usize,
         }
 
-        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
+        #[async_trait::async_trait]
+        impl StreamCompletion for BrokenStream {
+            async fn next(&mut self) -> Option<Result<String, AgentError>> {
+                self.chunks += 1;
+                match self.chunks {
+                    1 => Some(Ok(r#"{"language": "#.to_string())),
+                    2 => Some(Err("connection reset".into())),
+                    _ => None,
+                }
+            }
         }
-    }
 
-    impl reviewer::ModelProvider for MockModelProvider {
-        async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
+        struct BrokenStreamProvider;
 
-        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
-        }
-    }
+        #[async_trait::async_trait]
+        impl ModelProvider for BrokenStreamProvider {
+            async fn generate_completion(&self, _prompt: &str) -> Result<String, AgentError> {
+                Err("not streamed".into())
+            }
+
+            async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, AgentError> {
+                Err("not streamed".into())
+            }
 
-    impl debug::ModelProvider for MockModelProvider {
-        async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
+            async fn stream_completion(&self, _system_prompt: Option<&str>, _user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+                Ok(Box::new(BrokenStream { chunks: 0 }))
+            }
         }
 
-        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, Box<dyn std::error::Error>> {
-            Ok(self.response.clone())
+        let suite = AgentSuite::with_model_provider(Arc::new(BrokenStreamProvider));
+        let request = AgentOrchestrationRequest {
+            prompt: "Add user authentication".to_string(),
+            context: None,
+            agent_sequence: Some(vec![AgentType::Code]),
+            options: None,
+            pipeline: None,
+            workspace_root: None,
+        };
+
+        let mut events = suite.orchestrate_stream(request);
+        let mut received = Vec::new();
+        while let Some(event) = events.recv().await {
+            received.push(event);
         }
+
+        let execution = received
+            .iter()
+            .find_map(|event| match event {
+                AgentEvent::AgentFinished { execution } => Some(execution),
+                _ => None,
+            })
+            .unwrap();
+        assert!(!execution.success);
+        assert!(execution.error_message.as_deref().unwrap_or_default().contains("connection reset"));
     }
 
     #[tokio::test]
-    async fn test_agent_orchestration() {
-        let mock_provider = Box::new(MockModelProvider {
-            response: "Mock response".to_string(),
+    async fn test_agent_orchestration_pipeline() {
+        // One reply that satisfies every structured request made by these agents
+        let reply = serde_json::json!({
+            "requirements": [{"id": "REQ-001", "title": "Login", "description": "Users can log in", "priority": "High", "category": "Functional", "acceptance_criteria": []}],
+            "test_cases": [], "user_stories": [], "estimated_effort": "1 day", "dependencies": [],
+            "language": "rust", "framework": null, "patterns": [], "conventions": [], "style_guide": "rustfmt",
+            "patches": [], "findings": [],
+            "functions": [], "classes": [], "endpoints": [], "workflows": []
         });
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::new(reply.to_string())));
+
+        let pipeline = Pipeline::from_json(
+            r#"{"steps": [
+                {"id": "spec", "agent": "spec"},
+                {"id": "code", "agent": "code", "inputs": ["spec"]},
+                {"id": "review", "agent": "reviewer", "inputs": ["code"]},
+                {"id": "tests", "agent": "test_generator", "inputs": ["spec", "code"]},
+                {"id": "debug", "agent": "debug", "when": {"step": "tests", "status": "failed"}}
+            ]}"#,
+        )
+        .unwrap();
+        let request = AgentOrchestrationRequest {
+            prompt: "Add user authentication".to_string(),
+            context: None,
+            agent_sequence: None,
+            options: None,
+            pipeline: Some(pipeline),
+            workspace_root: None,
+        };
 
-        let suite = AgentSuite::new(
-            SpecAgent::new(mock_provider.clone()),
-            CodeAgent::new(mock_provider.clone()),
-            TestGeneratorAgent::new(mock_provider.clone()),
-            ReviewerAgent::new(mock_provider.clone()),
-            DebugAgent::new(mock_provider),
-        );
+        let response = suite.orchestrate(request).await.unwrap();
+        let tests = response.execution_order.iter().find(|e| e.step_id == "tests").unwrap();
+        assert!(tests.success, "{:?}", tests.error_message);
+        assert_eq!(tests.input["requirements"], serde_json::json!(["REQ-001: Login"]));
+        assert_eq!(response.metadata.agents_executed, 4);
+        assert_eq!(response.metadata.skipped_steps, vec!["debug".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_review_fix_loop() {
+        let workdir = tempfile::tempdir().unwrap();
+        let analysis = r#"{"language": "python", "framework": null, "patterns": [], "conventions": [], "dependencies": [], "style_guide": "pep8"}"#;
+        let changes = |body: &str| {
+            serde_json::json!({
+                "patches": [{"file_path": "auth.py", "patch": format!("*** Begin Patch\n*** Add File: auth.py\n+{body}\n*** End Patch"), "explanation": "Add login", "confidence": 0.9}],
+                "dependencies": []
+            })
+            .to_string()
+        };
+        let critical = r#"{"findings": [{"line_start": 1, "line_end": 1, "severity": "Critical", "category": "Security", "title": "Hardcoded password", "description": "The password is stored in source", "suggestion": "Read it from the environment", "examples": []}]}"#;
+        let no_findings = r#"{"findings": []}"#;
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::with_responses(
+            vec![
+                // Round 1: the reviewer rejects the hardcoded password
+                analysis.to_string(),
+                changes("PASSWORD = 'hunter2'"),
+                critical.to_string(),
+                no_findings.to_string(),
+                // Round 2: the revision is approved
+                analysis.to_string(),
+                changes("PASSWORD = os.environ['PASSWORD']"),
+                no_findings.to_string(),
+                no_findings.to_string(),
+            ],
+            no_findings,
+        )));
+
+        let request = AgentOrchestrationRequest {
+            prompt: "Add login".to_string(),
+            context: None,
+            agent_sequence: Some(vec![AgentType::Code]),
+            options: Some(HashMap::from([
+                ("cwd".to_string(), workdir.path().display().to_string()),
+                ("max_review_iterations".to_string(), "3".to_string()),
+            ])),
+            pipeline: None,
+            workspace_root: None,
+        };
 
+        let response = suite.orchestrate(request).await.unwrap();
+        let execution = &response.execution_order[0];
+        assert!(execution.success, "{:?}", execution.error_message);
+        assert_eq!(execution.review_rounds.len(), 2);
+        assert!(matches!(execution.review_rounds[0].approval, reviewer::ApprovalStatus::Rejected));
+        assert_eq!(execution.review_rounds[0].findings[0].title, "Hardcoded password");
+        assert!(execution.review_rounds[1].approved());
+        assert!(execution.output["patch"].as_str().unwrap().contains("os.environ"));
+        assert!(response.metadata.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_inputs_from_steps_with_the_same_agent() {
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::new("Mock response")));
         let request = AgentOrchestrationRequest {
             prompt: "Add user authentication".to_string(),
             context: None,
-            agent_sequence: Some(vec![AgentType::Spec, AgentType::Code]),
+            agent_sequence: None,
             options: None,
+            pipeline: None,
+            workspace_root: None,
+        };
+        let spec = |id: &str, title: &str| serde_json::json!({ "requirements": [{ "id": id, "title": title }] });
+        let code = |file_path: &str| serde_json::json!({ "changes": [{ "file_path": file_path }] });
+        let inputs = vec![
+            ("1-spec".to_string(), AgentType::Spec, spec("REQ-001", "Login")),
+            ("2-code".to_string(), AgentType::Code, code("src/first.rs")),
+            ("3-spec".to_string(), AgentType::Spec, spec("REQ-002", "Logout")),
+            ("4-code".to_string(), AgentType::Code, code("src/second.rs")),
+        ];
+
+        let input = suite.prepare_agent_input(&AgentType::TestGenerator, &request, &inputs).unwrap();
+        assert_eq!(input["requirements"], serde_json::json!(["REQ-001: Login", "REQ-002: Logout"]));
+        assert_eq!(input["code_changes"], serde_json::json!([{ "file_path": "src/second.rs" }]));
+    }
+
+    #[test]
+    fn test_paths_outside_the_workspace_are_rejected() {
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::new("Mock response")));
+        let workspace = tempfile::tempdir().unwrap();
+        let root = workspace.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("repo")).unwrap();
+        let request = |options: &[(&str, &str)]| AgentOrchestrationRequest {
+            prompt: "Why does it crash?".to_string(),
+            context: None,
+            agent_sequence: None,
+            options: Some(options.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()),
+            pipeline: None,
+            workspace_root: Some(root.clone()),
+        };
+
+        let input = suite
+            .prepare_agent_input(&AgentType::Debug, &request(&[("cwd", "repo"), ("log_files", "app.log, logs/worker.log")]), &[])
+            .unwrap();
+        assert_eq!(input["cwd"], root.join("repo").display().to_string());
+        assert_eq!(
+            input["log_sources"],
+            serde_json::json!([
+                { "path": root.join("repo/app.log").display().to_string() },
+                { "path": root.join("repo/logs/worker.log").display().to_string() },
+            ])
+        );
+        // `cwd` defaults to the workspace root
+        let input = suite.prepare_agent_input(&AgentType::Code, &request(&[]), &[]).unwrap();
+        assert_eq!(input["cwd"], root.display().to_string());
+        let input = suite.prepare_agent_input(&AgentType::Spec, &request(&[("cwd", "repo"), ("spec_dir", "specs")]), &[]).unwrap();
+        assert_eq!(input["document"]["dir"], root.join("repo/specs").display().to_string());
+        let error = suite
+            .prepare_agent_input(&AgentType::Spec, &request(&[("cwd", "repo"), ("spec_dir", "../../specs")]), &[])
+            .unwrap_err();
+        assert!(error.to_string().contains("is outside the workspace"), "{error}");
+
+        for options in [
+            vec![("cwd", "/")],
+            vec![("cwd", "repo/../..")],
+            vec![("cwd", "repo"), ("log_files", "/etc/passwd")],
+            vec![("log_files", "../secrets.log")],
+        ] {
+            let error = suite.prepare_agent_input(&AgentType::Debug, &request(&options), &[]).unwrap_err();
+            assert!(error.to_string().contains("is outside the workspace"), "{error}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reproduction_does_not_run_outside_the_workspace() {
+        let suite = AgentSuite::with_model_provider(Arc::new(MockModelProvider::new("Mock response")));
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+
+        let request = AgentOrchestrationRequest {
+            prompt: "Why does it crash?".to_string(),
+            context: None,
+            agent_sequence: Some(vec![AgentType::Debug]),
+            options: Some(HashMap::from([
+                ("cwd".to_string(), outside.path().display().to_string()),
+                ("logs".to_string(), "ERROR crash".to_string()),
+                ("reproduction_command".to_string(), "make test".to_string()),
+            ])),
+            pipeline: None,
+            workspace_root: Some(workspace.path().to_path_buf()),
         };
 
         let response = suite.orchestrate(request).await.unwrap();
-        assert_eq!(response.execution_order.len(), 2);
-        assert!(response.metadata.total_execution_time_ms > 0);
+        let execution = &response.execution_order[0];
+        assert!(!execution.success);
+        assert!(execution.error_message.as_ref().unwrap().contains("is outside the workspace"));
+        assert!(execution.input.is_null());
     }
 }
+
@@ -0,0 +1,760 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::debug::{LogEntry, LogLevel};
+
+/// Distinct templates kept; entries beyond that are only counted.
+const MAX_CLUSTERS: usize = 5_000;
+/// Example messages kept per template.
+const MAX_EXAMPLES: usize = 3;
+/// Longest example kept, enough for a typical stack trace.
+const MAX_EXAMPLE_BYTES: usize = 4 * 1024;
+/// Longest template, in characters.
+const MAX_TEMPLATE_CHARS: usize = 200;
+/// Largest binary field accepted from a journal export, matching journald's
+/// own `DATA_SIZE_MAX`.
+const MAX_JOURNAL_FIELD_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Format of a raw log file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Detect the format line by line; journald export files are detected
+    /// from their first field.
+    #[default]
+    Auto,
+    /// One JSON object per line, including `journalctl -o json`.
+    JsonLines,
+    /// `key=value` pairs, e.g. `level=error msg="pool exhausted"`.
+    Logfmt,
+    /// RFC 3164 or RFC 5424 syslog lines.
+    Syslog,
+    /// `journalctl -o export`: blank-line separated records of `FIELD=value` lines.
+    JournalExport,
+    /// Free text such as `2024-01-01T10:00:00Z ERROR db::pool: timeout`.
+    Plain,
+}
+
+/// A log file to ingest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSource {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+/// A frame of a stack trace found in a log message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StackFrame {
+    pub function: Option<String>,
+    /// File as it appears in the trace.
+    pub file: String,
+    pub line: Option<u32>,
+    /// The matching file in the repository, when there is one.
+    pub repo_path: Option<String>,
+}
+
+/// Log entries that share a message template, e.g. `connection to <*> timed out after <*>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogCluster {
+    pub template: String,
+    pub level: LogLevel,
+    pub source: String,
+    pub count: usize,
+    pub first_seen: Option<String>,
+    pub last_seen: Option<String>,
+    pub examples: Vec<String>,
+    /// Stack frames of the first example that has a trace.
+    pub frames: Vec<StackFrame>,
+}
+
+/// Aggregate of all ingested logs, small enough to put in a prompt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogDigest {
+    pub total_entries: usize,
+    pub entries_by_level: BTreeMap<String, usize>,
+    /// Most severe first, then most frequent.
+    pub clusters: Vec<LogCluster>,
+    /// Entries that did not fit once the template limit was reached.
+    pub unclustered_entries: usize,
+}
+
+impl LogDigest {
+    /// Repository files referenced by the clusters' stack frames, most frequent first.
+    pub fn repo_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = Vec::new();
+        for cluster in &self.clusters {
+            for path in cluster.frames.iter().filter_map(|frame| frame.repo_path.as_ref()) {
+                if !files.contains(path) {
+                    files.push(path.clone());
+                }
+            }
+        }
+        files
+    }
+}
+
+/// Streams log entries into [`LogCluster`]s without keeping the entries.
+///
+/// Messages are reduced to templates by replacing every token that contains
+/// a digit with `<*>`, so `timeout after 30s on 10.0.0.7` and
+/// `timeout after 5s on 10.0.0.9` land in the same cluster. Continuation
+/// lines of stack traces are folded into the entry they belong to.
+#[derive(Default)]
+pub struct LogIngester {
+    clusters: Vec<LogCluster>,
+    index: HashMap<(String, String, String), usize>,
+    total_entries: usize,
+    entries_by_level: BTreeMap<String, usize>,
+    unclustered_entries: usize,
+}
+
+impl LogIngester {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        self.total_entries += 1;
+        *self.entries_by_level.entry(format!("{:?}", entry.level)).or_insert(0) += 1;
+
+        let template = template(&entry.message);
+        let key = (format!("{:?}", entry.level), entry.source.clone(), template);
+        let index = match self.index.get(&key) {
+            Some(index) => *index,
+            None if self.clusters.len() >= MAX_CLUSTERS => {
+                self.unclustered_entries += 1;
+                return;
+            }
+            None => {
+                self.clusters.push(LogCluster {
+                    template: key.2.clone(),
+                    level: entry.level.clone(),
+                    source: entry.source.clone(),
+                    count: 0,
+                    first_seen: None,
+                    last_seen: None,
+                    examples: Vec::new(),
+                    frames: Vec::new(),
+                });
+                self.index.insert(key, self.clusters.len() - 1);
+                self.clusters.len() - 1
+            }
+        };
+
+        let cluster = &mut self.clusters[index];
+        cluster.count += 1;
+        if !entry.timestamp.is_empty() {
+            cluster.first_seen.get_or_insert_with(|| entry.timestamp.clone());
+            cluster.last_seen = Some(entry.timestamp.clone());
+        }
+        if cluster.frames.is_empty() {
+            cluster.frames = stack_frames(&entry.message);
+        }
+        if cluster.examples.len() < MAX_EXAMPLES && !cluster.examples.contains(&entry.message) {
+            cluster.examples.push(truncate(&entry.message, MAX_EXAMPLE_BYTES));
+        }
+    }
+
+    /// Parse raw log text, folding multi-line stack traces into one entry.
+    pub fn ingest_reader<R: BufRead>(&mut self, mut reader: R, format: LogFormat) -> std::io::Result<()> {
+        let format = match format {
+            LogFormat::Auto => {
+                let head = reader.fill_buf()?;
+                if head.starts_with(b"__CURSOR=") || head.starts_with(b"__REALTIME_TIMESTAMP=") {
+                    LogFormat::JournalExport
+                } else {
+                    LogFormat::Auto
+                }
+            }
+            format => format,
+        };
+        if format == LogFormat::JournalExport {
+            return self.ingest_journal_export(reader);
+        }
+
+        let mut pending: Option<LogEntry> = None;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(entry) = pending.as_mut().filter(|entry| is_continuation(entry, text)) {
+                entry.message.push('\n');
+                entry.message.push_str(text);
+                continue;
+            }
+            if let Some(entry) = pending.take() {
+                self.push(entry);
+            }
+            pending = Some(parse_line(text, format));
+        }
+        if let Some(entry) = pending {
+            self.push(entry);
+        }
+        Ok(())
+    }
+
+    pub fn ingest_file(&mut self, path: &Path, format: LogFormat) -> std::io::Result<()> {
+        let file = std::fs::File::open(path)?;
+        self.ingest_reader(std::io::BufReader::with_capacity(256 * 1024, file), format)
+    }
+
+    fn ingest_journal_export<R: BufRead>(&mut self, mut reader: R) -> std::io::Result<()> {
+        let mut fields: HashMap<String, String> = HashMap::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_until(b'\n', &mut line)?;
+            if read == 0 || line == b"\n" {
+                if !fields.is_empty() {
+                    self.push(entry_from_fields(std::mem::take(&mut fields)));
+                }
+                if read == 0 {
+                    return Ok(());
+                }
+                continue;
+            }
+
+            let text = line.strip_suffix(b"\n").unwrap_or(&line);
+            match text.iter().position(|byte| *byte == b'=') {
+                Some(split) => {
+                    let name = String::from_utf8_lossy(&text[..split]).into_owned();
+                    fields.insert(name, String::from_utf8_lossy(&text[split + 1..]).into_owned());
+                }
+                // Binary-safe field: name line, little-endian u64 size, data, newline
+                None => {
+                    let name = String::from_utf8_lossy(text).into_owned();
+                    let mut size = [0u8; 8];
+                    reader.read_exact(&mut size)?;
+                    let size = u64::from_le_bytes(size);
+                    if size > MAX_JOURNAL_FIELD_BYTES {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!("journal field {name} is {size} bytes, more than the {MAX_JOURNAL_FIELD_BYTES} allowed"),
+                        ));
+                    }
+                    let mut data = Vec::new();
+                    (&mut reader).take(size).read_to_end(&mut data)?;
+                    if data.len() as u64 != size {
+                        return Err(std::io::ErrorKind::UnexpectedEof.into());
+                    }
+                    let mut newline = [0u8; 1];
+                    reader.read_exact(&mut newline)?;
+                    fields.insert(name, String::from_utf8_lossy(&data).into_owned());
+                }
+            }
+        }
+    }
+
+    /// Sort the clusters and resolve their stack frames against `repo_files`,
+    /// paths relative to the repository root at `repo_root`.
+    pub fn finish(mut self, repo_root: Option<&Path>, repo_files: &[String]) -> LogDigest {
+        for frame in self.clusters.iter_mut().flat_map(|cluster| cluster.frames.iter_mut()) {
+            frame.repo_path = resolve_frame(&frame.file, repo_root, repo_files);
+        }
+        self.clusters
+            .sort_by(|a, b| level_rank(&b.level).cmp(&level_rank(&a.level)).then(b.count.cmp(&a.count)));
+
+        LogDigest {
+            total_entries: self.total_entries,
+            entries_by_level: self.entries_by_level,
+            clusters: self.clusters,
+            unclustered_entries: self.unclustered_entries,
+        }
+    }
+}
+
+fn level_rank(level: &LogLevel) -> u8 {
+    match level {
+        LogLevel::Trace => 0,
+        LogLevel::Debug => 1,
+        LogLevel::Info => 2,
+        LogLevel::Warn => 3,
+        LogLevel::Error => 4,
+        LogLevel::Fatal => 5,
+    }
+}
+
+/// Whether `line` continues `entry` rather than starting a new one.
+fn is_continuation(entry: &LogEntry, line: &str) -> bool {
+    if line.starts_with([' ', '\t']) {
+        return true;
+    }
+    if line.starts_with("Traceback (most recent call last)")
+        || line.starts_with("Caused by:")
+        || line.starts_with("stack backtrace:")
+        || line.starts_with("note: run with `RUST_BACKTRACE")
+        || line.starts_with("note: Some details are omitted")
+    {
+        return true;
+    }
+
+    let last_line = entry.message.lines().last().unwrap_or_default();
+    // The message of a Rust panic follows the unindented "panicked at <location>:" line
+    if entry.message.lines().count() == 1 && last_line.contains("panicked at ") && last_line.ends_with(':') {
+        return true;
+    }
+    // A Python traceback ends with an unindented "ValueError: ..." line
+    entry.message.contains("Traceback (most recent call last)")
+        && last_line.starts_with([' ', '\t'])
+        && PYTHON_EXCEPTION.is_match(line)
+}
+
+static PYTHON_EXCEPTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z_][\w.]*(Error|Exception|Exit|Interrupt|Warning)\b").unwrap());
+static SYSLOG_5424: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<(\d{1,3})>1 (\S+) (\S+) (\S+) (\S+) (\S+) (?:-|\[.*?\]) ?(.*)$").unwrap());
+static SYSLOG_3164: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (\S+) ([^:\[\s]+)(?:\[\d+\])?: (.*)$").unwrap()
+});
+static PLAIN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\[?(\d{4}-\d{2}-\d{2}[T ][\d:.,]+(?:Z|[+-]\d{2}:?\d{2})?)?\]?\s*\[?(trace|debug|info|warn|warning|error|fatal|critical|panic)\b\]?\s*:?\s*(.*)$",
+    )
+    .unwrap()
+});
+static PLAIN_SOURCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([A-Za-z_][\w:.\-]*): (.*)$").unwrap());
+static LOGFMT_PAIR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([\w.\-@]+)=("(?:[^"\\]|\\.)*"|\S*)"#).unwrap());
+
+fn parse_line(line: &str, format: LogFormat) -> LogEntry {
+    let parsed = match format {
+        LogFormat::JsonLines => parse_json(line),
+        LogFormat::Logfmt => parse_logfmt(line),
+        LogFormat::Syslog => parse_syslog(line),
+        LogFormat::Plain | LogFormat::JournalExport => None,
+        LogFormat::Auto => parse_json(line).or_else(|| parse_syslog(line)).or_else(|| parse_logfmt(line)),
+    };
+    parsed.unwrap_or_else(|| parse_plain(line))
+}
+
+fn parse_json(line: &str) -> Option<LogEntry> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    let serde_json::Value::Object(object) = serde_json::from_str(line).ok()? else {
+        return None;
+    };
+    let fields = object
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            };
+            (key, value)
+        })
+        .collect();
+    Some(entry_from_fields(fields))
+}
+
+fn parse_logfmt(line: &str) -> Option<LogEntry> {
+    let fields: HashMap<String, String> = LOGFMT_PAIR
+        .captures_iter(line)
+        .map(|captures| {
+            let value = &captures[2];
+            let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+                Some(quoted) => quoted.replace("\\\"", "\"").replace("\\n", "\n"),
+                None => value.to_string(),
+            };
+            (captures[1].to_string(), value)
+        })
+        .collect();
+    let keyed = ["msg", "message", "level", "lvl"].iter().any(|key| fields.contains_key(*key));
+    (fields.len() >= 2 && keyed).then(|| entry_from_fields(fields))
+}
+
+fn parse_syslog(line: &str) -> Option<LogEntry> {
+    if let Some(captures) = SYSLOG_5424.captures(line) {
+        return Some(LogEntry {
+            timestamp: captures[2].to_string(),
+            level: captures[1].parse::<u8>().map(|pri| syslog_level(pri % 8)).unwrap_or(LogLevel::Info),
+            message: captures[7].to_string(),
+            source: captures[4].to_string(),
+            context: Some(HashMap::from([("host".to_string(), captures[3].to_string())])),
+        });
+    }
+
+    let captures = SYSLOG_3164.captures(line)?;
+    let mut entry = LogEntry {
+        timestamp: captures[2].to_string(),
+        level: captures
+            .get(1)
+            .and_then(|pri| pri.as_str().parse::<u8>().ok())
+            .map(|pri| syslog_level(pri % 8))
+            .unwrap_or(LogLevel::Info),
+        message: captures[5].to_string(),
+        source: captures[4].to_string(),
+        context: Some(HashMap::from([("host".to_string(), captures[3].to_string())])),
+    };
+    if captures.get(1).is_none() {
+        refine_level(&mut entry);
+    }
+    Some(entry)
+}
+
+fn parse_plain(line: &str) -> LogEntry {
+    if line.contains("panicked at ") {
+        return LogEntry {
+            timestamp: String::new(),
+            level: LogLevel::Fatal,
+            message: line.to_string(),
+            source: "panic".to_string(),
+            context: None,
+        };
+    }
+
+    if let Some(captures) = PLAIN.captures(line) {
+        let rest = &captures[3];
+        let (source, message) = match PLAIN_SOURCE.captures(rest) {
+            Some(source) => (source[1].to_string(), source[2].to_string()),
+            None => (String::new(), rest.to_string()),
+        };
+        return LogEntry {
+            timestamp: captures.get(1).map(|ts| ts.as_str().to_string()).unwrap_or_default(),
+            level: parse_level(&captures[2]).unwrap_or(LogLevel::Info),
+            message,
+            source,
+            context: None,
+        };
+    }
+
+    let mut entry = LogEntry {
+        timestamp: String::new(),
+        level: LogLevel::Info,
+        message: line.to_string(),
+        source: String::new(),
+        context: None,
+    };
+    refine_level(&mut entry);
+    entry
+}
+
+/// Build an entry from structured fields (JSON, logfmt or journald), keeping
+/// the fields that are not mapped as context.
+fn entry_from_fields(mut fields: HashMap<String, String>) -> LogEntry {
+    let mut take = |keys: &[&str]| keys.iter().find_map(|key| fields.remove(*key));
+
+    let message = take(&["message", "msg", "MESSAGE", "log", "text"]).unwrap_or_default();
+    let level = take(&["level", "lvl", "severity", "log.level", "levelname"])
+        .and_then(|level| parse_level(&level))
+        .or_else(|| take(&["PRIORITY", "priority"]).and_then(|p| p.parse().ok()).map(syslog_level));
+    let timestamp = take(&["timestamp", "ts", "time", "@timestamp", "asctime"])
+        .or_else(|| take(&["__REALTIME_TIMESTAMP"]).map(|micros| journal_timestamp(&micros)))
+        .unwrap_or_default();
+    let source = take(&["target", "logger", "logger_name", "source", "module", "name", "SYSLOG_IDENTIFIER", "_COMM", "component"])
+        .unwrap_or_default();
+
+    // Traces kept outside the message still belong to it
+    let mut message = message;
+    if let Some(trace) = take(&["stack", "stacktrace", "stack_trace", "backtrace", "exception", "exc_info", "error.stack"]) {
+        message.push('\n');
+        message.push_str(&trace);
+    }
+
+    fields.retain(|key, _| !key.starts_with("__"));
+    let mut entry = LogEntry {
+        timestamp,
+        level: level.clone().unwrap_or(LogLevel::Info),
+        message,
+        source,
+        context: (!fields.is_empty()).then_some(fields),
+    };
+    if level.is_none() {
+        refine_level(&mut entry);
+    }
+    entry
+}
+
+fn parse_level(level: &str) -> Option<LogLevel> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "trace" | "verbose" => Some(LogLevel::Trace),
+        "debug" | "dbug" => Some(LogLevel::Debug),
+        "info" | "information" | "notice" => Some(LogLevel::Info),
+        "warn" | "warning" => Some(LogLevel::Warn),
+        "error" | "err" => Some(LogLevel::Error),
+        "fatal" | "critical" | "crit" | "panic" | "alert" | "emerg" => Some(LogLevel::Fatal),
+        _ => None,
+    }
+}
+
+/// Syslog severity: 0 emergency to 7 debug.
+fn syslog_level(severity: u8) -> LogLevel {
+    match severity {
+        0..=2 => LogLevel::Fatal,
+        3 => LogLevel::Error,
+        4 => LogLevel::Warn,
+        5 | 6 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// Raise the level of an entry without one when its text is clearly an error.
+fn refine_level(entry: &mut LogEntry) {
+    if entry.message.contains("panicked at ") {
+        entry.level = LogLevel::Fatal;
+    } else if entry.message.contains("Traceback (most recent call last)")
+        || entry.message.contains("Exception")
+        || entry.message.starts_with("error")
+        || entry.message.starts_with("ERROR")
+    {
+        entry.level = LogLevel::Error;
+    }
+}
+
+fn journal_timestamp(micros: &str) -> String {
+    micros
+        .parse::<i64>()
+        .ok()
+        .and_then(DateTime::<Utc>::from_timestamp_micros)
+        .map(|timestamp| timestamp.to_rfc3339())
+        .unwrap_or_else(|| micros.to_string())
+}
+
+/// The message with variable tokens replaced by `<*>`.
+///
+/// Only the first line is used, plus the second for a panic, whose message
+/// follows its location.
+fn template(message: &str) -> String {
+    let mut lines = message.lines();
+    let mut head = lines.next().unwrap_or_default().to_string();
+    if head.contains("panicked at ") {
+        if let Some(panic_message) = lines.next() {
+            head.push(' ');
+            head.push_str(panic_message.trim());
+        }
+    }
+
+    let mut template = String::new();
+    for token in head.split_whitespace() {
+        if !template.is_empty() {
+            template.push(' ');
+        }
+        if token.chars().any(|c| c.is_ascii_digit()) {
+            template.push_str("<*>");
+        } else {
+            template.push_str(token);
+        }
+        if template.chars().count() >= MAX_TEMPLATE_CHARS {
+            break;
+        }
+    }
+    template
+}
+
+static RUST_LOCATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:panicked at|at) ([^\s:()]+\.rs):(\d+)(?::\d+)?").unwrap());
+static RUST_FUNCTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\d+: (\S+)").unwrap());
+static PYTHON_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"File "([^"]+)", line (\d+)(?:, in (\S+))?"#).unwrap());
+static JAVA_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"at ([\w$.]+)\.([\w$<>]+)\(([\w$]+\.(?:java|kt|scala|groovy)):(\d+)\)").unwrap()
+});
+static JS_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"at (?:([^\s(]+) \()?([^\s()]+\.(?:[cm]?js|tsx?|jsx)):(\d+):\d+\)?").unwrap());
+
+/// Stack frames in a Rust panic, Python traceback, Java exception or Node stack.
+pub(crate) fn stack_frames(message: &str) -> Vec<StackFrame> {
+    let mut frames = Vec::new();
+    let mut rust_function: Option<String> = None;
+
+    for line in message.lines() {
+        if let Some(captures) = RUST_FUNCTION.captures(line) {
+            rust_function = Some(captures[1].to_string());
+            continue;
+        }
+        let frame = if let Some(captures) = PYTHON_FRAME.captures(line) {
+            StackFrame {
+                function: captures.get(3).map(|f| f.as_str().to_string()),
+                file: captures[1].to_string(),
+                line: captures[2].parse().ok(),
+                repo_path: None,
+            }
+        } else if let Some(captures) = JAVA_FRAME.captures(line) {
+            // The class's package gives the directory: com.acme.Pool -> com/acme/Pool.java
+            let class = captures[1].split('$').next().unwrap_or_default();
+            let package: Vec<&str> = class.split('.').collect();
+            let mut file = package[..package.len().saturating_sub(1)].join("/");
+            if !file.is_empty() {
+                file.push('/');
+            }
+            file.push_str(&captures[3]);
+            StackFrame {
+                function: Some(format!("{}.{}", &captures[1], &captures[2])),
+                file,
+                line: captures[4].parse().ok(),
+                repo_path: None,
+            }
+        } else if let Some(captures) = RUST_LOCATION.captures(line) {
+            StackFrame {
+                function: rust_function.take(),
+                file: captures[1].to_string(),
+                line: captures[2].parse().ok(),
+                repo_path: None,
+            }
+        } else if let Some(captures) = JS_FRAME.captures(line) {
+            StackFrame {
+                function: captures.get(1).map(|f| f.as_str().to_string()),
+                file: captures[2].trim_start_matches("file://").to_string(),
+                line: captures[3].parse().ok(),
+                repo_path: None,
+            }
+        } else {
+            continue;
+        };
+        if !frames.contains(&frame) {
+            frames.push(frame);
+        }
+    }
+    frames
+}
+
+/// Paths that belong to toolchains and dependencies, never to the repository.
+const EXTERNAL_PATH_MARKERS: &[&str] = &["/rustc/", "/.cargo/", "/.rustup/", "site-packages/", "dist-packages/", "node_modules/", "node:"];
+
+/// Match a frame's file to a repository file by the longest common suffix of
+/// path components; at least the file name and, where both have one, its
+/// parent directory must match.
+fn resolve_frame(file: &str, repo_root: Option<&Path>, repo_files: &[String]) -> Option<String> {
+    if EXTERNAL_PATH_MARKERS.iter().any(|marker| file.contains(marker)) {
+        return None;
+    }
+    let file = repo_root
+        .and_then(|root| Path::new(file).strip_prefix(root).ok())
+        .map(|relative| relative.to_string_lossy().into_owned())
+        .unwrap_or_else(|| file.trim_start_matches("./").to_string());
+    let frame_components: Vec<&str> = file.split(['/', '\\']).filter(|c| !c.is_empty() && *c != ".").collect();
+
+    repo_files
+        .iter()
+        .filter_map(|candidate| {
+            let candidate_components: Vec<&str> = candidate.split('/').collect();
+            let matched = frame_components
+                .iter()
+                .rev()
+                .zip(candidate_components.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            let required = 2.min(frame_components.len()).min(candidate_components.len());
+            (matched >= required.max(1)).then_some((matched, candidate))
+        })
+        .max_by_key(|(matched, candidate)| (*matched, std::cmp::Reverse(candidate.len())))
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// The first `max_bytes` of `text`, cut at a character boundary.
+fn truncate(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_ingestion_clusters_and_maps_frames() {
+        let mut ingester = LogIngester::new();
+
+        let jsonl = r#"{"ts":"2024-01-01T10:00:00Z","level":"error","target":"db::pool","msg":"timeout after 30s on 10.0.0.7"}
+{"ts":"2024-01-01T10:00:05Z","level":"error","target":"db::pool","msg":"timeout after 5s on 10.0.0.9"}
+thread 'tokio-runtime-worker' panicked at src/db/pool.rs:42:9:
+called `Option::unwrap()` on a `None` value
+stack backtrace:
+   0: std::panicking::begin_panic
+             at /rustc/abc/library/std/src/panicking.rs:616:12
+   1: app::db::pool::checkout
+             at ./src/db/pool.rs:42:9
+"#;
+        ingester.ingest_reader(jsonl.as_bytes(), LogFormat::Auto).unwrap();
+
+        let mixed = r#"level=warn msg="slow query" duration=1200ms
+2024-01-01 10:01:00,120 ERROR app.views: request failed
+Traceback (most recent call last):
+  File "/srv/app/app/views.py", line 12, in handler
+    return load(user_id)
+KeyError: 'user_id'
+<11>1 2024-01-01T10:02:00Z web-1 api 311 - - java.lang.IllegalStateException: closed
+	at com.acme.db.Pool.take(Pool.java:88)
+"#;
+        ingester.ingest_reader(mixed.as_bytes(), LogFormat::Auto).unwrap();
+
+        let mut journal = b"__CURSOR=s=1\n__REALTIME_TIMESTAMP=1704103200000000\nPRIORITY=3\nSYSLOG_IDENTIFIER=api\nMESSAGE\n".to_vec();
+        journal.extend_from_slice(&11u64.to_le_bytes());
+        journal.extend_from_slice(b"disk full 1\n\n");
+        ingester.ingest_reader(journal.as_slice(), LogFormat::Auto).unwrap();
+
+        let repo_files = vec![
+            "src/db/pool.rs".to_string(),
+            "app/views.py".to_string(),
+            "src/main/java/com/acme/db/Pool.java".to_string(),
+        ];
+        let digest = ingester.finish(Some(Path::new("/srv/app")), &repo_files);
+
+        assert_eq!(digest.total_entries, 7);
+        assert_eq!(digest.entries_by_level["Error"], 5);
+
+        let panic = &digest.clusters[0];
+        assert_eq!(panic.level, LogLevel::Fatal);
+        assert!(panic.template.contains("called `Option::unwrap()` on a `None` value"));
+        assert!(panic
+            .frames
+            .iter()
+            .any(|f| f.function.as_deref() == Some("app::db::pool::checkout") && f.repo_path.as_deref() == Some("src/db/pool.rs")));
+        // Standard library frames are not mapped
+        assert!(panic.frames.iter().any(|f| f.file.starts_with("/rustc/") && f.repo_path.is_none()));
+
+        let timeouts = digest.clusters.iter().find(|c| c.source == "db::pool").unwrap();
+        assert_eq!((timeouts.count, timeouts.template.as_str()), (2, "timeout after <*> on <*>"));
+        assert_eq!(timeouts.last_seen.as_deref(), Some("2024-01-01T10:00:05Z"));
+
+        let python = digest.clusters.iter().find(|c| c.source == "app.views").unwrap();
+        assert!(python.examples[0].ends_with("KeyError: 'user_id'"));
+        assert_eq!(python.frames[0].repo_path.as_deref(), Some("app/views.py"));
+
+        let java = digest.clusters.iter().find(|c| c.source == "api" && c.template.contains("IllegalState")).unwrap();
+        assert_eq!(java.frames[0].repo_path.as_deref(), Some("src/main/java/com/acme/db/Pool.java"));
+
+        let journal = digest.clusters.iter().find(|c| c.template == "disk full <*>").unwrap();
+        assert_eq!(journal.first_seen.as_deref(), Some("2024-01-01T10:00:00+00:00"));
+        assert!(digest.clusters.iter().any(|c| c.level == LogLevel::Warn && c.template == "slow query"));
+    }
+
+    #[test]
+    fn test_journal_export_rejects_oversized_field() {
+        let mut journal = b"__REALTIME_TIMESTAMP=1704103200000000\nMESSAGE\n".to_vec();
+        journal.extend_from_slice(&u64::MAX.to_le_bytes());
+        journal.extend_from_slice(b"disk full\n\n");
+
+        let mut ingester = LogIngester::new();
+        let err = ingester.ingest_reader(journal.as_slice(), LogFormat::JournalExport).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // A size larger than what is left of the export is a truncated file
+        let mut journal = b"MESSAGE\n".to_vec();
+        journal.extend_from_slice(&1024u64.to_le_bytes());
+        journal.extend_from_slice(b"disk full\n\n");
+        let err = ingester.ingest_reader(journal.as_slice(), LogFormat::JournalExport).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+
+pub use codex_protocol::protocol::TokenUsage;
+
+/// Error type shared by model providers and agents.
+pub type AgentError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Model backend consumed by every agent in the suite.
+///
+/// The trait is object safe so a single provider can be shared between agents
+/// as an `Arc<dyn ModelProvider>`.
+#[async_trait]
+pub trait ModelProvider: Send + Sync {
+    /// Complete a prompt without a system prompt.
+    async fn generate_completion(&self, prompt: &str) -> Result<String, AgentError>;
+
+    /// Complete a prompt with the given system prompt.
+    async fn generate_with_context(&self, system_prompt: &str, user_prompt: &str) -> Result<String, AgentError>;
+
+    /// Complete a prompt whose answer must conform to `output_schema` (a JSON Schema).
+    ///
+    /// Returns the raw JSON text produced by the model. Providers without
+    /// native structured-output support fall back to describing the schema in
+    /// the system prompt.
+    async fn generate_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<String, AgentError> {
+        let system_prompt = describe_schema(system_prompt, output_schema);
+        self.generate_with_context(&system_prompt, user_prompt).await
+    }
+
+    /// Stream the completion of a prompt as text deltas.
+    async fn stream_completion(&self, system_prompt: Option<&str>, user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError>;
+
+    /// Stream the completion of a prompt whose answer must conform to `output_schema`.
+    ///
+    /// The concatenated chunks form the same JSON text `generate_structured`
+    /// would return.
+    async fn stream_structured(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        output_schema: &serde_json::Value,
+    ) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        let system_prompt = describe_schema(system_prompt, output_schema);
+        self.stream_completion(Some(&system_prompt), user_prompt).await
+    }
+
+    /// Total tokens consumed by this provider so far.
+    fn token_usage(&self) -> TokenUsage {
+        TokenUsage::default()
+    }
+}
+
+/// System prompt for providers without native structured-output support.
+fn describe_schema(system_prompt: &str, output_schema: &serde_json::Value) -> String {
+    format!(
+        "{system_prompt}\n\nRespond with a single JSON document, without Markdown fences, that conforms to this JSON Schema:\n{output_schema}"
+    )
+}
+
+/// Incremental model output returned by [`ModelProvider::stream_completion`].
+#[async_trait]
+pub trait StreamCompletion: Send {
+    /// Next chunk of text, or `None` once the completion is finished.
+    ///
+    /// An error ends the stream; the text received before it is incomplete.
+    async fn next(&mut self) -> Option<Result<String, AgentError>>;
+}
+
+/// Stream over a fixed string, yielded in small chunks.
+pub struct StaticStreamCompletion {
+    content: String,
+    position: usize,
+}
+
+impl StaticStreamCompletion {
+    pub fn new(content: String) -> Self {
+        Self { content, position: 0 }
+    }
+}
+
+#[async_trait]
+impl StreamCompletion for StaticStreamCompletion {
+    async fn next(&mut self) -> Option<Result<String, AgentError>> {
+        if self.position >= self.content.len() {
+            return None;
+        }
+
+        let chunk_size = 10;
+        let mut end = (self.position + chunk_size).min(self.content.len());
+        while !self.content.is_char_boundary(end) {
+            end += 1;
+        }
+        let chunk = self.content[self.position..end].to_string();
+        self.position = end;
+
+        Some(Ok(chunk))
+    }
+}
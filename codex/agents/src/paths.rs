@@ -0,0 +1,66 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Resolve `path` against `root`, returning `None` when the result is outside
+/// `root`.
+///
+/// `path` may be absolute or relative and need not exist. `.` and `..` are
+/// normalised lexically, then symlinks in the part of the path that exists are
+/// followed, so a link pointing out of `root` is refused as well.
+pub(crate) fn resolve_within(root: &Path, path: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+
+    let mut normalized = PathBuf::new();
+    for component in root.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    // Canonicalise the longest existing prefix and re-attach the rest
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    let resolved = loop {
+        match existing.canonicalize() {
+            Ok(canonical) => break canonical,
+            Err(_) => {
+                rest.push(existing.file_name()?);
+                existing = existing.parent()?;
+            }
+        }
+    };
+    let resolved = rest.into_iter().rev().fold(resolved, |path, name| path.join(name));
+
+    resolved.starts_with(&root).then_some(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_within() {
+        let workdir = tempfile::tempdir().unwrap();
+        let root = workdir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+
+        assert_eq!(resolve_within(&root, Path::new("src/new/lib.rs")), Some(root.join("src/new/lib.rs")));
+        assert_eq!(resolve_within(&root, Path::new("./src/../lib.rs")), Some(root.join("lib.rs")));
+        assert_eq!(resolve_within(&root, &root.join("src")), Some(root.join("src")));
+        assert_eq!(resolve_within(&root, Path::new("../outside.rs")), None);
+        assert_eq!(resolve_within(&root, Path::new("src/../../outside.rs")), None);
+        assert_eq!(resolve_within(&root, Path::new("/etc/passwd")), None);
+
+        #[cfg(unix)]
+        {
+            let outside = tempfile::tempdir().unwrap();
+            std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+            assert_eq!(resolve_within(&root, Path::new("link/file.rs")), None);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model_provider::AgentError;
+use crate::reviewer::ReviewFocus;
+use crate::AgentType;
+
+/// A pipeline of agent steps forming a DAG.
+///
+/// Each step names the earlier steps whose outputs it consumes; steps whose
+/// dependencies have finished run concurrently. Pipelines can be written in
+/// TOML or JSON:
+///
+/// ```toml
+/// [[steps]]
+/// id = "spec"
+/// agent = "spec"
+///
+/// [[steps]]
+/// id = "code"
+/// agent = "code"
+/// inputs = ["spec"]
+/// review = { max_iterations = 3 }
+///
+/// [[steps]]
+/// id = "tests"
+/// agent = "test_generator"
+/// inputs = ["spec", "code"]
+///
+/// [[steps]]
+/// id = "debug"
+/// agent = "debug"
+/// inputs = ["code"]
+/// when = { step = "tests", status = "failed" }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub steps: Vec<PipelineStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    /// Unique name other steps use to refer to this one.
+    pub id: String,
+    pub agent: AgentType,
+    /// Steps whose outputs feed this step. Failed or skipped inputs are left
+    /// out of the agent's input.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Run the step only when this holds; otherwise it is skipped.
+    #[serde(default)]
+    pub when: Option<StepCondition>,
+    /// Review a code step's changes and revise them until the reviewer approves.
+    #[serde(default)]
+    pub review: Option<ReviewLoop>,
+}
+
+/// Review/fix loop for a code step.
+///
+/// After each attempt the reviewer checks the changes; findings that require
+/// changes are sent back to the code agent as a revision. The step's output is
+/// the last attempt, and every round is recorded in its execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewLoop {
+    /// Code generations allowed, including the first attempt.
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+    #[serde(default)]
+    pub review_focus: Option<Vec<ReviewFocus>>,
+}
+
+impl Default for ReviewLoop {
+    fn default() -> Self {
+        Self {
+            max_iterations: default_max_iterations(),
+            review_focus: None,
+        }
+    }
+}
+
+fn default_max_iterations() -> usize {
+    3
+}
+
+/// Condition on the outcome of an earlier step.
+///
+/// With neither `status` nor `pointer` set, the condition holds when the step
+/// succeeded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepCondition {
+    pub step: String,
+    #[serde(default)]
+    pub status: Option<StepStatus>,
+    /// JSON pointer into the step's output, e.g. `/overall_approval`.
+    /// Without `equals` the value must be truthy: not null, false, zero or empty.
+    #[serde(default)]
+    pub pointer: Option<String>,
+    #[serde(default)]
+    pub equals: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of a finished step, used to evaluate conditions and wire inputs.
+#[derive(Debug, Clone)]
+pub(crate) enum StepOutcome {
+    Succeeded(serde_json::Value),
+    Failed,
+    Skipped,
+}
+
+impl StepOutcome {
+    fn status(&self) -> StepStatus {
+        match self {
+            StepOutcome::Succeeded(_) => StepStatus::Succeeded,
+            StepOutcome::Failed => StepStatus::Failed,
+            StepOutcome::Skipped => StepStatus::Skipped,
+        }
+    }
+}
+
+impl Default for Pipeline {
+    /// Spec, then code, then review and tests side by side.
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                PipelineStep::new("spec", AgentType::Spec, &[]),
+                PipelineStep::new("code", AgentType::Code, &["spec"]),
+                PipelineStep::new("review", AgentType::Reviewer, &["spec", "code"]),
+                PipelineStep::new("tests", AgentType::TestGenerator, &["spec", "code"]),
+            ],
+        }
+    }
+}
+
+impl Pipeline {
+    /// Run `agents` one after another, each seeing the outputs of all earlier steps.
+    pub fn linear(agents: &[AgentType]) -> Self {
+        let mut steps: Vec<PipelineStep> = Vec::new();
+        for (index, agent) in agents.iter().enumerate() {
+            let mut step = PipelineStep::new(&format!("{}-{}", index + 1, agent_slug(agent)), agent.clone(), &[]);
+            step.inputs = steps.iter().map(|s| s.id.clone()).collect();
+            steps.push(step);
+        }
+        Self { steps }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AgentError> {
+        let pipeline: Self = serde_json::from_str(json)?;
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, AgentError> {
+        let pipeline: Self = toml::from_str(toml)?;
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+
+    /// Load a `.toml` or `.json` pipeline file.
+    pub fn load(path: &Path) -> Result<Self, AgentError> {
+        let contents = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(format!("unsupported pipeline file {}; expected .toml or .json", path.display()).into()),
+        }
+    }
+
+    /// Check that step ids are unique, references resolve and there are no cycles.
+    pub fn validate(&self) -> Result<(), AgentError> {
+        if self.steps.is_empty() {
+            return Err("pipeline has no steps".into());
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if !ids.insert(step.id.as_str()) {
+                return Err(format!("duplicate pipeline step '{}'", step.id).into());
+            }
+        }
+        for step in &self.steps {
+            if let Some(review) = &step.review {
+                if step.agent != AgentType::Code {
+                    return Err(format!("pipeline step '{}' has a review loop but is not a code step", step.id).into());
+                }
+                if review.max_iterations == 0 {
+                    return Err(format!("pipeline step '{}' allows no review iterations", step.id).into());
+                }
+            }
+            for dependency in step.dependencies() {
+                if dependency == step.id {
+                    return Err(format!("pipeline step '{}' depends on itself", step.id).into());
+                }
+                if !ids.contains(dependency) {
+                    return Err(format!("pipeline step '{}' refers to unknown step '{}'", step.id, dependency).into());
+                }
+            }
+        }
+
+        // Kahn's algorithm: every step must become ready eventually
+        let mut finished = HashSet::new();
+        while finished.len() < self.steps.len() {
+            let ready: Vec<&str> = self
+                .steps
+                .iter()
+                .filter(|step| !finished.contains(step.id.as_str()))
+                .filter(|step| step.dependencies().all(|dependency| finished.contains(dependency)))
+                .map(|step| step.id.as_str())
+                .collect();
+            if ready.is_empty() {
+                return Err("pipeline steps form a cycle".into());
+            }
+            finished.extend(ready);
+        }
+
+        Ok(())
+    }
+}
+
+impl PipelineStep {
+    pub fn new(id: &str, agent: AgentType, inputs: &[&str]) -> Self {
+        Self {
+            id: id.to_string(),
+            agent,
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            when: None,
+            review: None,
+        }
+    }
+
+    /// Steps that must finish before this one can start.
+    pub fn dependencies(&self) -> impl Iterator<Item = &str> {
+        self.inputs.iter().map(String::as_str).chain(self.when.as_ref().map(|when| when.step.as_str()))
+    }
+}
+
+impl StepCondition {
+    pub(crate) fn holds(&self, outcomes: &HashMap<String, StepOutcome>) -> bool {
+        let Some(outcome) = outcomes.get(&self.step) else {
+            return false;
+        };
+
+        if self.status.is_none() && self.pointer.is_none() && !matches!(outcome, StepOutcome::Succeeded(_)) {
+            return false;
+        }
+        if self.status.is_some_and(|status| status != outcome.status()) {
+            return false;
+        }
+        let Some(pointer) = &self.pointer else {
+            return true;
+        };
+
+        let value = match outcome {
+            StepOutcome::Succeeded(output) => output.pointer(pointer),
+            _ => None,
+        };
+        match (&self.equals, value) {
+            (Some(expected), Some(value)) => value == expected,
+            (Some(_), None) => false,
+            (None, value) => value.is_some_and(is_truthy),
+        }
+    }
+}
+
+fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(a) => !a.is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn agent_slug(agent: &AgentType) -> &'static str {
+    match agent {
+        AgentType::Spec => "spec",
+        AgentType::Code => "code",
+        AgentType::TestGenerator => "test_generator",
+        AgentType::Reviewer => "reviewer",
+        AgentType::Debug => "debug",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_from_toml() {
+        let pipeline = Pipeline::from_toml(
+            r#"
+            [[steps]]
+            id = "code"
+            agent = "code"
+
+            [[steps]]
+            id = "tests"
+            agent = "test_generator"
+            inputs = ["code"]
+
+            [[steps]]
+            id = "debug"
+            agent = "debug"
+            when = { step = "tests", status = "failed" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(pipeline.steps.len(), 3);
+        assert_eq!(pipeline.steps[2].dependencies().collect::<Vec<_>>(), vec!["tests"]);
+
+        let mut outcomes = HashMap::from([("tests".to_string(), StepOutcome::Failed)]);
+        assert!(pipeline.steps[2].when.as_ref().unwrap().holds(&outcomes));
+        outcomes.insert("tests".to_string(), StepOutcome::Succeeded(serde_json::json!({})));
+        assert!(!pipeline.steps[2].when.as_ref().unwrap().holds(&outcomes));
+
+        let cyclic = r#"{"steps": [
+            {"id": "a", "agent": "code", "inputs": ["b"]},
+            {"id": "b", "agent": "reviewer", "inputs": ["a"]}
+        ]}"#;
+        assert!(Pipeline::from_json(cyclic).is_err());
+        assert!(Pipeline::from_json(r#"{"steps": [{"id": "a", "agent": "code", "inputs": ["missing"]}]}"#).is_err());
+    }
+}
@@ -0,0 +1,663 @@
+use codex_protocol::protocol::{ReviewCodeLocation, ReviewLineRange, ReviewOutputEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::diff::{self, FileDiff};
+use crate::structured::{array_schema, enum_schema, generate_json, object_schema, string_array_schema};
+use crate::{AgentError, ModelProvider};
+
+/// Reviewer Agent: Automated code review producing annotated diffs
+#[derive(Clone)]
+pub struct ReviewerAgent {
+    model_provider: Arc<dyn ModelProvider>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRequest {
+    /// Changes to review, diffed from their old to their new content.
+    #[serde(default)]
+    pub code_changes: Vec<CodeChange>,
+    pub requirements: Option<Vec<String>>,
+    pub context: Option<HashMap<String, String>>,
+    pub review_focus: Option<Vec<ReviewFocus>>,
+    /// Repository the changes belong to. Defaults to the current directory.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Review the working tree at `cwd` against this git ref, e.g. `main` or
+    /// `HEAD~1`, including uncommitted and untracked files. `code_changes`
+    /// for files the diff does not cover are reviewed as well.
+    #[serde(default)]
+    pub base_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChange {
+    pub file_path: String,
+    pub old_content: String,
+    pub new_content: String,
+    pub change_type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReviewFocus {
+    Security,
+    Performance,
+    Maintainability,
+    Testing,
+    Documentation,
+    BestPractices,
+    Architecture,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewFinding {
+    pub id: String,
+    pub file_path: String,
+    /// Lines in the new version of the file, within a changed hunk.
+    pub line_start: Option<usize>,
+    pub line_end: Option<usize>,
+    pub severity: Severity,
+    pub category: ReviewCategory,
+    pub title: String,
+    pub description: String,
+    pub suggestion: Option<String>,
+    pub examples: Vec<String>,
+    /// The model's confidence that the finding is real, from 0 to 1.
+    #[serde(default = "default_confidence")]
+    pub confidence: f32,
+}
+
+fn default_confidence() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+    Info,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReviewCategory {
+    Security,
+    Performance,
+    Bug,
+    CodeSmell,
+    BestPractice,
+    Documentation,
+    Architecture,
+    Testing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnotatedDiff {
+    pub file_path: String,
+    pub hunks: Vec<DiffHunk>,
+    pub overall_score: f32,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+    pub annotations: Vec<LineAnnotation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    pub line_type: DiffLineType,
+    pub content: String,
+    pub old_line_number: Option<usize>,
+    pub new_line_number: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DiffLineType {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineAnnotation {
+    pub line_number: usize,
+    pub annotation_type: AnnotationType,
+    pub finding_id: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AnnotationType {
+    Error,
+    Warning,
+    Info,
+    Suggestion,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewReport {
+    pub findings: Vec<ReviewFinding>,
+    pub annotated_diffs: Vec<AnnotatedDiff>,
+    pub summary: ReviewSummary,
+    pub recommendations: Vec<String>,
+    pub overall_approval: ApprovalStatus,
+    /// The findings in the protocol format of `/review`, with absolute paths.
+    pub review_output: ReviewOutputEvent,
+}
+
+impl ReviewReport {
+    /// Render the findings the way the TUI prints a `/review` result.
+    pub fn format_findings(&self) -> String {
+        codex_core::review_format::format_review_findings_block(&self.review_output.findings, None)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewSummary {
+    pub total_findings: usize,
+    pub findings_by_severity: HashMap<String, usize>,
+    pub findings_by_category: HashMap<String, usize>,
+    pub code_quality_score: f32,
+    pub security_score: f32,
+    pub maintainability_score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ApprovalStatus {
+    Approved,
+    ApprovedWithComments,
+    RequiresChanges,
+    Rejected,
+}
+
+impl ReviewerAgent {
+    pub fn new(model_provider: Arc<dyn ModelProvider>) -> Self {
+        Self { model_provider }
+    }
+
+    pub async fn review_changes(&self, request: ReviewRequest) -> Result<ReviewReport, AgentError> {
+        let cwd = match &request.cwd {
+            Some(cwd) if cwd.is_absolute() => cwd.clone(),
+            Some(cwd) => std::env::current_dir()?.join(cwd),
+            None => std::env::current_dir()?,
+        };
+
+        // Compute the hunks under review
+        let mut files = match &request.base_ref {
+            Some(base_ref) => diff::git_diff(&cwd, base_ref).await?,
+            None => Vec::new(),
+        };
+        for change in &request.code_changes {
+            if !files.iter().any(|file| file.file_path == change.file_path) {
+                files.push(FileDiff::between(&change.file_path, &change.old_content, &change.new_content));
+            }
+        }
+        files.retain(|file| !file.hunks.is_empty());
+
+        // Analyze each changed file for issues
+        let mut all_findings = Vec::new();
+
+        for file in &files {
+            let change_findings = self.review_single_change(file, &request).await?;
+            all_findings.extend(change_findings);
+        }
+        assign_finding_ids(&mut all_findings);
+
+        // Generate annotated diffs
+        let annotated_diffs = self.generate_annotated_diffs(files, &all_findings)?;
+
+        // Generate summary statistics
+        let summary = self.generate_summary(&all_findings)?;
+
+        // Generate recommendations
+        let recommendations = self.generate_recommendations(&all_findings)?;
+
+        // Determine overall approval status
+        let overall_approval = self.determine_approval_status(&all_findings, &summary)?;
+
+        let review_output = review_output(&all_findings, &overall_approval, &recommendations, &cwd);
+
+        Ok(ReviewReport {
+            findings: all_findings,
+            annotated_diffs,
+            summary,
+            recommendations,
+            overall_approval,
+            review_output,
+        })
+    }
+
+    async fn review_single_change(&self, file: &FileDiff, request: &ReviewRequest) -> Result<Vec<ReviewFinding>, AgentError> {
+        let mut findings = Vec::new();
+
+        // Security review
+        if let Some(focus) = &request.review_focus {
+            if focus.contains(&ReviewFocus::Security) {
+                let security_findings = self.security_review(file).await?;
+                findings.extend(security_findings);
+            }
+        }
+
+        // Performance review
+        if let Some(focus) = &request.review_focus {
+            if focus.contains(&ReviewFocus::Performance) {
+                let performance_findings = self.performance_review(file).await?;
+                findings.extend(performance_findings);
+            }
+        }
+
+        // Code quality review
+        let quality_findings = self.code_quality_review(file).await?;
+        findings.extend(quality_findings);
+
+        // Best practices review
+        let practice_findings = self.best_practices_review(file).await?;
+        findings.extend(practice_findings);
+
+        Ok(findings)
+    }
+
+    async fn security_review(&self, file: &FileDiff) -> Result<Vec<ReviewFinding>, AgentError> {
+        let system_prompt = r#"You are a cybersecurity expert reviewing code for security vulnerabilities. Look for:
+
+1. Injection vulnerabilities (SQL, XSS, etc.)
+2. Authentication/authorization issues
+3. Cryptographic weaknesses
+4. Input validation problems
+5. Information disclosure
+6. Insecure direct object references
+
+Report each finding with its severity, location, and remediation suggestions."#;
+
+        let user_prompt = format!("Review this change to {} for security issues:\n\n{}", file.file_path, file.numbered());
+
+        self.request_findings(system_prompt, &user_prompt, file, "SEC").await
+    }
+
+    async fn performance_review(&self, file: &FileDiff) -> Result<Vec<ReviewFinding>, AgentError> {
+        let system_prompt = r#"You are a performance engineering expert. Identify performance issues:
+
+1. Inefficient algorithms (N+1 queries, nested loops)
+2. Memory leaks or excessive allocations
+3. Blocking I/O in async contexts
+4. Missing caching opportunities
+5. Database query optimization opportunities
+
+Return findings with performance impact and optimization suggestions."#;
+
+        let user_prompt = format!("Review this change to {} for performance issues:\n\n{}", file.file_path, file.numbered());
+
+        self.request_findings(system_prompt, &user_prompt, file, "PERF").await
+    }
+
+    async fn code_quality_review(&self, file: &FileDiff) -> Result<Vec<ReviewFinding>, AgentError> {
+        let system_prompt = r#"You are a senior software engineer conducting code quality review. Evaluate:
+
+1. Code readability and maintainability
+2. Proper error handling
+3. Consistent formatting and style
+4. Appropriate abstraction levels
+5. Clear naming and documentation
+
+Compare the removed and added lines and identify improvements or regressions."#;
+
+        let user_prompt = format!("Review code quality for file: {}\n\n{}", file.file_path, file.numbered());
+
+        self.request_findings(system_prompt, &user_prompt, file, "QUAL").await
+    }
+
+    async fn best_practices_review(&self, file: &FileDiff) -> Result<Vec<ReviewFinding>, AgentError> {
+        let system_prompt = r#"You are reviewing code for best practices violations:
+
+1. SOLID principles adherence
+2. Design pattern misuse
+3. Anti-patterns (god objects, spaghetti code)
+4. Missing error handling patterns
+5. Inconsistent coding standards
+
+Focus on maintainability and future-proofing."#;
+
+        let user_prompt = format!("Review this change to {} for best practices:\n\n{}", file.file_path, file.numbered());
+
+        self.request_findings(system_prompt, &user_prompt, file, "BP").await
+    }
+
+    async fn request_findings(
+        &self,
+        system_prompt: &str,
+        user_prompt: &str,
+        file: &FileDiff,
+        id_prefix: &str,
+    ) -> Result<Vec<ReviewFinding>, AgentError> {
+        let system_prompt = format!(
+            "{system_prompt}\n\nYou are shown the diff hunks of the change; each line is prefixed with its number in the new file, \
+             `+` for added and `-` for removed lines. Only report issues introduced by the change, cite the shortest range of new line \
+             numbers inside a hunk, and give your confidence from 0 to 1. Return an empty list when there is nothing to report."
+        );
+        let schema = object_schema(json!({ "findings": array_schema(finding_schema()) }));
+        let output: FindingsOutput =
+            generate_json(self.model_provider.as_ref(), &system_prompt, user_prompt, &schema).await?;
+
+        Ok(output
+            .findings
+            .into_iter()
+            .map(|finding| {
+                // Pin the reported lines to the hunks so they point at the change
+                let anchored = file.anchor(finding.line_start, finding.line_end);
+                ReviewFinding {
+                    id: id_prefix.to_string(),
+                    file_path: file.file_path.clone(),
+                    line_start: anchored.map(|(start, _)| start),
+                    line_end: anchored.map(|(_, end)| end),
+                    severity: finding.severity,
+                    category: finding.category,
+                    title: finding.title,
+                    description: finding.description,
+                    suggestion: finding.suggestion,
+                    examples: finding.examples,
+                    confidence: finding.confidence.clamp(0.0, 1.0),
+                }
+            })
+            .collect())
+    }
+
+    fn generate_annotated_diffs(&self, files: Vec<FileDiff>, findings: &[ReviewFinding]) -> Result<Vec<AnnotatedDiff>, AgentError> {
+        let mut annotated_diffs = Vec::new();
+
+        for file in files {
+            let relevant_findings: Vec<_> = findings.iter()
+                .filter(|f| f.file_path == file.file_path)
+                .collect();
+
+            // Annotate each finding on the hunk containing its first line
+            let mut hunks = file.hunks;
+            for finding in &relevant_findings {
+                let Some(line_number) = finding.line_start else { continue };
+                if let Some(hunk) = hunks
+                    .iter_mut()
+                    .find(|hunk| line_number >= hunk.new_start && line_number < hunk.new_start + hunk.new_lines.max(1))
+                {
+                    hunk.annotations.push(LineAnnotation {
+                        line_number,
+                        annotation_type: annotation_type(&finding.severity),
+                        finding_id: finding.id.clone(),
+                        message: finding.title.clone(),
+                    });
+                }
+            }
+
+            // Calculate overall score based on findings
+
+            let critical_count = relevant_findings.iter().filter(|f| matches!(f.severity, Severity::Critical)).count();
+            let high_count = relevant_findings.iter().filter(|f| matches!(f.severity, Severity::High)).count();
+
+            let overall_score = 100.0 - (critical_count as f32 * 20.0) - (high_count as f32 * 10.0);
+
+            annotated_diffs.push(AnnotatedDiff {
+                file_path: file.file_path,
+                hunks,
+                overall_score: overall_score.max(0.0),
+                summary: format!("{} findings in this file", relevant_findings.len()),
+            });
+        }
+
+        Ok(annotated_diffs)
+    }
+
+    fn generate_summary(&self, findings: &[ReviewFinding]) -> Result<ReviewSummary, AgentError> {
+        let mut by_severity = HashMap::new();
+        let mut by_category = HashMap::new();
+
+        for finding in findings {
+            *by_severity.entry(format!("{:?}", finding.severity)).or_insert(0) += 1;
+            *by_category.entry(format!("{:?}", finding.category)).or_insert(0) += 1;
+        }
+
+        // Calculate quality scores
+        let critical_count = findings.iter().filter(|f| matches!(f.severity, Severity::Critical)).count();
+        let high_count = findings.iter().filter(|f| matches!(f.severity, Severity::High)).count();
+        let total_findings = findings.len();
+
+        let code_quality_score = if total_findings == 0 {
+            100.0
+        } else {
+            100.0 - (critical_count as f32 * 15.0) - (high_count as f32 * 5.0)
+        };
+
+        let security_findings = findings.iter().filter(|f| matches!(f.category, ReviewCategory::Security)).count();
+        let security_score = 100.0 - (security_findings as f32 * 20.0);
+
+        let maintainability_issues = findings.iter()
+            .filter(|f| matches!(f.category, ReviewCategory::CodeSmell | ReviewCategory::BestPractice))
+            .count();
+        let maintainability_score = 100.0 - (maintainability_issues as f32 * 3.0);
+
+        Ok(ReviewSummary {
+            total_findings: findings.len(),
+            findings_by_severity: by_severity,
+            findings_by_category: by_category,
+            code_quality_score: code_quality_score.max(0.0),
+            security_score: security_score.max(0.0),
+            maintainability_score: maintainability_score.max(0.0),
+        })
+    }
+
+    fn generate_recommendations(&self, findings: &[ReviewFinding]) -> Result<Vec<String>, AgentError> {
+        let mut recommendations = Vec::new();
+
+        let critical_count = findings.iter().filter(|f| matches!(f.severity, Severity::Critical)).count();
+        if critical_count > 0 {
+            recommendations.push(format!("Fix {} critical issues before merging", critical_count));
+        }
+
+        let security_count = findings.iter().filter(|f| matches!(f.category, ReviewCategory::Security)).count();
+        if security_count > 0 {
+            recommendations.push("Security review recommended before deployment".to_string());
+        }
+
+        let test_findings = findings.iter().filter(|f| matches!(f.category, ReviewCategory::Testing)).count();
+        if test_findings == 0 {
+            recommendations.push("Consider adding more comprehensive test coverage".to_string());
+        }
+
+        Ok(recommendations)
+    }
+
+    fn determine_approval_status(&self, findings: &[ReviewFinding], summary: &ReviewSummary) -> Result<ApprovalStatus, AgentError> {
+        let critical_count = findings.iter().filter(|f| matches!(f.severity, Severity::Critical)).count();
+        let high_count = findings.iter().filter(|f| matches!(f.severity, Severity::High)).count();
+
+        if critical_count > 0 {
+            Ok(ApprovalStatus::Rejected)
+        } else if high_count > 3 || summary.code_quality_score < 70.0 {
+            Ok(ApprovalStatus::RequiresChanges)
+        } else if high_count > 0 || summary.code_quality_score < 90.0 {
+            Ok(ApprovalStatus::ApprovedWithComments)
+        } else {
+            Ok(ApprovalStatus::Approved)
+        }
+    }
+}
+
+/// Finding as reported by the model; the id and file path are filled in by the agent.
+#[derive(Debug, Deserialize)]
+struct FindingOutput {
+    line_start: Option<usize>,
+    line_end: Option<usize>,
+    severity: Severity,
+    category: ReviewCategory,
+    title: String,
+    description: String,
+    suggestion: Option<String>,
+    examples: Vec<String>,
+    #[serde(default = "default_confidence")]
+    confidence: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct FindingsOutput {
+    findings: Vec<FindingOutput>,
+}
+
+/// Number findings per review kind. Until this runs, each finding's id holds
+/// only the prefix of the review that produced it (e.g. "SEC").
+fn assign_finding_ids(findings: &mut [ReviewFinding]) {
+    let mut counters: HashMap<String, usize> = HashMap::new();
+    for finding in findings {
+        let counter = counters.entry(finding.id.clone()).or_insert(0);
+        *counter += 1;
+        finding.id = format!("{}-{:03}", finding.id, counter);
+    }
+}
+
+fn annotation_type(severity: &Severity) -> AnnotationType {
+    match severity {
+        Severity::Critical | Severity::High => AnnotationType::Error,
+        Severity::Medium => AnnotationType::Warning,
+        Severity::Low => AnnotationType::Suggestion,
+        Severity::Info => AnnotationType::Info,
+    }
+}
+
+/// `/review` priorities: 0 drop everything, 1 urgent, 2 normal, 3 nice to have.
+fn priority(severity: &Severity) -> i32 {
+    match severity {
+        Severity::Critical => 0,
+        Severity::High => 1,
+        Severity::Medium => 2,
+        Severity::Low | Severity::Info => 3,
+    }
+}
+
+impl ReviewFinding {
+    /// Convert to the protocol finding rendered by `/review`, resolving the
+    /// path against `cwd`, the directory the review ran in.
+    pub fn to_protocol(&self, cwd: &Path) -> codex_protocol::protocol::ReviewFinding {
+        let priority = priority(&self.severity);
+        let mut body = self.description.clone();
+        if let Some(suggestion) = &self.suggestion {
+            body.push_str(&format!("\n\nSuggestion: {}", suggestion));
+        }
+        let start = self.line_start.unwrap_or(1);
+        let end = self.line_end.unwrap_or(start).max(start);
+
+        codex_protocol::protocol::ReviewFinding {
+            title: format!("[P{}] {}", priority, self.title),
+            body,
+            confidence_score: self.confidence,
+            priority,
+            code_location: ReviewCodeLocation {
+                absolute_file_path: cwd.join(&self.file_path),
+                line_range: ReviewLineRange {
+                    start: u32::try_from(start).unwrap_or(u32::MAX),
+                    end: u32::try_from(end).unwrap_or(u32::MAX),
+                },
+            },
+        }
+    }
+}
+
+fn review_output(findings: &[ReviewFinding], approval: &ApprovalStatus, recommendations: &[String], cwd: &Path) -> ReviewOutputEvent {
+    let correct = matches!(approval, ApprovalStatus::Approved | ApprovalStatus::ApprovedWithComments);
+    let overall_explanation = match recommendations.first() {
+        Some(recommendation) if !correct => format!("{:?}: {}", approval, recommendation),
+        _ => format!("{:?} with {} findings", approval, findings.len()),
+    };
+    let overall_confidence_score = if findings.is_empty() {
+        1.0
+    } else {
+        findings.iter().map(|finding| finding.confidence).sum::<f32>() / findings.len() as f32
+    };
+
+    ReviewOutputEvent {
+        findings: findings.iter().map(|finding| finding.to_protocol(cwd)).collect(),
+        overall_correctness: if correct { "patch is correct" } else { "patch is incorrect" }.to_string(),
+        overall_explanation,
+        overall_confidence_score,
+    }
+}
+
+fn finding_schema() -> Value {
+    object_schema(json!({
+        "line_start": { "type": ["integer", "null"] },
+        "line_end": { "type": ["integer", "null"] },
+        "severity": enum_schema(&["Critical", "High", "Medium", "Low", "Info"]),
+        "category": enum_schema(&[
+            "Security",
+            "Performance",
+            "Bug",
+            "CodeSmell",
+            "BestPractice",
+            "Documentation",
+            "Architecture",
+            "Testing",
+        ]),
+        "title": { "type": "string" },
+        "description": { "type": "string" },
+        "suggestion": { "type": ["string", "null"] },
+        "examples": string_array_schema(),
+        "confidence": { "type": "number" },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockModelProvider;
+
+    #[tokio::test]
+    async fn test_code_review() {
+        let mock_provider = Arc::new(MockModelProvider::with_responses(
+            vec![
+                r#"{"findings": [{"line_start": 1, "line_end": 1, "severity": "High", "category": "Security", "title": "Hardcoded secret", "description": "A password is committed in source", "suggestion": "Load it from the environment", "examples": [], "confidence": 0.9}]}"#.to_string(),
+                r#"{"findings": [{"line_start": null, "line_end": null, "severity": "Low", "category": "Security", "title": "Weak hash", "description": "MD5 is used for hashing", "suggestion": null, "examples": []}]}"#.to_string(),
+            ],
+            r#"{"findings": []}"#,
+        ));
+
+        let agent = ReviewerAgent::new(mock_provider);
+        let request = ReviewRequest {
+            code_changes: vec![CodeChange {
+                file_path: "src/auth.js".to_string(),
+                old_content: "function login() {\n  return check(password);\n".to_string(),
+                new_content: "function login() {\n  const password = \"hunter2\";\n  return check(password, md5);\n".to_string(),
+                change_type: "modify".to_string(),
+            }],
+            requirements: None,
+            context: None,
+            review_focus: Some(vec![ReviewFocus::Security, ReviewFocus::Performance]),
+            cwd: Some(PathBuf::from("/repo")),
+            base_ref: None,
+        };
+
+        let response = agent.review_changes(request).await.unwrap();
+        let ids: Vec<_> = response.findings.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, vec!["SEC-001", "PERF-001"]);
+        assert!(response.findings.iter().all(|f| f.file_path == "src/auth.js"));
+
+        // Findings without a line are anchored to the changed lines
+        assert_eq!((response.findings[1].line_start, response.findings[1].line_end), (Some(2), Some(3)));
+        let hunk = &response.annotated_diffs[0].hunks[0];
+        assert_eq!((hunk.old_start, hunk.old_lines, hunk.new_start, hunk.new_lines), (1, 2, 1, 3));
+        let added = hunk.lines.iter().filter(|line| line.line_type == DiffLineType::Added).count();
+        assert_eq!(added, 2);
+        assert_eq!(hunk.annotations.len(), 2);
+
+        // The protocol findings render like `/review`
+        let finding = &response.review_output.findings[0];
+        assert_eq!(finding.title, "[P1] Hardcoded secret");
+        assert_eq!(finding.code_location.absolute_file_path, PathBuf::from("/repo/src/auth.js"));
+        assert_eq!(response.review_output.overall_correctness, "patch is correct");
+        assert!(response.format_findings().contains("- [P3] Weak hash — /repo/src/auth.js:2-3"));
+    }
+}
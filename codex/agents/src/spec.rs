@@ -0,0 +1,416 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::spec_document::{stabilize_requirement_ids, PersistedSpec, SpecDocument, SpecDocumentOptions, SpecPaths};
+use crate::structured::{array_schema, enum_schema, generate_json, object_schema, string_array_schema};
+use crate::{AgentError, ModelProvider};
+
+/// Spec Agent: Generates requirements, tests, and acceptance criteria from prompts
+#[derive(Clone)]
+pub struct SpecAgent {
+    model_provider: Arc<dyn ModelProvider>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecRequest {
+    pub prompt: String,
+    pub context: Option<HashMap<String, String>>,
+    pub project_type: Option<String>,
+    pub existing_requirements: Option<Vec<String>>,
+    /// Write the spec to the repository at `cwd`, revising the previous
+    /// version if there is one.
+    #[serde(default)]
+    pub document: Option<SpecDocumentOptions>,
+    /// Repository the spec document is written to. Defaults to the current directory.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub priority: Priority,
+    pub category: RequirementCategory,
+    pub acceptance_criteria: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Priority {
+    Critical,
+    High,
+    Medium,
+    Low,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RequirementCategory {
+    Functional,
+    NonFunctional,
+    Technical,
+    Security,
+    Performance,
+    Usability,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub test_type: TestType,
+    pub steps: Vec<String>,
+    pub expected_result: String,
+    pub requirement_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TestType {
+    Unit,
+    Integration,
+    E2E,
+    Manual,
+    Regression,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecResponse {
+    pub requirements: Vec<Requirement>,
+    pub test_cases: Vec<TestCase>,
+    pub user_stories: Vec<UserStory>,
+    pub acceptance_criteria: HashMap<String, Vec<String>>,
+    pub metadata: SpecMetadata,
+    /// Set when the spec was written to the repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<PersistedSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStory {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub role: String,
+    pub goal: String,
+    pub benefit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecMetadata {
+    pub estimated_effort: String,
+    pub complexity: Complexity,
+    pub dependencies: Vec<String>,
+    pub risk_level: RiskLevel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Complexity {
+    Simple,
+    Moderate,
+    Complex,
+    VeryComplex,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl SpecAgent {
+    pub fn new(model_provider: Arc<dyn ModelProvider>) -> Self {
+        Self { model_provider }
+    }
+
+    pub async fn generate_spec(&self, request: SpecRequest) -> Result<SpecResponse, AgentError> {
+        // Load the version of the spec this run revises
+        let paths = match &request.document {
+            Some(options) => {
+                let cwd = match &request.cwd {
+                    Some(cwd) => cwd.clone(),
+                    None => std::env::current_dir()?,
+                };
+                Some(SpecPaths::new(options, &cwd, &request.prompt)?)
+            }
+            None => None,
+        };
+        let previous = match &paths {
+            Some(paths) => paths.load().await?,
+            None => None,
+        };
+
+        // Generate requirements from prompt, keeping the IDs of earlier ones
+        let mut requirements = self.generate_requirements(&request, previous.as_ref()).await?;
+        stabilize_requirement_ids(&mut requirements, previous.as_ref());
+
+        // Generate test cases for requirements
+        let test_cases = self.generate_test_cases(&requirements).await?;
+
+        // Generate user stories
+        let user_stories = self.generate_user_stories(&request, &requirements).await?;
+
+        // Generate acceptance criteria
+        let acceptance_criteria = self.generate_acceptance_criteria(&requirements).await?;
+
+        // Generate metadata
+        let metadata = self.generate_metadata(&request, &requirements).await?;
+
+        let mut response = SpecResponse {
+            requirements,
+            test_cases,
+            user_stories,
+            acceptance_criteria,
+            metadata,
+            document: None,
+        };
+
+        // Persist the spec as Markdown plus a machine-readable sidecar
+        if let Some(paths) = &paths {
+            response.document = Some(paths.save(&request.prompt, &response, previous.as_ref()).await?);
+        }
+
+        Ok(response)
+    }
+
+    async fn generate_requirements(&self, request: &SpecRequest, previous: Option<&SpecDocument>) -> Result<Vec<Requirement>, AgentError> {
+        let system_prompt = r#"You are a senior product manager and systems analyst. Given a feature request, generate detailed, actionable requirements that:
+
+1. Cover functional, non-functional, and technical aspects
+2. Include clear acceptance criteria
+3. Are prioritized appropriately
+4. Are testable and measurable
+
+Number requirements sequentially as REQ-001, REQ-002, ..."#;
+
+        let mut user_prompt = format!("Generate requirements for this feature request:\n\n{}", request.prompt);
+        if let Some(project_type) = &request.project_type {
+            user_prompt.push_str(&format!("\n\nProject type: {}", project_type));
+        }
+        if let Some(context) = &request.context {
+            user_prompt.push_str(&format!("\n\nAdditional context: {:?}", context));
+        }
+        if let Some(existing) = request.existing_requirements.as_ref().filter(|existing| !existing.is_empty()) {
+            user_prompt.push_str(&format!("\n\nExisting requirements (do not duplicate):\n- {}", existing.join("\n- ")));
+        }
+        if let Some(previous) = previous {
+            user_prompt.push_str(&format!(
+                "\n\nThis revises version {} of the spec. Return the complete revised list of requirements. Keep the ID of every requirement that is unchanged or revised, drop requirements that no longer apply, and number new requirements after the highest ID ever used, REQ-{:03}.\n\nCurrent requirements:\n{}",
+                previous.version,
+                previous.last_requirement_number,
+                summarize_requirements(&previous.spec.requirements)
+            ));
+        }
+
+        let schema = object_schema(json!({ "requirements": array_schema(requirement_schema()) }));
+        let output: RequirementsOutput =
+            generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+        Ok(output.requirements)
+    }
+
+    async fn generate_test_cases(&self, requirements: &[Requirement]) -> Result<Vec<TestCase>, AgentError> {
+        let mut test_cases = Vec::new();
+        let schema = object_schema(json!({ "test_cases": array_schema(test_case_schema()) }));
+
+        for requirement in requirements {
+            let system_prompt = "Generate comprehensive test cases for this requirement. Include unit, integration, and end-to-end tests where applicable.";
+
+            let user_prompt = format!(
+                "Generate test cases for requirement {} - {}\n\n{}\n\nAcceptance criteria:\n- {}\n\nNumber test cases as TC-{}-001, TC-{}-002, ...",
+                requirement.id,
+                requirement.title,
+                requirement.description,
+                requirement.acceptance_criteria.join("\n- "),
+                requirement.id,
+                requirement.id,
+            );
+
+            let output: TestCasesOutput =
+                generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+            test_cases.extend(output.test_cases.into_iter().map(|mut test_case| {
+                test_case.requirement_id = requirement.id.clone();
+                test_case
+            }));
+        }
+
+        Ok(test_cases)
+    }
+
+    async fn generate_user_stories(&self, request: &SpecRequest, requirements: &[Requirement]) -> Result<Vec<UserStory>, AgentError> {
+        let system_prompt = r#"As a product owner, break down this feature into user stories. Each story should follow the format:
+"As a [type of user], I want [some goal] so that [some reason]"
+
+Focus on user value and outcomes. Number stories sequentially as US-001, US-002, ..."#;
+
+        let user_prompt = format!(
+            "Create user stories for this feature:\n\n{}\n\nRequirements:\n{}",
+            request.prompt,
+            summarize_requirements(requirements)
+        );
+
+        let schema = object_schema(json!({ "user_stories": array_schema(user_story_schema()) }));
+        let output: UserStoriesOutput =
+            generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+        Ok(output.user_stories)
+    }
+
+    async fn generate_acceptance_criteria(&self, requirements: &[Requirement]) -> Result<HashMap<String, Vec<String>>, AgentError> {
+        let mut criteria = HashMap::new();
+
+        for requirement in requirements {
+            criteria.insert(
+                requirement.id.clone(),
+                requirement.acceptance_criteria.clone(),
+            );
+        }
+
+        Ok(criteria)
+    }
+
+    async fn generate_metadata(&self, request: &SpecRequest, requirements: &[Requirement]) -> Result<SpecMetadata, AgentError> {
+        let critical_count = requirements.iter().filter(|r| matches!(r.priority, Priority::Critical)).count();
+        let complexity = match requirements.len() {
+            0..=3 => Complexity::Simple,
+            4..=7 => Complexity::Moderate,
+            8..=12 => Complexity::Complex,
+            _ => Complexity::VeryComplex,
+        };
+
+        let risk_level = if critical_count > 2 {
+            RiskLevel::High
+        } else if complexity == Complexity::VeryComplex {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::Low
+        };
+
+        let system_prompt = "You are a senior engineering lead. Estimate the effort to deliver these requirements (for example \"2-3 weeks\") and list the external services, libraries, or teams the work depends on.";
+        let user_prompt = format!(
+            "Feature request:\n{}\n\nRequirements:\n{}",
+            request.prompt,
+            summarize_requirements(requirements)
+        );
+
+        let schema = object_schema(json!({
+            "estimated_effort": { "type": "string" },
+            "dependencies": string_array_schema(),
+        }));
+        let estimate: EstimateOutput =
+            generate_json(self.model_provider.as_ref(), system_prompt, &user_prompt, &schema).await?;
+
+        Ok(SpecMetadata {
+            estimated_effort: estimate.estimated_effort,
+            complexity,
+            dependencies: estimate.dependencies,
+            risk_level,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RequirementsOutput {
+    requirements: Vec<Requirement>,
+}
+
+#[derive(Deserialize)]
+struct TestCasesOutput {
+    test_cases: Vec<TestCase>,
+}
+
+#[derive(Deserialize)]
+struct UserStoriesOutput {
+    user_stories: Vec<UserStory>,
+}
+
+#[derive(Deserialize)]
+struct EstimateOutput {
+    estimated_effort: String,
+    dependencies: Vec<String>,
+}
+
+fn summarize_requirements(requirements: &[Requirement]) -> String {
+    requirements
+        .iter()
+        .map(|r| format!("- {} [{:?}/{:?}] {}: {}", r.id, r.priority, r.category, r.title, r.description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn requirement_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "string" },
+        "title": { "type": "string" },
+        "description": { "type": "string" },
+        "priority": enum_schema(&["Critical", "High", "Medium", "Low"]),
+        "category": enum_schema(&["Functional", "NonFunctional", "Technical", "Security", "Performance", "Usability"]),
+        "acceptance_criteria": string_array_schema(),
+    }))
+}
+
+fn test_case_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "string" },
+        "title": { "type": "string" },
+        "description": { "type": "string" },
+        "test_type": enum_schema(&["Unit", "Integration", "E2E", "Manual", "Regression"]),
+        "steps": string_array_schema(),
+        "expected_result": { "type": "string" },
+        "requirement_id": { "type": "string" },
+    }))
+}
+
+fn user_story_schema() -> Value {
+    object_schema(json!({
+        "id": { "type": "string" },
+        "title": { "type": "string" },
+        "description": { "type": "string" },
+        "role": { "type": "string" },
+        "goal": { "type": "string" },
+        "benefit": { "type": "string" },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockModelProvider;
+
+    #[tokio::test]
+    async fn test_spec_agent_generation() {
+        let mock_provider = Arc::new(MockModelProvider::with_responses(
+            vec![
+                r#"{"requirements": [{"id": "REQ-001", "title": "Implement user authentication", "description": "Users can log in with email and password", "priority": "High", "category": "Functional", "acceptance_criteria": ["User can log in with correct credentials"]}]}"#.to_string(),
+                r#"```json
+{"test_cases": [{"id": "TC-REQ-001-001", "title": "Valid login", "description": "Log in with valid credentials", "test_type": "E2E", "steps": ["Open login page", "Submit valid credentials"], "expected_result": "Dashboard is shown", "requirement_id": "REQ-001"}]}
+```"#.to_string(),
+                r#"{"user_stories": [{"id": "US-001", "title": "User login", "description": "As a user, I want to log in so that I can see my dashboard", "role": "User", "goal": "Log in", "benefit": "Access my dashboard"}]}"#.to_string(),
+            ],
+            r#"{"estimated_effort": "1 week", "dependencies": ["Session store"]}"#,
+        ));
+
+        let agent = SpecAgent::new(mock_provider);
+        let request = SpecRequest {
+            prompt: "Implement user authentication".to_string(),
+            context: None,
+            project_type: Some("web-app".to_string()),
+            existing_requirements: None,
+            document: None,
+            cwd: None,
+        };
+
+        let response = agent.generate_spec(request).await.unwrap();
+        assert_eq!(response.requirements[0].id, "REQ-001");
+        assert_eq!(response.test_cases[0].requirement_id, "REQ-001");
+        assert!(matches!(response.test_cases[0].test_type, TestType::E2E));
+        assert_eq!(response.user_stories.len(), 1);
+        assert_eq!(response.metadata.dependencies, vec!["Session store".to_string()]);
+    }
+}
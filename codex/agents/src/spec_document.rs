@@ -0,0 +1,461 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::spec::{Requirement, SpecResponse};
+use crate::AgentError;
+
+/// Folder specs are written to when none is configured.
+pub const DEFAULT_SPEC_DIR: &str = "specs";
+
+/// Prefix of requirement IDs, e.g. `REQ-007`.
+const REQUIREMENT_PREFIX: &str = "REQ-";
+
+/// Words of the prompt used for a derived slug.
+const SLUG_WORDS: usize = 6;
+
+/// Where a generated spec is persisted.
+///
+/// The spec is written as `<dir>/<slug>.md` for people and `<dir>/<slug>.json`
+/// for tools. When the sidecar already exists, the new spec is a revision of
+/// it: requirements keep their IDs and the changes are reported.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecDocumentOptions {
+    /// Relative to the request's `cwd` unless absolute.
+    #[serde(default = "default_spec_dir")]
+    pub dir: PathBuf,
+    /// File name without extension; derived from the prompt when not set.
+    #[serde(default)]
+    pub slug: Option<String>,
+}
+
+impl Default for SpecDocumentOptions {
+    fn default() -> Self {
+        Self {
+            dir: default_spec_dir(),
+            slug: None,
+        }
+    }
+}
+
+fn default_spec_dir() -> PathBuf {
+    PathBuf::from(DEFAULT_SPEC_DIR)
+}
+
+/// The machine-readable sidecar, `<slug>.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecDocument {
+    pub slug: String,
+    /// Starts at 1 and increases with every revision.
+    pub version: u32,
+    pub prompt: String,
+    pub updated_at: DateTime<Utc>,
+    /// Highest requirement number ever assigned, so the IDs of removed
+    /// requirements are never reused.
+    pub last_requirement_number: u32,
+    pub spec: SpecResponse,
+}
+
+/// Where a spec was written and how it differs from the previous version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistedSpec {
+    pub slug: String,
+    pub version: u32,
+    pub markdown_path: PathBuf,
+    pub sidecar_path: PathBuf,
+    pub changes: SpecDiff,
+}
+
+/// Requirement-level differences between two versions of a spec.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpecDiff {
+    /// `None` for the first version.
+    pub previous_version: Option<u32>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<RequirementChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequirementChange {
+    pub id: String,
+    /// Names of the fields that differ, e.g. `description`.
+    pub fields: Vec<String>,
+}
+
+impl SpecDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Diff the requirements of `previous` against `current` by ID.
+    pub fn between(previous: &SpecDocument, current: &SpecResponse) -> Self {
+        let old: HashMap<&str, &Requirement> =
+            previous.spec.requirements.iter().map(|requirement| (requirement.id.as_str(), requirement)).collect();
+        let new_ids: HashSet<&str> = current.requirements.iter().map(|requirement| requirement.id.as_str()).collect();
+
+        let mut diff = SpecDiff {
+            previous_version: Some(previous.version),
+            ..Default::default()
+        };
+        for requirement in &current.requirements {
+            match old.get(requirement.id.as_str()) {
+                None => diff.added.push(requirement.id.clone()),
+                Some(before) => {
+                    let fields = changed_fields(before, requirement);
+                    if !fields.is_empty() {
+                        diff.changed.push(RequirementChange {
+                            id: requirement.id.clone(),
+                            fields,
+                        });
+                    }
+                }
+            }
+        }
+        diff.removed = previous
+            .spec
+            .requirements
+            .iter()
+            .filter(|requirement| !new_ids.contains(requirement.id.as_str()))
+            .map(|requirement| requirement.id.clone())
+            .collect();
+        diff
+    }
+}
+
+fn changed_fields(before: &Requirement, after: &Requirement) -> Vec<String> {
+    let mut fields = Vec::new();
+    if before.title != after.title {
+        fields.push("title".to_string());
+    }
+    if before.description != after.description {
+        fields.push("description".to_string());
+    }
+    if format!("{:?}", before.priority) != format!("{:?}", after.priority) {
+        fields.push("priority".to_string());
+    }
+    if format!("{:?}", before.category) != format!("{:?}", after.category) {
+        fields.push("category".to_string());
+    }
+    if before.acceptance_criteria != after.acceptance_criteria {
+        fields.push("acceptance_criteria".to_string());
+    }
+    fields
+}
+
+/// Resolved locations of one spec's files.
+pub(crate) struct SpecPaths {
+    pub(crate) slug: String,
+    pub(crate) markdown: PathBuf,
+    pub(crate) sidecar: PathBuf,
+}
+
+impl SpecPaths {
+    pub(crate) fn new(options: &SpecDocumentOptions, cwd: &Path, prompt: &str) -> Result<Self, AgentError> {
+        let slug = match &options.slug {
+            Some(slug) => {
+                let valid = !slug.is_empty()
+                    && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+                if !valid {
+                    return Err(format!("invalid spec slug '{}': use lowercase letters, digits, '-' and '_'", slug).into());
+                }
+                slug.clone()
+            }
+            None => slugify(prompt),
+        };
+        let dir = cwd.join(&options.dir);
+        Ok(Self {
+            markdown: dir.join(format!("{slug}.md")),
+            sidecar: dir.join(format!("{slug}.json")),
+            slug,
+        })
+    }
+
+    /// The current version of the spec, if it has been written before.
+    pub(crate) async fn load(&self) -> Result<Option<SpecDocument>, AgentError> {
+        match tokio::fs::read_to_string(&self.sidecar).await {
+            Ok(text) => {
+                let document = serde_json::from_str(&text)
+                    .map_err(|e| format!("failed to parse {}: {e}", self.sidecar.display()))?;
+                Ok(Some(document))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("failed to read {}: {e}", self.sidecar.display()).into()),
+        }
+    }
+
+    /// Write `spec` as the next version after `previous`.
+    pub(crate) async fn save(
+        &self,
+        prompt: &str,
+        spec: &SpecResponse,
+        previous: Option<&SpecDocument>,
+    ) -> Result<PersistedSpec, AgentError> {
+        let mut spec = spec.clone();
+        spec.document = None;
+
+        let changes = match previous {
+            Some(previous) => SpecDiff::between(previous, &spec),
+            None => SpecDiff {
+                added: spec.requirements.iter().map(|requirement| requirement.id.clone()).collect(),
+                ..Default::default()
+            },
+        };
+        let last_requirement_number = spec
+            .requirements
+            .iter()
+            .filter_map(|requirement| requirement_number(&requirement.id))
+            .chain(previous.map(|previous| previous.last_requirement_number))
+            .max()
+            .unwrap_or(0);
+        let document = SpecDocument {
+            slug: self.slug.clone(),
+            version: previous.map_or(1, |previous| previous.version + 1),
+            prompt: prompt.to_string(),
+            updated_at: Utc::now(),
+            last_requirement_number,
+            spec,
+        };
+
+        if let Some(dir) = self.sidecar.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(&self.markdown, render_markdown(&document, &changes)).await?;
+        tokio::fs::write(&self.sidecar, serde_json::to_string_pretty(&document)? + "\n").await?;
+
+        Ok(PersistedSpec {
+            slug: self.slug.clone(),
+            version: document.version,
+            markdown_path: self.markdown.clone(),
+            sidecar_path: self.sidecar.clone(),
+            changes,
+        })
+    }
+}
+
+/// `"Add rate limiting to the login endpoint"` -> `add-rate-limiting-to-the-login`.
+fn slugify(prompt: &str) -> String {
+    let words: Vec<String> = prompt
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(SLUG_WORDS)
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        "spec".to_string()
+    } else {
+        words.join("-")
+    }
+}
+
+/// `REQ-012` -> `12`.
+fn requirement_number(id: &str) -> Option<u32> {
+    id.strip_prefix(REQUIREMENT_PREFIX)?.parse().ok()
+}
+
+/// Give every requirement a stable, unique ID.
+///
+/// Against a previous version, a requirement keeps an ID the model carried
+/// over, or takes the ID of the previous requirement with the same title;
+/// anything else is new and numbered after every ID ever assigned. Without a
+/// previous version the model's IDs are kept unless malformed or duplicated.
+pub(crate) fn stabilize_requirement_ids(requirements: &mut [Requirement], previous: Option<&SpecDocument>) {
+    let previous_requirements: &[Requirement] = previous.map_or(&[], |previous| &previous.spec.requirements);
+    let mut claimed: HashSet<String> = HashSet::new();
+    let mut unresolved = Vec::new();
+
+    for (index, requirement) in requirements.iter_mut().enumerate() {
+        let carried_over = match previous {
+            Some(_) => previous_requirements.iter().any(|old| old.id == requirement.id),
+            None => requirement_number(&requirement.id).is_some(),
+        };
+        if carried_over && !claimed.contains(&requirement.id) {
+            claimed.insert(requirement.id.clone());
+            continue;
+        }
+        let title = normalize_title(&requirement.title);
+        let same_title = previous_requirements
+            .iter()
+            .find(|old| !claimed.contains(&old.id) && normalize_title(&old.title) == title);
+        match same_title {
+            Some(old) => {
+                requirement.id = old.id.clone();
+                claimed.insert(old.id.clone());
+            }
+            None => unresolved.push(index),
+        }
+    }
+
+    let mut next = claimed
+        .iter()
+        .filter_map(|id| requirement_number(id))
+        .chain(previous.map(|previous| previous.last_requirement_number))
+        .chain(previous_requirements.iter().filter_map(|old| requirement_number(&old.id)))
+        .max()
+        .unwrap_or(0);
+    for index in unresolved {
+        next += 1;
+        requirements[index].id = format!("{REQUIREMENT_PREFIX}{next:03}");
+    }
+}
+
+fn normalize_title(title: &str) -> String {
+    title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn render_markdown(document: &SpecDocument, changes: &SpecDiff) -> String {
+    let spec = &document.spec;
+    let mut out = format!("# {}\n\n", document.prompt.lines().next().unwrap_or_default().trim());
+    out.push_str(&format!(
+        "<!-- Generated by the spec agent. Machine-readable version: {}.json -->\n\n",
+        document.slug
+    ));
+    out.push_str(&format!(
+        "Version {} · updated {}\n",
+        document.version,
+        document.updated_at.format("%Y-%m-%d %H:%M UTC")
+    ));
+
+    if let Some(previous_version) = changes.previous_version.filter(|_| !changes.is_empty()) {
+        out.push_str(&format!("\n## Changes since version {previous_version}\n\n"));
+        for id in &changes.added {
+            out.push_str(&format!("- Added {id}\n"));
+        }
+        for change in &changes.changed {
+            out.push_str(&format!("- Changed {} ({})\n", change.id, change.fields.join(", ")));
+        }
+        for id in &changes.removed {
+            out.push_str(&format!("- Removed {id}\n"));
+        }
+    }
+
+    out.push_str("\n## Requirements\n");
+    for requirement in &spec.requirements {
+        out.push_str(&format!("\n### {}: {}\n\n", requirement.id, requirement.title));
+        out.push_str(&format!(
+            "**Priority:** {:?} · **Category:** {:?}\n\n{}\n",
+            requirement.priority, requirement.category, requirement.description
+        ));
+        if !requirement.acceptance_criteria.is_empty() {
+            out.push_str("\nAcceptance criteria:\n\n");
+            for criterion in &requirement.acceptance_criteria {
+                out.push_str(&format!("- [ ] {criterion}\n"));
+            }
+        }
+        let test_cases: Vec<_> = spec.test_cases.iter().filter(|test| test.requirement_id == requirement.id).collect();
+        if !test_cases.is_empty() {
+            out.push_str("\nTest cases:\n\n");
+            for test in test_cases {
+                out.push_str(&format!("- {} ({:?}): {} — expects {}\n", test.id, test.test_type, test.title, test.expected_result));
+            }
+        }
+    }
+
+    if !spec.user_stories.is_empty() {
+        out.push_str("\n## User stories\n\n");
+        for story in &spec.user_stories {
+            out.push_str(&format!(
+                "- **{} {}**: As a {}, I want {} so that {}.\n",
+                story.id, story.title, story.role, story.goal, story.benefit
+            ));
+        }
+    }
+
+    let metadata = &spec.metadata;
+    out.push_str("\n## Metadata\n\n");
+    out.push_str(&format!("- Estimated effort: {}\n", metadata.estimated_effort));
+    out.push_str(&format!("- Complexity: {:?}\n", metadata.complexity));
+    out.push_str(&format!("- Risk: {:?}\n", metadata.risk_level));
+    if !metadata.dependencies.is_empty() {
+        out.push_str(&format!("- Dependencies: {}\n", metadata.dependencies.join(", ")));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::spec::{SpecAgent, SpecRequest};
+    use crate::test_support::MockModelProvider;
+
+    const OTHER_OUTPUTS: &str = r#"{"test_cases": [], "user_stories": [], "estimated_effort": "1 week", "dependencies": []}"#;
+
+    async fn generate(repo: &Path, requirements: &str) -> SpecResponse {
+        let provider = Arc::new(MockModelProvider::with_responses(vec![requirements.to_string()], OTHER_OUTPUTS));
+        let request = SpecRequest {
+            prompt: "Implement user authentication".to_string(),
+            context: None,
+            project_type: None,
+            existing_requirements: None,
+            document: Some(SpecDocumentOptions::default()),
+            cwd: Some(repo.to_path_buf()),
+        };
+        SpecAgent::new(provider).generate_spec(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_spec_revisions_keep_requirement_ids() {
+        let repo = tempfile::tempdir().unwrap();
+
+        let first = generate(
+            repo.path(),
+            r#"{"requirements": [
+                {"id": "REQ-001", "title": "Log in", "description": "Users log in with email and password", "priority": "High", "category": "Functional", "acceptance_criteria": ["Valid credentials open the dashboard"]},
+                {"id": "REQ-002", "title": "Password reset", "description": "Users reset a forgotten password", "priority": "Medium", "category": "Functional", "acceptance_criteria": []}
+            ]}"#,
+        )
+        .await;
+        let document = first.document.unwrap();
+        assert_eq!(document.slug, "implement-user-authentication");
+        assert_eq!(document.version, 1);
+        assert_eq!(document.changes.added, vec!["REQ-001", "REQ-002"]);
+        let markdown = std::fs::read_to_string(repo.path().join("specs/implement-user-authentication.md")).unwrap();
+        assert!(markdown.contains("### REQ-001: Log in"));
+        assert!(markdown.contains("- [ ] Valid credentials open the dashboard"));
+
+        // The model renumbers the reset requirement and reuses REQ-001 for a new one
+        let second = generate(
+            repo.path(),
+            r#"{"requirements": [
+                {"id": "REQ-001", "title": "Log in", "description": "Users log in with email and a one-time code", "priority": "High", "category": "Security", "acceptance_criteria": ["Valid credentials open the dashboard"]},
+                {"id": "REQ-5", "title": "password  reset", "description": "Users reset a forgotten password", "priority": "Medium", "category": "Functional", "acceptance_criteria": []},
+                {"id": "REQ-001", "title": "Rate limit logins", "description": "Repeated failures lock the account", "priority": "High", "category": "Security", "acceptance_criteria": []}
+            ]}"#,
+        )
+        .await;
+        let ids: Vec<_> = second.requirements.iter().map(|requirement| requirement.id.as_str()).collect();
+        assert_eq!(ids, vec!["REQ-001", "REQ-002", "REQ-003"]);
+        let document = second.document.unwrap();
+        assert_eq!(document.version, 2);
+        assert_eq!(document.changes.previous_version, Some(1));
+        assert_eq!(document.changes.added, vec!["REQ-003"]);
+        assert!(document.changes.removed.is_empty());
+        assert_eq!(
+            document.changes.changed,
+            vec![
+                RequirementChange {
+                    id: "REQ-001".to_string(),
+                    fields: vec!["description".to_string(), "category".to_string()],
+                },
+                RequirementChange {
+                    id: "REQ-002".to_string(),
+                    fields: vec!["title".to_string()],
+                },
+            ]
+        );
+
+        let sidecar: SpecDocument = serde_json::from_str(&std::fs::read_to_string(&document.sidecar_path).unwrap()).unwrap();
+        assert_eq!(sidecar.last_requirement_number, 3);
+        assert!(sidecar.spec.document.is_none());
+        let markdown = std::fs::read_to_string(&document.markdown_path).unwrap();
+        assert!(markdown.contains("## Changes since version 1"));
+        assert!(markdown.contains("- Changed REQ-001 (description, category)"));
+
+        assert!(SpecPaths::new(&SpecDocumentOptions { slug: Some("../x".to_string()), ..Default::default() }, repo.path(), "")
+            .is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use crate::model_provider::{AgentError, ModelProvider};
+
+/// Number of times a malformed response is sent back to the model for repair.
+const MAX_REPAIR_ATTEMPTS: usize = 2;
+
+const REPAIR_SYSTEM_PROMPT: &str = r#"You repair JSON documents. You are given a response that failed to parse or did not match the required JSON Schema, together with the parser error.
+
+Return the corrected JSON document only. Preserve the original content wherever possible."#;
+
+/// Request schema-constrained output from the model and deserialize it into `T`.
+///
+/// When the response is not valid JSON for `T`, the invalid output and the
+/// parser error are sent back to the model for repair, up to
+/// [`MAX_REPAIR_ATTEMPTS`] times.
+pub(crate) async fn generate_json<T: DeserializeOwned>(
+    provider: &dyn ModelProvider,
+    system_prompt: &str,
+    user_prompt: &str,
+    schema: &Value,
+) -> Result<T, AgentError> {
+    let mut response = provider.generate_structured(system_prompt, user_prompt, schema).await?;
+    let mut attempt = 0;
+
+    loop {
+        let error = match parse_json::<T>(&response) {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        if attempt == MAX_REPAIR_ATTEMPTS {
+            return Err(format!("model returned malformed JSON after {attempt} repair attempts: {error}").into());
+        }
+        attempt += 1;
+        tracing::warn!("model returned malformed JSON ({error}); requesting repair {attempt}/{MAX_REPAIR_ATTEMPTS}");
+
+        let repair_prompt = format!("Parser error: {error}\n\nInvalid response:\n{response}");
+        response = provider.generate_structured(REPAIR_SYSTEM_PROMPT, &repair_prompt, schema).await?;
+    }
+}
+
+/// Parse a model response into `T`, tolerating Markdown fences and surrounding prose.
+pub(crate) fn parse_json<T: DeserializeOwned>(response: &str) -> Result<T, serde_json::Error> {
+    let trimmed = response.trim();
+    match serde_json::from_str(trimmed) {
+        Ok(value) => Ok(value),
+        Err(error) => match extract_json(trimmed) {
+            Some(candidate) if candidate != trimmed => serde_json::from_str(candidate),
+            _ => Err(error),
+        },
+    }
+}
+
+fn extract_json(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let close = if text[start..].starts_with('{') { '}' } else { ']' };
+    let end = text.rfind(close)?;
+    (end > start).then(|| &text[start..=end])
+}
+
+/// Object schema in the strict form required by structured outputs: every
+/// property is required and no additional properties are allowed.
+pub(crate) fn object_schema(properties: Value) -> Value {
+    let required: Vec<String> = properties
+        .as_object()
+        .map(|properties| properties.keys().cloned().collect())
+        .unwrap_or_default();
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+pub(crate) fn array_schema(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+pub(crate) fn enum_schema(variants: &[&str]) -> Value {
+    json!({ "type": "string", "enum": variants })
+}
+
+pub(crate) fn string_array_schema() -> Value {
+    array_schema(json!({ "type": "string" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockModelProvider;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Answer {
+        value: u32,
+    }
+
+    fn answer_schema() -> Value {
+        object_schema(json!({ "value": { "type": "integer" } }))
+    }
+
+    #[test]
+    fn test_parse_json_strips_fences_and_prose() {
+        let parsed: Answer = parse_json("Here you go:\n```json\n{\"value\": 7}\n```").unwrap();
+        assert_eq!(parsed, Answer { value: 7 });
+    }
+
+    #[tokio::test]
+    async fn test_generate_json_repairs_malformed_output() {
+        let provider = MockModelProvider::with_responses(vec!["{\"value\": ".to_string()], "{\"value\": 3}");
+
+        let parsed: Answer = generate_json(&provider, "system", "user", &answer_schema()).await.unwrap();
+        assert_eq!(parsed, Answer { value: 3 });
+    }
+
+    #[tokio::test]
+    async fn test_generate_json_gives_up_after_repair_attempts() {
+        let provider = MockModelProvider::new("not json");
+
+        let result: Result<Answer, _> = generate_json(&provider, "system", "user", &answer_schema()).await;
+        assert!(result.is_err());
+    }
+}
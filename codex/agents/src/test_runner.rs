@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use codex_core::config_types::ShellEnvironmentPolicy;
+use codex_core::error::{CodexErr, SandboxErr};
+use codex_core::exec::{process_exec_tool_call, ExecParams, ExecToolCallOutput, SandboxType};
+use codex_core::exec_env::create_env;
+use codex_core::get_platform_sandbox;
+use codex_core::protocol::SandboxPolicy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::test_generator::CodeChange;
+use crate::AgentError;
+
+/// Environment variable naming the `codex-linux-sandbox` executable, used on
+/// Linux when [`TestRunOptions::codex_linux_sandbox_exe`] is not set.
+pub const CODEX_LINUX_SANDBOX_EXE_ENV: &str = "CODEX_LINUX_SANDBOX_EXE";
+
+/// Output kept in a [`TestRun`] and fed back when regenerating tests.
+pub(crate) const OUTPUT_TAIL_BYTES: usize = 8 * 1024;
+
+/// Directories that are not copied into the scratch worktree.
+const SKIPPED_DIRS: &[&str] = &[".git", "target"];
+
+/// How generated tests are run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunOptions {
+    /// Time limit for one run of a framework.
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Times tests that fail to compile are sent back to the model.
+    #[serde(default = "default_max_regenerations")]
+    pub max_regenerations: usize,
+    /// Command to run instead of the framework's default. Generated test
+    /// files are not appended to it.
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+    /// Sandbox for the test command. Defaults to writing only inside the
+    /// scratch worktree, without network access; `danger-full-access` runs
+    /// the command unsandboxed.
+    #[serde(default)]
+    pub sandbox_policy: Option<SandboxPolicy>,
+    #[serde(default)]
+    pub codex_linux_sandbox_exe: Option<PathBuf>,
+}
+
+impl Default for TestRunOptions {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_timeout_ms(),
+            max_regenerations: default_max_regenerations(),
+            command: None,
+            sandbox_policy: None,
+            codex_linux_sandbox_exe: None,
+        }
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    10 * 60 * 1000
+}
+
+fn default_max_regenerations() -> usize {
+    2
+}
+
+/// Results of running a generated test suite.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestVerification {
+    /// Every run passed.
+    pub passed: bool,
+    /// The last run of each framework.
+    pub runs: Vec<TestRun>,
+    /// Tests rewritten because they failed to compile.
+    pub regenerated_tests: usize,
+    /// Frameworks of generated tests that cannot be run, e.g. `playwright`.
+    pub skipped_frameworks: Vec<String>,
+}
+
+/// One run of a test framework in the scratch worktree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRun {
+    pub framework: String,
+    pub command: Vec<String>,
+    pub test_files: Vec<String>,
+    pub status: TestRunStatus,
+    pub passed: usize,
+    pub failed: usize,
+    pub exit_code: i32,
+    pub duration_ms: u64,
+    /// Line coverage reported by the framework, when it measures any.
+    pub coverage_percentage: Option<f32>,
+    /// Tail of the combined stdout and stderr.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRunStatus {
+    Passed,
+    Failed,
+    /// The tests did not compile or could not be collected.
+    CompileError,
+    TimedOut,
+}
+
+/// Counts in test framework summaries, e.g. "5 passed; 1 failed" or "1 error".
+static PASSED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+) passed").expect("valid passed regex"));
+static FAILED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+) failed").expect("valid failed regex"));
+static ERRORS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+) errors?").expect("valid errors regex"));
+
+/// Test frameworks that can be run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Runner {
+    Cargo,
+    Pytest,
+    Jest,
+}
+
+impl Runner {
+    /// Runner for a generated test's framework name, e.g. "rust test" or "jest".
+    pub(crate) fn for_framework(framework: &str) -> Option<Self> {
+        let framework = framework.to_ascii_lowercase();
+        if framework.contains("cargo") || framework.contains("rust") {
+            Some(Runner::Cargo)
+        } else if framework.contains("pytest") {
+            Some(Runner::Pytest)
+        } else if framework.contains("jest") {
+            Some(Runner::Jest)
+        } else {
+            None
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Runner::Cargo => "cargo test",
+            Runner::Pytest => "pytest",
+            Runner::Jest => "jest",
+        }
+    }
+
+    fn command(self, test_files: &[String], coverage: bool, offline: bool) -> Vec<String> {
+        let mut command: Vec<String> = match self {
+            Runner::Cargo => ["cargo", "test", "--color", "never"].map(String::from).to_vec(),
+            Runner::Pytest => ["python3", "-m", "pytest", "-p", "no:cacheprovider"].map(String::from).to_vec(),
+            Runner::Jest => ["npx", "--no-install", "jest", "--ci"].map(String::from).to_vec(),
+        };
+        match self {
+            Runner::Cargo if offline => command.push("--offline".to_string()),
+            Runner::Cargo => {}
+            Runner::Pytest if coverage => command.extend(["--cov".to_string(), "--cov-report=term".to_string()]),
+            Runner::Jest if coverage => command.push("--coverage".to_string()),
+            Runner::Pytest | Runner::Jest => {}
+        }
+        // cargo runs every test target; the others can be pointed at the generated files
+        if self != Runner::Cargo {
+            command.extend(test_files.iter().cloned());
+        }
+        command
+    }
+
+    fn is_compile_error(self, output: &str) -> bool {
+        match self {
+            Runner::Cargo => output.contains("error: could not compile"),
+            Runner::Pytest => output.contains("ERROR collecting") || output.contains("errors during collection"),
+            Runner::Jest => output.contains("Test suite failed to run"),
+        }
+    }
+
+    /// Passed and failed test counts from the framework's summary lines.
+    fn counts(self, output: &str) -> (usize, usize) {
+        let count = |line: &str, pattern: &Regex| -> usize {
+            pattern.captures(line).and_then(|captures| captures[1].parse().ok()).unwrap_or(0)
+        };
+
+        match self {
+            // One "test result:" line per test binary
+            Runner::Cargo => output
+                .lines()
+                .filter(|line| line.starts_with("test result:"))
+                .fold((0, 0), |(passed, failed), line| (passed + count(line, &PASSED), failed + count(line, &FAILED))),
+            // e.g. "==== 2 failed, 5 passed, 1 error in 0.12s ===="
+            Runner::Pytest => output
+                .lines()
+                .rev()
+                .find(|line| line.starts_with('=') && (line.contains(" passed") || line.contains(" failed") || line.contains(" error")))
+                .map(|line| (count(line, &PASSED), count(line, &FAILED) + count(line, &ERRORS)))
+                .unwrap_or_default(),
+            // e.g. "Tests:       1 failed, 3 passed, 4 total"
+            Runner::Jest => output
+                .lines()
+                .rev()
+                .find(|line| line.trim_start().starts_with("Tests:"))
+                .map(|line| (count(line, &PASSED), count(line, &FAILED)))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Line coverage from the framework's coverage report.
+    fn coverage(self, output: &str) -> Option<f32> {
+        match self {
+            Runner::Cargo => None,
+            // "TOTAL    120     30    75%"
+            Runner::Pytest => output
+                .lines()
+                .rev()
+                .find(|line| line.starts_with("TOTAL"))
+                .and_then(|line| line.split_whitespace().last())
+                .and_then(|percent| percent.trim_end_matches('%').parse().ok()),
+            // "All files |   85.71 |    50 |   100 |   85.71 |" — the fourth column is % Lines
+            Runner::Jest => output
+                .lines()
+                .find(|line| line.trim_start().starts_with("All files"))
+                .and_then(|line| line.split('|').nth(4))
+                .and_then(|percent| percent.trim().parse().ok()),
+        }
+    }
+}
+
+/// Copy of a working tree that generated tests or other commands are run in.
+///
+/// The code changes under test are applied to the copy, so the caller's tree
+/// is never modified. The directory is removed when the worktree is dropped.
+pub(crate) struct ScratchWorktree {
+    dir: TempDir,
+}
+
+impl ScratchWorktree {
+    /// An empty worktree.
+    pub(crate) fn new() -> Result<Self, AgentError> {
+        let dir = tempfile::Builder::new().prefix("codex-tests-").tempdir()?;
+        Ok(Self { dir })
+    }
+
+    /// Copy `source`, skipping VCS metadata and build output, and apply `changes`.
+    pub(crate) async fn create(source: &Path, changes: &[CodeChange]) -> Result<Self, AgentError> {
+        let worktree = Self::new()?;
+        let source = source.to_path_buf();
+        let root = worktree.root().to_path_buf();
+        tokio::task::spawn_blocking(move || copy_tree(&source, &root)).await??;
+
+        for change in changes {
+            let path = worktree.path_for(&change.file_path)?;
+            if change.change_type.eq_ignore_ascii_case("delete") {
+                match std::fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            } else {
+                worktree.write(&change.file_path, &change.new_content)?;
+            }
+        }
+        Ok(worktree)
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub(crate) fn write(&self, file_path: &str, content: &str) -> Result<(), AgentError> {
+        let path = self.path_for(file_path)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Resolve a relative path, refusing paths that escape the worktree.
+    fn path_for(&self, file_path: &str) -> Result<PathBuf, AgentError> {
+        let relative = Path::new(file_path);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err(format!("path {} is outside the worktree", file_path).into());
+        }
+        Ok(self.root().join(relative))
+    }
+}
+
+fn copy_tree(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(destination)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let target = destination.join(&name);
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                continue;
+            }
+            // Dependencies are large and only read, so link rather than copy them
+            #[cfg(unix)]
+            if name == "node_modules" {
+                std::os::unix::fs::symlink(entry.path(), &target)?;
+                continue;
+            }
+            copy_tree(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(std::fs::read_link(entry.path())?, &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `runner` over `test_files` in the worktree through codex-core's sandboxed exec.
+pub(crate) async fn run_tests(
+    worktree: &ScratchWorktree,
+    runner: Runner,
+    test_files: &[String],
+    options: &TestRunOptions,
+) -> Result<TestRun, AgentError> {
+    let policy = options.sandbox_policy.clone().unwrap_or_else(SandboxPolicy::new_workspace_write_policy);
+    let offline = !policy.has_full_network_access();
+
+    let command = options.command.clone().unwrap_or_else(|| runner.command(test_files, true, offline));
+    let mut run = execute(worktree, runner, command, test_files, &policy, options).await?;
+
+    // pytest without the pytest-cov plugin rejects --cov; run again without coverage
+    if options.command.is_none() && runner == Runner::Pytest && run.output.contains("unrecognized arguments: --cov") {
+        let command = runner.command(test_files, false, offline);
+        run = execute(worktree, runner, command, test_files, &policy, options).await?;
+    }
+    Ok(run)
+}
+
+async fn execute(
+    worktree: &ScratchWorktree,
+    runner: Runner,
+    command: Vec<String>,
+    test_files: &[String],
+    policy: &SandboxPolicy,
+    options: &TestRunOptions,
+) -> Result<TestRun, AgentError> {
+    let output = sandboxed_exec(
+        worktree.root(),
+        command.clone(),
+        options.timeout_ms,
+        policy,
+        options.codex_linux_sandbox_exe.clone(),
+    )
+    .await
+    .map_err(|e| format!("failed to run {}: {}", runner.name(), e))?;
+
+    Ok(test_run(runner, command, test_files, &output))
+}
+
+/// Run `command` in `cwd` through codex-core's sandboxed exec.
+///
+/// A command that exits non-zero or times out still yields its output.
+pub(crate) async fn sandboxed_exec(
+    cwd: &Path,
+    command: Vec<String>,
+    timeout_ms: u64,
+    policy: &SandboxPolicy,
+    codex_linux_sandbox_exe: Option<PathBuf>,
+) -> Result<ExecToolCallOutput, AgentError> {
+    let sandbox_type = match policy {
+        SandboxPolicy::DangerFullAccess => SandboxType::None,
+        _ => get_platform_sandbox().ok_or("no sandbox is available on this platform")?,
+    };
+    let codex_linux_sandbox_exe =
+        codex_linux_sandbox_exe.or_else(|| std::env::var_os(CODEX_LINUX_SANDBOX_EXE_ENV).map(PathBuf::from));
+    if sandbox_type == SandboxType::LinuxSeccomp && codex_linux_sandbox_exe.is_none() {
+        return Err(format!("running sandboxed commands on Linux requires {}", CODEX_LINUX_SANDBOX_EXE_ENV).into());
+    }
+
+    let params = ExecParams {
+        command,
+        cwd: cwd.to_path_buf(),
+        timeout_ms: Some(timeout_ms),
+        env: test_env(),
+        with_escalated_permissions: None,
+        justification: None,
+    };
+
+    match process_exec_tool_call(params, sandbox_type, policy, cwd, &codex_linux_sandbox_exe, None).await {
+        Ok(output) => Ok(output),
+        // Failing commands exit non-zero, which the sandboxed exec reports as a denial
+        Err(CodexErr::Sandbox(SandboxErr::Denied { output } | SandboxErr::Timeout { output })) => Ok(*output),
+        Err(e) => Err(e.to_string().into()),
+    }
+}
+
+/// The inherited environment minus secrets, as for the shell tool.
+fn test_env() -> HashMap<String, String> {
+    let mut env = create_env(&ShellEnvironmentPolicy::default());
+    env.insert("CI".to_string(), "true".to_string());
+    env
+}
+
+fn test_run(runner: Runner, command: Vec<String>, test_files: &[String], output: &ExecToolCallOutput) -> TestRun {
+    let text = &output.aggregated_output.text;
+    let (passed, failed) = runner.counts(text);
+    let status = if output.timed_out {
+        TestRunStatus::TimedOut
+    } else if runner.is_compile_error(text) {
+        TestRunStatus::CompileError
+    } else if output.exit_code == 0 && failed == 0 {
+        TestRunStatus::Passed
+    } else {
+        TestRunStatus::Failed
+    };
+
+    TestRun {
+        framework: runner.name().to_string(),
+        command,
+        test_files: test_files.to_vec(),
+        status,
+        passed,
+        failed,
+        exit_code: output.exit_code,
+        duration_ms: duration_ms(output.duration),
+        coverage_percentage: runner.coverage(text),
+        output: tail(text, OUTPUT_TAIL_BYTES),
+    }
+}
+
+pub(crate) fn duration_ms(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// The last `max_bytes` of `text`, cut at a character boundary.
+pub(crate) fn tail(text: &str, max_bytes: usize) -> String {
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text[start..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framework_output_is_parsed() {
+        let cargo = "running 3 tests\ntest result: ok. 3 passed; 0 failed; 0 ignored\n\
+                     running 2 tests\ntest result: FAILED. 1 passed; 1 failed; 0 ignored\n";
+        assert_eq!(Runner::Cargo.counts(cargo), (4, 1));
+        assert!(Runner::Cargo.is_compile_error("error[E0425]: cannot find value `x`\nerror: could not compile `demo`"));
+
+        let pytest = "tests/test_auth.py ..F\n\
+                      TOTAL                 120     30    75%\n\
+                      ========== 1 failed, 2 passed in 0.12s ==========\n";
+        assert_eq!(Runner::Pytest.counts(pytest), (2, 1));
+        assert_eq!(Runner::Pytest.coverage(pytest), Some(75.0));
+
+        let jest = "All files |   85.71 |    50 |   100 |   83.33 |\n\
+                    Tests:       1 failed, 3 passed, 4 total\n";
+        assert_eq!(Runner::Jest.counts(jest), (3, 1));
+        assert_eq!(Runner::Jest.coverage(jest), Some(83.33));
+
+        assert_eq!(Runner::for_framework("rust test"), Some(Runner::Cargo));
+        assert_eq!(Runner::for_framework("playwright"), None);
+        assert_eq!(tail("héllo", 4), "llo");
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::model_provider::{AgentError, ModelProvider, StaticStreamCompletion, StreamCompletion};
+
+/// Model provider that replays canned responses.
+///
+/// Queued responses are returned in order; once the queue is empty every call
+/// returns the fallback response.
+pub(crate) struct MockModelProvider {
+    responses: Mutex<VecDeque<String>>,
+    fallback: String,
+}
+
+impl MockModelProvider {
+    pub(crate) fn new(response: impl Into<String>) -> Self {
+        Self {
+            responses: Mutex::new(VecDeque::new()),
+            fallback: response.into(),
+        }
+    }
+
+    pub(crate) fn with_responses(responses: Vec<String>, fallback: impl Into<String>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into()),
+            fallback: fallback.into(),
+        }
+    }
+
+    fn next_response(&self) -> String {
+        self.responses
+            .lock()
+            .ok()
+            .and_then(|mut responses| responses.pop_front())
+            .unwrap_or_else(|| self.fallback.clone())
+    }
+}
+
+#[async_trait]
+impl ModelProvider for MockModelProvider {
+    async fn generate_completion(&self, _prompt: &str) -> Result<String, AgentError> {
+        Ok(self.next_response())
+    }
+
+    async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, AgentError> {
+        Ok(self.next_response())
+    }
+
+    async fn stream_completion(&self, _system_prompt: Option<&str>, _user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+        Ok(Box::new(StaticStreamCompletion::new(self.next_response())))
+    }
+}
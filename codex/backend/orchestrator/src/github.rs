@@ -0,0 +1,896 @@
+//! Orchestrations triggered by pull request webhooks and reported through
+//! the GitHub Checks API.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use codex_agents::reviewer::{ApprovalStatus, ReviewFinding, Severity};
+use codex_agents::{AgentEvent, AgentExecution, AgentType, Pipeline, TestRunStatus, TestVerification};
+use codex_github_integration::webhook::{CheckRunEvent, HandlerError};
+use codex_github_integration::{
+    AnnotationLevel, CheckAnnotation, CheckConclusion, CheckRunOutput, CheckStatus, CommitState, CommitStatusRequest,
+    CreateCheckRunRequest, GitHubApp, GitHubConfig, PullRequestEvent, UpdateCheckRunRequest, WebhookHandler,
+};
+
+use crate::store::ORCHESTRATOR_SUBDIR;
+use crate::{AgentOrchestrator, JobObserver, OrchestrationRequest, OrchestrationStatus, RequestRecord};
+
+/// Name of the check run, and context of the commit status, on pull requests
+pub const CHECK_NAME: &str = "Codex";
+
+/// Pull request actions that start a run
+const TRIGGER_ACTIONS: &[&str] = &["opened", "synchronize", "reopened", "ready_for_review"];
+
+/// Largest `raw_details` GitHub accepts on an annotation
+const MAX_RAW_DETAILS_BYTES: usize = 64 * 1024;
+
+/// Settings for pull request runs
+#[derive(Debug, Clone)]
+pub struct GitHubChecksConfig {
+    /// App credentials and webhook secret
+    pub github: GitHubConfig,
+    /// Pipeline run on pull requests; a single reviewer step when unset
+    pub pipeline: Option<Pipeline>,
+    /// Where repositories are fetched and pull requests checked out
+    pub checkout_dir: PathBuf,
+    /// Base URL of this service, to link check runs to their request records
+    pub public_url: Option<String>,
+}
+
+impl GitHubChecksConfig {
+    /// Read `GITHUB_WEBHOOK_SECRET`, `GITHUB_APP_ID`, `GITHUB_PRIVATE_KEY_PATH`,
+    /// `GITHUB_TOKEN`, `GITHUB_API_URL`, `GITHUB_PIPELINE_FILE` and
+    /// `ORCHESTRATOR_PUBLIC_URL`. Returns `None` without a webhook secret.
+    pub fn from_env(codex_home: &Path) -> Result<Option<Self>> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let Some(webhook_secret) = var("GITHUB_WEBHOOK_SECRET") else {
+            return Ok(None);
+        };
+
+        let pipeline = match var("GITHUB_PIPELINE_FILE") {
+            Some(path) => Some(Pipeline::load(Path::new(&path)).map_err(|e| anyhow::anyhow!("GITHUB_PIPELINE_FILE: {e}"))?),
+            None => None,
+        };
+
+        Ok(Some(Self {
+            github: GitHubConfig {
+                app_id: var("GITHUB_APP_ID").unwrap_or_default(),
+                private_key_path: var("GITHUB_PRIVATE_KEY_PATH").unwrap_or_default(),
+                webhook_secret,
+                base_url: var("GITHUB_API_URL"),
+                access_token: var("GITHUB_TOKEN"),
+                installation_id: None,
+            },
+            pipeline,
+            checkout_dir: codex_home.join(ORCHESTRATOR_SUBDIR).join("checkouts"),
+            public_url: var("ORCHESTRATOR_PUBLIC_URL"),
+        }))
+    }
+}
+
+/// Create the GitHub client, with [`PullRequestChecks`] handling its webhooks
+pub fn github_app(orchestrator: Arc<AgentOrchestrator>, config: GitHubChecksConfig) -> Result<GitHubApp> {
+    let mut app = GitHubApp::new(config.github)?;
+    app.register_handler(Arc::new(PullRequestChecks::new(
+        orchestrator,
+        config.pipeline,
+        config.checkout_dir,
+        config.public_url,
+    )));
+    Ok(app)
+}
+
+/// The pull request commit a run checks
+#[derive(Debug, Clone)]
+pub struct PullRequestTarget {
+    pub owner: String,
+    pub repo: String,
+    /// URL to fetch the repository from
+    pub clone_url: String,
+    pub number: u32,
+    pub title: String,
+    pub body: Option<String>,
+    pub head_sha: String,
+    /// Branch the pull request merges into
+    pub base_ref: String,
+}
+
+impl PullRequestTarget {
+    fn from_event(event: &PullRequestEvent) -> Self {
+        let repository = &event.repository;
+        Self {
+            owner: repository.owner.login.clone(),
+            repo: repository.name.clone(),
+            clone_url: repository.clone_url.clone(),
+            number: event.number,
+            title: event.pull_request.title.clone(),
+            body: event.pull_request.body.clone(),
+            head_sha: event.pull_request.head.sha.clone(),
+            base_ref: event.pull_request.base.ref_name.clone(),
+        }
+    }
+}
+
+/// Runs the pipeline on every new commit of a pull request, and again when
+/// its check run is re-requested, reporting each run as a check run.
+///
+/// Without app authentication the Checks API is unavailable and runs are
+/// reported as commit statuses instead.
+#[derive(Clone)]
+pub struct PullRequestChecks {
+    orchestrator: Arc<AgentOrchestrator>,
+    pipeline: Option<Pipeline>,
+    checkouts: Arc<Checkouts>,
+    public_url: Option<String>,
+}
+
+impl PullRequestChecks {
+    pub fn new(orchestrator: Arc<AgentOrchestrator>, pipeline: Option<Pipeline>, checkout_dir: PathBuf, public_url: Option<String>) -> Self {
+        Self {
+            orchestrator,
+            pipeline,
+            checkouts: Arc::new(Checkouts::new(checkout_dir)),
+            public_url,
+        }
+    }
+
+    /// Create the check run, check out the pull request and submit the
+    /// orchestration. Returns the id of the recorded request.
+    pub async fn start(&self, app: &GitHubApp, target: PullRequestTarget) -> Result<String> {
+        let reporter = CheckReporter::begin(app.clone(), &target, self.public_url.clone()).await?;
+
+        let checkout = match self.checkouts.checkout(app, &target).await {
+            Ok(checkout) => checkout,
+            Err(e) => {
+                reporter.abort(&format!("Failed to check out the pull request: {e:#}")).await;
+                return Err(e);
+            }
+        };
+
+        let mut prompt = format!("Review pull request #{}: {}", target.number, target.title);
+        if let Some(body) = target.body.as_deref().filter(|body| !body.trim().is_empty()) {
+            prompt.push_str(&format!("\n\n{body}"));
+        }
+        let request = OrchestrationRequest {
+            prompt,
+            context: Some(json!({
+                "repository": format!("{}/{}", target.owner, target.repo),
+                "pull_request": target.number,
+                "head_sha": target.head_sha,
+            })),
+            agent_sequence: self.pipeline.is_none().then(|| vec!["reviewer".to_string()]),
+            options: Some(json!({
+                "cwd": checkout.path,
+                "base_ref": checkout.merge_base,
+            })),
+            pipeline: self.pipeline.clone(),
+        };
+
+        let observer = Arc::new(PullRequestRun {
+            reporter,
+            checkouts: self.checkouts.clone(),
+            checkout,
+        });
+        let workspace_root = observer.checkout.path.clone();
+        match self.orchestrator.submit_request_with_observer(request, workspace_root, observer.clone()).await {
+            Ok(record) => Ok(record.request_id),
+            Err(e) => {
+                observer.reporter.abort(&format!("The orchestrator did not accept the run: {e}")).await;
+                self.checkouts.remove(&observer.checkout).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Start a run in the background, so the webhook is answered right away
+    fn spawn(&self, app: &GitHubApp, target: PullRequestTarget) {
+        let (checks, app) = (self.clone(), app.clone());
+        tokio::spawn(async move {
+            let pull_request = format!("{}/{}#{}", target.owner, target.repo, target.number);
+            if let Err(e) = checks.start(&app, target).await {
+                tracing::warn!("failed to start a run for {pull_request}: {e:#}");
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl WebhookHandler for PullRequestChecks {
+    async fn pull_request(&self, app: &GitHubApp, event: &PullRequestEvent) -> Result<(), HandlerError> {
+        if TRIGGER_ACTIONS.contains(&event.action.as_str()) && !event.pull_request.draft {
+            self.spawn(app, PullRequestTarget::from_event(event));
+        }
+        Ok(())
+    }
+
+    async fn check_run(&self, app: &GitHubApp, event: &CheckRunEvent) -> Result<(), HandlerError> {
+        if event.action != "rerequested" || event.check_run.name != CHECK_NAME {
+            return Ok(());
+        }
+        for pull_request in &event.check_run.pull_requests {
+            self.spawn(
+                app,
+                PullRequestTarget {
+                    owner: event.repository.owner.login.clone(),
+                    repo: event.repository.name.clone(),
+                    clone_url: event.repository.clone_url.clone(),
+                    number: pull_request.number,
+                    title: String::new(),
+                    body: None,
+                    head_sha: event.check_run.head_sha.clone(),
+                    base_ref: pull_request.base.ref_name.clone(),
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+/// One pull request run: reports it and removes its checkout when it ends
+struct PullRequestRun {
+    reporter: CheckReporter,
+    checkouts: Arc<Checkouts>,
+    checkout: Checkout,
+}
+
+#[async_trait]
+impl JobObserver for PullRequestRun {
+    async fn on_event(&self, request_id: &str, event: &AgentEvent) {
+        self.reporter.progress(request_id, event).await;
+    }
+
+    async fn on_finished(&self, record: &RequestRecord) {
+        self.reporter.conclude(record).await;
+        self.checkouts.remove(&self.checkout).await;
+    }
+}
+
+/// Where a run is reported: a check run, or a commit status without app
+/// authentication
+enum CheckTarget {
+    CheckRun(u64),
+    CommitStatus,
+}
+
+#[derive(Debug, Clone)]
+struct StepProgress {
+    step_id: String,
+    agent_type: AgentType,
+    state: &'static str,
+    execution_time_ms: Option<u64>,
+}
+
+/// Reports one run on the pull request's head commit. Reporting failures are
+/// logged and never fail the run.
+struct CheckReporter {
+    app: GitHubApp,
+    owner: String,
+    repo: String,
+    head_sha: String,
+    target: CheckTarget,
+    public_url: Option<String>,
+    steps: Mutex<Vec<StepProgress>>,
+}
+
+impl CheckReporter {
+    /// Create a queued check run, or a pending commit status
+    async fn begin(app: GitHubApp, target: &PullRequestTarget, public_url: Option<String>) -> Result<Self> {
+        let check_target = if app.is_app() {
+            let check_run = app
+                .create_check_run(CreateCheckRunRequest {
+                    repo_owner: target.owner.clone(),
+                    repo_name: target.repo.clone(),
+                    name: CHECK_NAME.to_string(),
+                    head_sha: target.head_sha.clone(),
+                    status: CheckStatus::Queued,
+                    details_url: None,
+                    external_id: None,
+                    output: None,
+                })
+                .await?;
+            CheckTarget::CheckRun(check_run.id)
+        } else {
+            app.create_commit_status(CommitStatusRequest {
+                repo_owner: target.owner.clone(),
+                repo_name: target.repo.clone(),
+                sha: target.head_sha.clone(),
+                state: CommitState::Pending,
+                context: CHECK_NAME.to_string(),
+                description: Some("Queued".to_string()),
+                target_url: None,
+            })
+            .await?;
+            CheckTarget::CommitStatus
+        };
+
+        Ok(Self {
+            app,
+            owner: target.owner.clone(),
+            repo: target.repo.clone(),
+            head_sha: target.head_sha.clone(),
+            target: check_target,
+            public_url,
+            steps: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Update the run when a step starts, is skipped or finishes
+    async fn progress(&self, request_id: &str, event: &AgentEvent) {
+        let (title, summary) = {
+            let mut steps = self.steps.lock().await;
+            let title = match event {
+                AgentEvent::AgentStarted { step_id, agent_type } => {
+                    steps.push(StepProgress {
+                        step_id: step_id.clone(),
+                        agent_type: agent_type.clone(),
+                        state: "running",
+                        execution_time_ms: None,
+                    });
+                    format!("Running {step_id}")
+                }
+                AgentEvent::StepSkipped { step_id, agent_type } => {
+                    steps.push(StepProgress {
+                        step_id: step_id.clone(),
+                        agent_type: agent_type.clone(),
+                        state: "skipped",
+                        execution_time_ms: None,
+                    });
+                    format!("Skipped {step_id}")
+                }
+                AgentEvent::AgentFinished { execution } => {
+                    let state = if execution.success { "passed" } else { "failed" };
+                    match steps.iter_mut().rev().find(|step| step.step_id == execution.step_id) {
+                        Some(step) => {
+                            step.state = state;
+                            step.execution_time_ms = Some(execution.execution_time_ms);
+                        }
+                        None => steps.push(StepProgress {
+                            step_id: execution.step_id.clone(),
+                            agent_type: execution.agent_type.clone(),
+                            state,
+                            execution_time_ms: Some(execution.execution_time_ms),
+                        }),
+                    }
+                    format!("Finished {}", execution.step_id)
+                }
+                _ => return,
+            };
+            (title, self.summary(request_id, &steps))
+        };
+
+        let details_url = self.details_url(request_id);
+        let result = match self.target {
+            CheckTarget::CheckRun(check_run_id) => self
+                .app
+                .update_check_run(UpdateCheckRunRequest {
+                    repo_owner: self.owner.clone(),
+                    repo_name: self.repo.clone(),
+                    check_run_id,
+                    status: Some(CheckStatus::InProgress),
+                    conclusion: None,
+                    details_url,
+                    external_id: Some(request_id.to_string()),
+                    output: Some(CheckRunOutput {
+                        title,
+                        summary,
+                        text: None,
+                        annotations: Vec::new(),
+                    }),
+                })
+                .await
+                .map(drop),
+            CheckTarget::CommitStatus => self.commit_status(CommitState::Pending, title, details_url).await,
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to report progress of request {request_id}: {e}");
+        }
+    }
+
+    /// Complete the run with its conclusion and annotations for the review
+    /// findings and failed tests
+    async fn conclude(&self, record: &RequestRecord) {
+        let conclusion = conclusion(record);
+        let annotations: Vec<CheckAnnotation> = record.executions.iter().flat_map(annotations).collect();
+        let title = match conclusion {
+            CheckConclusion::Success => format!("Passed with {} annotations", annotations.len()),
+            CheckConclusion::Cancelled => "Cancelled".to_string(),
+            CheckConclusion::TimedOut => "Timed out".to_string(),
+            _ => match &record.error {
+                Some(_) => "Run failed".to_string(),
+                None => format!("Failed with {} annotations", annotations.len()),
+            },
+        };
+        let mut summary = {
+            let mut steps = self.steps.lock().await;
+            // Steps the job never reported finishing were stopped
+            for step in steps.iter_mut().filter(|step| step.state == "running") {
+                step.state = "stopped";
+            }
+            self.summary(&record.request_id, &steps)
+        };
+        if let Some(error) = &record.error {
+            summary.push_str(&format!("\n\n**Error:** {error}"));
+        }
+
+        let details_url = self.details_url(&record.request_id);
+        let result = match self.target {
+            CheckTarget::CheckRun(check_run_id) => self
+                .app
+                .update_check_run(UpdateCheckRunRequest {
+                    repo_owner: self.owner.clone(),
+                    repo_name: self.repo.clone(),
+                    check_run_id,
+                    status: Some(CheckStatus::Completed),
+                    conclusion: Some(conclusion),
+                    details_url,
+                    external_id: Some(record.request_id.clone()),
+                    output: Some(CheckRunOutput {
+                        title,
+                        summary,
+                        text: None,
+                        annotations,
+                    }),
+                })
+                .await
+                .map(drop),
+            CheckTarget::CommitStatus => {
+                let state = match conclusion {
+                    CheckConclusion::Success => CommitState::Success,
+                    CheckConclusion::Failure => CommitState::Failure,
+                    _ => CommitState::Error,
+                };
+                self.commit_status(state, title, details_url).await
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to report the result of request {}: {e}", record.request_id);
+        }
+    }
+
+    /// Fail the run before it reached the orchestrator
+    async fn abort(&self, error: &str) {
+        let result = match self.target {
+            CheckTarget::CheckRun(check_run_id) => self
+                .app
+                .update_check_run(UpdateCheckRunRequest {
+                    repo_owner: self.owner.clone(),
+                    repo_name: self.repo.clone(),
+                    check_run_id,
+                    status: Some(CheckStatus::Completed),
+                    conclusion: Some(CheckConclusion::Failure),
+                    details_url: None,
+                    external_id: None,
+                    output: Some(CheckRunOutput {
+                        title: "Run failed".to_string(),
+                        summary: error.to_string(),
+                        text: None,
+                        annotations: Vec::new(),
+                    }),
+                })
+                .await
+                .map(drop),
+            CheckTarget::CommitStatus => self.commit_status(CommitState::Error, error.to_string(), None).await,
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to report a failed run on {}: {e}", self.head_sha);
+        }
+    }
+
+    async fn commit_status(
+        &self,
+        state: CommitState,
+        description: String,
+        target_url: Option<String>,
+    ) -> Result<(), codex_github_integration::GitHubError> {
+        self.app
+            .create_commit_status(CommitStatusRequest {
+                repo_owner: self.owner.clone(),
+                repo_name: self.repo.clone(),
+                sha: self.head_sha.clone(),
+                state,
+                context: CHECK_NAME.to_string(),
+                description: Some(description),
+                target_url,
+            })
+            .await
+    }
+
+    fn details_url(&self, request_id: &str) -> Option<String> {
+        let public_url = self.public_url.as_deref()?;
+        Some(format!("{}/api/v1/requests/{request_id}", public_url.trim_end_matches('/')))
+    }
+
+    /// Markdown table of the pipeline's steps
+    fn summary(&self, request_id: &str, steps: &[StepProgress]) -> String {
+        let mut summary = format!("Orchestration request `{request_id}`");
+        if let Some(url) = self.details_url(request_id) {
+            summary.push_str(&format!(" ([details]({url}))"));
+        }
+        summary.push_str("\n\n| Step | Agent | Status | Time |\n| --- | --- | --- | --- |");
+        for step in steps {
+            let time = step
+                .execution_time_ms
+                .map(|ms| format!("{:.1}s", ms as f64 / 1000.0))
+                .unwrap_or_default();
+            summary.push_str(&format!("\n| {} | {:?} | {} | {} |", step.step_id, step.agent_type, step.state, time));
+        }
+        summary
+    }
+}
+
+/// Failure if the run failed, any step failed, the reviewer asked for
+/// changes or the generated tests failed
+fn conclusion(record: &RequestRecord) -> CheckConclusion {
+    match record.status {
+        OrchestrationStatus::Cancelled => CheckConclusion::Cancelled,
+        OrchestrationStatus::TimedOut => CheckConclusion::TimedOut,
+        OrchestrationStatus::Failed => CheckConclusion::Failure,
+        OrchestrationStatus::Pending | OrchestrationStatus::InProgress => CheckConclusion::Neutral,
+        OrchestrationStatus::Completed => {
+            let failed = record.executions.iter().any(|execution| {
+                let output = &execution.output;
+                let approval = serde_json::from_value::<ApprovalStatus>(output["overall_approval"].clone()).ok();
+                !execution.success
+                    || matches!(approval, Some(ApprovalStatus::RequiresChanges | ApprovalStatus::Rejected))
+                    || output["verification"]["passed"] == json!(false)
+            });
+            if failed {
+                CheckConclusion::Failure
+            } else {
+                CheckConclusion::Success
+            }
+        }
+    }
+}
+
+/// Annotations for a reviewer step's findings and a test step's failed runs
+fn annotations(execution: &AgentExecution) -> Vec<CheckAnnotation> {
+    match execution.agent_type {
+        AgentType::Reviewer => {
+            let findings: Vec<ReviewFinding> = serde_json::from_value(execution.output["findings"].clone()).unwrap_or_default();
+            findings.iter().filter_map(finding_annotation).collect()
+        }
+        AgentType::TestGenerator => {
+            let Ok(verification) = serde_json::from_value::<TestVerification>(execution.output["verification"].clone()) else {
+                return Vec::new();
+            };
+            verification
+                .runs
+                .iter()
+                .filter(|run| run.status != TestRunStatus::Passed)
+                .flat_map(|run| {
+                    let message = format!(
+                        "{} run {:?}: {} passed, {} failed (exit code {})",
+                        run.framework, run.status, run.passed, run.failed, run.exit_code
+                    );
+                    run.test_files.iter().map(move |path| CheckAnnotation {
+                        path: path.clone(),
+                        start_line: 1,
+                        end_line: 1,
+                        annotation_level: AnnotationLevel::Failure,
+                        message: message.clone(),
+                        title: Some(format!("{} tests failed", run.framework)),
+                        raw_details: Some(truncate(&run.output, MAX_RAW_DETAILS_BYTES)),
+                    })
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn finding_annotation(finding: &ReviewFinding) -> Option<CheckAnnotation> {
+    let start_line = u32::try_from(finding.line_start?).ok()?;
+    let end_line = finding.line_end.and_then(|line| u32::try_from(line).ok()).unwrap_or(start_line).max(start_line);
+    let mut message = finding.description.clone();
+    if let Some(suggestion) = &finding.suggestion {
+        message.push_str(&format!("\n\nSuggestion: {suggestion}"));
+    }
+    Some(CheckAnnotation {
+        path: finding.file_path.clone(),
+        start_line,
+        end_line,
+        annotation_level: match finding.severity {
+            Severity::Critical | Severity::High => AnnotationLevel::Failure,
+            Severity::Medium => AnnotationLevel::Warning,
+            Severity::Low | Severity::Info => AnnotationLevel::Notice,
+        },
+        message,
+        title: Some(format!("[{:?}] {}", finding.severity, finding.title)),
+        raw_details: None,
+    })
+}
+
+/// The last `max_bytes` of `text`, where failures are reported
+fn truncate(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut start = text.len() - max_bytes;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text[start..].to_string()
+}
+
+/// A pull request checked out for one run
+struct Checkout {
+    repo_dir: PathBuf,
+    path: PathBuf,
+    /// Where the pull request branched off its base, to review its changes only
+    merge_base: String,
+}
+
+/// Bare clones of the repositories under `root/repos`, with a detached
+/// worktree per run under `root/worktrees`
+struct Checkouts {
+    root: PathBuf,
+    /// Serializes fetches and worktree changes
+    lock: Mutex<()>,
+}
+
+impl Checkouts {
+    fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            lock: Mutex::new(()),
+        }
+    }
+
+    async fn checkout(&self, app: &GitHubApp, target: &PullRequestTarget) -> Result<Checkout> {
+        for name in [&target.owner, &target.repo] {
+            if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+                anyhow::bail!("invalid repository name {}/{}", target.owner, target.repo);
+            }
+        }
+        if target.base_ref.starts_with('-') || target.head_sha.starts_with('-') {
+            anyhow::bail!("invalid pull request refs");
+        }
+
+        let _guard = self.lock.lock().await;
+        let repo_dir = self.root.join("repos").join(&target.owner).join(format!("{}.git", target.repo));
+        if !repo_dir.exists() {
+            tokio::fs::create_dir_all(&repo_dir).await?;
+            git(&repo_dir, &["init", "--bare", "--quiet"], None).await?;
+        }
+
+        // The token is passed in the environment so it does not show up in the process list
+        let token = app.access_token(&target.owner, &target.repo).await?;
+        let header = format!(
+            "Authorization: Basic {}",
+            general_purpose::STANDARD.encode(format!("x-access-token:{token}"))
+        );
+        let pull_ref = format!("refs/pull/{}/head", target.number);
+        let base_ref = format!("refs/heads/{}", target.base_ref);
+        git(
+            &repo_dir,
+            &[
+                "fetch",
+                "--quiet",
+                "--no-tags",
+                "--",
+                &target.clone_url,
+                &format!("+{pull_ref}:{pull_ref}"),
+                &format!("+{base_ref}:{base_ref}"),
+            ],
+            Some(&header),
+        )
+        .await
+        .context("fetching the pull request")?;
+
+        let head = format!("{}^{{commit}}", target.head_sha);
+        git(&repo_dir, &["rev-parse", "--verify", "--quiet", &head], None)
+            .await
+            .with_context(|| format!("commit {} is no longer part of the pull request", target.head_sha))?;
+        let merge_base = git(&repo_dir, &["merge-base", &base_ref, &target.head_sha], None).await?;
+
+        let path = self.root.join("worktrees").join(uuid::Uuid::new_v4().to_string());
+        let worktree = path.to_string_lossy();
+        git(&repo_dir, &["worktree", "add", "--detach", "--quiet", &worktree, &target.head_sha], None).await?;
+
+        Ok(Checkout {
+            repo_dir,
+            path,
+            merge_base: merge_base.trim().to_string(),
+        })
+    }
+
+    async fn remove(&self, checkout: &Checkout) {
+        let _guard = self.lock.lock().await;
+        let worktree = checkout.path.to_string_lossy();
+        if let Err(e) = git(&checkout.repo_dir, &["worktree", "remove", "--force", &worktree], None).await {
+            tracing::warn!("failed to remove worktree {}: {e:#}", checkout.path.display());
+        }
+    }
+}
+
+/// Run git in `dir`, returning its stdout
+async fn git(dir: &Path, args: &[&str], extra_header: Option<&str>) -> Result<String> {
+    let mut command = tokio::process::Command::new("git");
+    command.current_dir(dir).args(args).env("GIT_TERMINAL_PROMPT", "0");
+    if let Some(header) = extra_header {
+        command
+            .env("GIT_CONFIG_COUNT", "1")
+            .env("GIT_CONFIG_KEY_0", "http.extraHeader")
+            .env("GIT_CONFIG_VALUE_0", header);
+    }
+    let output = command.output().await.context("running git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use codex_agents::{AgentError, ModelProvider, StaticStreamCompletion, StreamCompletion};
+    use serde_json::Value;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::RequestStore;
+
+    /// Test-only key pair of the GitHub integration's tests
+    const PRIVATE_KEY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../integrations/github/tests/fixtures/test-app-key.pem");
+
+    /// Provider answering every prompt with one critical finding
+    struct FixedModelProvider(String);
+
+    #[async_trait]
+    impl ModelProvider for FixedModelProvider {
+        async fn generate_completion(&self, _prompt: &str) -> Result<String, AgentError> {
+            Ok(self.0.clone())
+        }
+
+        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, AgentError> {
+            Ok(self.0.clone())
+        }
+
+        async fn stream_completion(&self, _system_prompt: Option<&str>, _user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+            Ok(Box::new(StaticStreamCompletion::new(self.0.clone())))
+        }
+    }
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=Octo", "-c", "user.email=octo@example.com", "-c", "init.defaultBranch=main"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?}: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_pull_request_run_concludes_check_run_with_annotations() {
+        // An origin whose pull request #7 adds a line to src/lib.rs
+        let origin = tempfile::tempdir().unwrap();
+        std::fs::create_dir(origin.path().join("src")).unwrap();
+        std::fs::write(origin.path().join("src/lib.rs"), "pub fn add(a: i32, b: i32) -> i32 { a + b }\n").unwrap();
+        run_git(origin.path(), &["init", "--quiet"]);
+        run_git(origin.path(), &["add", "."]);
+        run_git(origin.path(), &["commit", "--quiet", "-m", "Initial commit"]);
+        std::fs::write(
+            origin.path().join("src/lib.rs"),
+            "pub fn add(a: i32, b: i32) -> i32 { a + b }\npub const PASSWORD: &str = \"hunter2\";\n",
+        )
+        .unwrap();
+        run_git(origin.path(), &["commit", "--quiet", "-am", "Add password"]);
+        let head_sha = run_git(origin.path(), &["rev-parse", "HEAD"]);
+        run_git(origin.path(), &["update-ref", "refs/pull/7/head", "HEAD"]);
+        run_git(origin.path(), &["reset", "--quiet", "--hard", "HEAD~1"]);
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/app/installations/42/access_tokens"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "token": "ghs_token",
+                "expires_at": (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339(),
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/repos/octo/app/check-runs"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "id": 55, "html_url": null })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/octo/app/check-runs/55"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": 55, "html_url": null })))
+            .mount(&server)
+            .await;
+        let app = GitHubApp::new(GitHubConfig {
+            app_id: "12345".to_string(),
+            private_key_path: PRIVATE_KEY_PATH.to_string(),
+            webhook_secret: "secret".to_string(),
+            base_url: Some(server.uri()),
+            access_token: None,
+            installation_id: Some(42),
+        })
+        .unwrap();
+
+        let finding = json!({ "findings": [{
+            "line_start": 2, "line_end": 2, "severity": "Critical", "category": "Security",
+            "title": "Hardcoded password", "description": "A password is committed in source",
+            "suggestion": "Load it from the environment", "examples": [], "confidence": 0.9,
+        }]});
+        let orchestrator = Arc::new(AgentOrchestrator::with_model_provider(
+            Arc::new(FixedModelProvider(finding.to_string())),
+            RequestStore::in_memory(),
+        ));
+        let checkouts = tempfile::tempdir().unwrap();
+        let checks = PullRequestChecks::new(orchestrator.clone(), None, checkouts.path().to_path_buf(), None);
+        let request_id = checks
+            .start(
+                &app,
+                PullRequestTarget {
+                    owner: "octo".to_string(),
+                    repo: "app".to_string(),
+                    clone_url: origin.path().to_string_lossy().into_owned(),
+                    number: 7,
+                    title: "Add password".to_string(),
+                    body: None,
+                    head_sha: head_sha.clone(),
+                    base_ref: "main".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let concluded = tokio::time::timeout(Duration::from_secs(30), async {
+            loop {
+                let requests = server.received_requests().await.unwrap();
+                let concluded = requests.iter().rev().find_map(|request| {
+                    let body: Value = serde_json::from_slice(&request.body).ok()?;
+                    (request.method.as_str() == "PATCH" && body["status"] == "completed").then_some(body)
+                });
+                if let Some(body) = concluded {
+                    return body;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        let created: Value = requests
+            .iter()
+            .find(|request| request.url.path() == "/repos/octo/app/check-runs")
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .unwrap();
+        assert_eq!(created["name"], CHECK_NAME);
+        assert_eq!(created["head_sha"], head_sha);
+        assert_eq!(created["status"], "queued");
+
+        assert_eq!(concluded["conclusion"], "failure");
+        assert_eq!(concluded["external_id"], request_id);
+        let annotation = &concluded["output"]["annotations"][0];
+        assert_eq!(annotation["path"], "src/lib.rs");
+        assert_eq!(annotation["start_line"], 2);
+        assert_eq!(annotation["annotation_level"], "failure");
+
+        // The worktree is removed once the run is reported
+        let worktrees = checkouts.path().join("worktrees");
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while std::fs::read_dir(&worktrees).map(|entries| entries.count() > 0).unwrap_or(false) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let record = orchestrator.requests().get(&request_id).await.unwrap();
+        assert_eq!(record.status, OrchestrationStatus::Completed);
+    }
+}
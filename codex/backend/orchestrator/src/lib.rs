@@ -1,28 +1,133 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use async_trait::async_trait;
+use std::time::{Duration, Instant};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex, Semaphore};
 use anyhow::Result;
+use async_trait::async_trait;
+use codex_core::config::{find_codex_home, ConfigOverrides};
 
 use codex_agents::{
-    AgentSuite, AgentOrchestrationRequest, AgentType,
-    spec::SpecAgent,
-    code::CodeAgent,
-    test_generator::TestGeneratorAgent,
-    reviewer::ReviewerAgent,
-    debug::DebugAgent,
+    AgentEvent, AgentSuite, AgentOrchestrationRequest, AgentType, CodexModelProvider, ModelProvider, Pipeline, TokenUsage,
 };
+use tokio::sync::mpsc;
+
+pub mod github;
+pub mod store;
+pub mod telemetry;
+
+pub use store::{RequestFilter, RequestRecord, RequestStore};
 
 /// Orchestrator service for managing agent workflows
+///
+/// Requests run as background jobs: submitting one records it and returns
+/// immediately, and its progress is tracked in the [`RequestStore`].
 pub struct AgentOrchestrator {
     agent_suite: Arc<AgentSuite>,
-    state: Mutex<OrchestratorState>,
+    model_provider: Arc<dyn ModelProvider>,
+    store: Arc<RequestStore>,
+    config: OrchestratorConfig,
+    /// Permits for running plus queued jobs
+    admission: Arc<Semaphore>,
+    /// Permits for running jobs
+    running: Arc<Semaphore>,
+    /// Cancellation handles of unfinished jobs
+    jobs: Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>,
+}
+
+/// Limits applied to orchestration jobs
+#[derive(Debug, Clone)]
+pub struct OrchestratorConfig {
+    /// Jobs allowed to run at the same time
+    pub max_concurrent_jobs: usize,
+    /// Jobs allowed to wait for a free slot; further submissions are rejected
+    pub max_queued_jobs: usize,
+    /// Time limit for one job, measured from when it starts running
+    pub job_timeout: Duration,
+    /// Pipeline for requests that specify neither `pipeline` nor `agent_sequence`
+    pub default_pipeline: Option<Pipeline>,
+    /// Directory that the paths in submitted requests' options must stay in
+    pub workspace_root: PathBuf,
+}
+
+impl Default for OrchestratorConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_jobs: 10,
+            max_queued_jobs: 100,
+            job_timeout: Duration::from_secs(30 * 60),
+            default_pipeline: None,
+            workspace_root: PathBuf::from("."),
+        }
+    }
 }
 
-#[derive(Debug, Default)]
-struct OrchestratorState {
-    active_sessions: usize,
-    total_requests: u64,
+impl OrchestratorConfig {
+    /// Read `MAX_CONCURRENT_REQUESTS`, `MAX_QUEUED_REQUESTS`,
+    /// `REQUEST_TIMEOUT_SECS`, `PIPELINE_FILE` and `WORKSPACE_ROOT`, falling
+    /// back to the defaults
+    pub fn from_env() -> Result<Self> {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok()?.parse().ok()
+        }
+
+        let default_pipeline = match std::env::var_os("PIPELINE_FILE") {
+            Some(path) => Some(Pipeline::load(std::path::Path::new(&path)).map_err(|e| anyhow::anyhow!("PIPELINE_FILE: {e}"))?),
+            None => None,
+        };
+
+        let default = Self::default();
+        Ok(Self {
+            max_concurrent_jobs: var("MAX_CONCURRENT_REQUESTS").unwrap_or(default.max_concurrent_jobs).max(1),
+            max_queued_jobs: var("MAX_QUEUED_REQUESTS").unwrap_or(default.max_queued_jobs),
+            job_timeout: var("REQUEST_TIMEOUT_SECS").map(Duration::from_secs).unwrap_or(default.job_timeout),
+            default_pipeline,
+            workspace_root: std::env::var_os("WORKSPACE_ROOT").map(PathBuf::from).unwrap_or(default.workspace_root),
+        })
+    }
+}
+
+/// Why a request could not be submitted
+#[derive(Debug)]
+pub enum SubmitError {
+    /// Every running and queued slot is taken
+    QueueFull,
+    /// The request names an unknown agent
+    InvalidRequest(String),
+    /// The request could not be recorded
+    Store(anyhow::Error),
+}
+
+impl std::fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubmitError::QueueFull => write!(f, "too many orchestration requests in progress"),
+            SubmitError::InvalidRequest(reason) => write!(f, "invalid request: {reason}"),
+            SubmitError::Store(e) => write!(f, "failed to record request: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+impl From<anyhow::Error> for SubmitError {
+    fn from(e: anyhow::Error) -> Self {
+        SubmitError::Store(e)
+    }
+}
+
+/// Observer of one job's progress, e.g. to report it on a pull request.
+///
+/// Called from the job itself, after each change has been recorded.
+#[async_trait]
+pub trait JobObserver: Send + Sync {
+    /// A progress event of the job `request_id`
+    async fn on_event(&self, _request_id: &str, _event: &AgentEvent) {}
+
+    /// The final record, however the job ended: completed, failed, timed out
+    /// or cancelled
+    async fn on_finished(&self, record: &RequestRecord);
 }
 
 /// Request for agent orchestration
@@ -32,201 +137,433 @@ pub struct OrchestrationRequest {
     pub context: Option<serde_json::Value>,
     pub agent_sequence: Option<Vec<String>>,
     pub options: Option<serde_json::Value>,
-}
-
-/// Response from agent orchestration
-#[derive(Debug, Serialize, Deserialize)]
-pub struct OrchestrationResponse {
-    pub request_id: String,
-    pub status: OrchestrationStatus,
-    pub result: Option<serde_json::Value>,
-    pub metadata: OrchestrationMetadata,
+    /// DAG of agent steps; takes precedence over `agent_sequence`
+    #[serde(default)]
+    pub pipeline: Option<Pipeline>,
 }
 
 /// Status of an orchestration request
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum OrchestrationStatus {
     Pending,
     InProgress,
     Completed,
-    Failed(String),
-}
-
-/// Metadata about the orchestration
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct OrchestrationMetadata {
-    pub start_time: Option<chrono::DateTime<chrono::Utc>>,
-    pub end_time: Option<chrono::DateTime<chrono::Utc>>,
-    pub duration_ms: Option<i64>,
-    pub agent_sequence: Vec<String>,
-    pub success: bool,
-    pub error: Option<String>,
+    Failed,
+    /// Stopped after running longer than the job timeout
+    TimedOut,
+    Cancelled,
 }
 
 impl AgentOrchestrator {
-    /// Create a new orchestrator with default agents
-    pub fn new() -> Result<Self> {
-        // Initialize model provider (should be injected in production)
-        let model_provider = Arc::new(DefaultModelProvider::default());
-        
-        let agent_suite = AgentSuite::new(
-            SpecAgent::new(model_provider.clone()),
-            CodeAgent::new(model_provider.clone()),
-            TestGeneratorAgent::new(model_provider.clone()),
-            ReviewerAgent::new(model_provider.clone()),
-            DebugAgent::new(model_provider),
-        );
+    /// Create a new orchestrator whose agents use the model provider configured in `CODEX_HOME`,
+    /// whose requests are recorded under `CODEX_HOME/orchestrator` and whose limits come from
+    /// the environment
+    pub async fn new() -> Result<Self> {
+        let model_provider = CodexModelProvider::from_codex_home(ConfigOverrides::default()).await?;
+        let store = RequestStore::open(&find_codex_home()?).await?;
+        Ok(Self::with_model_provider(Arc::new(model_provider), store).with_config(OrchestratorConfig::from_env()?))
+    }
 
-        Ok(Self {
+    /// Create a new orchestrator with an explicit model provider and request store
+    pub fn with_model_provider(model_provider: Arc<dyn ModelProvider>, store: RequestStore) -> Self {
+        let agent_suite = AgentSuite::with_model_provider(model_provider.clone());
+        let config = OrchestratorConfig::default();
+
+        Self {
             agent_suite: Arc::new(agent_suite),
-            state: Mutex::new(OrchestratorState::default()),
-        })
+            model_provider,
+            store: Arc::new(store),
+            admission: Arc::new(Semaphore::new(config.max_concurrent_jobs + config.max_queued_jobs)),
+            running: Arc::new(Semaphore::new(config.max_concurrent_jobs)),
+            config,
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Replace the job limits
+    pub fn with_config(mut self, config: OrchestratorConfig) -> Self {
+        self.admission = Arc::new(Semaphore::new(config.max_concurrent_jobs + config.max_queued_jobs));
+        self.running = Arc::new(Semaphore::new(config.max_concurrent_jobs));
+        self.config = config;
+        self
     }
 
-    /// Process an orchestration request
-    pub async fn process_request(
+    /// Recorded orchestration requests
+    pub fn requests(&self) -> &RequestStore {
+        &self.store
+    }
+
+    /// Tokens used by the agents' model provider since startup
+    pub fn token_usage(&self) -> TokenUsage {
+        self.model_provider.token_usage()
+    }
+
+    /// Record a request and run it in the background.
+    ///
+    /// Returns the pending record; poll [`RequestStore::get`] for progress.
+    pub async fn submit_request(&self, request: OrchestrationRequest) -> Result<RequestRecord, SubmitError> {
+        self.spawn_job(request, self.config.workspace_root.clone(), None, None).await
+    }
+
+    /// Like [`AgentOrchestrator::submit_request`], but confine the request's
+    /// paths to `workspace_root` instead of the configured one and report the
+    /// job's progress to `observer`.
+    pub async fn submit_request_with_observer(
         &self,
         request: OrchestrationRequest,
-    ) -> Result<OrchestrationResponse> {
-        let start_time = chrono::Utc::now();
-        let request_id = uuid::Uuid::new_v4().to_string();
-        
-        // Update state
-        {
-            let mut state = self.state.lock().await;
-            state.active_sessions += 1;
-            state.total_requests += 1;
-        }
-
-        // Map request to agent suite format
-        let agent_sequence = request.agent_sequence
-            .unwrap_or_else(|| vec!["spec".to_string(), "code".to_string(), "reviewer".to_string()])
-            .into_iter()
-            .filter_map(|agent| match agent.to_lowercase().as_str() {
-                "spec" => Some(AgentType::Spec),
-                "code" => Some(AgentType::Code),
-                "test" | "test_generator" => Some(AgentType::TestGenerator),
-                "review" | "reviewer" => Some(AgentType::Reviewer),
-                "debug" => Some(AgentType::Debug),
-                _ => None,
-            })
-            .collect::<Vec<_>>();
-
-        let agent_request = AgentOrchestrationRequest {
-            prompt: request.prompt,
-            context: request.context.map(|ctx| {
-                ctx.as_object()
-                    .map(|map| map.iter().map(|(k, v)| (k.clone(), v.to_string())).collect())
-                    .unwrap_or_default()
-            }),
-            agent_sequence: Some(agent_sequence.clone()),
-            options: request.options.map(|opts| {
-                opts.as_object()
-                    .map(|map| map.iter().map(|(k, v)| (k.clone(), v.to_string())).collect())
-                    .unwrap_or_default()
-            }),
-        };
+        workspace_root: PathBuf,
+        observer: Arc<dyn JobObserver>,
+    ) -> Result<RequestRecord, SubmitError> {
+        self.spawn_job(request, workspace_root, None, Some(observer)).await
+    }
 
-        // Execute agent sequence
-        let response = match self.agent_suite.orchestrate(agent_request).await {
-            Ok(response) => {
-                let end_time = chrono::Utc::now();
-                let duration = end_time.signed_duration_since(start_time);
-                
-                OrchestrationResponse {
-                    request_id: request_id.clone(),
-                    status: OrchestrationStatus::Completed,
-                    result: Some(serde_json::to_value(response.final_result)?),
-                    metadata: OrchestrationMetadata {
-                        start_time: Some(start_time),
-                        end_time: Some(end_time),
-                        duration_ms: Some(duration.num_milliseconds()),
-                        agent_sequence: agent_sequence.into_iter().map(|a| format!("{:?}", a)).collect(),
-                        success: true,
-                        error: None,
-                    },
-                }
-            }
-            Err(e) => {
-                OrchestrationResponse {
-                    request_id: request_id.clone(),
-                    status: OrchestrationStatus::Failed(e.to_string()),
-                    result: None,
-                    metadata: OrchestrationMetadata {
-                        start_time: Some(start_time),
-                        end_time: Some(chrono::Utc::now()),
-                        duration_ms: Some(chrono::Utc::now().signed_duration_since(start_time).num_milliseconds()),
-                        agent_sequence: agent_sequence.into_iter().map(|a| format!("{:?}", a)).collect(),
-                        success: false,
-                        error: Some(e.to_string()),
-                    },
-                }
+    /// Like [`AgentOrchestrator::submit_request`], but also stream the job's
+    /// progress events.
+    ///
+    /// The job keeps running if the receiver is dropped. When the job is
+    /// cancelled or times out the stream ends with [`AgentEvent::Failed`].
+    pub async fn submit_request_stream(
+        &self,
+        request: OrchestrationRequest,
+    ) -> Result<(RequestRecord, mpsc::UnboundedReceiver<AgentEvent>), SubmitError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let record = self.spawn_job(request, self.config.workspace_root.clone(), Some(tx), None).await?;
+        Ok((record, rx))
+    }
+
+    /// Cancel a request that has not finished yet, stopping its agents.
+    ///
+    /// Returns the updated record, or `None` if the id is unknown. A request
+    /// that had already finished keeps its final status.
+    pub async fn cancel_request(&self, request_id: &str) -> Result<Option<RequestRecord>> {
+        let record = self.store.cancel(request_id).await?;
+        if record.as_ref().is_some_and(|record| record.status == OrchestrationStatus::Cancelled) {
+            if let Some(cancel) = self.jobs.lock().await.remove(request_id) {
+                let _ = cancel.send(());
             }
+        }
+        Ok(record)
+    }
+
+    async fn spawn_job(
+        &self,
+        request: OrchestrationRequest,
+        workspace_root: PathBuf,
+        subscriber: Option<mpsc::UnboundedSender<AgentEvent>>,
+        observer: Option<Arc<dyn JobObserver>>,
+    ) -> Result<RequestRecord, SubmitError> {
+        let (mut agent_request, agent_sequence) = to_agent_request(request, self.config.default_pipeline.as_ref())?;
+        let Ok(admission) = self.admission.clone().try_acquire_owned() else {
+            telemetry::request_rejected();
+            return Err(SubmitError::QueueFull);
         };
+        let submitted_at = Instant::now();
 
-        // Update state
-        {
-            let mut state = self.state.lock().await;
-            state.active_sessions -= 1;
-        }
+        agent_request.workspace_root = Some(workspace_root);
+        let agent_sequence = agent_sequence.into_iter().map(|a| format!("{:?}", a)).collect();
+        let record = self.store.create(agent_request.prompt.clone(), agent_sequence).await?;
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.jobs.lock().await.insert(record.request_id.clone(), cancel_tx);
 
-        Ok(response)
+        let job = Job {
+            request_id: record.request_id.clone(),
+            agent_suite: self.agent_suite.clone(),
+            store: self.store.clone(),
+            running: self.running.clone(),
+            jobs: self.jobs.clone(),
+            submitted_at,
+            timeout: self.config.job_timeout,
+            subscriber,
+            observer,
+        };
+        telemetry::job_queued();
+        tokio::spawn(async move {
+            let _admission = admission;
+            job.run(agent_request, cancel_rx).await;
+        });
+
+        Ok(record)
     }
 }
 
-/// Default model provider implementation
-#[derive(Clone)]
-struct DefaultModelProvider;
+/// One orchestration running in the background
+struct Job {
+    request_id: String,
+    agent_suite: Arc<AgentSuite>,
+    store: Arc<RequestStore>,
+    running: Arc<Semaphore>,
+    jobs: Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>,
+    submitted_at: Instant,
+    timeout: Duration,
+    subscriber: Option<mpsc::UnboundedSender<AgentEvent>>,
+    observer: Option<Arc<dyn JobObserver>>,
+}
 
-#[async_trait]
-impl codex_agents::ModelProvider for DefaultModelProvider {
-    async fn generate_completion(&self, _prompt: &str) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // In a real implementation, this would call the actual model provider API
-        Ok("Generated completion".to_string())
+impl Job {
+    async fn run(self, agent_request: AgentOrchestrationRequest, mut cancel: oneshot::Receiver<()>) {
+        // Wait for a free slot; `cancel_request` has already recorded a cancellation
+        let _running = tokio::select! {
+            permit = self.running.clone().acquire_owned() => permit,
+            _ = &mut cancel => {
+                telemetry::job_dequeued();
+                self.notify(AgentEvent::Failed { error: "request cancelled".to_string() });
+                self.finish().await;
+                return;
+            }
+        };
+
+        telemetry::job_dequeued();
+        telemetry::job_started();
+        if let Err(e) = self.store.start(&self.request_id).await {
+            tracing::warn!("failed to record start of request {}: {e}", self.request_id);
+        }
+
+        // Dropping `events` cancels the agents, including any model call in flight
+        let mut events = self.agent_suite.orchestrate_stream(agent_request);
+        let deadline = tokio::time::sleep(self.timeout);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    if let AgentEvent::AgentFinished { execution } = &event {
+                        telemetry::agent_finished(execution);
+                    }
+                    if let Err(e) = record_event(&self.store, &self.request_id, &event).await {
+                        tracing::warn!("failed to record event for request {}: {e}", self.request_id);
+                    }
+                    if let Some(observer) = &self.observer {
+                        observer.on_event(&self.request_id, &event).await;
+                    }
+                    self.notify(event);
+                }
+                _ = &mut cancel => {
+                    self.notify(AgentEvent::Failed { error: "request cancelled".to_string() });
+                    break;
+                }
+                _ = &mut deadline => {
+                    let error = format!("request timed out after {}s", self.timeout.as_secs());
+                    let timed_out = self.store.update(&self.request_id, |record| {
+                        record.status = OrchestrationStatus::TimedOut;
+                        record.error = Some(error.clone());
+                    }).await;
+                    if let Err(e) = timed_out {
+                        tracing::warn!("failed to record timeout of request {}: {e}", self.request_id);
+                    }
+                    self.notify(AgentEvent::Failed { error });
+                    break;
+                }
+            }
+        }
+        drop(events);
+
+        self.jobs.lock().await.remove(&self.request_id);
+        telemetry::job_stopped();
+        self.finish().await;
     }
 
-    async fn generate_with_context(
-        &self,
-        _system_prompt: &str,
-        _user_prompt: &str,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        // In a real implementation, this would call the actual model provider API
-        Ok("Generated completion with context".to_string())
+    async fn finish(&self) {
+        if let Some(record) = self.store.get(&self.request_id).await {
+            telemetry::request_finished(&record.status, self.submitted_at.elapsed());
+            if let Some(observer) = &self.observer {
+                observer.on_finished(&record).await;
+            }
+        }
+    }
+
+    fn notify(&self, event: AgentEvent) {
+        if let Some(subscriber) = &self.subscriber {
+            // The job keeps running without a listener
+            let _ = subscriber.send(event);
+        }
     }
 }
 
-#[async_trait]
-impl codex_agents::code::ModelProvider for DefaultModelProvider {
-    async fn stream_completion(
-        &self,
-        _prompt: &str,
-    ) -> Result<Box<dyn codex_agents::code::StreamCompletion + Send + Sync>, Box<dyn std::error::Error + Send + Sync>> {
-        // In a real implementation, this would return a streaming response
-        Ok(Box::new(MockStreamCompletion::new("".to_string())))
+/// Persist the effect of a streamed event on its request record.
+async fn record_event(store: &RequestStore, request_id: &str, event: &AgentEvent) -> Result<()> {
+    match event {
+        AgentEvent::AgentFinished { execution } => {
+            store.update(request_id, |record| record.executions.push(execution.clone())).await?;
+        }
+        AgentEvent::Completed { response } => {
+            let result = serde_json::to_value(&response.final_result)?;
+            store.update(request_id, |record| {
+                record.status = OrchestrationStatus::Completed;
+                record.result = Some(result);
+            }).await?;
+        }
+        AgentEvent::Failed { error } => {
+            store.update(request_id, |record| {
+                record.status = OrchestrationStatus::Failed;
+                record.error = Some(error.clone());
+            }).await?;
+        }
+        _ => {}
     }
+    Ok(())
+}
+
+/// Map an API request onto the agent suite's request format, returning the
+/// agents it will run in pipeline order.
+fn to_agent_request(
+    request: OrchestrationRequest,
+    default_pipeline: Option<&Pipeline>,
+) -> Result<(AgentOrchestrationRequest, Vec<AgentType>), SubmitError> {
+    let pipeline = match (request.pipeline, &request.agent_sequence) {
+        (Some(pipeline), _) => Some(pipeline),
+        (None, None) => default_pipeline.cloned(),
+        (None, Some(_)) => None,
+    };
+
+    let agent_sequence = match &pipeline {
+        Some(pipeline) => pipeline.steps.iter().map(|step| step.agent.clone()).collect(),
+        None => request.agent_sequence
+            .unwrap_or_else(|| vec!["spec".to_string(), "code".to_string(), "reviewer".to_string()])
+            .into_iter()
+            .map(|agent| match agent.to_lowercase().as_str() {
+                "spec" => Ok(AgentType::Spec),
+                "code" => Ok(AgentType::Code),
+                "test" | "test_generator" => Ok(AgentType::TestGenerator),
+                "review" | "reviewer" => Ok(AgentType::Reviewer),
+                "debug" => Ok(AgentType::Debug),
+                _ => Err(SubmitError::InvalidRequest(format!("unknown agent '{agent}'"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let agent_request = AgentOrchestrationRequest {
+        prompt: request.prompt,
+        context: request.context.as_ref().map(string_map),
+        agent_sequence: pipeline.is_none().then(|| agent_sequence.clone()),
+        options: request.options.as_ref().map(string_map),
+        pipeline,
+        workspace_root: None,
+    };
+
+    Ok((agent_request, agent_sequence))
 }
 
-struct MockStreamCompletion {
-    content: String,
-    position: usize,
+/// Flatten a JSON object into string values, keeping strings unquoted.
+fn string_map(value: &serde_json::Value) -> HashMap<String, String> {
+    value.as_object()
+        .map(|map| {
+            map.iter()
+                .map(|(k, v)| (k.clone(), v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
-impl MockStreamCompletion {
-    fn new(content: String) -> Self {
-        Self { content, position: 0 }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use codex_agents::{AgentError, StreamCompletion};
+
+    /// Provider whose completions never finish; counts streams opened and dropped.
+    #[derive(Default)]
+    struct StalledModelProvider {
+        started: Arc<AtomicUsize>,
+        dropped: Arc<AtomicUsize>,
     }
-}
 
-#[async_trait::async_trait]
-impl codex_agents::code::StreamCompletion for MockStreamCompletion {
-    async fn next(&mut self) -> Option<String> {
-        if self.position >= self.content.len() {
-            return None;
+    struct StalledStream(Arc<AtomicUsize>);
+
+    impl Drop for StalledStream {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl StreamCompletion for StalledStream {
+        async fn next(&mut self) -> Option<Result<String, AgentError>> {
+            std::future::pending().await
+        }
+    }
+
+    #[async_trait]
+    impl ModelProvider for StalledModelProvider {
+        async fn generate_completion(&self, _prompt: &str) -> Result<String, AgentError> {
+            std::future::pending().await
+        }
+
+        async fn generate_with_context(&self, _system_prompt: &str, _user_prompt: &str) -> Result<String, AgentError> {
+            std::future::pending().await
+        }
+
+        async fn stream_completion(&self, _system_prompt: Option<&str>, _user_prompt: &str) -> Result<Box<dyn StreamCompletion>, AgentError> {
+            self.started.fetch_add(1, Ordering::SeqCst);
+            Ok(Box::new(StalledStream(self.dropped.clone())))
         }
-        let chunk_size = 10;
-        let end = (self.position + chunk_size).min(self.content.len());
-        let chunk = self.content[self.position..end].to_string();
-        self.position = end;
-        Some(chunk)
+    }
+
+    fn request() -> OrchestrationRequest {
+        OrchestrationRequest {
+            prompt: "Add login".to_string(),
+            context: None,
+            agent_sequence: Some(vec!["spec".to_string()]),
+            options: None,
+            pipeline: None,
+        }
+    }
+
+    async fn wait_for(counter: &AtomicUsize, value: usize) {
+        while counter.load(Ordering::SeqCst) < value {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_queued_cancelled_and_timed_out() {
+        let provider = Arc::new(StalledModelProvider::default());
+        let (started, dropped) = (provider.started.clone(), provider.dropped.clone());
+        let orchestrator = AgentOrchestrator::with_model_provider(provider, RequestStore::in_memory()).with_config(OrchestratorConfig {
+            max_concurrent_jobs: 1,
+            max_queued_jobs: 1,
+            job_timeout: Duration::from_millis(200),
+            ..Default::default()
+        });
+
+        let running = orchestrator.submit_request(request()).await.unwrap();
+        let queued = orchestrator.submit_request(request()).await.unwrap();
+        assert!(matches!(orchestrator.submit_request(request()).await, Err(SubmitError::QueueFull)));
+
+        // Cancelling the running job stops its model call and frees the slot
+        wait_for(&started, 1).await;
+        let cancelled = orchestrator.cancel_request(&running.request_id).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, OrchestrationStatus::Cancelled);
+        wait_for(&dropped, 1).await;
+        wait_for(&started, 2).await;
+
+        // The queued job then runs into its timeout
+        let record = loop {
+            let record = orchestrator.requests().get(&queued.request_id).await.unwrap();
+            if record.is_finished() {
+                break record;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(record.status, OrchestrationStatus::TimedOut);
+        assert!(record.error.unwrap().contains("timed out"));
+        wait_for(&dropped, 2).await;
+
+        let cancelled = orchestrator.cancel_request(&queued.request_id).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, OrchestrationStatus::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_agents_are_rejected() {
+        let orchestrator = AgentOrchestrator::with_model_provider(Arc::new(StalledModelProvider::default()), RequestStore::in_memory());
+
+        let mut request = request();
+        request.agent_sequence = Some(vec!["spec".to_string(), "coder".to_string()]);
+        let err = orchestrator.submit_request(request).await.unwrap_err();
+        assert!(matches!(&err, SubmitError::InvalidRequest(reason) if reason.contains("coder")));
+        assert!(orchestrator.requests().list(&RequestFilter::default()).await.is_empty());
     }
 }
@@ -3,14 +3,13 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use axum::{
     routing::{get, post},
     Router,
-    extract::{State, Path},
-    response::{IntoResponse, Response, Sse},
+    body::Bytes,
+    extract::{State, Path, Query},
+    response::{sse::{Event, KeepAlive}, IntoResponse, Response, Sse},
     Json,
     http::{StatusCode, header, HeaderMap},
-    body::StreamBody,
 };
-use tokio_stream::{wrappers::BroadcastStream, StreamExt};
-use std::convert::Infallible;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 use std::time::Duration;
 use serde_json::json;
 use tower_http::{
@@ -19,18 +18,30 @@ use tower_http::{
     compression::CompressionLayer,
     timeout::TimeoutLayer,
 };
-use codex_orchestrator::{AgentOrchestrator, OrchestrationRequest, OrchestrationResponse};
+use codex_github_integration::{GitHubApp, GitHubError};
+use codex_orchestrator::github::{self as github_checks, GitHubChecksConfig};
+use codex_orchestrator::{
+    telemetry, AgentOrchestrator, OrchestrationRequest, OrchestrationStatus, RequestFilter, RequestRecord, SubmitError,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
 use uuid::Uuid;
-use chrono;
 
 struct AppState {
     orchestrator: Arc<AgentOrchestrator>,
+    metrics: PrometheusHandle,
+    /// Set when GITHUB_WEBHOOK_SECRET is configured
+    github: Option<GitHubApp>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize the orchestrator
-    let orchestrator = Arc::new(AgentOrchestrator::new()?);
+    // Initialize metrics and the orchestrator
+    let metrics = telemetry::install_recorder()?;
+    let orchestrator = Arc::new(AgentOrchestrator::new().await?);
+    let github = match GitHubChecksConfig::from_env(&codex_core::config::find_codex_home()?)? {
+        Some(config) => Some(github_checks::github_app(orchestrator.clone(), config)?),
+        None => None,
+    };
     
     // Set up CORS
     let cors = CorsLayer::new()
@@ -45,11 +56,13 @@ async fn main() -> anyhow::Result<()> {
         .route("/readyz", get(ready_check))
         // API v1 endpoints
         .route("/api/v1/orchestrate", post(handle_orchestration))
-        .route("/api/v1/orchestrate/stream", get(handle_streaming_orchestration))
-        .route("/api/v1/requests/:request_id", get(get_request_status))
+        .route("/api/v1/orchestrate/stream", post(handle_streaming_orchestration))
+        .route("/api/v1/requests", get(list_requests))
+        .route("/api/v1/requests/:request_id", get(get_request_status).delete(cancel_request))
         .route("/api/v1/agents", get(list_agents))
         .route("/api/v1/metrics", get(get_metrics))
-        .with_state(Arc::new(AppState { orchestrator }))
+        .route("/api/v1/github/webhook", post(handle_github_webhook))
+        .with_state(Arc::new(AppState { orchestrator, metrics, github }))
         .layer(cors)
         // Add middleware
         .layer(TraceLayer::new_for_http())
@@ -99,20 +112,61 @@ async fn ready_check() -> (StatusCode, Json<serde_json::Value>) {
     (StatusCode::OK, Json(response))
 }
 
+// Queue an orchestration and return its pending record
+async fn handle_orchestration(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<OrchestrationRequest>,
+) -> Result<(StatusCode, [(header::HeaderName, String); 1], Json<RequestRecord>), (StatusCode, Json<serde_json::Value>)> {
+    let record = state.orchestrator.submit_request(request).await.map_err(submit_error)?;
+    let location = format!("/api/v1/requests/{}", record.request_id);
+    Ok((StatusCode::ACCEPTED, [(header::LOCATION, location)], Json(record)))
+}
+
+fn submit_error(e: SubmitError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match e {
+        SubmitError::QueueFull => StatusCode::SERVICE_UNAVAILABLE,
+        SubmitError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+        SubmitError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(json!({ "error": e.to_string() })))
+}
+
 // Get request status
 async fn get_request_status(
     Path(request_id): Path<Uuid>,
     State(state): State<Arc<AppState>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    // In a real implementation, you would fetch this from a database
-    // For now, we'll return a mock response
-    Ok(Json(json!({
-        "request_id": request_id,
-        "status": "completed",
-        "created_at": "2024-03-20T12:00:00Z",
-        "completed_at": "2024-03-20T12:00:30Z",
-        "result": {}
-    })))
+) -> Result<Json<RequestRecord>, (StatusCode, Json<serde_json::Value>)> {
+    state.orchestrator.requests().get(&request_id.to_string()).await.map(Json).ok_or_else(|| not_found(request_id))
+}
+
+// List recorded requests, newest first
+async fn list_requests(
+    Query(filter): Query<RequestFilter>,
+    State(state): State<Arc<AppState>>,
+) -> Json<Vec<RequestRecord>> {
+    Json(state.orchestrator.requests().list(&filter).await)
+}
+
+// Cancel a request that has not finished yet, stopping its agents
+async fn cancel_request(
+    Path(request_id): Path<Uuid>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RequestRecord>, (StatusCode, Json<serde_json::Value>)> {
+    let record = state.orchestrator.cancel_request(&request_id.to_string()).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))))?
+        .ok_or_else(|| not_found(request_id))?;
+
+    if record.status != OrchestrationStatus::Cancelled {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({ "error": format!("request {request_id} already finished"), "status": record.status })),
+        ));
+    }
+    Ok(Json(record))
+}
+
+fn not_found(request_id: Uuid) -> (StatusCode, Json<serde_json::Value>) {
+    (StatusCode::NOT_FOUND, Json(json!({ "error": format!("request {request_id} not found") })))
 }
 
 // List available agents
@@ -146,22 +200,35 @@ async fn list_agents() -> Json<serde_json::Value> {
     ]))
 }
 
-// Get metrics
-async fn get_metrics() -> Response {
-    let metrics = format!(
-        "# HELP codex_requests_total Total number of requests\n".to_owned() + 
-        "# TYPE codex_requests_total counter\n" +
-        "codex_requests_total{{status=\"success\"}} 42\n" +
-        "codex_requests_total{{status=\"error\"}} 5\n" +
-        "# HELP codex_request_duration_seconds Request duration in seconds\n" +
-        "# TYPE codex_request_duration_seconds histogram\n" +
-        "codex_request_duration_seconds_bucket{{le=\"0.1\"}} 30\n" +
-        "codex_request_duration_seconds_bucket{{le=\"0.5\"}} 35\n" +
-        "codex_request_duration_seconds_bucket{{le=\"1.0\"}} 40\n" +
-        "codex_request_duration_seconds_bucket{{le=\"+Inf\"}} 47\n" +
-        "codex_request_duration_seconds_sum 15.3\n" +
-        "codex_request_duration_seconds_count 47"
-    );
+// Receive a GitHub webhook; pull request runs are started in the background
+async fn handle_github_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, Json<serde_json::Value>)> {
+    let Some(github) = &state.github else {
+        return Err((StatusCode::NOT_FOUND, Json(json!({ "error": "GitHub integration is not configured" }))));
+    };
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default();
+    let event = header("X-GitHub-Event");
+    let signature = header("X-Hub-Signature-256");
+
+    match github.handle_webhook(event, &body, signature).await {
+        Ok(_) => Ok(StatusCode::ACCEPTED),
+        Err(e) => {
+            let status = match e {
+                GitHubError::InvalidSignature => StatusCode::UNAUTHORIZED,
+                GitHubError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Err((status, Json(json!({ "error": e.to_string() }))))
+        }
+    }
+}
+
+// Get metrics in Prometheus text format
+async fn get_metrics(State(state): State<Arc<AppState>>) -> Response {
+    telemetry::record_token_usage(&state.orchestrator.token_usage());
 
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -169,7 +236,7 @@ async fn get_metrics() -> Response {
         "text/plain; version=0.0.4".parse().unwrap(),
     );
 
-    (headers, metrics).into_response()
+    (headers, state.metrics.render()).into_response()
 }
 
 // Graceful shutdown handler
@@ -200,23 +267,20 @@ async fn shutdown_signal() {
 }
 
 // Streaming orchestration endpoint
+//
+// Runs the agent pipeline for the posted request and streams its progress as
+// JSON events tagged by `type`: `agent_started`, `token_delta`,
+// `agent_finished`, `warning`, and finally `completed` or `failed`. The
+// recorded request's id is returned in the `x-request-id` header.
 async fn handle_streaming_orchestration(
     State(state): State<Arc<AppState>>,
-) -> Sse<impl tokio_stream::Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
-    let stream = tokio_stream::wrappers::IntervalStream::new(tokio::time::interval(Duration::from_secs(1)))
-        .then(move |_| {
-            let state = state.clone();
-            async move {
-                // Generate streaming content (this would be replaced with actual streaming from agents)
-                let content = format!("Streaming content at {}", chrono::Utc::now().format("%H:%M:%S"));
-
-                Ok(axum::response::sse::Event::default().data(content))
-            }
-        });
-
-    Sse::new(stream).keep_alive(
-        axum::response::sse::KeepAlive::new()
-            .interval(Duration::from_secs(1))
-            .text("keep-alive-text"),
-    )
+    Json(request): Json<OrchestrationRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let (record, events) = state.orchestrator.submit_request_stream(request).await.map_err(submit_error)?;
+    let stream = UnboundedReceiverStream::new(events).map(|event| Event::default().json_data(&event));
+
+    Ok((
+        [("x-request-id", record.request_id)],
+        Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15))),
+    ))
 }
@@ -0,0 +1,339 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+use codex_agents::AgentExecution;
+
+use crate::OrchestrationStatus;
+
+/// Directory under `CODEX_HOME` holding the orchestrator's state.
+pub const ORCHESTRATOR_SUBDIR: &str = "orchestrator";
+
+const REQUESTS_FILE: &str = "requests.jsonl";
+
+/// Requests kept by default; beyond that the oldest finished ones are dropped.
+pub const MAX_RECORDS: usize = 10_000;
+
+/// Everything recorded about one orchestration run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestRecord {
+    pub request_id: String,
+    pub status: OrchestrationStatus,
+    pub prompt: String,
+    pub agent_sequence: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub executions: Vec<AgentExecution>,
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+impl RequestRecord {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            OrchestrationStatus::Completed
+                | OrchestrationStatus::Failed
+                | OrchestrationStatus::TimedOut
+                | OrchestrationStatus::Cancelled
+        )
+    }
+}
+
+/// Filter for [`RequestStore::list`]. Records are returned newest first.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RequestFilter {
+    pub status: Option<OrchestrationStatus>,
+    /// Only requests whose sequence includes this agent (e.g. "code").
+    pub agent: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Persistent log of orchestration requests.
+///
+/// Every state change appends the full record as one line of
+/// `$CODEX_HOME/orchestrator/requests.jsonl`. On open the last line for each
+/// request wins and the file is rewritten with just those lines, so it only
+/// grows with the transitions of the current process.
+///
+/// At most `max_records` requests are kept; once there are more, the oldest
+/// finished ones are forgotten.
+pub struct RequestStore {
+    path: Option<PathBuf>,
+    max_records: usize,
+    records: Mutex<HashMap<String, RequestRecord>>,
+}
+
+impl RequestStore {
+    /// Store that is not persisted, for tests and embedding.
+    pub fn in_memory() -> Self {
+        Self {
+            path: None,
+            max_records: MAX_RECORDS,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Open the store under `codex_home`, keeping up to [`MAX_RECORDS`] requests.
+    pub async fn open(codex_home: &Path) -> Result<Self> {
+        Self::open_with_retention(codex_home, MAX_RECORDS).await
+    }
+
+    /// Open the store under `codex_home`, replaying and compacting any
+    /// existing log.
+    ///
+    /// Requests that were still pending or running when the previous process
+    /// exited are marked failed.
+    pub async fn open_with_retention(codex_home: &Path, max_records: usize) -> Result<Self> {
+        let dir = codex_home.join(ORCHESTRATOR_SUBDIR);
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join(REQUESTS_FILE);
+
+        let mut records = HashMap::new();
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => {
+                let mut lines = tokio::io::BufReader::new(file).lines();
+                while let Some(line) = lines.next_line().await? {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<RequestRecord>(&line) {
+                        Ok(record) => {
+                            records.insert(record.request_id.clone(), record);
+                        }
+                        Err(e) => tracing::warn!("skipping malformed request record in {}: {e}", path.display()),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        for record in records.values_mut().filter(|record| !record.is_finished()) {
+            record.status = OrchestrationStatus::Failed;
+            record.error = Some("orchestrator stopped before the request finished".to_string());
+            record.updated_at = Utc::now();
+            record.completed_at = Some(record.updated_at);
+        }
+        prune(&mut records, max_records);
+
+        // Rewrite the log with one line per request, replacing it atomically
+        let mut sorted: Vec<_> = records.values().collect();
+        sorted.sort_by_key(|record| record.created_at);
+        let mut contents = String::new();
+        for record in sorted {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        let compacted = dir.join(format!("{REQUESTS_FILE}.tmp"));
+        tokio::fs::write(&compacted, contents).await?;
+        tokio::fs::rename(&compacted, &path).await?;
+
+        Ok(Self {
+            path: Some(path),
+            max_records,
+            records: Mutex::new(records),
+        })
+    }
+
+    /// Record a new pending request.
+    pub async fn create(&self, prompt: String, agent_sequence: Vec<String>) -> Result<RequestRecord> {
+        let now = Utc::now();
+        let record = RequestRecord {
+            request_id: uuid::Uuid::new_v4().to_string(),
+            status: OrchestrationStatus::Pending,
+            prompt,
+            agent_sequence,
+            created_at: now,
+            updated_at: now,
+            started_at: None,
+            completed_at: None,
+            executions: Vec::new(),
+            result: None,
+            error: None,
+        };
+
+        self.append(&record).await?;
+        let mut records = self.records.lock().await;
+        records.insert(record.request_id.clone(), record.clone());
+        prune(&mut records, self.max_records);
+        Ok(record)
+    }
+
+    /// Apply `update` to a request and persist the result.
+    ///
+    /// Once a request has finished its status is final: later updates can
+    /// still attach data (such as the executions of a cancelled run) but the
+    /// status is left unchanged.
+    pub async fn update(&self, request_id: &str, update: impl FnOnce(&mut RequestRecord)) -> Result<Option<RequestRecord>> {
+        let mut records = self.records.lock().await;
+        let Some(record) = records.get_mut(request_id) else {
+            return Ok(None);
+        };
+
+        let final_status = record.is_finished().then(|| record.status.clone());
+        update(record);
+        if let Some(status) = final_status {
+            record.status = status;
+        }
+        record.updated_at = Utc::now();
+        if record.is_finished() {
+            record.completed_at.get_or_insert(record.updated_at);
+        }
+
+        // Append while holding the lock so the log matches the order of updates
+        let record = record.clone();
+        self.append(&record).await?;
+        Ok(Some(record))
+    }
+
+    /// Mark a request as running.
+    pub async fn start(&self, request_id: &str) -> Result<Option<RequestRecord>> {
+        self.update(request_id, |record| {
+            record.status = OrchestrationStatus::InProgress;
+            record.started_at = Some(Utc::now());
+        })
+        .await
+    }
+
+    /// Cancel a request that has not finished yet.
+    pub async fn cancel(&self, request_id: &str) -> Result<Option<RequestRecord>> {
+        self.update(request_id, |record| record.status = OrchestrationStatus::Cancelled).await
+    }
+
+    pub async fn get(&self, request_id: &str) -> Option<RequestRecord> {
+        self.records.lock().await.get(request_id).cloned()
+    }
+
+    pub async fn list(&self, filter: &RequestFilter) -> Vec<RequestRecord> {
+        let records = self.records.lock().await;
+        let mut matching: Vec<_> = records
+            .values()
+            .filter(|record| filter.status.as_ref().is_none_or(|status| record.status == *status))
+            .filter(|record| {
+                filter
+                    .agent
+                    .as_ref()
+                    .is_none_or(|agent| record.agent_sequence.iter().any(|a| a.eq_ignore_ascii_case(agent)))
+            })
+            .cloned()
+            .collect();
+
+        matching.sort_by_key(|record| std::cmp::Reverse(record.created_at));
+        if let Some(limit) = filter.limit {
+            matching.truncate(limit);
+        }
+        matching
+    }
+
+    async fn append(&self, record: &RequestRecord) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+/// Drop the oldest finished requests until at most `max_records` are left.
+/// Unfinished requests are always kept.
+fn prune(records: &mut HashMap<String, RequestRecord>, max_records: usize) {
+    let Some(excess) = records.len().checked_sub(max_records).filter(|excess| *excess > 0) else {
+        return;
+    };
+
+    let mut finished: Vec<_> = records
+        .values()
+        .filter(|record| record.is_finished())
+        .map(|record| (record.created_at, record.request_id.clone()))
+        .collect();
+    finished.sort();
+    for (_, request_id) in finished.into_iter().take(excess) {
+        records.remove(&request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_store_persists_transitions() {
+        let codex_home = tempfile::tempdir().unwrap();
+
+        let store = RequestStore::open(codex_home.path()).await.unwrap();
+        let finished = store.create("Add login".to_string(), vec!["Spec".to_string()]).await.unwrap();
+        store.start(&finished.request_id).await.unwrap();
+        store
+            .update(&finished.request_id, |record| {
+                record.status = OrchestrationStatus::Completed;
+                record.result = Some(serde_json::json!({ "ok": true }));
+            })
+            .await
+            .unwrap();
+        let interrupted = store.create("Fix bug".to_string(), vec!["Debug".to_string()]).await.unwrap();
+        store.start(&interrupted.request_id).await.unwrap();
+
+        // Finished requests keep their final status
+        let cancelled = store.cancel(&finished.request_id).await.unwrap().unwrap();
+        assert_eq!(cancelled.status, OrchestrationStatus::Completed);
+
+        let reopened = RequestStore::open(codex_home.path()).await.unwrap();
+        let finished = reopened.get(&finished.request_id).await.unwrap();
+        assert_eq!(finished.status, OrchestrationStatus::Completed);
+        assert!(finished.started_at.is_some() && finished.completed_at.is_some());
+        assert_eq!(reopened.get(&interrupted.request_id).await.unwrap().status, OrchestrationStatus::Failed);
+
+        let filter = RequestFilter {
+            agent: Some("debug".to_string()),
+            ..Default::default()
+        };
+        let listed = reopened.list(&filter).await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].request_id, interrupted.request_id);
+    }
+
+    #[tokio::test]
+    async fn test_request_store_compacts_and_prunes_on_open() {
+        let codex_home = tempfile::tempdir().unwrap();
+
+        let store = RequestStore::open(codex_home.path()).await.unwrap();
+        let mut ids = Vec::new();
+        for prompt in ["first", "second", "third"] {
+            let record = store.create(prompt.to_string(), vec!["Spec".to_string()]).await.unwrap();
+            store.start(&record.request_id).await.unwrap();
+            store.cancel(&record.request_id).await.unwrap();
+            ids.push(record.request_id);
+        }
+        let running = store.create("fourth".to_string(), vec!["Spec".to_string()]).await.unwrap();
+        store.start(&running.request_id).await.unwrap();
+        drop(store);
+
+        // The interrupted request is kept; the oldest finished one is dropped
+        let reopened = RequestStore::open_with_retention(codex_home.path(), 3).await.unwrap();
+        assert!(reopened.get(&ids[0]).await.is_none());
+        assert!(reopened.get(&ids[1]).await.is_some());
+        assert_eq!(reopened.get(&running.request_id).await.unwrap().status, OrchestrationStatus::Failed);
+
+        let path = codex_home.path().join(ORCHESTRATOR_SUBDIR).join(REQUESTS_FILE);
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 3);
+
+        // Creating past the limit forgets the oldest finished request
+        reopened.create("fifth".to_string(), vec!["Spec".to_string()]).await.unwrap();
+        assert!(reopened.get(&ids[1]).await.is_none());
+        assert_eq!(reopened.list(&RequestFilter::default()).await.len(), 3);
+    }
+}
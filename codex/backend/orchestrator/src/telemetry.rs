@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use metrics::{
+    absolute_counter, decrement_gauge, describe_counter, describe_gauge, describe_histogram, histogram, increment_counter,
+    increment_gauge, Unit,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+use codex_agents::{AgentExecution, AgentType, TokenUsage};
+
+use crate::OrchestrationStatus;
+
+pub const REQUESTS_TOTAL: &str = "codex_orchestrator_requests_total";
+pub const REQUEST_DURATION: &str = "codex_orchestrator_request_duration_seconds";
+pub const QUEUE_DEPTH: &str = "codex_orchestrator_queue_depth";
+pub const ACTIVE_JOBS: &str = "codex_orchestrator_active_jobs";
+pub const AGENT_EXECUTION_DURATION: &str = "codex_agent_execution_duration_seconds";
+pub const AGENT_FAILURES_TOTAL: &str = "codex_agent_failures_total";
+pub const MODEL_TOKENS_TOTAL: &str = "codex_model_tokens_total";
+
+/// Histogram buckets in seconds; agent runs take from seconds to many minutes.
+const DURATION_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0];
+
+/// Install the global Prometheus recorder and describe the orchestrator's metrics.
+///
+/// Render the returned handle to serve `/api/v1/metrics`.
+pub fn install_recorder() -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new().set_buckets(DURATION_BUCKETS)?.install_recorder()?;
+
+    describe_counter!(REQUESTS_TOTAL, "Orchestration requests by final status, including rejected submissions");
+    describe_histogram!(REQUEST_DURATION, Unit::Seconds, "Time from submission to the end of an orchestration");
+    describe_gauge!(QUEUE_DEPTH, "Orchestrations waiting for a free slot");
+    describe_gauge!(ACTIVE_JOBS, "Orchestrations currently running");
+    describe_histogram!(AGENT_EXECUTION_DURATION, Unit::Seconds, "Execution time of a single agent");
+    describe_counter!(AGENT_FAILURES_TOTAL, "Failed agent executions");
+    describe_counter!(MODEL_TOKENS_TOTAL, "Tokens used by the model provider");
+
+    Ok(handle)
+}
+
+/// Export the model provider's cumulative token usage.
+pub fn record_token_usage(usage: &TokenUsage) {
+    absolute_counter!(MODEL_TOKENS_TOTAL, usage.input_tokens, "kind" => "input");
+    absolute_counter!(MODEL_TOKENS_TOTAL, usage.cached_input_tokens, "kind" => "cached_input");
+    absolute_counter!(MODEL_TOKENS_TOTAL, usage.output_tokens, "kind" => "output");
+    absolute_counter!(MODEL_TOKENS_TOTAL, usage.reasoning_output_tokens, "kind" => "reasoning_output");
+}
+
+pub(crate) fn request_rejected() {
+    increment_counter!(REQUESTS_TOTAL, "status" => "rejected");
+}
+
+pub(crate) fn request_finished(status: &OrchestrationStatus, duration: Duration) {
+    let status = status_label(status);
+    increment_counter!(REQUESTS_TOTAL, "status" => status);
+    histogram!(REQUEST_DURATION, duration.as_secs_f64(), "status" => status);
+}
+
+pub(crate) fn job_queued() {
+    increment_gauge!(QUEUE_DEPTH, 1.0);
+}
+
+/// A queued job left the queue, either to run or because it was cancelled.
+pub(crate) fn job_dequeued() {
+    decrement_gauge!(QUEUE_DEPTH, 1.0);
+}
+
+pub(crate) fn job_started() {
+    increment_gauge!(ACTIVE_JOBS, 1.0);
+}
+
+pub(crate) fn job_stopped() {
+    decrement_gauge!(ACTIVE_JOBS, 1.0);
+}
+
+pub(crate) fn agent_finished(execution: &AgentExecution) {
+    let agent = agent_label(&execution.agent_type);
+    histogram!(AGENT_EXECUTION_DURATION, execution.execution_time_ms as f64 / 1000.0, "agent" => agent);
+    if !execution.success {
+        increment_counter!(AGENT_FAILURES_TOTAL, "agent" => agent);
+    }
+}
+
+fn status_label(status: &OrchestrationStatus) -> &'static str {
+    match status {
+        OrchestrationStatus::Pending => "pending",
+        OrchestrationStatus::InProgress => "in_progress",
+        OrchestrationStatus::Completed => "completed",
+        OrchestrationStatus::Failed => "failed",
+        OrchestrationStatus::TimedOut => "timed_out",
+        OrchestrationStatus::Cancelled => "cancelled",
+    }
+}
+
+fn agent_label(agent_type: &AgentType) -> &'static str {
+    match agent_type {
+        AgentType::Spec => "spec",
+        AgentType::Code => "code",
+        AgentType::TestGenerator => "test_generator",
+        AgentType::Reviewer => "reviewer",
+        AgentType::Debug => "debug",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_are_rendered_in_prometheus_format() {
+        let handle = install_recorder().unwrap();
+
+        request_finished(&OrchestrationStatus::Completed, Duration::from_secs(3));
+        request_rejected();
+        agent_finished(&AgentExecution {
+            step_id: "code".to_string(),
+            agent_type: AgentType::Code,
+            input: serde_json::Value::Null,
+            output: serde_json::Value::Null,
+            success: false,
+            execution_time_ms: 1500,
+            error_message: Some("model error".to_string()),
+            review_rounds: Vec::new(),
+        });
+        job_queued();
+        record_token_usage(&TokenUsage {
+            input_tokens: 120,
+            output_tokens: 30,
+            ..Default::default()
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains("# TYPE codex_orchestrator_requests_total counter"));
+        assert!(rendered.contains("codex_orchestrator_requests_total{status=\"completed\"} 1"));
+        assert!(rendered.contains("codex_orchestrator_requests_total{status=\"rejected\"}"));
+        assert!(rendered.contains("codex_orchestrator_request_duration_seconds_bucket{status=\"completed\",le=\"5\"} 1"));
+        assert!(rendered.contains("codex_agent_failures_total{agent=\"code\"} 1"));
+        assert!(rendered.contains("codex_agent_execution_duration_seconds_count{agent=\"code\"} 1"));
+        assert!(rendered.contains("codex_model_tokens_total{kind=\"input\"} 120"));
+        assert!(rendered.contains("codex_orchestrator_queue_depth"));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{api_error, GitHubError, USER_AGENT};
+
+/// GitHub rejects app JWTs that are valid for more than ten minutes.
+const JWT_LIFETIME_SECS: i64 = 9 * 60;
+/// JWTs are backdated to tolerate clock drift between us and GitHub.
+const JWT_CLOCK_SKEW_SECS: i64 = 60;
+/// Installation tokens last an hour; refresh them this long before they expire.
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 5 * 60;
+
+/// GitHub App credentials: signs app JWTs and exchanges them for
+/// per-installation access tokens, which are cached until shortly before
+/// they expire.
+pub(crate) struct AppAuth {
+    app_id: String,
+    key: EncodingKey,
+    /// Installation to use for every repository, skipping the lookup.
+    installation_id: Option<u64>,
+    /// `owner/repo` -> installation id.
+    installations: Mutex<HashMap<String, u64>>,
+    tokens: Mutex<HashMap<u64, InstallationToken>>,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iat: i64,
+    exp: i64,
+    iss: &'a str,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+impl AppAuth {
+    pub(crate) fn from_pem(app_id: &str, pem: &[u8], installation_id: Option<u64>) -> Result<Self, GitHubError> {
+        let key = EncodingKey::from_rsa_pem(pem).map_err(GitHubError::InvalidPrivateKey)?;
+        Ok(Self {
+            app_id: app_id.to_string(),
+            key,
+            installation_id,
+            installations: Mutex::new(HashMap::new()),
+            tokens: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// A short-lived RS256 JWT identifying the app itself.
+    pub(crate) fn jwt(&self) -> Result<String, GitHubError> {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            iat: now - JWT_CLOCK_SKEW_SECS,
+            exp: now + JWT_LIFETIME_SECS,
+            iss: &self.app_id,
+        };
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key).map_err(GitHubError::Jwt)
+    }
+
+    /// An access token for the installation covering `owner/repo`.
+    pub(crate) async fn installation_token(
+        &self,
+        client: &reqwest::Client,
+        base_url: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<String, GitHubError> {
+        let installation_id = match self.installation_id {
+            Some(id) => id,
+            None => self.installation_for(client, base_url, owner, repo).await?,
+        };
+
+        // Held across the refresh so concurrent callers wait for one exchange
+        let mut tokens = self.tokens.lock().await;
+        if let Some(cached) = tokens.get(&installation_id) {
+            if cached.expires_at - Utc::now() > Duration::seconds(TOKEN_REFRESH_MARGIN_SECS) {
+                return Ok(cached.token.clone());
+            }
+        }
+
+        let url = format!("{}/app/installations/{}/access_tokens", base_url, installation_id);
+        let response = self.app_request(client.post(&url))?.send().await?;
+        if !response.status().is_success() {
+            return Err(api_error("create an installation access token", response).await);
+        }
+        let token: InstallationToken = response.json().await?;
+        tokens.insert(installation_id, token.clone());
+        Ok(token.token)
+    }
+
+    async fn installation_for(
+        &self,
+        client: &reqwest::Client,
+        base_url: &str,
+        owner: &str,
+        repo: &str,
+    ) -> Result<u64, GitHubError> {
+        let full_name = format!("{}/{}", owner, repo);
+        if let Some(id) = self.installations.lock().await.get(&full_name) {
+            return Ok(*id);
+        }
+
+        let url = format!("{}/repos/{}/{}/installation", base_url, owner, repo);
+        let response = self.app_request(client.get(&url))?.send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(GitHubError::NotInstalled(full_name));
+        }
+        if !response.status().is_success() {
+            return Err(api_error("look up the app installation", response).await);
+        }
+        let installation: Installation = response.json().await?;
+        self.installations.lock().await.insert(full_name, installation.id);
+        Ok(installation.id)
+    }
+
+    fn app_request(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, GitHubError> {
+        Ok(builder
+            .bearer_auth(self.jwt()?)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", USER_AGENT))
+    }
+}
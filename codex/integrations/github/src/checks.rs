@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{send, GitHubApp, GitHubError};
+
+/// GitHub accepts at most 50 annotations per check run request; more are
+/// sent in further updates, which append to the earlier ones.
+const MAX_ANNOTATIONS_PER_REQUEST: usize = 50;
+/// Longest commit status description GitHub accepts.
+const MAX_STATUS_DESCRIPTION_CHARS: usize = 140;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Queued,
+    InProgress,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    TimedOut,
+    ActionRequired,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    Notice,
+    Warning,
+    Failure,
+}
+
+/// A message attached to lines of a file in the check run's diff view.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckAnnotation {
+    /// Path relative to the repository root.
+    pub path: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub annotation_level: AnnotationLevel,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_details: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunOutput {
+    pub title: String,
+    /// Markdown.
+    pub summary: String,
+    /// Markdown shown below the summary.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub annotations: Vec<CheckAnnotation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCheckRunRequest {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub name: String,
+    pub head_sha: String,
+    pub status: CheckStatus,
+    /// Page with the full details of the run.
+    pub details_url: Option<String>,
+    /// Our id for the run, e.g. an orchestration request id.
+    pub external_id: Option<String>,
+    pub output: Option<CheckRunOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCheckRunRequest {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub check_run_id: u64,
+    pub status: Option<CheckStatus>,
+    /// Completes the run.
+    pub conclusion: Option<CheckConclusion>,
+    pub details_url: Option<String>,
+    pub external_id: Option<String>,
+    pub output: Option<CheckRunOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRun {
+    pub id: u64,
+    pub html_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitStatusRequest {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub sha: String,
+    pub state: CommitState,
+    /// Label distinguishing this status from others on the commit.
+    pub context: String,
+    /// Truncated to the 140 characters GitHub accepts.
+    pub description: Option<String>,
+    pub target_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CheckRunBody<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_sha: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<CheckStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conclusion: Option<CheckConclusion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<CheckRunOutput>,
+}
+
+impl GitHubApp {
+    /// Create a check run on a commit. Requires app authentication.
+    pub async fn create_check_run(&self, request: CreateCheckRunRequest) -> Result<CheckRun, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+        let (output, remaining) = split_annotations(request.output);
+
+        let body = CheckRunBody {
+            name: Some(&request.name),
+            head_sha: Some(&request.head_sha),
+            status: Some(request.status),
+            conclusion: None,
+            details_url: request.details_url.as_deref(),
+            external_id: request.external_id.as_deref(),
+            output,
+        };
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, "/check-runs").await?.json(&body);
+        let check_run: CheckRun = send(create, "create a check run").await?;
+
+        for output in remaining {
+            self.patch_check_run(owner, repo, check_run.id, &CheckRunBody { output: Some(output), ..empty_body() }).await?;
+        }
+        Ok(check_run)
+    }
+
+    /// Update a check run. Annotations beyond the 50 GitHub accepts per
+    /// request are sent in further updates, with the status and conclusion
+    /// applied by the last one.
+    pub async fn update_check_run(&self, request: UpdateCheckRunRequest) -> Result<CheckRun, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+        let (first, remaining) = split_annotations(request.output);
+        let mut outputs: Vec<CheckRunOutput> = first.into_iter().chain(remaining).collect();
+        let last = outputs.pop();
+
+        for output in outputs {
+            self.patch_check_run(owner, repo, request.check_run_id, &CheckRunBody { output: Some(output), ..empty_body() }).await?;
+        }
+        let body = CheckRunBody {
+            status: request.status,
+            conclusion: request.conclusion,
+            details_url: request.details_url.as_deref(),
+            external_id: request.external_id.as_deref(),
+            output: last,
+            ..empty_body()
+        };
+        self.patch_check_run(owner, repo, request.check_run_id, &body).await
+    }
+
+    /// Set a commit status, for reporting without app authentication.
+    pub async fn create_commit_status(&self, request: CommitStatusRequest) -> Result<(), GitHubError> {
+        #[derive(Serialize)]
+        struct NewStatus<'a> {
+            state: CommitState,
+            context: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            description: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            target_url: Option<&'a str>,
+        }
+
+        let status = NewStatus {
+            state: request.state,
+            context: &request.context,
+            description: request
+                .description
+                .map(|description| description.chars().take(MAX_STATUS_DESCRIPTION_CHARS).collect()),
+            target_url: request.target_url.as_deref(),
+        };
+        let create = self
+            .repo_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, &format!("/statuses/{}", request.sha))
+            .await?
+            .json(&status);
+        send::<Value>(create, "create a commit status").await?;
+        Ok(())
+    }
+
+    async fn patch_check_run(&self, owner: &str, repo: &str, id: u64, body: &CheckRunBody<'_>) -> Result<CheckRun, GitHubError> {
+        let update = self
+            .repo_request(reqwest::Method::PATCH, owner, repo, &format!("/check-runs/{}", id))
+            .await?
+            .json(body);
+        send(update, "update a check run").await
+    }
+}
+
+fn empty_body<'a>() -> CheckRunBody<'a> {
+    CheckRunBody {
+        name: None,
+        head_sha: None,
+        status: None,
+        conclusion: None,
+        details_url: None,
+        external_id: None,
+        output: None,
+    }
+}
+
+/// Split `output` into copies carrying at most 50 annotations each: the
+/// first and the rest, in order.
+fn split_annotations(output: Option<CheckRunOutput>) -> (Option<CheckRunOutput>, Vec<CheckRunOutput>) {
+    let Some(mut output) = output else {
+        return (None, Vec::new());
+    };
+    let mut batches: Vec<Vec<CheckAnnotation>> = Vec::new();
+    let mut annotations = std::mem::take(&mut output.annotations);
+    while annotations.len() > MAX_ANNOTATIONS_PER_REQUEST {
+        let rest = annotations.split_off(MAX_ANNOTATIONS_PER_REQUEST);
+        batches.push(annotations);
+        annotations = rest;
+    }
+    batches.push(annotations);
+
+    let mut outputs = batches.into_iter().map(|annotations| CheckRunOutput {
+        annotations,
+        ..output.clone()
+    });
+    let first = outputs.next();
+    (first, outputs.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::GitHubConfig;
+
+    #[tokio::test]
+    async fn test_update_check_run_batches_annotations() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/octo/app/check-runs/55"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 55,
+                "html_url": "https://github.com/octo/app/runs/55",
+            })))
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/repos/octo/app/statuses/abc123"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "id": 1 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let app = GitHubApp::new(GitHubConfig {
+            app_id: String::new(),
+            private_key_path: String::new(),
+            webhook_secret: String::new(),
+            base_url: Some(server.uri()),
+            access_token: Some("ghp_token".to_string()),
+            installation_id: None,
+        })
+        .unwrap();
+        let annotations = (1..=120)
+            .map(|line| CheckAnnotation {
+                path: "src/lib.rs".to_string(),
+                start_line: line,
+                end_line: line,
+                annotation_level: AnnotationLevel::Warning,
+                message: format!("Finding on line {}", line),
+                title: None,
+                raw_details: None,
+            })
+            .collect();
+        let check_run = app
+            .update_check_run(UpdateCheckRunRequest {
+                repo_owner: "octo".to_string(),
+                repo_name: "app".to_string(),
+                check_run_id: 55,
+                status: Some(CheckStatus::Completed),
+                conclusion: Some(CheckConclusion::Failure),
+                details_url: None,
+                external_id: None,
+                output: Some(CheckRunOutput {
+                    title: "120 findings".to_string(),
+                    summary: "Review found issues".to_string(),
+                    text: None,
+                    annotations,
+                }),
+            })
+            .await
+            .unwrap();
+        assert_eq!(check_run.id, 55);
+        app.create_commit_status(CommitStatusRequest {
+            repo_owner: "octo".to_string(),
+            repo_name: "app".to_string(),
+            sha: "abc123".to_string(),
+            state: CommitState::Failure,
+            context: "codex".to_string(),
+            description: Some("x".repeat(200)),
+            target_url: None,
+        })
+        .await
+        .unwrap();
+
+        // 50 + 50 + 20 annotations; only the last update concludes the run
+        let requests = server.received_requests().await.unwrap();
+        let bodies: Vec<Value> = requests.iter().map(|request| serde_json::from_slice(&request.body).unwrap()).collect();
+        let counts: Vec<usize> = bodies[..3].iter().map(|body| body["output"]["annotations"].as_array().unwrap().len()).collect();
+        assert_eq!(counts, vec![50, 50, 20]);
+        assert!(bodies[..2].iter().all(|body| body.get("conclusion").is_none()));
+        assert_eq!(bodies[2]["conclusion"], "failure");
+        assert_eq!(bodies[2]["output"]["annotations"][19]["end_line"], 120);
+        assert_eq!(bodies[3]["state"], "failure");
+        assert_eq!(bodies[3]["description"].as_str().unwrap().len(), 140);
+    }
+}
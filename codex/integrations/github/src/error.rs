@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Errors returned by the GitHub integration and the other forge clients.
+#[derive(Debug, Error)]
+pub enum GitHubError {
+    #[error("no GitHub credentials configured: set app_id and private_key_path, or access_token")]
+    MissingCredentials,
+
+    #[error("failed to read GitHub App private key {path}: {source}")]
+    PrivateKeyRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("invalid GitHub App private key: {0}")]
+    InvalidPrivateKey(#[source] jsonwebtoken::errors::Error),
+
+    #[error("failed to sign GitHub App JWT: {0}")]
+    Jwt(#[source] jsonwebtoken::errors::Error),
+
+    #[error("GitHub App is not installed on {0}")]
+    NotInstalled(String),
+
+    #[error("forge API request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The API answered with a non-success status.
+    #[error("forge API failed to {action}: {status} {message}")]
+    Api {
+        action: String,
+        status: u16,
+        message: String,
+    },
+
+    /// A GraphQL request returned errors.
+    #[error("GitHub GraphQL API failed to {action}: {message}")]
+    GraphQl { action: String, message: String },
+
+    /// A forge's `base_url` cannot be used as an API base.
+    #[error("invalid API URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("invalid webhook payload: {0}")]
+    InvalidPayload(#[from] serde_json::Error),
+
+    #[error("invalid webhook signature")]
+    InvalidSignature,
+
+    /// One or more webhook handlers failed.
+    #[error("webhook handler failed for {event}: {message}")]
+    Handler { event: String, message: String },
+}
@@ -0,0 +1,302 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::gitea::{GiteaClient, GiteaConfig};
+use crate::gitlab::{GitLabClient, GitLabConfig};
+use crate::webhook::{self, WebhookEvent};
+use crate::{
+    CreateBranchRequest, CreateBranchResponse, CreateCommitRequest, CreateCommitResponse, CreatePullRequestRequest,
+    CreatePullRequestResponse, GitHubApp, GitHubConfig, GitHubError, PublishReviewRequest, PublishReviewResponse,
+    RepositoryData,
+};
+
+/// Hosting service of a repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+    Gitea,
+}
+
+/// The operations workflows need from wherever a repository is hosted.
+///
+/// Requests name repositories by `repo_owner` and `repo_name`; on GitLab the
+/// owner is the full namespace, e.g. `group/subgroup`. Pull requests are
+/// merge requests on GitLab, numbered by their `iid`.
+#[async_trait]
+pub trait Forge: Send + Sync {
+    fn kind(&self) -> ForgeKind;
+
+    /// Create a branch pointing at the head of `base_branch`.
+    async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, GitHubError>;
+
+    /// Commit file changes on top of `branch_name`, adding or replacing each file.
+    async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, GitHubError>;
+
+    /// Open a pull request, or a merge request on GitLab.
+    async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, GitHubError>;
+
+    /// Publish a review, with comments on the changed lines of the diff.
+    async fn publish_review(&self, request: PublishReviewRequest) -> Result<PublishReviewResponse, GitHubError>;
+
+    /// Header naming the webhook event.
+    fn event_header(&self) -> &'static str;
+
+    /// Header authenticating the webhook delivery.
+    fn signature_header(&self) -> &'static str;
+
+    /// Authenticate a webhook delivery and parse it. `event` and `signature`
+    /// are the values of [`Self::event_header`] and [`Self::signature_header`].
+    fn parse_webhook(&self, event: &str, payload: &[u8], signature: &str) -> Result<ForgeEvent, GitHubError>;
+}
+
+/// Credentials for one of the forges, tagged by `kind`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ForgeConfig {
+    GitHub(GitHubConfig),
+    GitLab(GitLabConfig),
+    Gitea(GiteaConfig),
+}
+
+impl ForgeConfig {
+    pub fn kind(&self) -> ForgeKind {
+        match self {
+            Self::GitHub(_) => ForgeKind::GitHub,
+            Self::GitLab(_) => ForgeKind::GitLab,
+            Self::Gitea(_) => ForgeKind::Gitea,
+        }
+    }
+
+    /// Create the client for the configured forge.
+    pub fn connect(self) -> Result<Arc<dyn Forge>, GitHubError> {
+        Ok(match self {
+            Self::GitHub(config) => Arc::new(GitHubApp::new(config)?),
+            Self::GitLab(config) => Arc::new(GitLabClient::new(config)?),
+            Self::Gitea(config) => Arc::new(GiteaClient::new(config)?),
+        })
+    }
+}
+
+/// A webhook delivery, the same for every forge.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ForgeEvent {
+    PullRequest(ForgePullRequestEvent),
+    Push(ForgePushEvent),
+    Comment(ForgeCommentEvent),
+    /// Any other event, by the forge's name for it.
+    Other { event: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeRepository {
+    /// User, organization or, on GitLab, full namespace.
+    pub owner: String,
+    pub name: String,
+    pub clone_url: String,
+    pub html_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullRequestAction {
+    Opened,
+    Reopened,
+    /// New commits were pushed.
+    Synchronized,
+    /// The title, description or target branch changed.
+    Edited,
+    Closed,
+    Merged,
+    /// Any other action, by the forge's name for it.
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgePullRequestEvent {
+    pub action: PullRequestAction,
+    pub repository: ForgeRepository,
+    pub number: u32,
+    pub title: String,
+    pub body: Option<String>,
+    pub html_url: String,
+    pub head_branch: String,
+    pub head_sha: String,
+    pub base_branch: String,
+    pub draft: bool,
+    /// Login of the user who triggered the event.
+    pub sender: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgePushEvent {
+    pub repository: ForgeRepository,
+    /// Full ref that was pushed, e.g. `refs/heads/main`.
+    pub ref_name: String,
+    pub before: String,
+    pub after: String,
+    pub sender: String,
+}
+
+impl ForgePushEvent {
+    /// The branch name for pushes to `refs/heads/*`.
+    pub fn branch(&self) -> Option<&str> {
+        self.ref_name.strip_prefix("refs/heads/")
+    }
+}
+
+/// A comment on an issue or pull request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeCommentEvent {
+    pub repository: ForgeRepository,
+    /// Set when the comment is on a pull request.
+    pub pull_request: Option<u32>,
+    pub body: String,
+    pub author: String,
+}
+
+/// Compare a shared-secret token without leaking where it differs.
+pub(crate) fn tokens_match(expected: &str, actual: &str) -> bool {
+    expected.len() == actual.len() && expected.bytes().zip(actual.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[async_trait]
+impl Forge for GitHubApp {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitHub
+    }
+
+    async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, GitHubError> {
+        GitHubApp::create_branch(self, request).await
+    }
+
+    async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, GitHubError> {
+        GitHubApp::create_commit(self, request).await
+    }
+
+    async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, GitHubError> {
+        GitHubApp::create_pull_request(self, request).await
+    }
+
+    async fn publish_review(&self, request: PublishReviewRequest) -> Result<PublishReviewResponse, GitHubError> {
+        GitHubApp::publish_review(self, request).await
+    }
+
+    fn event_header(&self) -> &'static str {
+        webhook::EVENT_HEADER
+    }
+
+    fn signature_header(&self) -> &'static str {
+        webhook::SIGNATURE_HEADER
+    }
+
+    /// Registered [`crate::WebhookHandler`]s are not called; use
+    /// [`GitHubApp::handle_webhook`] for that.
+    fn parse_webhook(&self, event: &str, payload: &[u8], signature: &str) -> Result<ForgeEvent, GitHubError> {
+        webhook::verify_signature(self.webhook_secret().as_bytes(), payload, signature)?;
+        Ok(match WebhookEvent::parse(event, payload)? {
+            WebhookEvent::PullRequest(event) => {
+                let pull_request = &event.pull_request;
+                ForgeEvent::PullRequest(ForgePullRequestEvent {
+                    action: match event.action.as_str() {
+                        "opened" => PullRequestAction::Opened,
+                        "reopened" => PullRequestAction::Reopened,
+                        "synchronize" => PullRequestAction::Synchronized,
+                        "edited" => PullRequestAction::Edited,
+                        "closed" if pull_request.merged => PullRequestAction::Merged,
+                        "closed" => PullRequestAction::Closed,
+                        other => PullRequestAction::Other(other.to_string()),
+                    },
+                    repository: github_repository(&event.repository),
+                    number: event.number,
+                    title: pull_request.title.clone(),
+                    body: pull_request.body.clone(),
+                    html_url: pull_request.html_url.clone(),
+                    head_branch: pull_request.head.ref_name.clone(),
+                    head_sha: pull_request.head.sha.clone(),
+                    base_branch: pull_request.base.ref_name.clone(),
+                    draft: pull_request.draft,
+                    sender: event.sender.login.clone(),
+                })
+            }
+            WebhookEvent::Push(event) => ForgeEvent::Push(ForgePushEvent {
+                repository: github_repository(&event.repository),
+                ref_name: event.ref_name,
+                before: event.before,
+                after: event.after,
+                sender: event.sender.login,
+            }),
+            WebhookEvent::IssueComment(event) if event.action == "created" => ForgeEvent::Comment(ForgeCommentEvent {
+                repository: github_repository(&event.repository),
+                pull_request: event.issue.is_pull_request().then_some(event.issue.number),
+                body: event.comment.body,
+                author: event.comment.user.login,
+            }),
+            other => ForgeEvent::Other {
+                event: other.name().to_string(),
+            },
+        })
+    }
+}
+
+fn github_repository(repository: &RepositoryData) -> ForgeRepository {
+    ForgeRepository {
+        owner: repository.owner.login.clone(),
+        name: repository.name.clone(),
+        clone_url: repository.clone_url.clone(),
+        html_url: repository.html_url.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    use super::*;
+
+    const PULL_REQUEST_OPENED: &[u8] = include_bytes!("../tests/fixtures/webhooks/pull_request_opened.json");
+    const ISSUE_COMMENT_CREATED: &[u8] = include_bytes!("../tests/fixtures/webhooks/issue_comment_created.json");
+
+    #[test]
+    fn test_github_webhooks_parse_into_forge_events() {
+        let config = ForgeConfig::GitHub(GitHubConfig {
+            app_id: String::new(),
+            private_key_path: String::new(),
+            webhook_secret: "secret".to_string(),
+            base_url: None,
+            access_token: Some("ghp_token".to_string()),
+            installation_id: None,
+        });
+        assert_eq!(config.kind(), ForgeKind::GitHub);
+        let forge = config.connect().unwrap();
+        let sign = |payload: &[u8]| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+            mac.update(payload);
+            format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+        };
+
+        let event = forge.parse_webhook("pull_request", PULL_REQUEST_OPENED, &sign(PULL_REQUEST_OPENED)).unwrap();
+        let ForgeEvent::PullRequest(pull_request) = event else {
+            panic!("expected a pull request event, got {:?}", event);
+        };
+        assert_eq!(pull_request.action, PullRequestAction::Opened);
+        assert_eq!(pull_request.number, 1347);
+        assert_eq!(pull_request.repository.owner, "octocat");
+        assert_eq!(pull_request.repository.name, "Hello-World");
+        assert_eq!(pull_request.head_sha, "6dcb09b5b57875f334f61aebed695e2e4193db5e");
+
+        let event = forge.parse_webhook("issue_comment", ISSUE_COMMENT_CREATED, &sign(ISSUE_COMMENT_CREATED)).unwrap();
+        assert!(matches!(event, ForgeEvent::Comment(ForgeCommentEvent { pull_request: Some(1347), .. })));
+
+        let error = forge.parse_webhook("pull_request", PULL_REQUEST_OPENED, "sha256=00").unwrap_err();
+        assert!(matches!(error, GitHubError::InvalidSignature));
+        assert!(tokens_match("token", "token"));
+        assert!(!tokens_match("token", "tokem"));
+        assert!(!tokens_match("token", "tokens"));
+    }
+}
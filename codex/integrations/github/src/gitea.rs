@@ -0,0 +1,655 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::forge::{
+    Forge, ForgeCommentEvent, ForgeEvent, ForgeKind, ForgePullRequestEvent, ForgePushEvent, ForgeRepository, PullRequestAction,
+};
+use crate::gitlab::api_base_url;
+use crate::review::{anchor, marked_body, patch_lines, review_body};
+use crate::webhook::verify_signature;
+use crate::{
+    api_error, send, CreateBranchRequest, CreateBranchResponse, CreateCommitRequest, CreateCommitResponse,
+    CreatePullRequestRequest, CreatePullRequestResponse, GitHubError, PublishReviewRequest, PublishReviewResponse,
+    ReviewVerdict, USER_AGENT,
+};
+
+/// Header naming the event, e.g. `pull_request`.
+pub const EVENT_HEADER: &str = "X-Gitea-Event";
+/// Header carrying the hex HMAC-SHA256 of the payload.
+pub const SIGNATURE_HEADER: &str = "X-Gitea-Signature";
+/// Title prefixes Gitea treats as marking a work-in-progress pull request.
+const WIP_PREFIXES: &[&str] = &["WIP:", "[WIP]"];
+
+/// Gitea (or Forgejo) configuration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiteaConfig {
+    /// REST API root, e.g. `https://gitea.example.com/api/v1`.
+    pub base_url: String,
+    /// Access token with repository read and write scopes.
+    pub access_token: String,
+    /// Secret configured on the webhook.
+    pub webhook_secret: String,
+}
+
+/// Gitea REST API client.
+#[derive(Clone)]
+pub struct GiteaClient {
+    config: GiteaConfig,
+    base_url: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl GiteaClient {
+    /// Fails if `base_url` is not an absolute http(s) URL.
+    pub fn new(config: GiteaConfig) -> Result<Self, GitHubError> {
+        let base_url = api_base_url(&config.base_url)?;
+        Ok(Self {
+            config,
+            base_url,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// An authenticated request to `/repos/{owner}/{repo}/{path}`; each
+    /// element of `path` is one escaped segment.
+    fn repo_request(&self, method: reqwest::Method, owner: &str, repo: &str, path: &[&str]) -> reqwest::RequestBuilder {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked when the client was created")
+            .pop_if_empty()
+            .extend(["repos", owner, repo])
+            .extend(path);
+        self.client
+            .request(method, url)
+            .header("Authorization", format!("token {}", self.config.access_token))
+            .header("User-Agent", USER_AGENT)
+    }
+
+    /// Blob SHA of `path` on `branch`, if the file exists.
+    async fn file_sha(&self, owner: &str, repo: &str, branch: &str, path: &str) -> Result<Option<String>, GitHubError> {
+        #[derive(Deserialize)]
+        struct Contents {
+            sha: String,
+        }
+
+        let mut segments = vec!["contents"];
+        segments.extend(path.split('/'));
+        let get = self.repo_request(reqwest::Method::GET, owner, repo, &segments).query(&[("ref", branch)]);
+        let response = get.send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(api_error(&format!("look up {}", path), response).await);
+        }
+        let contents: Contents = response.json().await?;
+        Ok(Some(contents.sha))
+    }
+}
+
+#[async_trait]
+impl Forge for GiteaClient {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::Gitea
+    }
+
+    async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, GitHubError> {
+        #[derive(Deserialize)]
+        struct Branch {
+            name: String,
+            commit: Commit,
+        }
+
+        #[derive(Deserialize)]
+        struct Commit {
+            id: String,
+        }
+
+        let create = self
+            .repo_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, &["branches"])
+            .json(&json!({
+                "new_branch_name": request.branch_name,
+                "old_branch_name": request.base_branch,
+            }));
+        let branch: Branch = send(create, "create the branch").await?;
+        Ok(CreateBranchResponse {
+            branch_ref: format!("refs/heads/{}", branch.name),
+            object_sha: branch.commit.id,
+        })
+    }
+
+    async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+
+        // Updates must name the blob they replace
+        let mut files = Vec::new();
+        for change in &request.changes {
+            let content = if change.encoding.as_deref() == Some("base64") {
+                change.content.clone()
+            } else {
+                general_purpose::STANDARD.encode(change.content.as_bytes())
+            };
+            let file = match self.file_sha(owner, repo, &request.branch_name, &change.path).await? {
+                Some(sha) => json!({ "operation": "update", "path": change.path, "content": content, "sha": sha }),
+                None => json!({ "operation": "create", "path": change.path, "content": content }),
+            };
+            files.push(file);
+        }
+
+        let mut commit = json!({
+            "branch": request.branch_name,
+            "message": request.message,
+            "files": files,
+        });
+        if let (Some(name), Some(email)) = (&request.author_name, &request.author_email) {
+            commit["author"] = json!({ "name": name, "email": email });
+        }
+
+        #[derive(Deserialize)]
+        struct FilesResponse {
+            commit: FileCommit,
+        }
+
+        #[derive(Deserialize)]
+        struct FileCommit {
+            sha: String,
+            tree: Tree,
+        }
+
+        #[derive(Deserialize)]
+        struct Tree {
+            sha: String,
+        }
+
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, &["contents"]).json(&commit);
+        let response: FilesResponse = send(create, "create a commit").await?;
+        Ok(CreateCommitResponse {
+            commit_sha: response.commit.sha,
+            tree_sha: response.commit.tree.sha,
+        })
+    }
+
+    /// Drafts are marked with Gitea's `WIP:` title prefix.
+    async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, GitHubError> {
+        let title = if request.draft.unwrap_or(false) {
+            format!("{} {}", WIP_PREFIXES[0], request.title)
+        } else {
+            request.title
+        };
+
+        #[derive(Deserialize)]
+        struct PullRequest {
+            number: u32,
+            url: String,
+            html_url: String,
+        }
+
+        let create = self
+            .repo_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, &["pulls"])
+            .json(&json!({
+                "title": title,
+                "body": request.body,
+                "head": request.head_branch,
+                "base": request.base_branch,
+            }));
+        let pull_request: PullRequest = send(create, "create the pull request").await?;
+        Ok(CreatePullRequestResponse {
+            pr_number: pull_request.number,
+            pr_url: pull_request.url,
+            html_url: pull_request.html_url,
+        })
+    }
+
+    /// Comments are posted on single lines of the diff with the review. Gitea
+    /// cannot resolve review comments through its API, so comments from
+    /// earlier runs are left as they are.
+    async fn publish_review(&self, request: PublishReviewRequest) -> Result<PublishReviewResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+        let diff_name = format!("{}.diff", request.pr_number);
+
+        let get_diff = self.repo_request(reqwest::Method::GET, owner, repo, &["pulls", &diff_name]);
+        let response = get_diff.send().await?;
+        if !response.status().is_success() {
+            return Err(api_error("get the pull request diff", response).await);
+        }
+        let commentable = diff_lines(&response.text().await?);
+
+        let mut comments = Vec::new();
+        let mut unanchored = Vec::new();
+        let mut reported = HashSet::new();
+        for comment in &request.review.comments {
+            if !reported.insert(comment.fingerprint.as_str()) {
+                continue;
+            }
+            match anchor(comment, commentable.get(&comment.path)) {
+                Some((_, line)) => comments.push(json!({
+                    "path": comment.path,
+                    "body": marked_body(comment),
+                    "new_position": line,
+                })),
+                None => unanchored.push(comment),
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct CreatedReview {
+            id: u64,
+            html_url: String,
+        }
+
+        let created = comments.len();
+        let submit = self
+            .repo_request(reqwest::Method::POST, owner, repo, &["pulls", &request.pr_number.to_string(), "reviews"])
+            .json(&json!({
+                "commit_id": request.commit_sha,
+                "body": review_body(&request.review.summary, &unanchored),
+                "event": match request.review.verdict {
+                    ReviewVerdict::Approve => "APPROVED",
+                    ReviewVerdict::RequestChanges => "REQUEST_CHANGES",
+                    ReviewVerdict::Comment => "COMMENT",
+                },
+                "comments": comments,
+            }));
+        let review: CreatedReview = send(submit, "submit the pull request review").await?;
+
+        Ok(PublishReviewResponse {
+            review_id: review.id,
+            html_url: review.html_url,
+            created,
+            updated: 0,
+            resolved: 0,
+        })
+    }
+
+    fn event_header(&self) -> &'static str {
+        EVENT_HEADER
+    }
+
+    fn signature_header(&self) -> &'static str {
+        SIGNATURE_HEADER
+    }
+
+    fn parse_webhook(&self, event: &str, payload: &[u8], signature: &str) -> Result<ForgeEvent, GitHubError> {
+        // Gitea sends the same HMAC as GitHub, without the `sha256=` prefix
+        verify_signature(self.config.webhook_secret.as_bytes(), payload, &format!("sha256={}", signature.trim()))?;
+        Ok(match event {
+            "pull_request" => {
+                let hook: PullRequestHook = serde_json::from_slice(payload)?;
+                let pull_request = hook.pull_request;
+                let action = match hook.action.as_str() {
+                    "opened" => PullRequestAction::Opened,
+                    "reopened" => PullRequestAction::Reopened,
+                    "synchronized" => PullRequestAction::Synchronized,
+                    "edited" => PullRequestAction::Edited,
+                    "closed" if pull_request.merged => PullRequestAction::Merged,
+                    "closed" => PullRequestAction::Closed,
+                    other => PullRequestAction::Other(other.to_string()),
+                };
+                let draft = pull_request.draft || WIP_PREFIXES.iter().any(|prefix| pull_request.title.starts_with(prefix));
+                ForgeEvent::PullRequest(ForgePullRequestEvent {
+                    action,
+                    repository: hook.repository.into(),
+                    number: hook.number,
+                    title: pull_request.title,
+                    body: pull_request.body,
+                    html_url: pull_request.html_url,
+                    head_branch: pull_request.head.ref_name,
+                    head_sha: pull_request.head.sha,
+                    base_branch: pull_request.base.ref_name,
+                    draft,
+                    sender: hook.sender.login,
+                })
+            }
+            "push" => {
+                let hook: PushHook = serde_json::from_slice(payload)?;
+                ForgeEvent::Push(ForgePushEvent {
+                    repository: hook.repository.into(),
+                    ref_name: hook.ref_name,
+                    before: hook.before,
+                    after: hook.after,
+                    sender: hook.sender.login,
+                })
+            }
+            // Comments on pull requests are sent as `pull_request_comment` by newer versions
+            "issue_comment" | "pull_request_comment" => {
+                let hook: IssueCommentHook = serde_json::from_slice(payload)?;
+                if hook.action != "created" {
+                    return Ok(ForgeEvent::Other { event: event.to_string() });
+                }
+                let on_pull_request = hook.is_pull || hook.issue.pull_request.is_some();
+                ForgeEvent::Comment(ForgeCommentEvent {
+                    repository: hook.repository.into(),
+                    pull_request: on_pull_request.then_some(hook.issue.number),
+                    body: hook.comment.body,
+                    author: hook.comment.user.login,
+                })
+            }
+            _ => ForgeEvent::Other { event: event.to_string() },
+        })
+    }
+}
+
+/// New-file line numbers covered by each file of a unified diff.
+fn diff_lines(diff: &str) -> HashMap<String, BTreeSet<u32>> {
+    let mut files = HashMap::new();
+    let mut current: Option<(String, String)> = None;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            if let Some((path, patch)) = current.take() {
+                files.insert(path, patch_lines(&patch));
+            }
+            continue;
+        }
+        if let Some(path) = line.strip_prefix("+++ ") {
+            // Deleted files have no new version to comment on
+            current = path.strip_prefix("b/").map(|path| (path.to_string(), String::new()));
+            continue;
+        }
+        if let Some((_, patch)) = current.as_mut() {
+            patch.push_str(line);
+            patch.push('\n');
+        }
+    }
+    if let Some((path, patch)) = current {
+        files.insert(path, patch_lines(&patch));
+    }
+    files
+}
+
+#[derive(Deserialize)]
+struct HookUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct HookRepository {
+    name: String,
+    owner: HookUser,
+    clone_url: String,
+    html_url: String,
+}
+
+impl From<HookRepository> for ForgeRepository {
+    fn from(repository: HookRepository) -> Self {
+        ForgeRepository {
+            owner: repository.owner.login,
+            name: repository.name,
+            clone_url: repository.clone_url,
+            html_url: repository.html_url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PullRequestHook {
+    action: String,
+    number: u32,
+    pull_request: HookPullRequest,
+    repository: HookRepository,
+    sender: HookUser,
+}
+
+#[derive(Deserialize)]
+struct HookPullRequest {
+    title: String,
+    body: Option<String>,
+    html_url: String,
+    head: HookBranch,
+    base: HookBranch,
+    #[serde(default)]
+    merged: bool,
+    /// Reported by versions with draft pull requests; older ones only use
+    /// the title prefix.
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Deserialize)]
+struct HookBranch {
+    #[serde(rename = "ref")]
+    ref_name: String,
+    sha: String,
+}
+
+#[derive(Deserialize)]
+struct PushHook {
+    #[serde(rename = "ref")]
+    ref_name: String,
+    before: String,
+    after: String,
+    repository: HookRepository,
+    sender: HookUser,
+}
+
+#[derive(Deserialize)]
+struct IssueCommentHook {
+    action: String,
+    issue: HookIssue,
+    comment: HookComment,
+    repository: HookRepository,
+    #[serde(default)]
+    is_pull: bool,
+}
+
+#[derive(Deserialize)]
+struct HookIssue {
+    number: u32,
+    #[serde(default)]
+    pull_request: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct HookComment {
+    body: String,
+    user: HookUser,
+}
+
+#[cfg(test)]
+mod tests {
+    use hmac::{Hmac, Mac};
+    use serde_json::Value;
+    use sha2::Sha256;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{FileChange, PullRequestReview, ReviewComment};
+
+    const PULL_REQUEST_SYNCHRONIZED: &[u8] = include_bytes!("../tests/fixtures/gitea/pull_request_synchronized.json");
+    const PUSH: &[u8] = include_bytes!("../tests/fixtures/gitea/push.json");
+    const ISSUE_COMMENT: &[u8] = include_bytes!("../tests/fixtures/gitea/issue_comment.json");
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!("{}/tests/fixtures/gitea/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+    }
+
+    fn json_fixture(name: &str) -> Value {
+        serde_json::from_str(&fixture(name)).unwrap()
+    }
+
+    fn config(base_url: String) -> GiteaConfig {
+        GiteaConfig {
+            base_url,
+            access_token: "gitea-token".to_string(),
+            webhook_secret: "hook-secret".to_string(),
+        }
+    }
+
+    async fn mount(server: &MockServer, verb: &str, route: &str, response: ResponseTemplate) {
+        Mock::given(method(verb))
+            .and(path(route))
+            .and(header("Authorization", "token gitea-token"))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_gitea_branch_commit_pull_request_and_review() {
+        let server = MockServer::start().await;
+        let repo = "/api/v1/repos/infra/deployer";
+        let json = |status: u16, name: &str| ResponseTemplate::new(status).set_body_json(json_fixture(name));
+        mount(&server, "POST", &format!("{}/branches", repo), json(201, "create_branch.json")).await;
+        Mock::given(method("GET"))
+            .and(path(format!("{}/contents/src/main.rs", repo)))
+            .and(query_param("ref", "codex/retry"))
+            .respond_with(json(200, "contents.json"))
+            .mount(&server)
+            .await;
+        mount(
+            &server,
+            "GET",
+            &format!("{}/contents/src/retry.rs", repo),
+            ResponseTemplate::new(404).set_body_json(json!({ "message": "object does not exist" })),
+        )
+        .await;
+        mount(&server, "POST", &format!("{}/contents", repo), json(201, "change_files.json")).await;
+        mount(&server, "POST", &format!("{}/pulls", repo), json(201, "create_pull_request.json")).await;
+        mount(&server, "GET", &format!("{}/pulls/3.diff", repo), ResponseTemplate::new(200).set_body_string(fixture("pull_request.diff"))).await;
+        mount(&server, "POST", &format!("{}/pulls/3/reviews", repo), json(200, "create_review.json")).await;
+        let client = GiteaClient::new(config(format!("{}/api/v1/", server.uri()))).unwrap();
+
+        let branch = client
+            .create_branch(CreateBranchRequest {
+                repo_owner: "infra".to_string(),
+                repo_name: "deployer".to_string(),
+                branch_name: "codex/retry".to_string(),
+                base_branch: "main".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(branch.branch_ref, "refs/heads/codex/retry");
+        assert_eq!(branch.object_sha, "c7f08b0b5f3a2ad0b5c4d1e8a1a3c2d4e5f60718");
+
+        let commit = client
+            .create_commit(CreateCommitRequest {
+                repo_owner: "infra".to_string(),
+                repo_name: "deployer".to_string(),
+                branch_name: "codex/retry".to_string(),
+                message: "Retry failed deploys".to_string(),
+                changes: vec![
+                    FileChange {
+                        path: "src/main.rs".to_string(),
+                        content: "mod retry;\n".to_string(),
+                        encoding: None,
+                    },
+                    FileChange {
+                        path: "src/retry.rs".to_string(),
+                        content: "pub fn retry() {}\n".to_string(),
+                        encoding: Some("utf-8".to_string()),
+                    },
+                ],
+                author_name: None,
+                author_email: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(commit.commit_sha, "3f1d2c4b5a6978877665544332211aabbccddeef");
+        assert_eq!(commit.tree_sha, "9a8b7c6d5e4f30211203948576abcdef01234567");
+
+        let pull_request = client
+            .create_pull_request(CreatePullRequestRequest {
+                repo_owner: "infra".to_string(),
+                repo_name: "deployer".to_string(),
+                title: "Retry failed deploys".to_string(),
+                body: "Retries each deploy step up to three times".to_string(),
+                head_branch: "codex/retry".to_string(),
+                base_branch: "main".to_string(),
+                draft: Some(true),
+            })
+            .await
+            .unwrap();
+        assert_eq!(pull_request.pr_number, 3);
+        assert_eq!(pull_request.html_url, "https://gitea.example.com/infra/deployer/pulls/3");
+
+        let comment = |path: &str, line: u32, fingerprint: &str| ReviewComment {
+            path: path.to_string(),
+            start_line: None,
+            line: Some(line),
+            body: format!("Finding {}", fingerprint),
+            fingerprint: fingerprint.to_string(),
+        };
+        let review = client
+            .publish_review(PublishReviewRequest {
+                repo_owner: "infra".to_string(),
+                repo_name: "deployer".to_string(),
+                pr_number: 3,
+                commit_sha: "3f1d2c4b5a6978877665544332211aabbccddeef".to_string(),
+                review: PullRequestReview {
+                    summary: "### Codex review: Approved with comments".to_string(),
+                    verdict: ReviewVerdict::Comment,
+                    comments: vec![comment("src/retry.rs", 2, "aaaa1111"), comment("src/main.rs", 40, "bbbb2222")],
+                },
+            })
+            .await
+            .unwrap();
+        assert_eq!(review.review_id, 77);
+        assert_eq!((review.created, review.updated, review.resolved), (1, 0, 0));
+
+        let requests = server.received_requests().await.unwrap();
+        let body = |verb: &str, suffix: &str| -> Value {
+            let request = requests
+                .iter()
+                .find(|request| request.method.as_str() == verb && request.url.path().ends_with(suffix))
+                .unwrap();
+            serde_json::from_slice(&request.body).unwrap()
+        };
+        let files = body("POST", "/contents");
+        assert_eq!(files["files"][0]["operation"], "update");
+        assert_eq!(files["files"][0]["sha"], "4b825dc642cb6eb9a060e54bf8d69288fbee4904");
+        assert_eq!(files["files"][1]["operation"], "create");
+        assert_eq!(files["files"][1]["content"], general_purpose::STANDARD.encode("pub fn retry() {}\n"));
+        assert_eq!(body("POST", "/pulls")["title"], "WIP: Retry failed deploys");
+        let review = body("POST", "/pulls/3/reviews");
+        assert_eq!(review["event"], "COMMENT");
+        assert_eq!(review["comments"].as_array().unwrap().len(), 1);
+        assert_eq!(review["comments"][0]["new_position"], 2);
+        assert!(review["body"].as_str().unwrap().contains("`src/main.rs:40`"));
+    }
+
+    #[test]
+    fn test_gitea_webhooks_parse_into_forge_events() {
+        let client = GiteaClient::new(config("https://gitea.example.com/api/v1".to_string())).unwrap();
+        let sign = |payload: &[u8]| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"hook-secret").unwrap();
+            mac.update(payload);
+            hex::encode(mac.finalize().into_bytes())
+        };
+
+        let event = client
+            .parse_webhook("pull_request", PULL_REQUEST_SYNCHRONIZED, &sign(PULL_REQUEST_SYNCHRONIZED))
+            .unwrap();
+        let ForgeEvent::PullRequest(pull_request) = event else {
+            panic!("expected a pull request event, got {:?}", event);
+        };
+        assert_eq!(pull_request.action, PullRequestAction::Synchronized);
+        assert_eq!(pull_request.number, 3);
+        assert_eq!(pull_request.repository.owner, "infra");
+        assert_eq!(pull_request.head_branch, "codex/retry");
+        assert_eq!(pull_request.head_sha, "3f1d2c4b5a6978877665544332211aabbccddeef");
+        assert!(pull_request.draft);
+
+        let event = client.parse_webhook("push", PUSH, &sign(PUSH)).unwrap();
+        assert!(matches!(event, ForgeEvent::Push(ref push) if push.branch() == Some("main") && push.sender == "alice"));
+
+        let event = client.parse_webhook("issue_comment", ISSUE_COMMENT, &sign(ISSUE_COMMENT)).unwrap();
+        assert!(matches!(event, ForgeEvent::Comment(ForgeCommentEvent { pull_request: Some(3), .. })));
+
+        assert!(matches!(
+            client.parse_webhook("push", PUSH, &sign(b"something else")),
+            Err(GitHubError::InvalidSignature)
+        ));
+
+        let diff = fixture("pull_request.diff");
+        let lines = diff_lines(&diff);
+        assert_eq!(lines["src/retry.rs"], BTreeSet::from([1, 2, 3, 4]));
+        assert_eq!(lines["src/main.rs"], BTreeSet::from([1, 2, 3, 4]));
+        assert!(!lines.contains_key("src/old.rs"));
+    }
+}
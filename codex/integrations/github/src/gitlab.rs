@@ -0,0 +1,786 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::forge::{
+    tokens_match, Forge, ForgeCommentEvent, ForgeEvent, ForgeKind, ForgePullRequestEvent, ForgePushEvent, ForgeRepository,
+    PullRequestAction,
+};
+use crate::review::{anchor, marked_body, marker_fingerprint, patch_lines, review_body};
+use crate::{
+    api_error, send, CreateBranchRequest, CreateBranchResponse, CreateCommitRequest, CreateCommitResponse,
+    CreatePullRequestRequest, CreatePullRequestResponse, GitHubError, PublishReviewRequest, PublishReviewResponse,
+    ReviewVerdict, USER_AGENT,
+};
+
+const DEFAULT_BASE_URL: &str = "https://gitlab.com/api/v4";
+/// Header naming the event, e.g. `Merge Request Hook`.
+pub const EVENT_HEADER: &str = "X-Gitlab-Event";
+/// Header carrying the webhook's secret token as configured, in plain text.
+pub const TOKEN_HEADER: &str = "X-Gitlab-Token";
+/// Largest page size the API accepts.
+const PER_PAGE: usize = 100;
+
+/// GitLab configuration, for gitlab.com or a self-managed instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabConfig {
+    /// REST API root, e.g. `https://gitlab.example.com/api/v4`. Defaults to gitlab.com.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Personal, group or project access token with the `api` scope.
+    pub access_token: String,
+    /// Secret token configured on the webhook.
+    pub webhook_secret: String,
+}
+
+/// GitLab REST API client.
+#[derive(Clone)]
+pub struct GitLabClient {
+    config: GitLabConfig,
+    base_url: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl GitLabClient {
+    /// Fails if `base_url` is not an absolute http(s) URL.
+    pub fn new(config: GitLabConfig) -> Result<Self, GitHubError> {
+        let base_url = api_base_url(config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))?;
+        Ok(Self {
+            config,
+            base_url,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// URL of `/projects/{owner%2Frepo}/{path}`; each element of `path` is
+    /// one escaped segment.
+    fn project_url(&self, owner: &str, repo: &str, path: &[&str]) -> reqwest::Url {
+        let mut url = self.base_url.clone();
+        url.path_segments_mut()
+            .expect("checked when the client was created")
+            .pop_if_empty()
+            .push("projects")
+            .push(&format!("{}/{}", owner, repo))
+            .extend(path);
+        url
+    }
+
+    fn project_request(&self, method: reqwest::Method, owner: &str, repo: &str, path: &[&str]) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, self.project_url(owner, repo, path))
+            .header("PRIVATE-TOKEN", &self.config.access_token)
+            .header("User-Agent", USER_AGENT)
+    }
+
+    /// Every page of a list endpoint.
+    async fn list<T: serde::de::DeserializeOwned>(&self, owner: &str, repo: &str, path: &[&str], action: &str) -> Result<Vec<T>, GitHubError> {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let get = self
+                .project_request(reqwest::Method::GET, owner, repo, path)
+                .query(&[("per_page", PER_PAGE), ("page", page)]);
+            let batch: Vec<T> = send(get, action).await?;
+            let last = batch.len() < PER_PAGE;
+            items.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    async fn file_exists(&self, owner: &str, repo: &str, branch: &str, path: &str) -> Result<bool, GitHubError> {
+        let head = self
+            .project_request(reqwest::Method::HEAD, owner, repo, &["repository", "files", path])
+            .query(&[("ref", branch)]);
+        let response = head.send().await?;
+        match response.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(api_error(&format!("look up {}", path), response).await),
+        }
+    }
+
+    /// Lines of each changed file that comments can be attached to.
+    async fn commentable_lines(&self, owner: &str, repo: &str, iid: &str) -> Result<HashMap<String, BTreeSet<u32>>, GitHubError> {
+        #[derive(Deserialize)]
+        struct Diff {
+            new_path: String,
+            diff: String,
+            #[serde(default)]
+            deleted_file: bool,
+        }
+
+        let diffs: Vec<Diff> = self
+            .list(owner, repo, &["merge_requests", iid, "diffs"], "list the merge request diffs")
+            .await?;
+        Ok(diffs
+            .into_iter()
+            .filter(|diff| !diff.deleted_file)
+            .map(|diff| (diff.new_path, patch_lines(&diff.diff)))
+            .collect())
+    }
+
+    async fn set_discussion_resolved(&self, owner: &str, repo: &str, iid: &str, discussion_id: &str, resolved: bool) -> Result<(), GitHubError> {
+        let update = self
+            .project_request(reqwest::Method::PUT, owner, repo, &["merge_requests", iid, "discussions", discussion_id])
+            .query(&[("resolved", resolved)]);
+        send::<Value>(update, "resolve a discussion").await?;
+        Ok(())
+    }
+}
+
+/// A comment left by an earlier review run: the first note of a discussion.
+struct PreviousDiscussion {
+    id: String,
+    note_id: u64,
+    fingerprint: String,
+    body: String,
+    resolved: bool,
+}
+
+#[async_trait]
+impl Forge for GitLabClient {
+    fn kind(&self) -> ForgeKind {
+        ForgeKind::GitLab
+    }
+
+    async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, GitHubError> {
+        #[derive(Deserialize)]
+        struct Branch {
+            name: String,
+            commit: Commit,
+        }
+
+        #[derive(Deserialize)]
+        struct Commit {
+            id: String,
+        }
+
+        let create = self
+            .project_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, &["repository", "branches"])
+            .query(&[("branch", &request.branch_name), ("ref", &request.base_branch)]);
+        let branch: Branch = send(create, "create the branch").await?;
+        Ok(CreateBranchResponse {
+            branch_ref: format!("refs/heads/{}", branch.name),
+            object_sha: branch.commit.id,
+        })
+    }
+
+    async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+
+        // GitLab needs to be told whether each file is new
+        let mut actions = Vec::new();
+        for change in &request.changes {
+            let exists = self.file_exists(owner, repo, &request.branch_name, &change.path).await?;
+            actions.push(json!({
+                "action": if exists { "update" } else { "create" },
+                "file_path": change.path,
+                "content": change.content,
+                "encoding": if change.encoding.as_deref() == Some("base64") { "base64" } else { "text" },
+            }));
+        }
+
+        let mut commit = json!({
+            "branch": request.branch_name,
+            "commit_message": request.message,
+            "actions": actions,
+        });
+        if let (Some(name), Some(email)) = (&request.author_name, &request.author_email) {
+            commit["author_name"] = json!(name);
+            commit["author_email"] = json!(email);
+        }
+
+        #[derive(Deserialize)]
+        struct CreatedCommit {
+            id: String,
+        }
+
+        let create = self
+            .project_request(reqwest::Method::POST, owner, repo, &["repository", "commits"])
+            .json(&commit);
+        let created: CreatedCommit = send(create, "create a commit").await?;
+        Ok(CreateCommitResponse {
+            commit_sha: created.id,
+            tree_sha: String::new(),
+        })
+    }
+
+    /// Drafts are marked with GitLab's `Draft:` title prefix.
+    async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, GitHubError> {
+        let title = if request.draft.unwrap_or(false) {
+            format!("Draft: {}", request.title)
+        } else {
+            request.title
+        };
+
+        #[derive(Deserialize)]
+        struct MergeRequest {
+            iid: u32,
+            web_url: String,
+        }
+
+        let create = self
+            .project_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, &["merge_requests"])
+            .json(&json!({
+                "source_branch": request.head_branch,
+                "target_branch": request.base_branch,
+                "title": title,
+                "description": request.body,
+            }));
+        let merge_request: MergeRequest = send(create, "create the merge request").await?;
+        let iid = merge_request.iid.to_string();
+        Ok(CreatePullRequestResponse {
+            pr_number: merge_request.iid,
+            pr_url: self
+                .project_url(&request.repo_owner, &request.repo_name, &["merge_requests", &iid])
+                .to_string(),
+            html_url: merge_request.web_url,
+        })
+    }
+
+    /// Comments become diff discussions and the summary a note on the merge
+    /// request. As on GitHub, discussions from earlier runs are updated when
+    /// their finding is reported again and resolved when it is not. GitLab
+    /// has no "request changes", so only approvals change the merge request's
+    /// state.
+    async fn publish_review(&self, request: PublishReviewRequest) -> Result<PublishReviewResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+        let iid = request.pr_number.to_string();
+
+        #[derive(Deserialize)]
+        struct MergeRequest {
+            web_url: String,
+            diff_refs: DiffRefs,
+        }
+
+        #[derive(Deserialize)]
+        struct DiffRefs {
+            base_sha: String,
+            start_sha: String,
+            head_sha: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Discussion {
+            id: String,
+            notes: Vec<Note>,
+        }
+
+        #[derive(Deserialize)]
+        struct Note {
+            id: u64,
+            body: String,
+            #[serde(default)]
+            resolved: bool,
+        }
+
+        let get = self.project_request(reqwest::Method::GET, owner, repo, &["merge_requests", &iid]);
+        let merge_request: MergeRequest = send(get, "get the merge request").await?;
+        let commentable = self.commentable_lines(owner, repo, &iid).await?;
+        let discussions: Vec<Discussion> = self
+            .list(owner, repo, &["merge_requests", &iid, "discussions"], "list the merge request discussions")
+            .await?;
+        let previous: Vec<PreviousDiscussion> = discussions
+            .into_iter()
+            .filter_map(|discussion| {
+                let note = discussion.notes.into_iter().next()?;
+                Some(PreviousDiscussion {
+                    fingerprint: marker_fingerprint(&note.body)?.to_string(),
+                    id: discussion.id,
+                    note_id: note.id,
+                    body: note.body,
+                    resolved: note.resolved,
+                })
+            })
+            .collect();
+        let mut previous_by_fingerprint = HashMap::new();
+        for discussion in &previous {
+            previous_by_fingerprint.entry(discussion.fingerprint.as_str()).or_insert(discussion);
+        }
+
+        let mut unanchored = Vec::new();
+        let mut reported = HashSet::new();
+        let (mut created, mut updated) = (0, 0);
+        for comment in &request.review.comments {
+            if !reported.insert(comment.fingerprint.as_str()) {
+                continue;
+            }
+            let body = marked_body(comment);
+
+            if let Some(discussion) = previous_by_fingerprint.get(comment.fingerprint.as_str()) {
+                if discussion.body != body {
+                    let note_id = discussion.note_id.to_string();
+                    let edit = self
+                        .project_request(
+                            reqwest::Method::PUT,
+                            owner,
+                            repo,
+                            &["merge_requests", &iid, "discussions", &discussion.id, "notes", &note_id],
+                        )
+                        .json(&json!({ "body": body }));
+                    send::<Value>(edit, "update a discussion note").await?;
+                }
+                if discussion.resolved {
+                    self.set_discussion_resolved(owner, repo, &iid, &discussion.id, false).await?;
+                }
+                updated += 1;
+                continue;
+            }
+
+            // GitLab positions a discussion on a single line
+            let Some((_, line)) = anchor(comment, commentable.get(&comment.path)) else {
+                unanchored.push(comment);
+                continue;
+            };
+            let refs = &merge_request.diff_refs;
+            let create = self
+                .project_request(reqwest::Method::POST, owner, repo, &["merge_requests", &iid, "discussions"])
+                .json(&json!({
+                    "body": body,
+                    "position": {
+                        "position_type": "text",
+                        "base_sha": refs.base_sha,
+                        "start_sha": refs.start_sha,
+                        "head_sha": refs.head_sha,
+                        "old_path": comment.path,
+                        "new_path": comment.path,
+                        "new_line": line,
+                    },
+                }));
+            send::<Value>(create, "create a diff discussion").await?;
+            created += 1;
+        }
+
+        let mut resolved = 0;
+        for discussion in &previous {
+            let current = reported.contains(discussion.fingerprint.as_str())
+                && previous_by_fingerprint.get(discussion.fingerprint.as_str()).map(|kept| &kept.id) == Some(&discussion.id);
+            if !current && !discussion.resolved {
+                self.set_discussion_resolved(owner, repo, &iid, &discussion.id, true).await?;
+                resolved += 1;
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct CreatedNote {
+            id: u64,
+        }
+
+        let note = self
+            .project_request(reqwest::Method::POST, owner, repo, &["merge_requests", &iid, "notes"])
+            .json(&json!({ "body": review_body(&request.review.summary, &unanchored) }));
+        let note: CreatedNote = send(note, "comment on the merge request").await?;
+
+        #[derive(Deserialize)]
+        struct Approvals {
+            #[serde(default)]
+            user_has_approved: bool,
+        }
+
+        // Approve, or withdraw an earlier approval, so it follows the latest verdict
+        let approvals = self.project_request(reqwest::Method::GET, owner, repo, &["merge_requests", &iid, "approvals"]);
+        let approvals: Approvals = send(approvals, "read the merge request approvals").await?;
+        match (request.review.verdict == ReviewVerdict::Approve, approvals.user_has_approved) {
+            (true, false) => {
+                let approve = self
+                    .project_request(reqwest::Method::POST, owner, repo, &["merge_requests", &iid, "approve"])
+                    .json(&json!({ "sha": request.commit_sha }));
+                send::<Value>(approve, "approve the merge request").await?;
+            }
+            (false, true) => {
+                let unapprove = self.project_request(reqwest::Method::POST, owner, repo, &["merge_requests", &iid, "unapprove"]);
+                match send::<Value>(unapprove, "withdraw the merge request approval").await {
+                    // Returned when the approval was withdrawn in the meantime
+                    Ok(_) | Err(GitHubError::Api { status: 404, .. }) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            _ => {}
+        }
+
+        Ok(PublishReviewResponse {
+            review_id: note.id,
+            html_url: format!("{}#note_{}", merge_request.web_url, note.id),
+            created,
+            updated,
+            resolved,
+        })
+    }
+
+    fn event_header(&self) -> &'static str {
+        EVENT_HEADER
+    }
+
+    fn signature_header(&self) -> &'static str {
+        TOKEN_HEADER
+    }
+
+    /// GitLab sends the webhook's secret token as is, so `signature` is
+    /// compared with `webhook_secret` rather than verified as an HMAC.
+    fn parse_webhook(&self, event: &str, payload: &[u8], signature: &str) -> Result<ForgeEvent, GitHubError> {
+        if !tokens_match(&self.config.webhook_secret, signature) {
+            return Err(GitHubError::InvalidSignature);
+        }
+        Ok(match event {
+            "Merge Request Hook" => {
+                let hook: MergeRequestHook = serde_json::from_slice(payload)?;
+                let attributes = hook.object_attributes;
+                let action = match attributes.action.as_deref().unwrap_or_default() {
+                    "open" => PullRequestAction::Opened,
+                    "reopen" => PullRequestAction::Reopened,
+                    // `oldrev` is only set when the update pushed commits
+                    "update" if attributes.oldrev.is_some() => PullRequestAction::Synchronized,
+                    "update" => PullRequestAction::Edited,
+                    "close" => PullRequestAction::Closed,
+                    "merge" => PullRequestAction::Merged,
+                    other => PullRequestAction::Other(other.to_string()),
+                };
+                ForgeEvent::PullRequest(ForgePullRequestEvent {
+                    action,
+                    repository: hook.project.into(),
+                    number: attributes.iid,
+                    title: attributes.title,
+                    body: attributes.description,
+                    html_url: attributes.url,
+                    head_branch: attributes.source_branch,
+                    head_sha: attributes.last_commit.id,
+                    base_branch: attributes.target_branch,
+                    draft: attributes.draft,
+                    sender: hook.user.username,
+                })
+            }
+            "Push Hook" => {
+                let hook: PushHook = serde_json::from_slice(payload)?;
+                ForgeEvent::Push(ForgePushEvent {
+                    repository: hook.project.into(),
+                    ref_name: hook.ref_name,
+                    before: hook.before,
+                    after: hook.after,
+                    sender: hook.user_username,
+                })
+            }
+            "Note Hook" => {
+                let hook: NoteHook = serde_json::from_slice(payload)?;
+                ForgeEvent::Comment(ForgeCommentEvent {
+                    repository: hook.project.into(),
+                    pull_request: hook.merge_request.map(|merge_request| merge_request.iid),
+                    body: hook.object_attributes.note,
+                    author: hook.user.username,
+                })
+            }
+            _ => ForgeEvent::Other { event: event.to_string() },
+        })
+    }
+}
+
+/// Parse `base_url` as an API root that paths can be appended to.
+pub(crate) fn api_base_url(base_url: &str) -> Result<reqwest::Url, GitHubError> {
+    let url = reqwest::Url::parse(base_url).map_err(|err| GitHubError::InvalidUrl(format!("{}: {}", base_url, err)))?;
+    if url.cannot_be_a_base() || !matches!(url.scheme(), "http" | "https") {
+        return Err(GitHubError::InvalidUrl(base_url.to_string()));
+    }
+    Ok(url)
+}
+
+#[derive(Deserialize)]
+struct Project {
+    path_with_namespace: String,
+    web_url: String,
+    git_http_url: String,
+}
+
+impl From<Project> for ForgeRepository {
+    fn from(project: Project) -> Self {
+        let (owner, name) = project
+            .path_with_namespace
+            .rsplit_once('/')
+            .unwrap_or(("", &project.path_with_namespace));
+        ForgeRepository {
+            owner: owner.to_string(),
+            name: name.to_string(),
+            clone_url: project.git_http_url,
+            html_url: project.web_url,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct HookUser {
+    username: String,
+}
+
+#[derive(Deserialize)]
+struct MergeRequestHook {
+    user: HookUser,
+    project: Project,
+    object_attributes: MergeRequestAttributes,
+}
+
+#[derive(Deserialize)]
+struct MergeRequestAttributes {
+    iid: u32,
+    title: String,
+    description: Option<String>,
+    url: String,
+    source_branch: String,
+    target_branch: String,
+    last_commit: LastCommit,
+    /// Absent on merge requests created before actions were reported.
+    action: Option<String>,
+    oldrev: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+#[derive(Deserialize)]
+struct LastCommit {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct PushHook {
+    #[serde(rename = "ref")]
+    ref_name: String,
+    before: String,
+    after: String,
+    user_username: String,
+    project: Project,
+}
+
+#[derive(Deserialize)]
+struct NoteHook {
+    user: HookUser,
+    project: Project,
+    object_attributes: NoteAttributes,
+    /// Set for comments on merge requests.
+    merge_request: Option<NoteMergeRequest>,
+}
+
+#[derive(Deserialize)]
+struct NoteAttributes {
+    note: String,
+}
+
+#[derive(Deserialize)]
+struct NoteMergeRequest {
+    iid: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{FileChange, PullRequestReview, ReviewComment};
+
+    const MERGE_REQUEST_HOOK: &[u8] = include_bytes!("../tests/fixtures/gitlab/merge_request_hook.json");
+    const PUSH_HOOK: &[u8] = include_bytes!("../tests/fixtures/gitlab/push_hook.json");
+    const NOTE_HOOK: &[u8] = include_bytes!("../tests/fixtures/gitlab/note_hook.json");
+
+    fn fixture(name: &str) -> Value {
+        let path = format!("{}/tests/fixtures/gitlab/{}", env!("CARGO_MANIFEST_DIR"), name);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    fn client(server: &MockServer) -> GitLabClient {
+        GitLabClient::new(GitLabConfig {
+            base_url: Some(format!("{}/api/v4", server.uri())),
+            access_token: "glpat-token".to_string(),
+            webhook_secret: "hook-secret".to_string(),
+        })
+        .unwrap()
+    }
+
+    async fn mount(server: &MockServer, verb: &str, route: &str, status: u16, body: Value) {
+        Mock::given(method(verb))
+            .and(path(route))
+            .and(header("PRIVATE-TOKEN", "glpat-token"))
+            .respond_with(ResponseTemplate::new(status).set_body_json(body))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_gitlab_branch_commit_merge_request_and_review() {
+        let server = MockServer::start().await;
+        let project = "/api/v4/projects/platform%2Fbackend%2Fapi";
+        Mock::given(method("POST"))
+            .and(path(format!("{}/repository/branches", project)))
+            .and(query_param("branch", "codex/login"))
+            .and(query_param("ref", "main"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(fixture("create_branch.json")))
+            .mount(&server)
+            .await;
+        mount(&server, "HEAD", &format!("{}/repository/files/src%2Flib.rs", project), 200, json!({})).await;
+        mount(&server, "HEAD", &format!("{}/repository/files/src%2Flogin.rs", project), 404, json!({})).await;
+        mount(&server, "POST", &format!("{}/repository/commits", project), 201, fixture("create_commit.json")).await;
+        mount(&server, "POST", &format!("{}/merge_requests", project), 201, fixture("create_merge_request.json")).await;
+        mount(&server, "GET", &format!("{}/merge_requests/12", project), 200, fixture("merge_request.json")).await;
+        mount(&server, "GET", &format!("{}/merge_requests/12/diffs", project), 200, fixture("merge_request_diffs.json")).await;
+        mount(&server, "GET", &format!("{}/merge_requests/12/discussions", project), 200, fixture("discussions.json")).await;
+        mount(&server, "PUT", &format!("{}/merge_requests/12/discussions/6a9c1750b37d513a43987b574953fceb50b03ce7/notes/301", project), 200, json!({})).await;
+        mount(&server, "PUT", &format!("{}/merge_requests/12/discussions/87805b7c09016a7058e91bdbe7b29d1f284a39e6", project), 200, json!({})).await;
+        mount(&server, "POST", &format!("{}/merge_requests/12/discussions", project), 201, json!({ "id": "new" })).await;
+        mount(&server, "POST", &format!("{}/merge_requests/12/notes", project), 201, json!({ "id": 305 })).await;
+        mount(&server, "GET", &format!("{}/merge_requests/12/approvals", project), 200, json!({ "user_has_approved": true })).await;
+        mount(&server, "POST", &format!("{}/merge_requests/12/unapprove", project), 201, json!({})).await;
+        let client = client(&server);
+
+        let branch = client
+            .create_branch(CreateBranchRequest {
+                repo_owner: "platform/backend".to_string(),
+                repo_name: "api".to_string(),
+                branch_name: "codex/login".to_string(),
+                base_branch: "main".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(branch.branch_ref, "refs/heads/codex/login");
+        assert_eq!(branch.object_sha, "7b5c3cc8be40ee161ae89a06bba6229da1032a0c");
+
+        let commit = client
+            .create_commit(CreateCommitRequest {
+                repo_owner: "platform/backend".to_string(),
+                repo_name: "api".to_string(),
+                branch_name: "codex/login".to_string(),
+                message: "Add login".to_string(),
+                changes: vec![
+                    FileChange {
+                        path: "src/lib.rs".to_string(),
+                        content: "pub mod login;\n".to_string(),
+                        encoding: None,
+                    },
+                    FileChange {
+                        path: "src/login.rs".to_string(),
+                        content: "cHViIGZuIGxvZ2luKCkge30K".to_string(),
+                        encoding: Some("base64".to_string()),
+                    },
+                ],
+                author_name: Some("Codex".to_string()),
+                author_email: Some("codex@example.com".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(commit.commit_sha, "ed899a2f4b50b4370feeea94676502b42383c746");
+
+        let merge_request = client
+            .create_pull_request(CreatePullRequestRequest {
+                repo_owner: "platform/backend".to_string(),
+                repo_name: "api".to_string(),
+                title: "Add login".to_string(),
+                body: "Adds a login endpoint".to_string(),
+                head_branch: "codex/login".to_string(),
+                base_branch: "main".to_string(),
+                draft: Some(true),
+            })
+            .await
+            .unwrap();
+        assert_eq!(merge_request.pr_number, 12);
+        assert_eq!(merge_request.html_url, "https://gitlab.example.com/platform/backend/api/-/merge_requests/12");
+
+        // One finding is new and on a changed line, one was reported before
+        let comment = |title: &str, line: u32, fingerprint: &str| ReviewComment {
+            path: "src/login.rs".to_string(),
+            start_line: None,
+            line: Some(line),
+            body: format!("**{}**", title),
+            fingerprint: fingerprint.to_string(),
+        };
+        let review = client
+            .publish_review(PublishReviewRequest {
+                repo_owner: "platform/backend".to_string(),
+                repo_name: "api".to_string(),
+                pr_number: 12,
+                commit_sha: "ed899a2f4b50b4370feeea94676502b42383c746".to_string(),
+                review: PullRequestReview {
+                    summary: "### Codex review: Changes requested".to_string(),
+                    verdict: ReviewVerdict::RequestChanges,
+                    comments: vec![comment("Missing rate limit", 2, "aaaa1111"), comment("Password logged", 1, "bbbb2222")],
+                },
+            })
+            .await
+            .unwrap();
+        assert_eq!((review.created, review.updated, review.resolved), (1, 1, 1));
+        assert_eq!(review.html_url, "https://gitlab.example.com/platform/backend/api/-/merge_requests/12#note_305");
+
+        let requests = server.received_requests().await.unwrap();
+        let body = |verb: &str, suffix: &str| -> Value {
+            let request = requests
+                .iter()
+                .find(|request| request.method.as_str() == verb && request.url.path().ends_with(suffix))
+                .unwrap();
+            serde_json::from_slice(&request.body).unwrap()
+        };
+        let commit = body("POST", "/repository/commits");
+        assert_eq!(commit["actions"][0]["action"], "update");
+        assert_eq!(commit["actions"][1]["action"], "create");
+        assert_eq!(commit["actions"][1]["encoding"], "base64");
+        assert_eq!(commit["author_email"], "codex@example.com");
+        assert_eq!(body("POST", "/merge_requests")["title"], "Draft: Add login");
+        let discussion = body("POST", "/merge_requests/12/discussions");
+        assert_eq!(discussion["position"]["new_line"], 2);
+        assert_eq!(discussion["position"]["base_sha"], "1a0b36b3cdad1d2ee32457c102a8c0b7056fa863");
+        assert!(discussion["body"].as_str().unwrap().ends_with("<!-- codex-review:aaaa1111 -->"));
+        let resolve = requests
+            .iter()
+            .find(|request| request.url.path().ends_with("/discussions/87805b7c09016a7058e91bdbe7b29d1f284a39e6"))
+            .unwrap();
+        assert_eq!(resolve.url.query(), Some("resolved=true"));
+        // Requesting changes withdraws the earlier approval
+        assert!(!requests.iter().any(|request| request.url.path().ends_with("/approve")));
+        assert!(requests.iter().any(|request| request.method.as_str() == "POST" && request.url.path().ends_with("/unapprove")));
+    }
+
+    #[test]
+    fn test_gitlab_webhooks_parse_into_forge_events() {
+        let client = GitLabClient::new(GitLabConfig {
+            base_url: None,
+            access_token: String::new(),
+            webhook_secret: "hook-secret".to_string(),
+        })
+        .unwrap();
+
+        let event = client.parse_webhook("Merge Request Hook", MERGE_REQUEST_HOOK, "hook-secret").unwrap();
+        let ForgeEvent::PullRequest(merge_request) = event else {
+            panic!("expected a merge request event, got {:?}", event);
+        };
+        assert_eq!(merge_request.action, PullRequestAction::Synchronized);
+        assert_eq!(merge_request.number, 12);
+        assert_eq!(merge_request.repository.owner, "platform/backend");
+        assert_eq!(merge_request.repository.name, "api");
+        assert_eq!(merge_request.head_sha, "ed899a2f4b50b4370feeea94676502b42383c746");
+        assert_eq!(merge_request.base_branch, "main");
+        assert!(!merge_request.draft);
+
+        let event = client.parse_webhook("Push Hook", PUSH_HOOK, "hook-secret").unwrap();
+        let ForgeEvent::Push(push) = event else {
+            panic!("expected a push event, got {:?}", event);
+        };
+        assert_eq!(push.branch(), Some("main"));
+        assert_eq!(push.repository.clone_url, "https://gitlab.example.com/platform/backend/api.git");
+
+        let event = client.parse_webhook("Note Hook", NOTE_HOOK, "hook-secret").unwrap();
+        assert!(matches!(event, ForgeEvent::Comment(ForgeCommentEvent { pull_request: Some(12), ref body, .. }) if body == "@codex review"));
+
+        assert!(matches!(
+            client.parse_webhook("Push Hook", PUSH_HOOK, "wrong-secret"),
+            Err(GitHubError::InvalidSignature)
+        ));
+        assert!(matches!(client.parse_webhook("Job Hook", b"{}", "hook-secret"), Ok(ForgeEvent::Other { .. })));
+        assert!(matches!(
+            GitLabClient::new(GitLabConfig {
+                base_url: Some("gitlab.example.com".to_string()),
+                access_token: String::new(),
+                webhook_secret: String::new(),
+            }),
+            Err(GitHubError::InvalidUrl(_))
+        ));
+    }
+}
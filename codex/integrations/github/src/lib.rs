@@ -1,8 +1,41 @@
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use base64::{Engine as _, engine::general_purpose};
-use chrono;
 
+mod auth;
+mod checks;
+mod error;
+mod forge;
+mod gitea;
+mod gitlab;
+mod review;
+pub mod webhook;
+
+use auth::AppAuth;
+pub use checks::{
+    AnnotationLevel, CheckAnnotation, CheckConclusion, CheckRun, CheckRunOutput, CheckStatus, CommitState, CommitStatusRequest,
+    CreateCheckRunRequest, UpdateCheckRunRequest,
+};
+pub use error::GitHubError;
+pub use forge::{
+    Forge, ForgeCommentEvent, ForgeConfig, ForgeEvent, ForgeKind, ForgePullRequestEvent, ForgePushEvent, ForgeRepository,
+    PullRequestAction,
+};
+pub use gitea::{GiteaClient, GiteaConfig};
+pub use gitlab::{GitLabClient, GitLabConfig};
+pub use review::{PublishReviewRequest, PublishReviewResponse, PullRequestReview, ReviewComment, ReviewVerdict};
+pub use webhook::{verify_signature, InstallationRef, WebhookEvent, WebhookHandler};
+
+const DEFAULT_BASE_URL: &str = "https://api.github.com";
+const USER_AGENT: &str = "Codex-GitHub-App";
+
+/// GitHub App configuration.
+///
+/// When `app_id` and `private_key_path` are set, API calls authenticate as
+/// the app installation that covers the repository; otherwise the
+/// `access_token` is used.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubConfig {
     pub app_id: String,
@@ -10,6 +43,9 @@ pub struct GitHubConfig {
     pub webhook_secret: String,
     pub base_url: Option<String>, // For GitHub Enterprise
     pub access_token: Option<String>, // For personal access token auth
+    /// Installation to use for every repository. Looked up per repository when unset.
+    #[serde(default)]
+    pub installation_id: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +83,7 @@ pub struct FileChange {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateCommitResponse {
     pub commit_sha: String,
+    /// Empty on GitLab, whose API does not return it.
     pub tree_sha: String,
 }
 
@@ -75,6 +112,8 @@ pub struct PullRequestEvent {
     pub pull_request: PullRequestData,
     pub repository: RepositoryData,
     pub sender: UserData,
+    #[serde(default)]
+    pub installation: Option<InstallationRef>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,10 +127,16 @@ pub struct PullRequestData {
     pub base: BranchData,
     pub head: BranchData,
     pub user: UserData,
+    #[serde(default)]
+    pub draft: bool,
+    /// Set on `closed` events when the pull request was merged.
+    #[serde(default)]
+    pub merged: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BranchData {
+    #[serde(rename = "ref")]
     pub ref_name: String,
     pub sha: String,
     pub repo: RepositoryData,
@@ -102,6 +147,7 @@ pub struct RepositoryData {
     pub id: u64,
     pub name: String,
     pub full_name: String,
+    pub owner: UserData,
     pub private: bool,
     pub html_url: String,
     pub clone_url: String,
@@ -115,131 +161,107 @@ pub struct UserData {
     pub html_url: String,
 }
 
+/// GitHub API client. Clones share the HTTP client, cached tokens and
+/// registered handlers.
+#[derive(Clone)]
 pub struct GitHubApp {
     config: GitHubConfig,
     client: reqwest::Client,
+    app_auth: Option<Arc<AppAuth>>,
+    handlers: Vec<Arc<dyn WebhookHandler>>,
 }
 
 impl GitHubApp {
-    pub fn new(config: GitHubConfig) -> Self {
+    /// Fails if the app's private key cannot be read or is not an RSA key.
+    pub fn new(config: GitHubConfig) -> Result<Self, GitHubError> {
+        let app_auth = if config.app_id.is_empty() || config.private_key_path.is_empty() {
+            None
+        } else {
+            let pem = std::fs::read(&config.private_key_path).map_err(|source| GitHubError::PrivateKeyRead {
+                path: config.private_key_path.clone().into(),
+                source,
+            })?;
+            Some(Arc::new(AppAuth::from_pem(&config.app_id, &pem, config.installation_id)?))
+        };
+
         let client = reqwest::Client::new();
-        Self { config, client }
+        Ok(Self { config, client, app_auth, handlers: Vec::new() })
     }
 
-    /// Create a new branch from the specified base branch
-    pub async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().ok_or("Access token required")?;
+    pub(crate) fn webhook_secret(&self) -> &str {
+        &self.config.webhook_secret
+    }
 
-        // First, get the base branch SHA
-        let branch_url = format!("{}/repos/{}/{}/git/ref/heads/{}",
-            base_url, request.repo_owner, request.repo_name, request.base_branch);
-
-        let branch_response = self.client
-            .get(&branch_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .send()
-            .await?;
-
-        if !branch_response.status().is_success() {
-            return Err(format!("Failed to get base branch: {}", branch_response.status()).into());
-        }
+    fn base_url(&self) -> &str {
+        self.config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/')
+    }
 
-        #[derive(Deserialize)]
-        struct BranchRef {
-            object: BranchObject,
-        }
+    /// Whether API calls authenticate as a GitHub App installation, which the
+    /// Checks API requires, rather than with a personal access token.
+    pub fn is_app(&self) -> bool {
+        self.app_auth.is_some()
+    }
 
-        #[derive(Deserialize)]
-        struct BranchObject {
-            sha: String,
+    /// Token for API calls on `owner/repo`: an installation access token when
+    /// the app is configured, otherwise the personal access token.
+    pub async fn access_token(&self, owner: &str, repo: &str) -> Result<String, GitHubError> {
+        match (&self.app_auth, &self.config.access_token) {
+            (Some(app_auth), _) => app_auth.installation_token(&self.client, self.base_url(), owner, repo).await,
+            (None, Some(token)) => Ok(token.clone()),
+            (None, None) => Err(GitHubError::MissingCredentials),
         }
+    }
+
+    /// An authenticated request to `/repos/{owner}/{repo}{path}`.
+    async fn repo_request(&self, method: reqwest::Method, owner: &str, repo: &str, path: &str) -> Result<reqwest::RequestBuilder, GitHubError> {
+        let token = self.access_token(owner, repo).await?;
+        let url = format!("{}/repos/{}/{}{}", self.base_url(), owner, repo, path);
+        Ok(self.client
+            .request(method, url)
+            .bearer_auth(token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", USER_AGENT))
+    }
 
-        let branch_data: BranchRef = branch_response.json().await?;
-        let base_sha = branch_data.object.sha;
+    /// Create a new branch from the specified base branch
+    pub async fn create_branch(&self, request: CreateBranchRequest) -> Result<CreateBranchResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
 
-        // Create the new branch reference
-        let new_ref_url = format!("{}/repos/{}/{}/git/refs",
-            base_url, request.repo_owner, request.repo_name);
+        // First, get the base branch SHA
+        let base_sha = self.branch_sha(owner, repo, &request.base_branch).await?;
 
+        // Create the new branch reference
         #[derive(Serialize)]
         struct NewRef {
+            #[serde(rename = "ref")]
             ref_name: String,
             sha: String,
         }
 
         let new_ref = NewRef {
             ref_name: format!("refs/heads/{}", request.branch_name),
-            sha: base_sha,
+            sha: base_sha.clone(),
         };
 
-        let create_response = self.client
-            .post(&new_ref_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .json(&new_ref)
-            .send()
-            .await?;
-
-        if !create_response.status().is_success() {
-            return Err(format!("Failed to create branch: {}", create_response.status()).into());
-        }
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, "/git/refs").await?.json(&new_ref);
+        send::<serde_json::Value>(create, "create the branch").await?;
 
         Ok(CreateBranchResponse {
-            branch_ref: format!("refs/heads/{}", request.branch_name),
+            branch_ref: new_ref.ref_name,
             object_sha: base_sha,
         })
     }
 
     /// Create a commit with the given file changes
-    pub async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().ok_or("Access token required")?;
+    pub async fn create_commit(&self, request: CreateCommitRequest) -> Result<CreateCommitResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
 
         // Get the current branch SHA
-        let branch_url = format!("{}/repos/{}/{}/git/ref/heads/{}",
-            base_url, request.repo_owner, request.repo_name, request.branch_name);
-
-        let branch_response = self.client
-            .get(&branch_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .send()
-            .await?;
-
-        if !branch_response.status().is_success() {
-            return Err(format!("Failed to get branch: {}", branch_response.status()).into());
-        }
-
-        #[derive(Deserialize)]
-        struct BranchRef {
-            object: BranchObject,
-        }
-
-        #[derive(Deserialize)]
-        struct BranchObject {
-            sha: String,
-        }
-
-        let branch_data: BranchRef = branch_response.json().await?;
-        let base_sha = branch_data.object.sha;
+        let base_sha = self.branch_sha(owner, repo, &request.branch_name).await?;
 
         // Get the current tree
-        let commit_url = format!("{}/repos/{}/{}/git/commits/{}",
-            base_url, request.repo_owner, request.repo_name, base_sha);
-
-        let commit_response = self.client
-            .get(&commit_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .send()
-            .await?;
-
-        if !commit_response.status().is_success() {
-            return Err(format!("Failed to get commit: {}", commit_response.status()).into());
-        }
-
         #[derive(Deserialize)]
         struct CommitData {
             tree: TreeData,
@@ -250,31 +272,30 @@ impl GitHubApp {
             sha: String,
         }
 
-        let commit_data: CommitData = commit_response.json().await?;
+        let get_commit = self.repo_request(reqwest::Method::GET, owner, repo, &format!("/git/commits/{}", base_sha)).await?;
+        let commit_data: CommitData = send(get_commit, "get the branch commit").await?;
         let base_tree_sha = commit_data.tree.sha;
 
         // Create blobs for file changes
         let mut tree_entries = Vec::new();
 
         for change in &request.changes {
-            let blob_sha = self.create_blob(&request.repo_owner, &request.repo_name,
+            let blob_sha = self.create_blob(owner, repo,
                 &change.content, change.encoding.as_deref().unwrap_or("utf-8")).await?;
 
             tree_entries.push(TreeEntry {
                 path: change.path.clone(),
                 mode: "100644".to_string(),
-                #[serde(rename = "type")]
                 entry_type: "blob".to_string(),
                 sha: blob_sha,
             });
         }
 
         // Create a new tree
-        let tree_sha = self.create_tree(&request.repo_owner, &request.repo_name,
-            &base_tree_sha, tree_entries).await?;
+        let tree_sha = self.create_tree(owner, repo, &base_tree_sha, tree_entries).await?;
 
         // Create the commit
-        let commit_sha = self.create_git_commit(&request.repo_owner, &request.repo_name,
+        let commit_sha = self.create_git_commit(owner, repo,
             &request.message, &tree_sha, &[&base_sha],
             request.author_name.as_deref(), request.author_email.as_deref()).await?;
 
@@ -285,13 +306,7 @@ impl GitHubApp {
     }
 
     /// Create a pull request
-    pub async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().ok_or("Access token required")?;
-
-        let pr_url = format!("{}/repos/{}/{}/pulls",
-            base_url, request.repo_owner, request.repo_name);
-
+    pub async fn create_pull_request(&self, request: CreatePullRequestRequest) -> Result<CreatePullRequestResponse, GitHubError> {
         #[derive(Serialize)]
         struct NewPR {
             title: String,
@@ -309,73 +324,75 @@ impl GitHubApp {
             draft: request.draft.unwrap_or(false),
         };
 
-        let response = self.client
-            .post(&pr_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .json(&new_pr)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(format!("Failed to create PR: {}", response.status()).into());
-        }
-
         #[derive(Deserialize)]
         struct PRData {
             number: u32,
+            url: String,
             html_url: String,
         }
 
-        let pr_data: PRData = response.json().await?;
+        let create = self
+            .repo_request(reqwest::Method::POST, &request.repo_owner, &request.repo_name, "/pulls")
+            .await?
+            .json(&new_pr);
+        let pr_data: PRData = send(create, "create the pull request").await?;
 
         Ok(CreatePullRequestResponse {
             pr_number: pr_data.number,
-            pr_url: format!("https://api.github.com/repos/{}/{}/pulls/{}",
-                request.repo_owner, request.repo_name, pr_data.number),
+            pr_url: pr_data.url,
             html_url: pr_data.html_url,
         })
     }
 
-    /// Handle incoming webhook events
-    pub async fn handle_webhook(&self, payload: &[u8], signature: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Verify webhook signature (simplified - in production use proper HMAC verification)
-        if signature.is_empty() {
-            return Err("Missing webhook signature".into());
-        }
+    /// Register a handler called for every verified webhook delivery.
+    pub fn register_handler(&mut self, handler: Arc<dyn WebhookHandler>) {
+        self.handlers.push(handler);
+    }
 
-        // Parse the webhook payload
-        let event: serde_json::Value = serde_json::from_slice(payload)?;
-
-        if let Some(action) = event.get("action").and_then(|a| a.as_str()) {
-            println!("GitHub webhook event: {} - {}", event.get("event_type").unwrap_or(&serde_json::Value::Null), action);
-
-            // Handle different event types
-            match action {
-                "opened" | "synchronize" => {
-                    // PR opened or updated - could trigger code review agent
-                    println!("PR event detected, could trigger code review workflow");
-                }
-                "closed" => {
-                    // PR closed - could trigger deployment or cleanup
-                    println!("PR closed, could trigger cleanup workflow");
-                }
-                _ => {
-                    println!("Unhandled webhook action: {}", action);
-                }
+    /// Handle an incoming webhook delivery: verify `signature` (the
+    /// `X-Hub-Signature-256` header) against the configured secret, parse the
+    /// payload according to `event` (the `X-GitHub-Event` header) and pass it
+    /// to every registered handler.
+    ///
+    /// All handlers run even if one fails; their failures are reported together.
+    pub async fn handle_webhook(&self, event: &str, payload: &[u8], signature: &str) -> Result<WebhookEvent, GitHubError> {
+        verify_signature(self.config.webhook_secret.as_bytes(), payload, signature)?;
+        let event = WebhookEvent::parse(event, payload)?;
+
+        let mut failures = Vec::new();
+        for handler in &self.handlers {
+            if let Err(err) = webhook::dispatch(handler.as_ref(), self, &event).await {
+                failures.push(err.to_string());
             }
         }
+        if !failures.is_empty() {
+            return Err(GitHubError::Handler {
+                event: event.name().to_string(),
+                message: failures.join("; "),
+            });
+        }
 
-        Ok(())
+        Ok(event)
     }
 
     // Helper methods
-    async fn create_blob(&self, owner: &str, repo: &str, content: &str, encoding: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().unwrap();
+    async fn branch_sha(&self, owner: &str, repo: &str, branch: &str) -> Result<String, GitHubError> {
+        #[derive(Deserialize)]
+        struct BranchRef {
+            object: BranchObject,
+        }
 
-        let blob_url = format!("{}/repos/{}/{}/git/blobs", base_url, owner, repo);
+        #[derive(Deserialize)]
+        struct BranchObject {
+            sha: String,
+        }
 
+        let get_ref = self.repo_request(reqwest::Method::GET, owner, repo, &format!("/git/ref/heads/{}", branch)).await?;
+        let branch_ref: BranchRef = send(get_ref, &format!("get branch {}", branch)).await?;
+        Ok(branch_ref.object.sha)
+    }
+
+    async fn create_blob(&self, owner: &str, repo: &str, content: &str, encoding: &str) -> Result<String, GitHubError> {
         #[derive(Serialize)]
         struct NewBlob {
             content: String,
@@ -394,33 +411,17 @@ impl GitHubApp {
             }
         };
 
-        let response = self.client
-            .post(&blob_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .json(&blob_data)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(format!("Failed to create blob: {}", response.status()).into());
-        }
-
         #[derive(Deserialize)]
         struct BlobResponse {
             sha: String,
         }
 
-        let blob_response: BlobResponse = response.json().await?;
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, "/git/blobs").await?.json(&blob_data);
+        let blob_response: BlobResponse = send(create, "create a blob").await?;
         Ok(blob_response.sha)
     }
 
-    async fn create_tree(&self, owner: &str, repo: &str, base_tree_sha: &str, entries: Vec<TreeEntry>) -> Result<String, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().unwrap();
-
-        let tree_url = format!("{}/repos/{}/{}/git/trees", base_url, owner, repo);
-
+    async fn create_tree(&self, owner: &str, repo: &str, base_tree_sha: &str, entries: Vec<TreeEntry>) -> Result<String, GitHubError> {
         #[derive(Serialize)]
         struct NewTree {
             base_tree: String,
@@ -432,34 +433,19 @@ impl GitHubApp {
             tree: entries,
         };
 
-        let response = self.client
-            .post(&tree_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .json(&tree_data)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(format!("Failed to create tree: {}", response.status()).into());
-        }
-
         #[derive(Deserialize)]
         struct TreeResponse {
             sha: String,
         }
 
-        let tree_response: TreeResponse = response.json().await?;
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, "/git/trees").await?.json(&tree_data);
+        let tree_response: TreeResponse = send(create, "create a tree").await?;
         Ok(tree_response.sha)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn create_git_commit(&self, owner: &str, repo: &str, message: &str, tree_sha: &str,
-        parents: &[&str], author_name: Option<&str>, author_email: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
-        let base_url = self.config.base_url.as_deref().unwrap_or("https://api.github.com");
-        let token = self.config.access_token.as_ref().unwrap();
-
-        let commit_url = format!("{}/repos/{}/{}/git/commits", base_url, owner, repo);
-
+        parents: &[&str], author_name: Option<&str>, author_email: Option<&str>) -> Result<String, GitHubError> {
         #[derive(Serialize)]
         struct CommitAuthor {
             name: String,
@@ -471,6 +457,7 @@ impl GitHubApp {
             message: String,
             tree: String,
             parents: Vec<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
             author: Option<CommitAuthor>,
         }
 
@@ -488,28 +475,44 @@ impl GitHubApp {
             });
         }
 
-        let response = self.client
-            .post(&commit_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("User-Agent", "Codex-GitHub-App")
-            .json(&new_commit)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            return Err(format!("Failed to create commit: {}", response.status()).into());
-        }
-
         #[derive(Deserialize)]
         struct CommitResponse {
             sha: String,
         }
 
-        let commit_response: CommitResponse = response.json().await?;
+        let create = self.repo_request(reqwest::Method::POST, owner, repo, "/git/commits").await?.json(&new_commit);
+        let commit_response: CommitResponse = send(create, "create a commit").await?;
         Ok(commit_response.sha)
     }
 }
 
+/// Send `request` and decode a successful response; `action` describes the
+/// call in the error otherwise, e.g. "create a blob".
+async fn send<T: DeserializeOwned>(request: reqwest::RequestBuilder, action: &str) -> Result<T, GitHubError> {
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(api_error(action, response).await);
+    }
+    Ok(response.json().await?)
+}
+
+/// Turn a failed response into [`GitHubError::Api`], keeping GitHub's message.
+pub(crate) async fn api_error(action: &str, response: reqwest::Response) -> GitHubError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        message: String,
+    }
+
+    let status = response.status().as_u16();
+    let text = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ErrorBody>(&text).map(|body| body.message).unwrap_or(text);
+    GitHubError::Api {
+        action: action.to_string(),
+        status,
+        message,
+    }
+}
+
 #[derive(Serialize)]
 struct TreeEntry {
     path: String,
@@ -518,3 +521,142 @@ struct TreeEntry {
     entry_type: String,
     sha: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Test-only key pair, generated for these tests.
+    const PRIVATE_KEY_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/test-app-key.pem");
+    const PUBLIC_KEY: &str = include_str!("../tests/fixtures/test-app-key.pub.pem");
+
+    fn app_config(server: &MockServer) -> GitHubConfig {
+        GitHubConfig {
+            app_id: "12345".to_string(),
+            private_key_path: PRIVATE_KEY_PATH.to_string(),
+            webhook_secret: "secret".to_string(),
+            base_url: Some(server.uri()),
+            access_token: None,
+            installation_id: None,
+        }
+    }
+
+    fn pull_request() -> CreatePullRequestRequest {
+        CreatePullRequestRequest {
+            repo_owner: "octo".to_string(),
+            repo_name: "app".to_string(),
+            title: "Add login".to_string(),
+            body: "Generated by Codex".to_string(),
+            head_branch: "codex/login".to_string(),
+            base_branch: "main".to_string(),
+            draft: None,
+        }
+    }
+
+    async fn mount_token(server: &MockServer, token: &str, expires_in: Duration, calls: u64) {
+        Mock::given(method("POST"))
+            .and(path("/app/installations/42/access_tokens"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "token": token,
+                "expires_at": (Utc::now() + expires_in).to_rfc3339(),
+            })))
+            .expect(calls)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_app_auth_uses_cached_installation_tokens() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/octo/app/installation"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": 42 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_token(&server, "ghs_cached", Duration::hours(1), 1).await;
+        Mock::given(method("POST"))
+            .and(path("/repos/octo/app/pulls"))
+            .and(header("authorization", "Bearer ghs_cached"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "number": 7,
+                "url": format!("{}/repos/octo/app/pulls/7", server.uri()),
+                "html_url": "https://github.com/octo/app/pull/7",
+            })))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let app = GitHubApp::new(app_config(&server)).unwrap();
+        for _ in 0..2 {
+            let response = app.create_pull_request(pull_request()).await.unwrap();
+            assert_eq!(response.pr_number, 7);
+        }
+
+        // The installation lookup is signed with the app's key
+        let requests = server.received_requests().await.unwrap();
+        let authorization = requests[0].headers.get("authorization").unwrap().to_str().unwrap();
+        let jwt = authorization.strip_prefix("Bearer ").unwrap();
+        let key = DecodingKey::from_rsa_pem(PUBLIC_KEY.as_bytes()).unwrap();
+        let claims = jsonwebtoken::decode::<serde_json::Value>(jwt, &key, &Validation::new(Algorithm::RS256))
+            .unwrap()
+            .claims;
+        assert_eq!(claims["iss"], "12345");
+        assert!(claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap() <= 600);
+    }
+
+    #[tokio::test]
+    async fn test_app_auth_refreshes_expiring_tokens_and_reports_errors() {
+        let server = MockServer::start().await;
+        // Tokens inside the refresh margin are exchanged again on every call,
+        // including the one made by create_branch
+        mount_token(&server, "ghs_expiring", Duration::minutes(1), 3).await;
+        Mock::given(method("GET"))
+            .and(path("/repos/octo/app/git/ref/heads/main"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({ "message": "Not Found" })))
+            .mount(&server)
+            .await;
+
+        let app = GitHubApp::new(GitHubConfig {
+            installation_id: Some(42),
+            ..app_config(&server)
+        })
+        .unwrap();
+        for _ in 0..2 {
+            assert_eq!(app.access_token("octo", "app").await.unwrap(), "ghs_expiring");
+        }
+
+        let error = app
+            .create_branch(CreateBranchRequest {
+                repo_owner: "octo".to_string(),
+                repo_name: "app".to_string(),
+                branch_name: "codex/login".to_string(),
+                base_branch: "main".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(error, GitHubError::Api { status: 404, ref message, .. } if message == "Not Found"));
+
+        // Repositories without an installation and missing credentials are errors, not panics
+        let app = GitHubApp::new(app_config(&server)).unwrap();
+        assert!(matches!(app.access_token("octo", "other").await, Err(GitHubError::NotInstalled(_))));
+        let app = GitHubApp::new(GitHubConfig {
+            app_id: String::new(),
+            ..app_config(&server)
+        })
+        .unwrap();
+        assert!(matches!(app.create_pull_request(pull_request()).await, Err(GitHubError::MissingCredentials)));
+        assert!(matches!(
+            GitHubApp::new(GitHubConfig {
+                private_key_path: "/nonexistent/key.pem".to_string(),
+                ..app_config(&server)
+            }),
+            Err(GitHubError::PrivateKeyRead { .. })
+        ));
+    }
+}
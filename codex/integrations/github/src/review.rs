@@ -0,0 +1,723 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use codex_agents::reviewer::{ApprovalStatus, ReviewFinding, ReviewReport};
+use codex_protocol::protocol::ReviewOutputEvent;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{api_error, send, GitHubApp, GitHubError, USER_AGENT};
+
+/// Hidden marker embedded in every inline comment the reviewer posts,
+/// carrying the finding's fingerprint: `<!-- codex-review:<fingerprint> -->`.
+const MARKER_PREFIX: &str = "<!-- codex-review:";
+const MARKER_SUFFIX: &str = " -->";
+/// Largest page size the REST and GraphQL APIs accept.
+const PER_PAGE: usize = 100;
+/// GitHub lists at most 3000 files of a pull request.
+const MAX_FILE_PAGES: usize = 30;
+
+/// The review event submitted with a pull request review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewVerdict {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+impl From<&ApprovalStatus> for ReviewVerdict {
+    fn from(status: &ApprovalStatus) -> Self {
+        match status {
+            ApprovalStatus::Approved => Self::Approve,
+            ApprovalStatus::ApprovedWithComments => Self::Comment,
+            ApprovalStatus::RequiresChanges | ApprovalStatus::Rejected => Self::RequestChanges,
+        }
+    }
+}
+
+/// A review ready to be published on a pull request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestReview {
+    /// Markdown body of the review.
+    pub summary: String,
+    pub verdict: ReviewVerdict,
+    pub comments: Vec<ReviewComment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewComment {
+    /// Path relative to the repository root.
+    pub path: String,
+    /// Lines in the new version of the file. Comments without a line, or
+    /// whose lines are outside the pull request's diff, are added to the
+    /// review summary instead.
+    pub start_line: Option<u32>,
+    pub line: Option<u32>,
+    /// Markdown body.
+    pub body: String,
+    /// Identifies the finding across review runs.
+    pub fingerprint: String,
+}
+
+impl PullRequestReview {
+    /// Build a review from a reviewer report; the verdict follows its
+    /// `overall_approval`.
+    pub fn from_report(report: &ReviewReport) -> Self {
+        let mut summary = format!("### Codex review: {}\n\n", approval_label(&report.overall_approval));
+        summary.push_str(&severity_counts(report));
+        for recommendation in &report.recommendations {
+            summary.push_str(&format!("\n- {}", recommendation));
+        }
+
+        Self {
+            summary,
+            verdict: ReviewVerdict::from(&report.overall_approval),
+            comments: report.findings.iter().map(finding_comment).collect(),
+        }
+    }
+
+    /// Build a review from a `/review` result, whose paths are absolute;
+    /// `repo_root` is the checkout they are relative to.
+    pub fn from_output(output: &ReviewOutputEvent, repo_root: &Path) -> Self {
+        let verdict = if output.overall_correctness != "patch is correct" {
+            ReviewVerdict::RequestChanges
+        } else if output.findings.is_empty() {
+            ReviewVerdict::Approve
+        } else {
+            ReviewVerdict::Comment
+        };
+        let comments = output
+            .findings
+            .iter()
+            .map(|finding| {
+                let location = &finding.code_location;
+                let path = location.absolute_file_path.strip_prefix(repo_root).unwrap_or(&location.absolute_file_path);
+                let path = path.to_string_lossy().replace('\\', "/");
+                ReviewComment {
+                    fingerprint: fingerprint(&path, &finding.title),
+                    start_line: Some(location.line_range.start),
+                    line: Some(location.line_range.end),
+                    body: format!("**{}**\n\n{}", finding.title, finding.body),
+                    path,
+                }
+            })
+            .collect();
+
+        Self {
+            summary: format!("### Codex review\n\n{}", output.overall_explanation),
+            verdict,
+            comments,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishReviewRequest {
+    pub repo_owner: String,
+    pub repo_name: String,
+    pub pr_number: u32,
+    /// Commit the review was computed for, normally the pull request's head.
+    pub commit_sha: String,
+    pub review: PullRequestReview,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishReviewResponse {
+    pub review_id: u64,
+    pub html_url: String,
+    /// Inline comments posted for new findings.
+    pub created: usize,
+    /// Comments from earlier runs kept for findings reported again, with
+    /// their text updated.
+    pub updated: usize,
+    /// Comment threads from earlier runs resolved because their finding is gone.
+    pub resolved: usize,
+}
+
+/// A comment posted by an earlier review run.
+struct PreviousComment {
+    id: u64,
+    fingerprint: String,
+    body: String,
+}
+
+struct ReviewThread {
+    id: String,
+    is_resolved: bool,
+}
+
+impl GitHubApp {
+    /// Publish `review` as a pull request review.
+    ///
+    /// Comments are anchored to the lines of the pull request's diff. Comments
+    /// left by earlier runs are matched by fingerprint: findings reported again
+    /// update their existing comment instead of posting a new one, and threads
+    /// whose finding is gone are resolved.
+    pub async fn publish_review(&self, request: PublishReviewRequest) -> Result<PublishReviewResponse, GitHubError> {
+        let owner = &request.repo_owner;
+        let repo = &request.repo_name;
+        let number = request.pr_number;
+
+        let commentable = self.commentable_lines(owner, repo, number).await?;
+        let previous = self.previous_comments(owner, repo, number).await?;
+        let threads = if previous.is_empty() {
+            HashMap::new()
+        } else {
+            self.review_threads(owner, repo, number).await?
+        };
+        let mut previous_by_fingerprint = HashMap::new();
+        for comment in &previous {
+            previous_by_fingerprint.entry(comment.fingerprint.as_str()).or_insert(comment);
+        }
+
+        let mut comments = Vec::new();
+        let mut unanchored = Vec::new();
+        let mut reported = HashSet::new();
+        let mut updated = 0;
+        for comment in &request.review.comments {
+            if !reported.insert(comment.fingerprint.as_str()) {
+                continue;
+            }
+            let body = marked_body(comment);
+
+            if let Some(previous) = previous_by_fingerprint.get(comment.fingerprint.as_str()) {
+                if previous.body != body {
+                    let edit = self
+                        .repo_request(reqwest::Method::PATCH, owner, repo, &format!("/pulls/comments/{}", previous.id))
+                        .await?
+                        .json(&json!({ "body": body }));
+                    send::<Value>(edit, "update a review comment").await?;
+                }
+                if let Some(thread) = threads.get(&previous.id).filter(|thread| thread.is_resolved) {
+                    self.set_thread_resolved(owner, repo, &thread.id, false).await?;
+                }
+                updated += 1;
+                continue;
+            }
+
+            match anchor(comment, commentable.get(&comment.path)) {
+                Some((start_line, line)) => {
+                    let mut inline = json!({ "path": comment.path, "line": line, "side": "RIGHT", "body": body });
+                    if let Some(start_line) = start_line {
+                        inline["start_line"] = json!(start_line);
+                        inline["start_side"] = json!("RIGHT");
+                    }
+                    comments.push(inline);
+                }
+                None => unanchored.push(comment),
+            }
+        }
+
+        // Resolve what earlier runs reported that this one did not, including
+        // duplicates of a finding that is still reported
+        let mut resolved = 0;
+        for comment in &previous {
+            let current = reported.contains(comment.fingerprint.as_str())
+                && previous_by_fingerprint.get(comment.fingerprint.as_str()).map(|kept| kept.id) == Some(comment.id);
+            if current {
+                continue;
+            }
+            if let Some(thread) = threads.get(&comment.id).filter(|thread| !thread.is_resolved) {
+                self.set_thread_resolved(owner, repo, &thread.id, true).await?;
+                resolved += 1;
+            }
+        }
+
+        let body = review_body(&request.review.summary, &unanchored);
+
+        #[derive(Deserialize)]
+        struct CreatedReview {
+            id: u64,
+            html_url: String,
+        }
+
+        let created = comments.len();
+        let submit = self
+            .repo_request(reqwest::Method::POST, owner, repo, &format!("/pulls/{}/reviews", number))
+            .await?
+            .json(&json!({
+                "commit_id": request.commit_sha,
+                "body": body,
+                "event": request.review.verdict,
+                "comments": comments,
+            }));
+        let review: CreatedReview = send(submit, "submit the pull request review").await?;
+
+        Ok(PublishReviewResponse {
+            review_id: review.id,
+            html_url: review.html_url,
+            created,
+            updated,
+            resolved,
+        })
+    }
+
+    /// Lines of each changed file that review comments can be attached to:
+    /// added and context lines of the new version.
+    async fn commentable_lines(&self, owner: &str, repo: &str, number: u32) -> Result<HashMap<String, BTreeSet<u32>>, GitHubError> {
+        #[derive(Deserialize)]
+        struct PullFile {
+            filename: String,
+            /// Missing for binary and very large files.
+            #[serde(default)]
+            patch: Option<String>,
+        }
+
+        let mut lines = HashMap::new();
+        for page in 1..=MAX_FILE_PAGES {
+            let list = self
+                .repo_request(reqwest::Method::GET, owner, repo, &format!("/pulls/{}/files?per_page={}&page={}", number, PER_PAGE, page))
+                .await?;
+            let files: Vec<PullFile> = send(list, "list the pull request files").await?;
+            let last_page = files.len() < PER_PAGE;
+            for file in files {
+                let patch = file.patch.unwrap_or_default();
+                lines.insert(file.filename, patch_lines(&patch));
+            }
+            if last_page {
+                break;
+            }
+        }
+        Ok(lines)
+    }
+
+    async fn previous_comments(&self, owner: &str, repo: &str, number: u32) -> Result<Vec<PreviousComment>, GitHubError> {
+        #[derive(Deserialize)]
+        struct Comment {
+            id: u64,
+            body: String,
+            #[serde(default)]
+            in_reply_to_id: Option<u64>,
+        }
+
+        let mut previous = Vec::new();
+        for page in 1.. {
+            let list = self
+                .repo_request(reqwest::Method::GET, owner, repo, &format!("/pulls/{}/comments?per_page={}&page={}", number, PER_PAGE, page))
+                .await?;
+            let comments: Vec<Comment> = send(list, "list the pull request review comments").await?;
+            let last_page = comments.len() < PER_PAGE;
+            previous.extend(comments.into_iter().filter(|comment| comment.in_reply_to_id.is_none()).filter_map(|comment| {
+                let fingerprint = marker_fingerprint(&comment.body)?.to_string();
+                Some(PreviousComment {
+                    id: comment.id,
+                    fingerprint,
+                    body: comment.body,
+                })
+            }));
+            if last_page {
+                break;
+            }
+        }
+        Ok(previous)
+    }
+
+    /// Review threads of the pull request, keyed by the id of their first comment.
+    async fn review_threads(&self, owner: &str, repo: &str, number: u32) -> Result<HashMap<u64, ReviewThread>, GitHubError> {
+        const QUERY: &str = "query($owner: String!, $repo: String!, $number: Int!, $first: Int!, $after: String) {
+  repository(owner: $owner, name: $repo) {
+    pullRequest(number: $number) {
+      reviewThreads(first: $first, after: $after) {
+        pageInfo { hasNextPage endCursor }
+        nodes { id isResolved comments(first: 1) { nodes { databaseId } } }
+      }
+    }
+  }
+}";
+
+        let mut threads = HashMap::new();
+        let mut after: Option<String> = None;
+        loop {
+            let variables = json!({ "owner": owner, "repo": repo, "number": number, "first": PER_PAGE, "after": after });
+            let data: Value = self.graphql(owner, repo, QUERY, variables, "list the review threads").await?;
+            let page = &data["repository"]["pullRequest"]["reviewThreads"];
+            for thread in page["nodes"].as_array().into_iter().flatten() {
+                let first_comment = thread["comments"]["nodes"][0]["databaseId"].as_u64();
+                if let (Some(comment_id), Some(id)) = (first_comment, thread["id"].as_str()) {
+                    threads.insert(
+                        comment_id,
+                        ReviewThread {
+                            id: id.to_string(),
+                            is_resolved: thread["isResolved"].as_bool().unwrap_or(false),
+                        },
+                    );
+                }
+            }
+            match page["pageInfo"]["endCursor"].as_str() {
+                Some(cursor) if page["pageInfo"]["hasNextPage"].as_bool() == Some(true) => after = Some(cursor.to_string()),
+                _ => break,
+            }
+        }
+        Ok(threads)
+    }
+
+    async fn set_thread_resolved(&self, owner: &str, repo: &str, thread_id: &str, resolved: bool) -> Result<(), GitHubError> {
+        let (mutation, action) = if resolved {
+            ("resolveReviewThread", "resolve a review thread")
+        } else {
+            ("unresolveReviewThread", "reopen a review thread")
+        };
+        let query = format!("mutation($id: ID!) {{ {}(input: {{threadId: $id}}) {{ thread {{ id }} }} }}", mutation);
+        self.graphql::<Value>(owner, repo, &query, json!({ "id": thread_id }), action).await?;
+        Ok(())
+    }
+
+    /// Run a GraphQL query with the token for `owner/repo` and return its `data`.
+    async fn graphql<T: DeserializeOwned>(
+        &self,
+        owner: &str,
+        repo: &str,
+        query: &str,
+        variables: Value,
+        action: &str,
+    ) -> Result<T, GitHubError> {
+        #[derive(Deserialize)]
+        struct GraphQlResponse<T> {
+            data: Option<T>,
+            #[serde(default)]
+            errors: Vec<GraphQlError>,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphQlError {
+            message: String,
+        }
+
+        let token = self.access_token(owner, repo).await?;
+        let response = self
+            .client
+            .post(self.graphql_url())
+            .bearer_auth(token)
+            .header("User-Agent", USER_AGENT)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(api_error(action, response).await);
+        }
+        let response: GraphQlResponse<T> = response.json().await?;
+        match response.data {
+            Some(data) if response.errors.is_empty() => Ok(data),
+            _ => Err(GitHubError::GraphQl {
+                action: action.to_string(),
+                message: response.errors.into_iter().map(|error| error.message).collect::<Vec<_>>().join("; "),
+            }),
+        }
+    }
+
+    /// `https://api.github.com/graphql`, or `https://<host>/api/graphql` on
+    /// GitHub Enterprise, whose REST API lives under `/api/v3`.
+    fn graphql_url(&self) -> String {
+        let base_url = self.base_url();
+        match base_url.strip_suffix("/api/v3") {
+            Some(host) => format!("{}/api/graphql", host),
+            None => format!("{}/graphql", base_url),
+        }
+    }
+}
+
+/// The review's summary followed by the comments that could not be anchored
+/// to the diff.
+pub(crate) fn review_body(summary: &str, unanchored: &[&ReviewComment]) -> String {
+    let mut body = summary.to_string();
+    if !unanchored.is_empty() {
+        body.push_str("\n\n#### Findings outside the diff");
+        for comment in unanchored {
+            let location = match comment.line.or(comment.start_line) {
+                Some(line) => format!("{}:{}", comment.path, line),
+                None => comment.path.clone(),
+            };
+            body.push_str(&format!("\n\n`{}`\n\n{}", location, comment.body));
+        }
+    }
+    body
+}
+
+/// The comment's body with its fingerprint marker appended.
+pub(crate) fn marked_body(comment: &ReviewComment) -> String {
+    format!("{}\n\n{}{}{}", comment.body, MARKER_PREFIX, comment.fingerprint, MARKER_SUFFIX)
+}
+
+/// New-file line numbers covered by the hunks of a file patch.
+pub(crate) fn patch_lines(patch: &str) -> BTreeSet<u32> {
+    let mut lines = BTreeSet::new();
+    let mut next = None;
+    for line in patch.lines() {
+        if let Some(header) = line.strip_prefix("@@ ") {
+            // @@ -old_start,old_lines +new_start,new_lines @@
+            next = header
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse::<u32>().ok());
+            continue;
+        }
+        let Some(number) = next.as_mut() else { continue };
+        match line.chars().next() {
+            Some('-') | Some('\\') => {}
+            _ => {
+                lines.insert(*number);
+                *number += 1;
+            }
+        }
+    }
+    lines
+}
+
+/// The `(start_line, line)` to attach `comment` to, if its last line is in
+/// the diff. Multi-line ranges are kept only when every line is.
+pub(crate) fn anchor(comment: &ReviewComment, lines: Option<&BTreeSet<u32>>) -> Option<(Option<u32>, u32)> {
+    let lines = lines?;
+    let line = comment.line.or(comment.start_line)?;
+    if !lines.contains(&line) {
+        return None;
+    }
+    let start_line = comment
+        .start_line
+        .filter(|start| *start < line && (*start..line).all(|number| lines.contains(&number)));
+    Some((start_line, line))
+}
+
+pub(crate) fn marker_fingerprint(body: &str) -> Option<&str> {
+    let start = body.rfind(MARKER_PREFIX)? + MARKER_PREFIX.len();
+    let end = body[start..].find(MARKER_SUFFIX)?;
+    Some(&body[start..start + end])
+}
+
+/// Stable id of a finding: its file and normalized title, so it survives the
+/// code moving within the file.
+fn fingerprint(path: &str, title: &str) -> String {
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let digest = Sha256::digest(format!("{}\n{}", path, title).as_bytes());
+    hex::encode(&digest[..8])
+}
+
+fn finding_comment(finding: &ReviewFinding) -> ReviewComment {
+    let mut body = format!(
+        "**[{:?}] {}** ({:?})\n\n{}",
+        finding.severity, finding.title, finding.category, finding.description
+    );
+    if let Some(suggestion) = &finding.suggestion {
+        body.push_str(&format!("\n\n**Suggestion:** {}", suggestion));
+    }
+    for example in &finding.examples {
+        body.push_str(&format!("\n\n```\n{}\n```", example));
+    }
+
+    let line_start = finding.line_start.and_then(|line| u32::try_from(line).ok());
+    let line_end = finding.line_end.and_then(|line| u32::try_from(line).ok());
+    ReviewComment {
+        path: finding.file_path.clone(),
+        start_line: line_start,
+        line: line_end.or(line_start),
+        body,
+        fingerprint: fingerprint(&finding.file_path, &finding.title),
+    }
+}
+
+fn approval_label(status: &ApprovalStatus) -> &'static str {
+    match status {
+        ApprovalStatus::Approved => "approved",
+        ApprovalStatus::ApprovedWithComments => "approved with comments",
+        ApprovalStatus::RequiresChanges => "changes requested",
+        ApprovalStatus::Rejected => "rejected",
+    }
+}
+
+/// "2 findings: 1 High, 1 Low."
+fn severity_counts(report: &ReviewReport) -> String {
+    let total = report.summary.total_findings;
+    if total == 0 {
+        return "No findings.\n".to_string();
+    }
+    let counts: Vec<String> = ["Critical", "High", "Medium", "Low", "Info"]
+        .iter()
+        .filter_map(|severity| {
+            let count = report.summary.findings_by_severity.get(*severity)?;
+            Some(format!("{} {}", count, severity))
+        })
+        .collect();
+    format!("{} finding{}: {}.\n", total, if total == 1 { "" } else { "s" }, counts.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use codex_agents::reviewer::{ReviewCategory, ReviewSummary, Severity};
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::GitHubConfig;
+
+    fn finding(title: &str, line: Option<usize>, severity: Severity) -> ReviewFinding {
+        ReviewFinding {
+            id: "SEC-001".to_string(),
+            file_path: "src/auth.js".to_string(),
+            line_start: line,
+            line_end: line,
+            severity,
+            category: ReviewCategory::Security,
+            title: title.to_string(),
+            description: format!("{} in the login handler", title),
+            suggestion: None,
+            examples: Vec::new(),
+            confidence: 0.8,
+        }
+    }
+
+    fn report(findings: Vec<ReviewFinding>) -> ReviewReport {
+        ReviewReport {
+            summary: ReviewSummary {
+                total_findings: findings.len(),
+                findings_by_severity: HashMap::from([("High".to_string(), 2), ("Low".to_string(), 1)]),
+                findings_by_category: HashMap::new(),
+                code_quality_score: 80.0,
+                security_score: 40.0,
+                maintainability_score: 100.0,
+            },
+            findings,
+            annotated_diffs: Vec::new(),
+            recommendations: vec!["Security review recommended before deployment".to_string()],
+            overall_approval: ApprovalStatus::RequiresChanges,
+            review_output: ReviewOutputEvent::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_review_updates_and_resolves_previous_comments() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/octo/app/pulls/7/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "filename": "src/auth.js",
+                "patch": "@@ -1,2 +1,4 @@\n function login() {\n+  const password = \"hunter2\";\n+  const hash = md5(password);\n   return check(password);",
+            }])))
+            .mount(&server)
+            .await;
+
+        // An earlier run reported the hardcoded secret (now resolved by a
+        // human) and a finding that is gone; a human comment has no marker
+        let secret = fingerprint("src/auth.js", "Hardcoded secret");
+        let gone = fingerprint("src/auth.js", "Unused import");
+        Mock::given(method("GET"))
+            .and(path("/repos/octo/app/pulls/7/comments"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "id": 101, "body": format!("Old wording\n\n<!-- codex-review:{} -->", secret) },
+                { "id": 102, "body": format!("Remove it\n\n<!-- codex-review:{} -->", gone) },
+                { "id": 103, "body": "Looks fine to me" },
+                { "id": 104, "body": format!("Fixed\n\n<!-- codex-review:{} -->", gone), "in_reply_to_id": 102 },
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_string_contains("reviewThreads"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": { "repository": { "pullRequest": {
+                "reviewThreads": {
+                    "pageInfo": { "hasNextPage": false, "endCursor": null },
+                    "nodes": [
+                        { "id": "T_secret", "isResolved": true, "comments": { "nodes": [{ "databaseId": 101 }] } },
+                        { "id": "T_gone", "isResolved": false, "comments": { "nodes": [{ "databaseId": 102 }] } },
+                    ],
+                },
+            } } } })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        for (mutation, thread) in [("unresolveReviewThread", "T_secret"), ("resolveReviewThread(", "T_gone")] {
+            Mock::given(method("POST"))
+                .and(path("/graphql"))
+                .and(body_string_contains(mutation))
+                .and(body_string_contains(thread))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "data": {} })))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("PATCH"))
+            .and(path("/repos/octo/app/pulls/comments/101"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": 101 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/repos/octo/app/pulls/7/reviews"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": 900,
+                "html_url": "https://github.com/octo/app/pull/7#pullrequestreview-900",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let app = GitHubApp::new(GitHubConfig {
+            app_id: String::new(),
+            private_key_path: String::new(),
+            webhook_secret: String::new(),
+            base_url: Some(server.uri()),
+            access_token: Some("ghp_token".to_string()),
+            installation_id: None,
+        })
+        .unwrap();
+        let review = PullRequestReview::from_report(&report(vec![
+            finding("Hardcoded secret", Some(2), Severity::High),
+            finding("Weak hash", Some(3), Severity::High),
+            finding("Missing rate limit", Some(40), Severity::Low),
+        ]));
+        assert_eq!(review.verdict, ReviewVerdict::RequestChanges);
+        let response = app
+            .publish_review(PublishReviewRequest {
+                repo_owner: "octo".to_string(),
+                repo_name: "app".to_string(),
+                pr_number: 7,
+                commit_sha: "abc123".to_string(),
+                review,
+            })
+            .await
+            .unwrap();
+        assert_eq!((response.review_id, response.created, response.updated, response.resolved), (900, 1, 1, 1));
+
+        // Only the new finding on a changed line is posted inline; the one
+        // outside the diff goes into the summary
+        let requests = server.received_requests().await.unwrap();
+        let submitted = requests.iter().find(|request| request.url.path().ends_with("/reviews")).unwrap();
+        let submitted: Value = serde_json::from_slice(&submitted.body).unwrap();
+        assert_eq!(submitted["event"], "REQUEST_CHANGES");
+        assert_eq!(submitted["commit_id"], "abc123");
+        let comments = submitted["comments"].as_array().unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0]["line"], 3);
+        assert_eq!(comments[0]["side"], "RIGHT");
+        let body = comments[0]["body"].as_str().unwrap();
+        assert!(body.starts_with("**[High] Weak hash** (Security)"));
+        assert_eq!(marker_fingerprint(body), Some(fingerprint("src/auth.js", "weak  HASH").as_str()));
+        let summary = submitted["body"].as_str().unwrap();
+        assert!(summary.starts_with("### Codex review: changes requested\n\n3 findings: 2 High, 1 Low."));
+        assert!(summary.contains("`src/auth.js:40`"));
+    }
+
+    #[test]
+    fn test_patch_lines_and_anchors() {
+        let lines = patch_lines("@@ -10,3 +10,3 @@ fn main() {\n a\n-b\n+c\n d\n@@ -30 +30,2 @@\n+e\n f\n\\ No newline at end of file");
+        assert_eq!(lines.into_iter().collect::<Vec<_>>(), vec![10, 11, 12, 30, 31]);
+
+        let lines = BTreeSet::from([10, 11, 12, 30]);
+        let comment = |start_line, line| ReviewComment {
+            path: "src/main.rs".to_string(),
+            start_line,
+            line,
+            body: String::new(),
+            fingerprint: String::new(),
+        };
+        assert_eq!(anchor(&comment(Some(10), Some(12)), Some(&lines)), Some((Some(10), 12)));
+        // Ranges spanning two hunks keep only their last line
+        assert_eq!(anchor(&comment(Some(12), Some(30)), Some(&lines)), Some((None, 30)));
+        assert_eq!(anchor(&comment(Some(20), None), Some(&lines)), None);
+        assert_eq!(anchor(&comment(Some(10), Some(10)), None), None);
+    }
+}
@@ -0,0 +1,421 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{GitHubApp, GitHubError, PullRequestEvent, RepositoryData, UserData};
+
+/// Header carrying the HMAC-SHA256 of the payload, `sha256=<hex>`.
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+/// Header naming the event, e.g. `pull_request`.
+pub const EVENT_HEADER: &str = "X-GitHub-Event";
+/// Header carrying the unique id of the delivery.
+pub const DELIVERY_HEADER: &str = "X-GitHub-Delivery";
+
+/// Error type handlers report failures with.
+pub type HandlerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Check `signature` (the `X-Hub-Signature-256` header) against the
+/// HMAC-SHA256 of the raw `payload` keyed with the webhook secret.
+///
+/// The comparison is constant-time.
+pub fn verify_signature(secret: &[u8], payload: &[u8], signature: &str) -> Result<(), GitHubError> {
+    let digest = signature
+        .trim()
+        .strip_prefix("sha256=")
+        .and_then(|digest| hex::decode(digest).ok())
+        .ok_or(GitHubError::InvalidSignature)?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|_| GitHubError::InvalidSignature)?;
+    mac.update(payload);
+    mac.verify_slice(&digest).map_err(|_| GitHubError::InvalidSignature)
+}
+
+/// A webhook delivery parsed according to its `X-GitHub-Event` header.
+#[derive(Debug, Clone)]
+pub enum WebhookEvent {
+    PullRequest(Box<PullRequestEvent>),
+    IssueComment(IssueCommentEvent),
+    CheckRun(CheckRunEvent),
+    Push(PushEvent),
+    /// Sent when the webhook is created.
+    Ping(PingEvent),
+    /// Any other event, left untyped.
+    Other { event: String, payload: serde_json::Value },
+}
+
+impl WebhookEvent {
+    pub fn parse(event: &str, payload: &[u8]) -> Result<Self, GitHubError> {
+        Ok(match event {
+            "pull_request" => Self::PullRequest(Box::new(serde_json::from_slice(payload)?)),
+            "issue_comment" => Self::IssueComment(serde_json::from_slice(payload)?),
+            "check_run" => Self::CheckRun(serde_json::from_slice(payload)?),
+            "push" => Self::Push(serde_json::from_slice(payload)?),
+            "ping" => Self::Ping(serde_json::from_slice(payload)?),
+            _ => Self::Other {
+                event: event.to_string(),
+                payload: serde_json::from_slice(payload)?,
+            },
+        })
+    }
+
+    /// The event name, as in the `X-GitHub-Event` header.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::PullRequest(_) => "pull_request",
+            Self::IssueComment(_) => "issue_comment",
+            Self::CheckRun(_) => "check_run",
+            Self::Push(_) => "push",
+            Self::Ping(_) => "ping",
+            Self::Other { event, .. } => event,
+        }
+    }
+
+    /// The activity that triggered the event, e.g. `opened`; pushes have none.
+    pub fn action(&self) -> Option<&str> {
+        match self {
+            Self::PullRequest(event) => Some(&event.action),
+            Self::IssueComment(event) => Some(&event.action),
+            Self::CheckRun(event) => Some(&event.action),
+            Self::Push(_) | Self::Ping(_) => None,
+            Self::Other { payload, .. } => payload.get("action").and_then(|action| action.as_str()),
+        }
+    }
+}
+
+/// The app installation a delivery was sent for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallationRef {
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueCommentEvent {
+    /// `created`, `edited` or `deleted`.
+    pub action: String,
+    pub issue: IssueData,
+    pub comment: CommentData,
+    pub repository: RepositoryData,
+    pub sender: UserData,
+    #[serde(default)]
+    pub installation: Option<InstallationRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssueData {
+    pub number: u32,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub html_url: String,
+    pub user: UserData,
+    /// Present when the issue is a pull request.
+    #[serde(default)]
+    pub pull_request: Option<serde_json::Value>,
+}
+
+impl IssueData {
+    pub fn is_pull_request(&self) -> bool {
+        self.pull_request.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentData {
+    pub id: u64,
+    pub body: String,
+    pub html_url: String,
+    pub user: UserData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunEvent {
+    /// `created`, `completed`, `rerequested` or `requested_action`.
+    pub action: String,
+    pub check_run: CheckRunData,
+    /// The button pressed, for `requested_action`.
+    #[serde(default)]
+    pub requested_action: Option<RequestedAction>,
+    pub repository: RepositoryData,
+    pub sender: UserData,
+    #[serde(default)]
+    pub installation: Option<InstallationRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunData {
+    pub id: u64,
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    pub conclusion: Option<String>,
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub pull_requests: Vec<CheckRunPullRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunPullRequest {
+    pub number: u32,
+    pub head: CheckRunPullRequestRef,
+    pub base: CheckRunPullRequestRef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRunPullRequestRef {
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub sha: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestedAction {
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushEvent {
+    /// Full ref that was pushed, e.g. `refs/heads/main`.
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub before: String,
+    pub after: String,
+    #[serde(default)]
+    pub created: bool,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub forced: bool,
+    #[serde(default)]
+    pub commits: Vec<PushCommit>,
+    pub head_commit: Option<PushCommit>,
+    pub repository: RepositoryData,
+    pub sender: UserData,
+    #[serde(default)]
+    pub installation: Option<InstallationRef>,
+}
+
+impl PushEvent {
+    /// The branch name for pushes to `refs/heads/*`.
+    pub fn branch(&self) -> Option<&str> {
+        self.ref_name.strip_prefix("refs/heads/")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PushCommit {
+    pub id: String,
+    pub message: String,
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PingEvent {
+    pub zen: String,
+    pub hook_id: u64,
+}
+
+/// Reactions to webhook events, registered with [`GitHubApp::register_handler`].
+///
+/// Every method defaults to doing nothing, so a handler only implements the
+/// events it cares about. `app` is the client that received the delivery,
+/// for calling back into the API; clone it to keep it beyond the call.
+#[async_trait]
+pub trait WebhookHandler: Send + Sync {
+    async fn pull_request(&self, _app: &GitHubApp, _event: &PullRequestEvent) -> Result<(), HandlerError> {
+        Ok(())
+    }
+
+    async fn issue_comment(&self, _app: &GitHubApp, _event: &IssueCommentEvent) -> Result<(), HandlerError> {
+        Ok(())
+    }
+
+    async fn check_run(&self, _app: &GitHubApp, _event: &CheckRunEvent) -> Result<(), HandlerError> {
+        Ok(())
+    }
+
+    async fn push(&self, _app: &GitHubApp, _event: &PushEvent) -> Result<(), HandlerError> {
+        Ok(())
+    }
+
+    /// Events without a dedicated method, including `ping`.
+    async fn other(&self, _app: &GitHubApp, _event: &WebhookEvent) -> Result<(), HandlerError> {
+        Ok(())
+    }
+}
+
+/// Call the method of `handler` matching `event`.
+pub(crate) async fn dispatch(handler: &dyn WebhookHandler, app: &GitHubApp, event: &WebhookEvent) -> Result<(), HandlerError> {
+    match event {
+        WebhookEvent::PullRequest(event) => handler.pull_request(app, event).await,
+        WebhookEvent::IssueComment(event) => handler.issue_comment(app, event).await,
+        WebhookEvent::CheckRun(event) => handler.check_run(app, event).await,
+        WebhookEvent::Push(event) => handler.push(app, event).await,
+        WebhookEvent::Ping(_) | WebhookEvent::Other { .. } => handler.other(app, event).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::GitHubConfig;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+    const PULL_REQUEST_OPENED: &[u8] = include_bytes!("../tests/fixtures/webhooks/pull_request_opened.json");
+    const ISSUE_COMMENT_CREATED: &[u8] = include_bytes!("../tests/fixtures/webhooks/issue_comment_created.json");
+    const CHECK_RUN_REREQUESTED: &[u8] = include_bytes!("../tests/fixtures/webhooks/check_run_rerequested.json");
+    const PUSH: &[u8] = include_bytes!("../tests/fixtures/webhooks/push.json");
+
+    fn sign(payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn app() -> GitHubApp {
+        GitHubApp::new(GitHubConfig {
+            app_id: String::new(),
+            private_key_path: String::new(),
+            webhook_secret: SECRET.to_string(),
+            base_url: None,
+            access_token: None,
+            installation_id: None,
+        })
+        .unwrap()
+    }
+
+    /// Records what it saw and asks for a review on opened/synchronize.
+    #[derive(Default)]
+    struct Recorder {
+        reviews: Mutex<Vec<(String, u32, String)>>,
+        events: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl WebhookHandler for Recorder {
+        async fn pull_request(&self, _app: &GitHubApp, event: &PullRequestEvent) -> Result<(), HandlerError> {
+            if matches!(event.action.as_str(), "opened" | "synchronize") {
+                self.reviews.lock().unwrap().push((
+                    event.repository.full_name.clone(),
+                    event.number,
+                    event.pull_request.head.sha.clone(),
+                ));
+            }
+            Ok(())
+        }
+
+        async fn issue_comment(&self, _app: &GitHubApp, event: &IssueCommentEvent) -> Result<(), HandlerError> {
+            self.events.lock().unwrap().push(format!("comment {}", event.comment.body));
+            Ok(())
+        }
+
+        async fn check_run(&self, _app: &GitHubApp, event: &CheckRunEvent) -> Result<(), HandlerError> {
+            self.events.lock().unwrap().push(format!("check_run {} {}", event.action, event.check_run.name));
+            Ok(())
+        }
+
+        async fn push(&self, _app: &GitHubApp, event: &PushEvent) -> Result<(), HandlerError> {
+            self.events.lock().unwrap().push(format!("push {}", event.branch().unwrap_or_default()));
+            Ok(())
+        }
+
+        async fn other(&self, _app: &GitHubApp, event: &WebhookEvent) -> Result<(), HandlerError> {
+            Err(format!("unexpected {}", event.name()).into())
+        }
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signature = sign(PUSH);
+        verify_signature(SECRET.as_bytes(), PUSH, &signature).unwrap();
+
+        let mut tampered = PUSH.to_vec();
+        tampered[0] = b' ';
+        for (secret, payload, signature) in [
+            (SECRET, &tampered[..], signature.as_str()),
+            ("wrong secret", PUSH, signature.as_str()),
+            (SECRET, PUSH, signature.trim_start_matches("sha256=")),
+            (SECRET, PUSH, "sha256=not-hex"),
+            (SECRET, PUSH, ""),
+        ] {
+            assert!(matches!(
+                verify_signature(secret.as_bytes(), payload, signature),
+                Err(GitHubError::InvalidSignature)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_webhook_dispatches_recorded_payloads() {
+        let recorder = Arc::new(Recorder::default());
+        let mut app = app();
+        app.register_handler(recorder.clone());
+
+        let event = app
+            .handle_webhook("pull_request", PULL_REQUEST_OPENED, &sign(PULL_REQUEST_OPENED))
+            .await
+            .unwrap();
+        let WebhookEvent::PullRequest(pull_request) = &event else {
+            panic!("expected a pull_request event, got {:?}", event);
+        };
+        assert_eq!(event.action(), Some("opened"));
+        assert_eq!(pull_request.pull_request.base.ref_name, "main");
+        assert_eq!(pull_request.repository.owner.login, "octocat");
+        assert_eq!(pull_request.installation.as_ref().unwrap().id, 42);
+
+        let event = app
+            .handle_webhook("issue_comment", ISSUE_COMMENT_CREATED, &sign(ISSUE_COMMENT_CREATED))
+            .await
+            .unwrap();
+        let WebhookEvent::IssueComment(comment) = &event else {
+            panic!("expected an issue_comment event, got {:?}", event);
+        };
+        assert!(comment.issue.is_pull_request());
+
+        let event = app
+            .handle_webhook("check_run", CHECK_RUN_REREQUESTED, &sign(CHECK_RUN_REREQUESTED))
+            .await
+            .unwrap();
+        let WebhookEvent::CheckRun(check_run) = &event else {
+            panic!("expected a check_run event, got {:?}", event);
+        };
+        assert_eq!(check_run.check_run.pull_requests[0].number, 1347);
+
+        let event = app.handle_webhook("push", PUSH, &sign(PUSH)).await.unwrap();
+        let WebhookEvent::Push(push) = &event else {
+            panic!("expected a push event, got {:?}", event);
+        };
+        assert_eq!(push.head_commit.as_ref().unwrap().modified, vec!["README.md".to_string()]);
+
+        assert_eq!(
+            *recorder.reviews.lock().unwrap(),
+            vec![(
+                "octocat/Hello-World".to_string(),
+                1347,
+                "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_string()
+            )]
+        );
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec!["comment /codex review", "check_run rerequested Codex review", "push main"]
+        );
+
+        // Unsigned deliveries never reach the handlers; handler failures are reported
+        assert!(matches!(
+            app.handle_webhook("push", PUSH, &sign(PULL_REQUEST_OPENED)).await,
+            Err(GitHubError::InvalidSignature)
+        ));
+        assert_eq!(recorder.events.lock().unwrap().len(), 3);
+        let ping = br#"{"zen":"Keep it logically awesome.","hook_id":1}"#;
+        assert!(matches!(
+            app.handle_webhook("ping", ping, &sign(ping)).await,
+            Err(GitHubError::Handler { ref event, .. }) if event == "ping"
+        ));
+    }
+}
@@ -3,7 +3,7 @@ const axios = require('axios');
 
 async function testOrchestrator() {
   try {
-    const response = await axios.post('http://localhost:3000/api/orchestrate', {
+    const response = await axios.post('http://localhost:3000/api/v1/orchestrate', {
       prompt: 'Create a simple HTTP server in Node.js',
       context: {
         language: 'javascript',
@@ -16,10 +16,22 @@ async function testOrchestrator() {
       },
     });
 
-    console.log('Orchestration successful!');
-    console.log('Request ID:', response.data.request_id);
-    console.log('Status:', response.data.status);
-    console.log('Result:', JSON.stringify(response.data.result, null, 2));
+    const requestId = response.data.request_id;
+    console.log('Orchestration queued!');
+    console.log('Request ID:', requestId);
+
+    // Poll until the background job finishes
+    let record = response.data;
+    while (!['completed', 'failed', 'cancelled'].includes(record.status)) {
+      await new Promise((resolve) => setTimeout(resolve, 1000));
+      record = (await axios.get(`http://localhost:3000/api/v1/requests/${requestId}`)).data;
+    }
+
+    console.log('Status:', record.status);
+    if (record.error) {
+      console.log('Error:', record.error);
+    }
+    console.log('Result:', JSON.stringify(record.result, null, 2));
   } catch (error) {
     console.error('Error:', error.response?.data || error.message);
   }
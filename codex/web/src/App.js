@@ -7,6 +7,24 @@ import ErrorBoundary from './components/ErrorBoundary';
 import SessionRecording from './components/SessionRecording';
 import { SessionRecordingProvider, useSessionRecording } from './contexts/SessionRecordingContext';
 
+const REQUESTS_ENDPOINT = 'http://localhost:3000/api/v1/requests';
+const POLL_INTERVAL_MS = 1000;
+
+// Poll a queued orchestration until it completes, fails or is cancelled
+async function waitForRequest(requestId) {
+  for (;;) {
+    const response = await fetch(`${REQUESTS_ENDPOINT}/${requestId}`);
+    if (!response.ok) {
+      throw new Error(`HTTP error! status: ${response.status}`);
+    }
+    const record = await response.json();
+    if (['completed', 'failed', 'cancelled'].includes(record.status)) {
+      return record;
+    }
+    await new Promise(resolve => setTimeout(resolve, POLL_INTERVAL_MS));
+  }
+}
+
 function AppContent() {
   const [patches, setPatches] = useState([]);
   const [isLoading, setIsLoading] = useState(false);
@@ -68,7 +86,12 @@ function AppContent() {
         throw new Error(`HTTP error! status: ${response.status}`);
       }
 
-      const result = await response.json();
+      // The orchestration runs as a background job; wait for it to finish
+      const { request_id } = await response.json();
+      const result = await waitForRequest(request_id);
+      if (result.status !== 'completed') {
+        throw new Error(result.error || `Request ${result.status}`);
+      }
 
       // Record successful response
       recordEvent('api_response_received', {
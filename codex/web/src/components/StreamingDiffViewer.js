@@ -1,77 +1,147 @@
 import React, { useState, useEffect, useRef } from 'react';
 import './StreamingDiffViewer.css';
 
-const StreamingDiffViewer = ({ isStreaming, onStreamingComplete, onStreamingError }) => {
+const STREAM_ENDPOINT = 'http://localhost:3000/api/v1/orchestrate/stream';
+
+// Split a buffer of server-sent events into complete `data:` payloads and the
+// unfinished remainder.
+const takeEvents = (buffer) => {
+  const frames = buffer.split('\n\n');
+  const rest = frames.pop();
+  const payloads = frames
+    .map(frame => frame
+      .split('\n')
+      .filter(line => line.startsWith('data:'))
+      .map(line => line.slice(5).trimStart())
+      .join('\n'))
+    .filter(payload => payload.length > 0);
+  return { payloads, rest };
+};
+
+const StreamingDiffViewer = ({ request, isStreaming, onStreamingComplete, onStreamingError }) => {
   const [streamingContent, setStreamingContent] = useState('');
+  const [currentAgent, setCurrentAgent] = useState(null);
+  const [warnings, setWarnings] = useState([]);
   const [isConnected, setIsConnected] = useState(false);
-  const eventSourceRef = useRef(null);
+  const abortRef = useRef(null);
 
   useEffect(() => {
-    if (isStreaming && !eventSourceRef.current) {
+    if (isStreaming && request && !abortRef.current) {
       startStreaming();
-    } else if (!isStreaming && eventSourceRef.current) {
+    } else if (!isStreaming && abortRef.current) {
       stopStreaming();
     }
 
     return () => {
-      if (eventSourceRef.current) {
-        eventSourceRef.current.close();
+      if (abortRef.current) {
+        abortRef.current.abort();
+        abortRef.current = null;
       }
     };
-  }, [isStreaming]);
+  }, [isStreaming, request]);
+
+  const handleEvent = (event, state) => {
+    switch (event.type) {
+      case 'agent_started':
+        setCurrentAgent(event.step_id || event.agent_type);
+        setStreamingContent(prev => `${prev}${prev ? '\n\n' : ''}── ${event.step_id || event.agent_type} ──\n`);
+        break;
+      case 'token_delta':
+        setStreamingContent(prev => prev + event.delta);
+        break;
+      case 'agent_finished':
+        if (event.execution.output && event.execution.output.patch) {
+          state.patch = event.execution.output.patch;
+        }
+        break;
+      case 'warning':
+        setWarnings(prev => [...prev, event.message]);
+        break;
+      case 'step_skipped':
+        setStreamingContent(prev => `${prev}${prev ? '\n\n' : ''}── ${event.step_id} (skipped) ──\n`);
+        break;
+      case 'completed':
+        setCurrentAgent(null);
+        if (onStreamingComplete) {
+          onStreamingComplete({ patch: state.patch, response: event.response });
+        }
+        break;
+      case 'failed':
+        setCurrentAgent(null);
+        if (onStreamingError) {
+          onStreamingError(new Error(event.error));
+        }
+        break;
+      default:
+        break;
+    }
+  };
+
+  const startStreaming = async () => {
+    const controller = new AbortController();
+    abortRef.current = controller;
+    setStreamingContent('');
+    setWarnings([]);
 
-  const startStreaming = () => {
     try {
-      // Connect to the streaming endpoint
-      const eventSource = new EventSource('http://localhost:3000/api/v1/orchestrate/stream');
-      eventSourceRef.current = eventSource;
-
-      eventSource.onopen = () => {
-        setIsConnected(true);
-        console.log('Streaming connection established');
-      };
-
-      eventSource.onmessage = (event) => {
-        try {
-          const data = JSON.parse(event.data);
-
-          if (data.type === 'chunk') {
-            setStreamingContent(prev => prev + data.content);
-          } else if (data.type === 'patch') {
-            // Handle complete patch data
-            setStreamingContent(data.content);
-          } else if (data.type === 'complete') {
-            // Streaming completed
-            setIsConnected(false);
-            if (onStreamingComplete) {
-              onStreamingComplete(data.result);
-            }
-          }
-        } catch (error) {
-          console.error('Error parsing streaming data:', error);
+      // EventSource cannot POST a request body, so read the SSE stream with fetch
+      const response = await fetch(STREAM_ENDPOINT, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json',
+          'Accept': 'text/event-stream',
+        },
+        body: JSON.stringify(request),
+        signal: controller.signal,
+      });
+
+      if (!response.ok) {
+        throw new Error(`HTTP error! status: ${response.status}`);
+      }
+
+      setIsConnected(true);
+      console.log('Streaming connection established');
+
+      const reader = response.body.getReader();
+      const decoder = new TextDecoder();
+      const state = { patch: null };
+      let buffer = '';
+
+      while (true) {
+        const { value, done } = await reader.read();
+        if (done) {
+          break;
         }
-      };
 
-      eventSource.onerror = (error) => {
+        buffer += decoder.decode(value, { stream: true });
+        const { payloads, rest } = takeEvents(buffer);
+        buffer = rest;
+
+        for (const payload of payloads) {
+          try {
+            handleEvent(JSON.parse(payload), state);
+          } catch (error) {
+            console.error('Error parsing streaming data:', error);
+          }
+        }
+      }
+    } catch (error) {
+      if (error.name !== 'AbortError') {
         console.error('Streaming error:', error);
-        setIsConnected(false);
         if (onStreamingError) {
           onStreamingError(error);
         }
-      };
-
-    } catch (error) {
-      console.error('Failed to start streaming:', error);
-      if (onStreamingError) {
-        onStreamingError(error);
       }
+    } finally {
+      setIsConnected(false);
+      abortRef.current = null;
     }
   };
 
   const stopStreaming = () => {
-    if (eventSourceRef.current) {
-      eventSourceRef.current.close();
-      eventSourceRef.current = null;
+    if (abortRef.current) {
+      abortRef.current.abort();
+      abortRef.current = null;
       setIsConnected(false);
     }
   };
@@ -100,10 +170,18 @@ const StreamingDiffViewer = ({ isStreaming, onStreamingComplete, onStreamingErro
           <div className="progress-bar">
             <div className="progress-fill"></div>
           </div>
-          <span>Generating code...</span>
+          <span>{currentAgent ? `Running ${currentAgent} agent...` : 'Generating code...'}</span>
         </div>
       )}
 
+      {warnings.length > 0 && (
+        <ul className="streaming-warnings">
+          {warnings.map((warning, index) => (
+            <li key={index}>⚠️ {warning}</li>
+          ))}
+        </ul>
+      )}
+
       <div className="streaming-content">
         <pre className="content-display">
           <code>{formatStreamingContent(streamingContent)}</code>